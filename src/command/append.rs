@@ -0,0 +1,142 @@
+use bytes::Bytes;
+
+use crate::{config::ServerConfig, resp::RESP, Db, RespReader, RespReaderError};
+
+#[derive(Debug, Default)]
+pub struct Append {
+    /// cache lookup key
+    key: String,
+
+    /// bytes to append to the stored string
+    value: Bytes,
+}
+
+impl Append {
+    /// contruct new Append command
+    pub fn new(key: String, value: Bytes) -> Self {
+        Append { key, value }
+    }
+
+    /// Construct new Append command by consuming the RespReader
+    pub fn from_parts(reader: &mut RespReader) -> Result<Self, RespReaderError> {
+        let key = reader.next_string()?;
+        let value = reader.next_byte()?;
+
+        Ok(Append { key, value })
+    }
+
+    /// Apply the append command and return the length of the string,
+    /// the string can't grow past `proto-max-bulk-len`
+    pub async fn apply(self, db: &Db, config: ServerConfig) -> crate::Result<Option<RESP>> {
+        let len = db.append(self.key, &self.value, config.proto_max_bulk_len)?;
+
+        Ok(Some(RESP::Integer(len as i64)))
+    }
+}
+
+/// Convert Append command back into an equivalent `RESP`
+impl From<Append> for RESP {
+    fn from(value: Append) -> Self {
+        let mut resp = RESP::array();
+        resp.push_bulk(Bytes::from("append"));
+        resp.push_bulk(Bytes::from(value.key));
+        resp.push_bulk(value.value);
+        resp
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bytes::Bytes;
+    use tokio::time::Duration;
+
+    use super::Append;
+    use crate::{config::ServerConfig, resp::RESP, CommandError, Db, Stream, ValueType};
+
+    #[tokio::test]
+    async fn append_creates_and_extends_binary_value() {
+        let db = Db::new();
+
+        let resp = Append::new("key".into(), Bytes::from_static(&[0x00, 0xff]))
+            .apply(&db, ServerConfig::for_test())
+            .await
+            .unwrap();
+        assert!(matches!(resp, Some(RESP::Integer(2))));
+
+        let resp = Append::new("key".into(), Bytes::from_static(b"\r\n"))
+            .apply(&db, ServerConfig::for_test())
+            .await
+            .unwrap();
+        assert!(matches!(resp, Some(RESP::Integer(4))));
+        assert!(matches!(
+            db.get("key"),
+            Some(ValueType::String(value)) if value == [0x00, 0xff, b'\r', b'\n'][..]
+        ));
+    }
+
+    #[tokio::test]
+    async fn append_keeps_expiry() {
+        let db = Db::new();
+        db.set(
+            "key".into(),
+            ValueType::String(Bytes::from("a")),
+            Some(Duration::from_secs(100)),
+        );
+
+        Append::new("key".into(), Bytes::from("b"))
+            .apply(&db, ServerConfig::for_test())
+            .await
+            .unwrap();
+
+        assert!(db.inner.state.lock().unwrap().next_expiration().is_some());
+    }
+
+    #[tokio::test]
+    async fn append_to_stream_is_an_error() {
+        let db = Db::new();
+        db.set("key".into(), ValueType::Stream(Stream::default()), None);
+
+        let err = Append::new("key".into(), Bytes::from("b"))
+            .apply(&db, ServerConfig::for_test())
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<CommandError>(),
+            Some(&CommandError::WrongType)
+        );
+    }
+
+    #[tokio::test]
+    async fn append_past_proto_max_bulk_len_is_an_error() {
+        let db = Db::new();
+        let mut config = ServerConfig::for_test();
+        config.proto_max_bulk_len = 4;
+
+        let resp = Append::new("key".into(), Bytes::from("abc"))
+            .apply(&db, config.clone())
+            .await
+            .unwrap();
+        assert!(matches!(resp, Some(RESP::Integer(3))));
+
+        let err = Append::new("key".into(), Bytes::from("de"))
+            .apply(&db, config.clone())
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<CommandError>(),
+            Some(&CommandError::StringTooLong)
+        );
+        assert_eq!(
+            err.to_string(),
+            "ERR string exceeds maximum allowed size (proto-max-bulk-len)"
+        );
+        assert!(matches!(db.get("key"), Some(ValueType::String(v)) if v == "abc"));
+
+        // a new key isn't created by a failed append
+        let err = Append::new("missing".into(), Bytes::from("abcde"))
+            .apply(&db, config)
+            .await;
+        assert!(err.is_err());
+        assert!(db.get("missing").is_none());
+    }
+}
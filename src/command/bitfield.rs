@@ -0,0 +1,338 @@
+use bytes::{Bytes, BytesMut};
+
+use crate::{
+    config::ServerConfig, resp::RESP, CommandError, Db, RespReader, RespReaderError, ValueType,
+};
+
+#[derive(Debug, Default)]
+pub struct BitField {
+    /// cache lookup key
+    key: String,
+
+    /// subcommands and their arguments, validated when applied
+    args: Vec<String>,
+}
+
+/// Integer type a bitfield is read or written as
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct FieldType {
+    signed: bool,
+    bits: u32,
+}
+
+/// How SET and INCRBY handle a value that doesn't fit the field
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Overflow {
+    Wrap,
+    Sat,
+    Fail,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Get(FieldType, u64),
+    Set(FieldType, u64, i64, Overflow),
+    IncrBy(FieldType, u64, i64, Overflow),
+}
+
+impl BitField {
+    /// contruct new BitField command
+    pub fn new(key: String, args: Vec<String>) -> Self {
+        BitField { key, args }
+    }
+
+    /// Construct new BitField command by consuming the RespReader
+    pub fn from_parts(reader: &mut RespReader) -> Result<Self, RespReaderError> {
+        let key = reader.next_string()?;
+
+        let mut args = vec![];
+        while let Ok(arg) = reader.next_string() {
+            args.push(arg);
+        }
+
+        Ok(BitField { key, args })
+    }
+
+    /// Apply the bitfield subcommands in order, returns one reply per
+    /// GET, SET and INCRBY
+    ///
+    /// Fields can't be addressed past `proto-max-bulk-len`
+    pub async fn apply(self, db: &Db, config: ServerConfig) -> crate::Result<Option<RESP>> {
+        let max_bits = config.proto_max_bulk_len as u64 * 8;
+        let ops = parse_ops(&self.args, max_bits)?;
+
+        let results = if ops.iter().any(|op| !matches!(op, Op::Get(..))) {
+            db.update_string(self.key, "setbit", |data| Ok(run_ops(data, &ops)))?
+        } else {
+            // reads alone never create the key
+            let mut data = match db.get(&self.key) {
+                Some(ValueType::String(bytes)) => BytesMut::from(&bytes[..]),
+                Some(_) => return Err(CommandError::WrongType.into()),
+                None => BytesMut::new(),
+            };
+            run_ops(&mut data, &ops)
+        };
+
+        let resp = results
+            .into_iter()
+            .map(|result| result.map_or(RESP::Null, RESP::Integer))
+            .collect();
+
+        Ok(Some(RESP::Array(resp)))
+    }
+}
+
+/// Parse the subcommands, OVERFLOW applies to every SET and
+/// INCRBY following it
+fn parse_ops(args: &[String], max_bits: u64) -> Result<Vec<Op>, CommandError> {
+    let mut ops = vec![];
+    let mut overflow = Overflow::Wrap;
+    let mut args = args.iter();
+
+    while let Some(subcommand) = args.next() {
+        let mut next = || args.next().ok_or(CommandError::SyntaxError);
+
+        match subcommand.to_lowercase().as_str() {
+            "get" => {
+                let ty = parse_type(next()?)?;
+                let offset = parse_offset(next()?, ty, max_bits)?;
+                ops.push(Op::Get(ty, offset));
+            }
+            "set" | "incrby" => {
+                let ty = parse_type(next()?)?;
+                let offset = parse_offset(next()?, ty, max_bits)?;
+                let value = next()?.parse().map_err(|_| CommandError::NotInteger)?;
+                ops.push(match subcommand.to_lowercase().as_str() {
+                    "set" => Op::Set(ty, offset, value, overflow),
+                    _ => Op::IncrBy(ty, offset, value, overflow),
+                });
+            }
+            "overflow" => {
+                overflow = match next()?.to_lowercase().as_str() {
+                    "wrap" => Overflow::Wrap,
+                    "sat" => Overflow::Sat,
+                    "fail" => Overflow::Fail,
+                    _ => return Err(CommandError::SyntaxError),
+                };
+            }
+            _ => return Err(CommandError::SyntaxError),
+        }
+    }
+
+    Ok(ops)
+}
+
+/// Parse `i1`..`i64` or `u1`..`u63`
+fn parse_type(arg: &str) -> Result<FieldType, CommandError> {
+    let signed = match arg.as_bytes().first() {
+        Some(b'i') | Some(b'I') => true,
+        Some(b'u') | Some(b'U') => false,
+        _ => return Err(CommandError::BitfieldType),
+    };
+
+    let bits: u32 = arg[1..].parse().map_err(|_| CommandError::BitfieldType)?;
+    let max = if signed { 64 } else { 63 };
+    if bits == 0 || bits > max {
+        return Err(CommandError::BitfieldType);
+    }
+
+    Ok(FieldType { signed, bits })
+}
+
+/// Parse a bit offset, `#N` addresses the N-th field of the type's width.
+/// The field has to end within `max_bits`
+fn parse_offset(arg: &str, ty: FieldType, max_bits: u64) -> Result<u64, CommandError> {
+    let offset = match arg.strip_prefix('#') {
+        Some(index) => index
+            .parse::<u64>()
+            .ok()
+            .and_then(|index| index.checked_mul(ty.bits as u64)),
+        None => arg.parse::<u64>().ok(),
+    };
+
+    match offset {
+        Some(offset) if offset.saturating_add(ty.bits as u64) <= max_bits => Ok(offset),
+        _ => Err(CommandError::BitOffset),
+    }
+}
+
+/// Run the ops against the string, growing it to fit every write
+///
+/// A failed SET or INCRBY under OVERFLOW FAIL is `None`
+fn run_ops(data: &mut BytesMut, ops: &[Op]) -> Vec<Option<i64>> {
+    let len = ops
+        .iter()
+        .filter_map(|op| match *op {
+            Op::Set(ty, offset, ..) | Op::IncrBy(ty, offset, ..) => {
+                Some((offset + ty.bits as u64).div_ceil(8) as usize)
+            }
+            Op::Get(..) => None,
+        })
+        .max()
+        .unwrap_or(0);
+    if data.len() < len {
+        data.resize(len, 0);
+    }
+
+    ops.iter()
+        .map(|op| match *op {
+            Op::Get(ty, offset) => Some(read_field(data, ty, offset)),
+            Op::Set(ty, offset, value, overflow) => {
+                let old = read_field(data, ty, offset);
+                let value = fit(ty, value as i128, overflow)?;
+                write_field(data, ty, offset, value);
+                Some(old)
+            }
+            Op::IncrBy(ty, offset, increment, overflow) => {
+                let old = read_field(data, ty, offset);
+                let value = fit(ty, old as i128 + increment as i128, overflow)?;
+                write_field(data, ty, offset, value);
+                Some(value)
+            }
+        })
+        .collect()
+}
+
+/// Bring `value` into the range of the type as per the overflow mode
+fn fit(ty: FieldType, value: i128, overflow: Overflow) -> Option<i64> {
+    let (min, max) = if ty.signed {
+        (-(1i128 << (ty.bits - 1)), (1i128 << (ty.bits - 1)) - 1)
+    } else {
+        (0, (1i128 << ty.bits) - 1)
+    };
+
+    if (min..=max).contains(&value) {
+        return Some(value as i64);
+    }
+
+    match overflow {
+        Overflow::Wrap => {
+            let wrapped = value.rem_euclid(1i128 << ty.bits);
+            if wrapped > max {
+                Some((wrapped - (1i128 << ty.bits)) as i64)
+            } else {
+                Some(wrapped as i64)
+            }
+        }
+        Overflow::Sat => Some(value.clamp(min, max) as i64),
+        Overflow::Fail => None,
+    }
+}
+
+/// Read the field at `offset`, bit 0 is the most significant bit of
+/// the first byte and bits past the end of the string are zeros
+fn read_field(data: &[u8], ty: FieldType, offset: u64) -> i64 {
+    let mut value = 0u64;
+    for bit in offset..offset + ty.bits as u64 {
+        let byte = data.get((bit / 8) as usize).copied().unwrap_or(0);
+        value = (value << 1) | ((byte >> (7 - bit % 8)) & 1) as u64;
+    }
+
+    // sign extend negative values
+    if ty.signed {
+        let shift = 64 - ty.bits;
+        ((value << shift) as i64) >> shift
+    } else {
+        value as i64
+    }
+}
+
+/// Write the low bits of `value` to the field at `offset`, the string
+/// must already be long enough to hold it
+fn write_field(data: &mut [u8], ty: FieldType, offset: u64, value: i64) {
+    let value = value as u64;
+    for (idx, bit) in (offset..offset + ty.bits as u64).enumerate() {
+        let set = (value >> (ty.bits as usize - 1 - idx)) & 1 == 1;
+        let byte = &mut data[(bit / 8) as usize];
+        let mask = 1 << (7 - bit % 8);
+        if set {
+            *byte |= mask;
+        } else {
+            *byte &= !mask;
+        }
+    }
+}
+
+/// Convert BitField command back into an equivalent `RESP`
+impl From<BitField> for RESP {
+    fn from(value: BitField) -> Self {
+        let mut resp = RESP::array();
+        resp.push_bulk(Bytes::from("bitfield"));
+        resp.push_bulk(Bytes::from(value.key));
+        for arg in value.args {
+            resp.push_bulk(Bytes::from(arg));
+        }
+        resp
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::BitField;
+    use crate::{config::ServerConfig, resp::RESP, Db, ValueType};
+
+    fn bitfield(key: &str, args: &str) -> BitField {
+        BitField::new(key.into(), args.split(' ').map(String::from).collect())
+    }
+
+    fn integers(resp: Option<RESP>) -> Vec<Option<i64>> {
+        match resp {
+            Some(RESP::Array(values)) => values
+                .into_iter()
+                .map(|value| match value {
+                    RESP::Integer(int) => Some(int),
+                    RESP::Null => None,
+                    value => panic!("unexpected reply {value:?}"),
+                })
+                .collect(),
+            resp => panic!("expected an array, got {resp:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn set_then_get_u8_at_an_offset() {
+        let db = Db::new();
+
+        let resp = bitfield("key", "SET u8 4 255 GET u8 4 GET u4 0 GET i8 4")
+            .apply(&db, ServerConfig::for_test())
+            .await
+            .unwrap();
+        assert_eq!(integers(resp), vec![Some(0), Some(255), Some(0), Some(-1)]);
+        assert!(matches!(db.get("key"), Some(ValueType::String(v)) if v == [0x0f, 0xf0][..]));
+
+        // reads alone don't create the key
+        let resp = bitfield("missing", "GET u8 #3")
+            .apply(&db, ServerConfig::for_test())
+            .await
+            .unwrap();
+        assert_eq!(integers(resp), vec![Some(0)]);
+        assert!(db.get("missing").is_none());
+    }
+
+    #[tokio::test]
+    async fn incrby_overflow_modes() {
+        let db = Db::new();
+
+        let resp = bitfield(
+            "key",
+            "SET u8 0 250 OVERFLOW SAT INCRBY u8 0 10 INCRBY i8 8 -200 OVERFLOW WRAP INCRBY u8 0 10 OVERFLOW FAIL INCRBY u8 0 250",
+        )
+        .apply(&db, ServerConfig::for_test())
+        .await
+        .unwrap();
+        assert_eq!(
+            integers(resp),
+            vec![Some(0), Some(255), Some(-128), Some(9), None]
+        );
+        assert!(matches!(db.get("key"), Some(ValueType::String(v)) if v == [9, 0x80][..]));
+
+        let resp = bitfield("wide", "SET i63 0 -1 GET i63 0 GET u63 0 INCRBY i64 64 -1")
+            .apply(&db, ServerConfig::for_test())
+            .await
+            .unwrap();
+        assert_eq!(
+            integers(resp),
+            vec![Some(0), Some(-1), Some(i64::MAX), Some(-1)]
+        );
+    }
+}
@@ -0,0 +1,130 @@
+use std::time::Duration;
+
+use bytes::Bytes;
+
+use crate::{
+    config::ServerConfig, connection::Connection, is_keyword, pause::PauseMode, resp::RESP,
+    CommandError, Db, RespReader, RespReaderError,
+};
+
+#[derive(Debug, Default)]
+pub struct Client {
+    subcommand: String,
+    args: Vec<String>,
+}
+
+impl Client {
+    /// contruct new Client command
+    pub fn new(subcommand: String, args: Vec<String>) -> Self {
+        Client { subcommand, args }
+    }
+
+    /// Construct new Client command by consuming the RespReader
+    ///
+    /// The subcommand arguments are validated when the command is applied
+    pub fn from_parts(reader: &mut RespReader) -> Result<Self, RespReaderError> {
+        let subcommand = reader.next_string()?;
+
+        let mut args = vec![];
+        while let Ok(arg) = reader.next_string() {
+            args.push(arg);
+        }
+
+        Ok(Client { subcommand, args })
+    }
+
+    /// Apply the client subcommand
+    pub async fn apply(
+        self,
+        dst: &mut Connection,
+        db: &Db,
+        config: ServerConfig,
+    ) -> crate::Result<Option<RESP>> {
+        let resp = match (
+            self.subcommand.to_lowercase().as_str(),
+            self.args.as_slice(),
+        ) {
+            ("pause", [timeout, mode @ ..]) if mode.len() <= 1 => {
+                let millis = match timeout.parse::<u64>() {
+                    Ok(millis) => millis,
+                    Err(_) => {
+                        return Ok(Some(RESP::Error(
+                            "ERR timeout is not an integer or out of range".to_string(),
+                        )))
+                    }
+                };
+
+                let mode = match mode.first().map(|mode| mode.to_lowercase()) {
+                    None => PauseMode::All,
+                    Some(mode) if mode == "all" => PauseMode::All,
+                    Some(mode) if mode == "write" => PauseMode::Write,
+                    Some(_) => return Err(CommandError::SyntaxError.into()),
+                };
+
+                config
+                    .client_pause
+                    .pause(Duration::from_millis(millis), mode);
+                RESP::Simple("OK".to_string())
+            }
+            ("unpause", []) => {
+                config.client_pause.unpause();
+                RESP::Simple("OK".to_string())
+            }
+            ("setinfo", [attribute, value]) => {
+                let field = if is_keyword(attribute, "lib-name") {
+                    &mut dst.lib_name
+                } else if is_keyword(attribute, "lib-ver") {
+                    &mut dst.lib_ver
+                } else {
+                    return Ok(Some(RESP::Error(format!(
+                        "ERR Unrecognized option '{attribute}'"
+                    ))));
+                };
+
+                // the values are listed space separated in CLIENT INFO
+                if value.chars().any(|c| !('!'..='~').contains(&c)) {
+                    return Ok(Some(RESP::Error(format!(
+                        "ERR {} cannot contain spaces, newlines or special characters.",
+                        attribute.to_lowercase()
+                    ))));
+                }
+
+                *field = value.clone();
+                RESP::Simple("OK".to_string())
+            }
+            ("info", []) => RESP::Bulk(Bytes::from(info_line(dst, db))),
+            _ => return Err(CommandError::UnknownSubcommand("CLIENT", self.subcommand).into()),
+        };
+
+        Ok(Some(resp))
+    }
+}
+
+/// Describe the connection the way CLIENT INFO does, as space
+/// separated `field=value` pairs on a single line
+fn info_line(connection: &Connection, db: &Db) -> String {
+    let addr = |addr: Option<std::net::SocketAddr>| addr.map(|a| a.to_string()).unwrap_or_default();
+
+    format!(
+        "addr={} laddr={} db={} resp={} lib-name={} lib-ver={}\n",
+        addr(connection.peer_addr()),
+        addr(connection.local_addr()),
+        db.index(),
+        connection.protocol_version,
+        connection.lib_name,
+        connection.lib_ver,
+    )
+}
+
+/// Convert Client command back into an equivalent `RESP`
+impl From<Client> for RESP {
+    fn from(value: Client) -> Self {
+        let mut resp = RESP::array();
+        resp.push_bulk(Bytes::from("client"));
+        resp.push_bulk(Bytes::from(value.subcommand));
+        for arg in value.args {
+            resp.push_bulk(Bytes::from(arg));
+        }
+        resp
+    }
+}
@@ -1,11 +1,71 @@
 use bytes::Bytes;
 
-use crate::{config::ServerConfig, resp::RESP, RespReader, RespReaderError};
+use crate::{
+    config::ServerConfig, is_keyword, resp::RESP, CommandError, RespReader, RespReaderError,
+};
+
+/// Reads the value of a parameter from the server config
+type Getter = fn(&ServerConfig) -> String;
+
+/// Parameters CONFIG GET knows about and how their value is read,
+/// clients probe some of these on connect so the features that don't
+/// exist report their defaults
+const PARAMETERS: &[(&str, Getter)] = &[
+    ("appendonly", |_| "no".into()),
+    ("dbfilename", |config| {
+        config
+            .dbfilename
+            .clone()
+            .unwrap_or_else(|| "dump.rdb".into())
+    }),
+    ("dir", |config| {
+        config.dir.clone().unwrap_or_else(|| {
+            std::env::current_dir()
+                .map(|dir| dir.to_string_lossy().into_owned())
+                .unwrap_or_default()
+        })
+    }),
+    ("lazyfree-lazy-eviction", |config| {
+        yes_no(config.lazyfree.lazy_eviction)
+    }),
+    ("lazyfree-lazy-expire", |config| {
+        yes_no(config.lazyfree.lazy_expire)
+    }),
+    ("lazyfree-lazy-user-del", |config| {
+        yes_no(config.lazyfree.lazy_user_del)
+    }),
+    ("maxmemory", |_| "0".into()),
+    ("proto-max-bulk-len", |config| {
+        config.proto_max_bulk_len.to_string()
+    }),
+    ("save", |config| {
+        config
+            .save
+            .iter()
+            .map(|(seconds, changes)| format!("{seconds} {changes}"))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }),
+    ("set-max-intset-entries", |config| {
+        config.encoding.set_max_intset_entries.to_string()
+    }),
+    ("set-max-listpack-entries", |config| {
+        config.encoding.set_max_listpack_entries.to_string()
+    }),
+    ("timeout", |config| {
+        config.timeout.unwrap_or_default().as_secs().to_string()
+    }),
+];
+
+/// Boolean parameters are reported as `yes` or `no`
+fn yes_no(value: bool) -> String {
+    if value { "yes" } else { "no" }.into()
+}
 
 #[derive(Debug, Default)]
 pub struct Config {
     command: String,
-    key: String,
+    key: Option<String>,
 }
 
 impl Config {
@@ -19,36 +79,38 @@ impl Config {
 
     /// Construct new Config command by consuming the RespReader
     ///
-    /// # default
-    ///
-    /// Return `Config::default` if RespReader has no stream left
-    /// otherwise return the error
+    /// The parameter is optional so subcommands that don't exist get
+    /// their own error rather than a wrong arity one
     pub fn from_parts(reader: &mut RespReader) -> Result<Self, RespReaderError> {
         let command = reader.next_string()?;
-        let key = reader.next_string()?;
+        let key = match reader.next_string() {
+            Ok(key) => Some(key),
+            Err(RespReaderError::EndOfStream) => None,
+            Err(err) => return Err(err),
+        };
         Ok(Config { command, key })
     }
 
-    /// Apply the echo command and write to the Tcp connection stream
+    /// Apply the config command
+    ///
+    /// CONFIG GET replies with the parameter name and its value, or an
+    /// empty array for parameters that aren't known
     pub async fn apply(self, config: ServerConfig) -> crate::Result<Option<RESP>> {
-        let mut resp = RESP::Null;
+        let mut resp = RESP::array();
 
         match (self.command, self.key) {
-            (cmd, key) if cmd.to_lowercase() == "get" && key.to_lowercase() == "dir" => {
-                resp = RESP::Array(vec![
-                    RESP::Bulk(Bytes::from("dir")),
-                    RESP::Bulk(Bytes::from(config.dir.unwrap().clone())),
-                ]);
-            }
-            (cmd, key) if cmd.to_lowercase() == "get" && key.to_lowercase() == "dbfilename" => {
-                resp = RESP::Array(vec![
-                    RESP::Bulk(Bytes::from("dbfilename")),
-                    RESP::Bulk(Bytes::from(config.dbfilename.unwrap().clone())),
-                ]);
+            (cmd, Some(key)) if is_keyword(&cmd, "get") => {
+                if let Some((name, value)) =
+                    PARAMETERS.iter().find(|(name, _)| is_keyword(&key, name))
+                {
+                    resp.push_bulk(Bytes::from(*name));
+                    resp.push_bulk(Bytes::from(value(&config)));
+                }
             }
-            (cmd, key) => {
-                println!("Unsupported Config request: CONFIG {cmd} {key}");
+            (cmd, None) if is_keyword(&cmd, "get") => {
+                return Err(CommandError::WrongArity("config|get".into()).into());
             }
+            (cmd, _) => return Err(CommandError::UnknownSubcommand("CONFIG", cmd).into()),
         }
 
         Ok(Some(resp))
@@ -61,8 +123,64 @@ impl From<Config> for RESP {
         let mut resp = RESP::array();
         resp.push_bulk(Bytes::from("CONFIG"));
         resp.push_bulk(Bytes::from(value.command.into_bytes()));
-        resp.push_bulk(Bytes::from(value.key.into_bytes()));
+        if let Some(key) = value.key {
+            resp.push_bulk(Bytes::from(key.into_bytes()));
+        }
 
         resp
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::Config;
+    use crate::{config::ServerConfig, resp::RESP};
+
+    async fn config_get(key: &str) -> Vec<String> {
+        let config = ServerConfig::for_test();
+
+        let resp = Config {
+            command: "GET".into(),
+            key: Some(key.into()),
+        }
+        .apply(config)
+        .await
+        .unwrap();
+
+        match resp {
+            Some(RESP::Array(values)) => values
+                .into_iter()
+                .map(|value| match value {
+                    RESP::Bulk(value) => String::from_utf8(value.to_vec()).unwrap(),
+                    value => panic!("unexpected reply {value:?}"),
+                })
+                .collect(),
+            resp => panic!("expected an array, got {resp:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn parameters_probed_on_connect_have_values() {
+        for key in ["appendonly", "maxmemory", "save", "dir", "dbfilename"] {
+            let reply = config_get(key).await;
+            assert_eq!(reply.len(), 2, "{key}");
+            assert_eq!(reply[0], key);
+        }
+
+        assert_eq!(config_get("APPENDONLY").await, ["appendonly", "no"]);
+        assert_eq!(
+            config_get("proto-max-bulk-len").await,
+            ["proto-max-bulk-len", "536870912"]
+        );
+        assert_eq!(
+            config_get("lazyfree-lazy-user-del").await,
+            ["lazyfree-lazy-user-del", "no"]
+        );
+        assert_eq!(config_get("timeout").await, ["timeout", "0"]);
+        assert_eq!(
+            config_get("save").await,
+            ["save", "3600 1 300 100 60 10000"]
+        );
+        assert!(config_get("unknown").await.is_empty());
+    }
+}
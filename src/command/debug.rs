@@ -0,0 +1,229 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use bytes::Bytes;
+
+use crate::{
+    rdb::serialized_length, resp::RESP, CommandError, Db, RespReader, RespReaderError, Value,
+};
+
+/// Helpers used by tests to drive internal code paths
+/// deterministically
+#[derive(Debug, Default)]
+pub struct Debug {
+    subcommand: String,
+    args: Vec<String>,
+}
+
+impl Debug {
+    /// contruct new Debug command
+    pub fn new(subcommand: String, args: Vec<String>) -> Self {
+        Debug { subcommand, args }
+    }
+
+    /// Construct new Debug command by consuming the RespReader
+    ///
+    /// The subcommand arguments are validated when the command is applied
+    pub fn from_parts(reader: &mut RespReader) -> Result<Self, RespReaderError> {
+        let subcommand = reader.next_string()?;
+
+        let mut args = vec![];
+        while let Ok(arg) = reader.next_string() {
+            args.push(arg);
+        }
+
+        Ok(Debug { subcommand, args })
+    }
+
+    /// Apply the debug subcommand
+    pub async fn apply(self, db: &Db) -> crate::Result<Option<RESP>> {
+        let resp = match (
+            self.subcommand.to_lowercase().as_str(),
+            self.args.as_slice(),
+        ) {
+            // expire the key right away and purge it
+            ("expire", [key]) => {
+                if !db.expire_now(key) {
+                    return Err(CommandError::NoSuchKey.into());
+                }
+                RESP::Simple("OK".to_string())
+            }
+            ("object", [key]) => match db.lookup_object(key) {
+                Some((address, value)) => RESP::Simple(object_line(address, &value)),
+                None => return Err(CommandError::NoSuchKey.into()),
+            },
+            _ => return Err(CommandError::UnknownSubcommand("DEBUG", self.subcommand).into()),
+        };
+
+        Ok(Some(resp))
+    }
+}
+
+/// The `DEBUG OBJECT` line, laid out like redis so tests grepping it
+/// for a field find it
+///
+/// Values are never shared so the refcount is always 1. Reads aren't
+/// tracked, the idle time counts from when the value was stored
+fn object_line(address: usize, value: &Value) -> String {
+    // redis keeps the LRU clock in 24 bits of seconds
+    const LRU_CLOCK_MAX: u64 = (1 << 24) - 1;
+
+    let idle = value._created_at.elapsed().as_secs();
+    let unix_now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|now| now.as_secs())
+        .unwrap_or_default();
+
+    format!(
+        "Value at:{:#x} refcount:1 encoding:{} serializedlength:{} lru:{} lru_seconds_idle:{}",
+        address,
+        value.data.encoding(),
+        serialized_length(&value.data),
+        unix_now.saturating_sub(idle) & LRU_CLOCK_MAX,
+        idle
+    )
+}
+
+/// Convert Debug command back into an equivalent `RESP`
+impl From<Debug> for RESP {
+    fn from(value: Debug) -> Self {
+        let mut resp = RESP::array();
+        resp.push_bulk(Bytes::from("debug"));
+        resp.push_bulk(Bytes::from(value.subcommand));
+        for arg in value.args {
+            resp.push_bulk(Bytes::from(arg));
+        }
+        resp
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::{HashMap, VecDeque};
+
+    use bytes::Bytes;
+    use tokio::time::Instant;
+
+    use super::Debug;
+    use crate::{
+        config::EncodingConfig, resp::RESP, CommandError, Db, Hash, Set, StreamData, ValueType,
+        ZSet,
+    };
+
+    /// Split a `DEBUG OBJECT` line into its `name:value` fields
+    async fn debug_object(db: &Db, key: &str) -> Vec<(String, String)> {
+        let resp = Debug::new("OBJECT".into(), vec![key.into()])
+            .apply(db)
+            .await
+            .unwrap();
+        let Some(RESP::Simple(line)) = resp else {
+            panic!("expected a simple string, got {resp:?}");
+        };
+
+        let line = line.strip_prefix("Value ").expect(&line);
+        line.split(' ')
+            .map(|field| {
+                let (name, value) = field.split_once(':').expect(field);
+                (name.to_string(), value.to_string())
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn debug_object_line_for_every_type() {
+        let db = Db::new();
+        let config = EncodingConfig::default();
+
+        let mut hash = Hash::new();
+        hash.insert("field".into(), "value".into(), &config);
+        let mut set = Set::new();
+        set.insert("member".into(), &config);
+        let mut zset = ZSet::new();
+        zset.insert("member".into(), 1.0);
+        let entry = StreamData {
+            id: (1, 1),
+            pairs: HashMap::from([("field".into(), "value".into())]),
+            _created_at: Instant::now(),
+        };
+
+        let values = [
+            (
+                "string",
+                ValueType::String(Bytes::from("hello")),
+                "embstr",
+                6,
+            ),
+            (
+                "list",
+                ValueType::List(VecDeque::from([Bytes::from("a"), Bytes::from("b")])),
+                "quicklist",
+                5,
+            ),
+            ("hash", ValueType::Hash(hash), "listpack", 13),
+            ("set", ValueType::Set(set), "listpack", 8),
+            ("zset", ValueType::ZSet(zset), "skiplist", 16),
+            (
+                "stream",
+                ValueType::Stream(vec![entry].into()),
+                "stream",
+                69,
+            ),
+        ];
+
+        for (key, value, encoding, length) in values {
+            db.set(key.into(), value, None);
+
+            let fields = debug_object(&db, key).await;
+            let names: Vec<&str> = fields.iter().map(|(name, _)| name.as_str()).collect();
+            assert_eq!(
+                names,
+                [
+                    "at",
+                    "refcount",
+                    "encoding",
+                    "serializedlength",
+                    "lru",
+                    "lru_seconds_idle"
+                ],
+                "{key}"
+            );
+
+            let field = |name: &str| &fields.iter().find(|(n, _)| n == name).unwrap().1;
+            assert!(field("at").starts_with("0x"), "{key}");
+            assert_eq!(field("refcount"), "1", "{key}");
+            assert_eq!(field("encoding"), encoding, "{key}");
+            assert_eq!(field("serializedlength"), &length.to_string(), "{key}");
+            assert!(field("lru").parse::<u64>().unwrap() < 1 << 24, "{key}");
+            assert_eq!(field("lru_seconds_idle"), "0", "{key}");
+        }
+
+        // the same value reports the same address
+        assert_eq!(
+            debug_object(&db, "hash").await[0],
+            debug_object(&db, "hash").await[0]
+        );
+    }
+
+    #[tokio::test]
+    async fn debug_expire_purges_key() {
+        let db = Db::new();
+        db.set("key".into(), ValueType::String(Bytes::from("1")), None);
+
+        let resp = Debug::new("EXPIRE".into(), vec!["key".into()])
+            .apply(&db)
+            .await
+            .unwrap();
+
+        assert!(matches!(resp, Some(RESP::Simple(s)) if s == "OK"));
+        assert!(db.keys().is_empty());
+        assert!(db.inner.state.lock().unwrap().next_expiration().is_none());
+
+        let err = Debug::new("EXPIRE".into(), vec!["key".into()])
+            .apply(&db)
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<CommandError>(),
+            Some(&CommandError::NoSuchKey)
+        );
+    }
+}
@@ -0,0 +1,119 @@
+use bytes::Bytes;
+
+use crate::{resp::RESP, Db, RespReader, RespReaderError};
+
+#[derive(Debug, Default)]
+pub struct Del {
+    /// keys to remove from the cache
+    keys: Vec<String>,
+}
+
+impl Del {
+    /// contruct new Del command
+    pub fn new(keys: Vec<String>) -> Self {
+        Del { keys }
+    }
+
+    /// Construct new Del command by consuming the RespReader
+    ///
+    /// Every remaining entry in the reader is a key to delete
+    pub fn from_parts(reader: &mut RespReader) -> Result<Self, RespReaderError> {
+        let mut keys = vec![reader.next_string()?];
+
+        while let Ok(key) = reader.next_string() {
+            keys.push(key);
+        }
+
+        Ok(Del { keys })
+    }
+
+    /// Apply the del command and return the number of removed keys
+    pub async fn apply(self, db: &Db) -> crate::Result<Option<RESP>> {
+        let removed = db.remove_all(&self.keys);
+
+        Ok(Some(RESP::Integer(removed as i64)))
+    }
+}
+
+/// Convert Del command back into an equivalent `RESP`
+impl From<Del> for RESP {
+    fn from(value: Del) -> Self {
+        let mut resp = RESP::array();
+        resp.push_bulk(Bytes::from("del"));
+        for key in value.keys {
+            resp.push_bulk(Bytes::from(key));
+        }
+        resp
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bytes::Bytes;
+
+    use super::Del;
+    use crate::{resp::RESP, Db, ValueType};
+
+    #[tokio::test]
+    async fn del_counts_existing_keys() {
+        let db = Db::new();
+        db.set("a".into(), ValueType::String(Bytes::from("1")), None);
+        db.set(
+            "b".into(),
+            ValueType::String(Bytes::from("2")),
+            Some(std::time::Duration::from_secs(60)),
+        );
+
+        let del = Del::new(vec!["a".into(), "b".into(), "missing".into()]);
+        let resp = del.apply(&db).await.unwrap();
+
+        assert!(matches!(resp, Some(RESP::Integer(2))));
+        assert!(db.get("a").is_none());
+        assert!(db.get("b").is_none());
+        assert!(db.inner.state.lock().unwrap().next_expiration().is_none());
+
+        let resp = Del::new(vec!["a".into()]).apply(&db).await.unwrap();
+        assert!(matches!(resp, Some(RESP::Integer(0))));
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn concurrent_overlapping_dels_remove_each_key_once() {
+        const KEYS: usize = 100;
+        let db = Db::new();
+        for i in 0..KEYS {
+            db.set(format!("key{i}"), ValueType::String(Bytes::from("1")), None);
+        }
+
+        // every task deletes an overlapping window of keys, half of
+        // them in reverse order
+        let tasks = (0..32)
+            .map(|task| {
+                let db = db.clone();
+                let mut keys = (0..20)
+                    .map(|i| format!("key{}", (task * 7 + i) % KEYS))
+                    .collect::<Vec<_>>();
+                if task % 2 == 1 {
+                    keys.reverse();
+                }
+                tokio::spawn(async move {
+                    match Del::new(keys).apply(&db).await.unwrap() {
+                        Some(RESP::Integer(removed)) => removed,
+                        other => panic!("unexpected DEL reply {other:?}"),
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let mut removed = 0;
+        for task in tasks {
+            removed += tokio::time::timeout(std::time::Duration::from_secs(5), task)
+                .await
+                .expect("DEL deadlocked")
+                .unwrap();
+        }
+
+        // 32 windows of 20 keys 7 apart cover all 100 keys
+        assert_eq!(removed, KEYS as i64);
+        assert!(db.keys().is_empty());
+    }
+}
@@ -0,0 +1,82 @@
+use bytes::Bytes;
+
+use crate::resp::RESP;
+
+/// Argument of a command as reported by COMMAND DOCS
+#[derive(Debug)]
+pub struct ArgDoc {
+    pub name: &'static str,
+    /// redis argument type, `key`, `string`, `integer`, `pure-token`...
+    pub kind: &'static str,
+    /// `optional` and/or `multiple`
+    pub flags: &'static [&'static str],
+}
+
+/// Documentation of a supported command
+#[derive(Debug)]
+pub struct CommandDoc {
+    pub summary: &'static str,
+    /// redis version the command was introduced in
+    pub since: &'static str,
+    pub group: &'static str,
+    pub arguments: &'static [ArgDoc],
+}
+
+pub const fn arg(name: &'static str, kind: &'static str) -> ArgDoc {
+    ArgDoc {
+        name,
+        kind,
+        flags: &[],
+    }
+}
+
+pub const fn optional(name: &'static str, kind: &'static str) -> ArgDoc {
+    ArgDoc {
+        name,
+        kind,
+        flags: &["optional"],
+    }
+}
+
+pub const fn multiple(name: &'static str, kind: &'static str) -> ArgDoc {
+    ArgDoc {
+        name,
+        kind,
+        flags: &["multiple"],
+    }
+}
+
+/// Encode the doc as a map of field name to value, RESP2 clients get
+/// it flattened to an array
+impl From<&CommandDoc> for RESP {
+    fn from(doc: &CommandDoc) -> Self {
+        let bulk = |value: &'static str| RESP::Bulk(Bytes::from(value));
+
+        let arguments = doc
+            .arguments
+            .iter()
+            .map(|arg| {
+                let mut fields = vec![
+                    (bulk("name"), bulk(arg.name)),
+                    (bulk("type"), bulk(arg.kind)),
+                ];
+                if !arg.flags.is_empty() {
+                    let flags = arg
+                        .flags
+                        .iter()
+                        .map(|flag| RESP::Simple(flag.to_string()))
+                        .collect();
+                    fields.push((bulk("flags"), RESP::Array(flags)));
+                }
+                RESP::Map(fields)
+            })
+            .collect();
+
+        RESP::Map(vec![
+            (bulk("summary"), bulk(doc.summary)),
+            (bulk("since"), bulk(doc.since)),
+            (bulk("group"), bulk(doc.group)),
+            (bulk("arguments"), RESP::Array(arguments)),
+        ])
+    }
+}
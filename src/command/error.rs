@@ -0,0 +1,82 @@
+use thiserror::Error;
+
+/// Errors a command can reply with
+///
+/// `Display` produces the exact error string redis replies with so
+/// `Command::apply` can write it back as a `RESP::Error` as is
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum CommandError {
+    #[error("WRONGTYPE Operation against a key holding the wrong kind of value")]
+    WrongType,
+
+    #[error("ERR wrong number of arguments for '{0}' command")]
+    WrongArity(String),
+
+    #[error("ERR value is not an integer or out of range")]
+    NotInteger,
+
+    #[error("ERR increment or decrement would overflow")]
+    Overflow,
+
+    #[error("ERR value is not a valid float")]
+    NotFloat,
+
+    #[error("ERR timeout is not a float or out of range")]
+    Timeout,
+
+    #[error("ERR hash value is not an integer")]
+    HashNotInteger,
+
+    #[error("ERR syntax error")]
+    SyntaxError,
+
+    /// Subcommand a container command like OBJECT doesn't know, or
+    /// one given the wrong number of arguments
+    #[error("ERR Unknown subcommand or wrong number of arguments for '{1}'. Try {0} HELP.")]
+    UnknownSubcommand(&'static str, String),
+
+    #[error("ERR no such key")]
+    NoSuchKey,
+
+    #[error("ERR Invalid bitfield type. Use something like i16 u8. Note that u64 is not supported but i64 is.")]
+    BitfieldType,
+
+    #[error("ERR bit offset is not an integer or out of range")]
+    BitOffset,
+
+    #[error("ERR min or max not valid string range item")]
+    LexRange,
+
+    #[error("ERR min or max is not a float")]
+    ScoreRange,
+
+    #[error("ERR string exceeds maximum allowed size (proto-max-bulk-len)")]
+    StringTooLong,
+
+    #[error("ERR Invalid stream ID specified as stream command argument")]
+    StreamId,
+
+    #[error("ERR The ID specified in XADD must be greater than 0-0")]
+    StreamIdZero,
+
+    #[error("ERR The ID specified in XADD is equal or smaller than the target stream top item")]
+    StreamIdTooSmall,
+
+    #[error("ERR Unbalanced 'xread' list of streams: for each stream key an ID or '$' must be specified.")]
+    UnbalancedStreams,
+
+    #[error("ERR The `numfields` parameter must match the number of arguments")]
+    NumFields,
+
+    #[error("ERR numkeys should be greater than 0")]
+    NumKeys,
+
+    #[error("ERR count should be greater than 0")]
+    Count,
+
+    #[error("NOPROTO unsupported protocol version")]
+    NoProto,
+
+    #[error("ERR Protocol error: {0}")]
+    Protocol(String),
+}
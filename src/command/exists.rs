@@ -0,0 +1,79 @@
+use bytes::Bytes;
+
+use crate::{resp::RESP, Db, RespReader, RespReaderError};
+
+#[derive(Debug, Default)]
+pub struct Exists {
+    /// keys to look up, duplicates are counted once per occurrence
+    keys: Vec<String>,
+}
+
+impl Exists {
+    /// contruct new Exists command
+    pub fn new(keys: Vec<String>) -> Self {
+        Exists { keys }
+    }
+
+    /// Construct new Exists command by consuming the RespReader
+    ///
+    /// Every remaining entry in the reader is a key to look up
+    pub fn from_parts(reader: &mut RespReader) -> Result<Self, RespReaderError> {
+        let mut keys = vec![reader.next_string()?];
+
+        while let Ok(key) = reader.next_string() {
+            keys.push(key);
+        }
+
+        Ok(Exists { keys })
+    }
+
+    /// Apply the exists command and return how many of the keys exist
+    pub async fn apply(self, db: &Db) -> crate::Result<Option<RESP>> {
+        let count = db.count_existing(&self.keys);
+
+        Ok(Some(RESP::Integer(count as i64)))
+    }
+}
+
+/// Convert Exists command back into an equivalent `RESP`
+impl From<Exists> for RESP {
+    fn from(value: Exists) -> Self {
+        let mut resp = RESP::array();
+        resp.push_bulk(Bytes::from("exists"));
+        for key in value.keys {
+            resp.push_bulk(Bytes::from(key));
+        }
+        resp
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use bytes::Bytes;
+
+    use super::Exists;
+    use crate::{resp::RESP, Db, ValueType};
+
+    #[tokio::test]
+    async fn exists_counts_duplicates_and_skips_expired() {
+        let db = Db::new();
+        db.set("foo".into(), ValueType::String(Bytes::from("1")), None);
+        db.set(
+            "gone".into(),
+            ValueType::String(Bytes::from("1")),
+            Some(Duration::ZERO),
+        );
+
+        let exists = Exists::new(vec![
+            "foo".into(),
+            "foo".into(),
+            "gone".into(),
+            "missing".into(),
+        ]);
+
+        let resp = exists.apply(&db).await.unwrap();
+        assert!(matches!(resp, Some(RESP::Integer(2))));
+    }
+}
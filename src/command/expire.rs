@@ -0,0 +1,181 @@
+use bytes::Bytes;
+use tokio::time::{Duration, Instant};
+
+use crate::{resp::RESP, Db, RespReader, RespReaderError};
+
+#[derive(Debug, Default)]
+pub struct Expire {
+    /// cache lookup key
+    key: String,
+
+    /// time to live in seconds, the key is deleted when it's not positive
+    seconds: i64,
+}
+
+#[derive(Debug, Default)]
+pub struct Pexpire {
+    /// cache lookup key
+    key: String,
+
+    /// time to live in milliseconds, the key is deleted when it's not positive
+    millis: i64,
+}
+
+/// Attach an expiry `ttl` from now to an existing key, a ttl that
+/// isn't positive deletes it right away
+fn expire(db: &Db, key: &str, ttl: Option<Duration>) -> RESP {
+    let found = match ttl {
+        Some(ttl) => db.set_expiry(key, Instant::now() + ttl),
+        None => db.expire_now(key),
+    };
+    if found {
+        RESP::Integer(1)
+    } else {
+        RESP::Integer(0)
+    }
+}
+
+impl Expire {
+    /// contruct new Expire command
+    pub fn new(key: String, seconds: i64) -> Self {
+        Expire { key, seconds }
+    }
+
+    /// Construct new Expire command by consuming the RespReader
+    pub fn from_parts(reader: &mut RespReader) -> Result<Self, RespReaderError> {
+        let key = reader.next_string()?;
+        let seconds = reader.next_signed_int()?;
+
+        Ok(Expire { key, seconds })
+    }
+
+    /// Apply the expire command, returns 1 if the timeout was set
+    pub async fn apply(self, db: &Db) -> crate::Result<Option<RESP>> {
+        Ok(Some(expire(
+            db,
+            &self.key,
+            (self.seconds > 0).then(|| Duration::from_secs(self.seconds as u64)),
+        )))
+    }
+}
+
+impl Pexpire {
+    /// contruct new Pexpire command
+    pub fn new(key: String, millis: i64) -> Self {
+        Pexpire { key, millis }
+    }
+
+    /// Construct new Pexpire command by consuming the RespReader
+    pub fn from_parts(reader: &mut RespReader) -> Result<Self, RespReaderError> {
+        let key = reader.next_string()?;
+        let millis = reader.next_signed_int()?;
+
+        Ok(Pexpire { key, millis })
+    }
+
+    /// Apply the pexpire command, returns 1 if the timeout was set
+    pub async fn apply(self, db: &Db) -> crate::Result<Option<RESP>> {
+        Ok(Some(expire(
+            db,
+            &self.key,
+            (self.millis > 0).then(|| Duration::from_millis(self.millis as u64)),
+        )))
+    }
+}
+
+/// Convert Expire command back into an equivalent `RESP`
+impl From<Expire> for RESP {
+    fn from(value: Expire) -> Self {
+        let mut resp = RESP::array();
+        resp.push_bulk(Bytes::from("expire"));
+        resp.push_bulk(Bytes::from(value.key));
+        resp.push_bulk(Bytes::from(value.seconds.to_string()));
+        resp
+    }
+}
+
+/// Convert Pexpire command back into an equivalent `RESP`
+impl From<Pexpire> for RESP {
+    fn from(value: Pexpire) -> Self {
+        let mut resp = RESP::array();
+        resp.push_bulk(Bytes::from("pexpire"));
+        resp.push_bulk(Bytes::from(value.key));
+        resp.push_bulk(Bytes::from(value.millis.to_string()));
+        resp
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bytes::Bytes;
+    use tokio::time::Duration;
+
+    use super::{Expire, Pexpire};
+    use crate::{resp::RESP, Db, RespReader, ValueType};
+
+    #[tokio::test]
+    async fn expire_existing_key() {
+        let db = Db::new();
+        db.set("key".into(), ValueType::String(Bytes::from("1")), None);
+
+        let resp = Expire::new("key".into(), 100).apply(&db).await.unwrap();
+        assert!(matches!(resp, Some(RESP::Integer(1))));
+        assert!(db.inner.state.lock().unwrap().next_expiration().is_some());
+
+        let resp = Pexpire::new("missing".into(), 100)
+            .apply(&db)
+            .await
+            .unwrap();
+        assert!(matches!(resp, Some(RESP::Integer(0))));
+    }
+
+    #[tokio::test]
+    async fn pexpire_replaces_previous_expiry() {
+        let db = Db::new();
+        db.set(
+            "key".into(),
+            ValueType::String(Bytes::from("1")),
+            Some(Duration::from_secs(100)),
+        );
+
+        Pexpire::new("key".into(), 10).apply(&db).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(20)).await;
+
+        assert!(db.get("key").is_none());
+    }
+
+    #[tokio::test]
+    async fn non_positive_ttls_delete_the_key() {
+        let db = Db::new();
+        for key in ["a", "b", "c"] {
+            db.set(key.into(), ValueType::String(Bytes::from("1")), None);
+        }
+
+        let parse = |args: &[&str]| {
+            let parts = args
+                .iter()
+                .map(|arg| RESP::Bulk(Bytes::from(arg.to_string())))
+                .collect();
+            RespReader::new(RESP::Array(parts)).unwrap()
+        };
+        let expire = Expire::from_parts(&mut parse(&["a", "-1"])).unwrap();
+        assert!(matches!(
+            expire.apply(&db).await.unwrap(),
+            Some(RESP::Integer(1))
+        ));
+        let pexpire = Pexpire::from_parts(&mut parse(&["b", "-1"])).unwrap();
+        assert!(matches!(
+            pexpire.apply(&db).await.unwrap(),
+            Some(RESP::Integer(1))
+        ));
+        let resp = Expire::new("c".into(), 0).apply(&db).await.unwrap();
+        assert!(matches!(resp, Some(RESP::Integer(1))));
+
+        // purged from the store, not only hidden
+        assert!(db.keys().is_empty());
+        assert!(db.inner.state.lock().unwrap().next_expiration().is_none());
+
+        let resp = Expire::new("missing".into(), -1).apply(&db).await.unwrap();
+        assert!(matches!(resp, Some(RESP::Integer(0))));
+    }
+}
@@ -1,6 +1,6 @@
 use bytes::Bytes;
 
-use crate::{connection::Connection, resp::RESP, Db, RespReader, RespReaderError, ValueType};
+use crate::{resp::RESP, CommandError, Db, RespReader, RespReaderError, ValueType};
 
 #[derive(Debug, Default)]
 pub struct Get {
@@ -27,16 +27,17 @@ impl Get {
     }
 
     /// Apply the echo command and write to the Tcp connection stream
-    pub async fn apply(self, db: &Db, _dst: &mut Connection) -> crate::Result<Option<RESP>> {
+    pub async fn apply(self, db: &Db) -> crate::Result<Option<RESP>> {
         // set the value in the shared cache.
         let value = db.get(&self.key);
 
         let response = if let Some(value) = value {
             match value {
-                ValueType::Stream(_) => {
-                    // println!("Get stream: {:?}", stream);
-                    RESP::Null
-                }
+                ValueType::Stream(_)
+                | ValueType::Hash(_)
+                | ValueType::ZSet(_)
+                | ValueType::List(_)
+                | ValueType::Set(_) => return Err(CommandError::WrongType.into()),
                 ValueType::String(bytes) => RESP::Bulk(bytes),
             }
         } else {
@@ -57,3 +58,39 @@ impl From<Get> for RESP {
         resp
     }
 }
+
+#[cfg(test)]
+mod test {
+    use bytes::Bytes;
+
+    use super::Get;
+    use crate::{resp::RESP, set::Set, CommandError, Db, Stream, ValueType};
+
+    #[tokio::test]
+    async fn set_get_binary_value() {
+        let db = Db::new();
+        let value = Bytes::from_static(&[0x00, 0xff, 0xfe, b'\r', b'\n', 0xc3]);
+
+        Set::new("key".into(), value.clone(), None)
+            .apply(&db)
+            .await
+            .unwrap();
+
+        match Get::new("key".into()).apply(&db).await.unwrap() {
+            Some(RESP::Bulk(bytes)) => assert_eq!(bytes, value),
+            other => panic!("Expected bulk reply, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn get_stream_is_wrong_type() {
+        let db = Db::new();
+        db.set("key".into(), ValueType::Stream(Stream::default()), None);
+
+        let err = Get::new("key".into()).apply(&db).await.unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<CommandError>(),
+            Some(CommandError::WrongType)
+        ));
+    }
+}
@@ -0,0 +1,100 @@
+use bytes::Bytes;
+
+use crate::{resp::RESP, CommandError, Db, RespReader, RespReaderError, ValueType};
+
+#[derive(Debug, Default)]
+pub struct GetSet {
+    /// cache lookup key
+    key: String,
+
+    /// value to store in db
+    value: Bytes,
+}
+
+impl GetSet {
+    /// contruct new GetSet command
+    pub fn new(key: String, value: Bytes) -> Self {
+        GetSet { key, value }
+    }
+
+    /// Construct new GetSet command by consuming the RespReader
+    pub fn from_parts(reader: &mut RespReader) -> Result<Self, RespReaderError> {
+        let key = reader.next_string()?;
+        let value = reader.next_byte()?;
+
+        Ok(GetSet { key, value })
+    }
+
+    /// Apply the getset command and return the previous value
+    pub async fn apply(self, db: &Db) -> crate::Result<Option<RESP>> {
+        let old = db.get_set(self.key, ValueType::String(self.value))?;
+
+        match old {
+            Some(ValueType::String(bytes)) => Ok(Some(RESP::Bulk(bytes))),
+            Some(_) => Err(CommandError::WrongType.into()),
+            None => Ok(Some(RESP::Null)),
+        }
+    }
+}
+
+/// Convert GetSet command back into an equivalent `RESP`
+impl From<GetSet> for RESP {
+    fn from(value: GetSet) -> Self {
+        let mut resp = RESP::array();
+        resp.push_bulk(Bytes::from("getset"));
+        resp.push_bulk(Bytes::from(value.key));
+        resp.push_bulk(value.value);
+        resp
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bytes::Bytes;
+    use tokio::time::Duration;
+
+    use super::GetSet;
+    use crate::{resp::RESP, CommandError, Db, Stream, ValueType};
+
+    #[tokio::test]
+    async fn getset_returns_previous_value() {
+        let db = Db::new();
+
+        let resp = GetSet::new("key".into(), Bytes::from("a"))
+            .apply(&db)
+            .await
+            .unwrap();
+        assert!(matches!(resp, Some(RESP::Null)));
+
+        db.set(
+            "key".into(),
+            ValueType::String(Bytes::from("b")),
+            Some(Duration::from_secs(100)),
+        );
+        let resp = GetSet::new("key".into(), Bytes::from("c"))
+            .apply(&db)
+            .await
+            .unwrap();
+        assert!(matches!(resp, Some(RESP::Bulk(old)) if old == "b"));
+        assert!(matches!(db.get("key"), Some(ValueType::String(new)) if new == "c"));
+
+        // the new value doesn't inherit the expiry
+        assert!(db.inner.state.lock().unwrap().next_expiration().is_none());
+    }
+
+    #[tokio::test]
+    async fn getset_on_stream_is_wrong_type() {
+        let db = Db::new();
+        db.set("key".into(), ValueType::Stream(Stream::default()), None);
+
+        let err = GetSet::new("key".into(), Bytes::from("a"))
+            .apply(&db)
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<CommandError>(),
+            Some(&CommandError::WrongType)
+        );
+        assert!(matches!(db.get("key"), Some(ValueType::Stream(_))));
+    }
+}
@@ -0,0 +1,117 @@
+use bytes::Bytes;
+
+use crate::{resp::RESP, Db, RespReader, RespReaderError};
+
+#[derive(Debug, Default)]
+pub struct HDel {
+    /// cache lookup key
+    key: String,
+
+    /// fields to remove
+    fields: Vec<String>,
+}
+
+impl HDel {
+    /// contruct new HDel command
+    pub fn new(key: String, fields: Vec<String>) -> Self {
+        HDel { key, fields }
+    }
+
+    /// Construct new HDel command by consuming the RespReader
+    pub fn from_parts(reader: &mut RespReader) -> Result<Self, RespReaderError> {
+        let key = reader.next_string()?;
+
+        let mut fields = vec![reader.next_string()?];
+        while let Ok(field) = reader.next_string() {
+            fields.push(field);
+        }
+
+        Ok(HDel { key, fields })
+    }
+
+    /// Apply the hdel command and return the number of removed fields
+    ///
+    /// The key is deleted once the last field is removed
+    pub async fn apply(self, db: &Db) -> crate::Result<Option<RESP>> {
+        let removed = db.hash_op(&self.key, "hdel", |hash| {
+            let removed = self
+                .fields
+                .iter()
+                .filter(|field| hash.remove(field))
+                .count();
+            (removed, removed > 0)
+        })?;
+
+        Ok(Some(RESP::Integer(removed as i64)))
+    }
+}
+
+/// Convert HDel command back into an equivalent `RESP`
+impl From<HDel> for RESP {
+    fn from(value: HDel) -> Self {
+        let mut resp = RESP::array();
+        resp.push_bulk(Bytes::from("hdel"));
+        resp.push_bulk(Bytes::from(value.key));
+        for field in value.fields {
+            resp.push_bulk(Bytes::from(field));
+        }
+        resp
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use bytes::Bytes;
+
+    use super::HDel;
+    use crate::{resp::RESP, CommandError, Db, Hash, ValueType};
+
+    #[tokio::test]
+    async fn removing_the_last_field_deletes_the_key() {
+        let db = Db::new();
+        let hash = Hash::from(HashMap::from([
+            ("a".to_string(), "1".to_string()),
+            ("b".to_string(), "2".to_string()),
+        ]));
+        db.set("hash".into(), ValueType::Hash(hash), None);
+
+        let resp = HDel::new("hash".into(), vec!["a".into(), "missing".into()])
+            .apply(&db)
+            .await
+            .unwrap();
+        assert!(matches!(resp, Some(RESP::Integer(1))));
+        assert!(db.get("hash").is_some());
+
+        let resp = HDel::new("hash".into(), vec!["b".into()])
+            .apply(&db)
+            .await
+            .unwrap();
+        assert!(matches!(resp, Some(RESP::Integer(1))));
+        assert!(db.get("hash").is_none());
+
+        // nothing to remove from a missing key, and nothing is created
+        let resp = HDel::new("hash".into(), vec!["b".into()])
+            .apply(&db)
+            .await
+            .unwrap();
+        assert!(matches!(resp, Some(RESP::Integer(0))));
+        assert!(db.get("hash").is_none());
+    }
+
+    #[tokio::test]
+    async fn hdel_on_a_string_is_a_wrong_type_error() {
+        let db = Db::new();
+        db.set("key".into(), ValueType::String(Bytes::from("v")), None);
+
+        let err = HDel::new("key".into(), vec!["a".into()])
+            .apply(&db)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<CommandError>(),
+            Some(CommandError::WrongType)
+        ));
+    }
+}
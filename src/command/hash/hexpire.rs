@@ -0,0 +1,259 @@
+use bytes::Bytes;
+use tokio::time::{Duration, Instant};
+
+use crate::{
+    command::ttl::round_to_seconds, resp::RESP, CommandError, Db, RespReader, RespReaderError,
+    ValueType,
+};
+
+#[derive(Debug, Default)]
+pub struct HExpire {
+    /// cache lookup key
+    key: String,
+
+    /// time to live of the fields
+    seconds: u64,
+
+    /// fields to expire
+    fields: Vec<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct HTtl {
+    /// cache lookup key
+    key: String,
+
+    /// fields whose time to live is returned
+    fields: Vec<String>,
+}
+
+impl HExpire {
+    /// contruct new HExpire command
+    pub fn new(key: String, seconds: u64, fields: Vec<String>) -> Self {
+        HExpire {
+            key,
+            seconds,
+            fields,
+        }
+    }
+
+    /// Construct new HExpire command by consuming the RespReader
+    pub fn from_parts(reader: &mut RespReader) -> Result<Self, RespReaderError> {
+        let key = reader.next_string()?;
+        let seconds = reader.next_int()?;
+        let fields = parse_fields(reader)?;
+
+        Ok(HExpire {
+            key,
+            seconds,
+            fields,
+        })
+    }
+
+    /// Apply the hexpire command and return the status of every field,
+    /// -2 if there's no such field, 1 if the TTL was set and 2 if the
+    /// field was deleted because the TTL is 0
+    pub async fn apply(self, db: &Db) -> crate::Result<Option<RESP>> {
+        let at = Instant::now() + Duration::from_secs(self.seconds);
+        let statuses = db.hexpire(&self.key, &self.fields, at)?;
+
+        let mut resp = RESP::array();
+        for status in statuses {
+            resp.push(RESP::Integer(status));
+        }
+
+        Ok(Some(resp))
+    }
+}
+
+impl HTtl {
+    /// contruct new HTtl command
+    pub fn new(key: String, fields: Vec<String>) -> Self {
+        HTtl { key, fields }
+    }
+
+    /// Construct new HTtl command by consuming the RespReader
+    pub fn from_parts(reader: &mut RespReader) -> Result<Self, RespReaderError> {
+        let key = reader.next_string()?;
+        let fields = parse_fields(reader)?;
+
+        Ok(HTtl { key, fields })
+    }
+
+    /// Apply the httl command and return the seconds left for every
+    /// field, -2 if there's no such field and -1 if it has no TTL
+    pub async fn apply(self, db: &Db) -> crate::Result<Option<RESP>> {
+        let hash = match db.get(&self.key) {
+            Some(ValueType::Hash(hash)) => Some(hash),
+            Some(_) => return Err(CommandError::WrongType.into()),
+            None => None,
+        };
+
+        let mut resp = RESP::array();
+        for field in self.fields.iter() {
+            let ttl = match hash.as_ref().and_then(|hash| hash.expires_at(field)) {
+                Some(Some(at)) => {
+                    let remaining = at.saturating_duration_since(Instant::now());
+                    round_to_seconds(remaining.as_millis() as i64)
+                }
+                Some(None) => -1,
+                None => -2,
+            };
+            resp.push(RESP::Integer(ttl));
+        }
+
+        Ok(Some(resp))
+    }
+}
+
+/// Parse `FIELDS numfields field [field ...]`
+fn parse_fields(reader: &mut RespReader) -> Result<Vec<String>, RespReaderError> {
+    if !reader.next_string()?.eq_ignore_ascii_case("fields") {
+        return Err(CommandError::SyntaxError.into());
+    }
+    let numfields = reader.next_int()? as usize;
+
+    let mut fields = vec![];
+    while let Ok(field) = reader.next_string() {
+        fields.push(field);
+    }
+
+    if numfields != fields.len() {
+        return Err(CommandError::NumFields.into());
+    }
+
+    Ok(fields)
+}
+
+fn fields_resp(resp: &mut RESP, fields: Vec<String>) {
+    resp.push_bulk(Bytes::from("FIELDS"));
+    resp.push_bulk(Bytes::from(fields.len().to_string()));
+    for field in fields {
+        resp.push_bulk(Bytes::from(field));
+    }
+}
+
+/// Convert HExpire command back into an equivalent `RESP`
+impl From<HExpire> for RESP {
+    fn from(value: HExpire) -> Self {
+        let mut resp = RESP::array();
+        resp.push_bulk(Bytes::from("hexpire"));
+        resp.push_bulk(Bytes::from(value.key));
+        resp.push_bulk(Bytes::from(value.seconds.to_string()));
+        fields_resp(&mut resp, value.fields);
+        resp
+    }
+}
+
+/// Convert HTtl command back into an equivalent `RESP`
+impl From<HTtl> for RESP {
+    fn from(value: HTtl) -> Self {
+        let mut resp = RESP::array();
+        resp.push_bulk(Bytes::from("httl"));
+        resp.push_bulk(Bytes::from(value.key));
+        fields_resp(&mut resp, value.fields);
+        resp
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use tokio::time::Duration;
+
+    use bytes::Bytes;
+
+    use super::{HExpire, HTtl};
+    use crate::{
+        command::hash::HGet, resp::RESP, CommandError, Db, Hash, RespReader, RespReaderError,
+        ValueType,
+    };
+
+    fn reader(args: &[&str]) -> RespReader {
+        let args = args
+            .iter()
+            .map(|arg| RESP::Bulk(Bytes::from(arg.to_string())))
+            .collect();
+        RespReader::new(RESP::Array(args)).unwrap()
+    }
+
+    fn integers(resp: crate::Result<Option<RESP>>) -> Vec<i64> {
+        match resp.unwrap() {
+            Some(RESP::Array(values)) => values
+                .into_iter()
+                .map(|value| match value {
+                    RESP::Integer(int) => int,
+                    value => panic!("unexpected value {value:?}"),
+                })
+                .collect(),
+            resp => panic!("expected an array, got {resp:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn expired_fields_are_removed_on_their_own() {
+        let db = Db::new();
+        let hash = Hash::from(HashMap::from([
+            ("a".to_string(), "1".to_string()),
+            ("b".to_string(), "2".to_string()),
+        ]));
+        db.set("hash".into(), ValueType::Hash(hash), None);
+
+        let fields = vec!["a".to_string(), "missing".to_string()];
+        let resp = HExpire::new("hash".into(), 1, fields).apply(&db).await;
+        assert_eq!(integers(resp), [1, -2]);
+
+        let fields = vec!["a".to_string(), "b".to_string(), "missing".to_string()];
+        let resp = HTtl::new("hash".into(), fields.clone()).apply(&db).await;
+        assert_eq!(integers(resp), [1, -1, -2]);
+
+        tokio::time::sleep(Duration::from_millis(1100)).await;
+
+        let resp = HGet::new("hash".into(), "a".into()).apply(&db).await;
+        assert!(matches!(resp, Ok(Some(RESP::Null))));
+        let resp = HGet::new("hash".into(), "b".into()).apply(&db).await;
+        assert!(matches!(resp, Ok(Some(RESP::Bulk(value))) if value == "2"));
+        let resp = HTtl::new("hash".into(), fields).apply(&db).await;
+        assert_eq!(integers(resp), [-2, -1, -2]);
+
+        // the expired field was purged without being read
+        match db.get("hash") {
+            Some(ValueType::Hash(hash)) => {
+                assert_eq!(hash.len(), 1);
+                assert!(hash.next_expiration().is_none());
+            }
+            value => panic!("expected a hash, got {value:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn expiring_the_last_field_deletes_the_hash() {
+        let db = Db::new();
+        let hash = Hash::from(HashMap::from([("a".to_string(), "1".to_string())]));
+        db.set("hash".into(), ValueType::Hash(hash), None);
+
+        let resp = HExpire::new("hash".into(), 0, vec!["a".into()])
+            .apply(&db)
+            .await;
+        assert_eq!(integers(resp), [2]);
+        assert!(db.get("hash").is_none());
+
+        let resp = HExpire::new("hash".into(), 10, vec!["a".into()])
+            .apply(&db)
+            .await;
+        assert_eq!(integers(resp), [-2]);
+    }
+
+    #[test]
+    fn malformed_fields_are_command_errors() {
+        assert!(matches!(
+            HExpire::from_parts(&mut reader(&["h", "10", "FILEDS", "1", "a"])),
+            Err(RespReaderError::Command(CommandError::SyntaxError))
+        ));
+        assert!(matches!(
+            HTtl::from_parts(&mut reader(&["h", "FIELDS", "2", "a"])),
+            Err(RespReaderError::Command(CommandError::NumFields))
+        ));
+    }
+}
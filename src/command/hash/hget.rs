@@ -0,0 +1,172 @@
+use bytes::Bytes;
+
+use crate::{resp::RESP, CommandError, Db, RespReader, RespReaderError, ValueType};
+
+#[derive(Debug, Default)]
+pub struct HGet {
+    /// cache lookup key
+    key: String,
+
+    /// field whose value is returned
+    field: String,
+}
+
+#[derive(Debug, Default)]
+pub struct HGetAll {
+    /// cache lookup key
+    key: String,
+}
+
+impl HGet {
+    /// contruct new HGet command
+    pub fn new(key: String, field: String) -> Self {
+        HGet { key, field }
+    }
+
+    /// Construct new HGet command by consuming the RespReader
+    pub fn from_parts(reader: &mut RespReader) -> Result<Self, RespReaderError> {
+        let key = reader.next_string()?;
+        let field = reader.next_string()?;
+
+        Ok(HGet { key, field })
+    }
+
+    /// Apply the hget command and return the value of the field
+    pub async fn apply(self, db: &Db) -> crate::Result<Option<RESP>> {
+        let value = match db.get(&self.key) {
+            Some(ValueType::Hash(hash)) => hash.get(&self.field).cloned(),
+            Some(_) => return Err(CommandError::WrongType.into()),
+            None => None,
+        };
+
+        Ok(Some(value.map_or(RESP::Null, |value| {
+            RESP::Bulk(Bytes::from(value))
+        })))
+    }
+}
+
+impl HGetAll {
+    /// contruct new HGetAll command
+    pub fn new(key: String) -> Self {
+        HGetAll { key }
+    }
+
+    /// Construct new HGetAll command by consuming the RespReader
+    pub fn from_parts(reader: &mut RespReader) -> Result<Self, RespReaderError> {
+        let key = reader.next_string()?;
+
+        Ok(HGetAll { key })
+    }
+
+    /// Apply the hgetall command and return every field and value
+    ///
+    /// The reply is a map, RESP2 clients get it as a flat array
+    pub async fn apply(self, db: &Db) -> crate::Result<Option<RESP>> {
+        let pairs = match db.get(&self.key) {
+            Some(ValueType::Hash(hash)) => hash
+                .iter()
+                .map(|(field, value)| {
+                    (
+                        RESP::Bulk(Bytes::from(field.clone())),
+                        RESP::Bulk(Bytes::from(value.clone())),
+                    )
+                })
+                .collect(),
+            Some(_) => return Err(CommandError::WrongType.into()),
+            None => vec![],
+        };
+
+        Ok(Some(RESP::Map(pairs)))
+    }
+}
+
+/// Convert HGet command back into an equivalent `RESP`
+impl From<HGet> for RESP {
+    fn from(value: HGet) -> Self {
+        let mut resp = RESP::array();
+        resp.push_bulk(Bytes::from("hget"));
+        resp.push_bulk(Bytes::from(value.key));
+        resp.push_bulk(Bytes::from(value.field));
+        resp
+    }
+}
+
+/// Convert HGetAll command back into an equivalent `RESP`
+impl From<HGetAll> for RESP {
+    fn from(value: HGetAll) -> Self {
+        let mut resp = RESP::array();
+        resp.push_bulk(Bytes::from("hgetall"));
+        resp.push_bulk(Bytes::from(value.key));
+        resp
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use bytes::Bytes;
+
+    use super::{HGet, HGetAll};
+    use crate::{resp::RESP, CommandError, Db, Hash, ValueType};
+
+    fn bulk(resp: &RESP) -> String {
+        match resp {
+            RESP::Bulk(value) => String::from_utf8(value.to_vec()).unwrap(),
+            resp => panic!("expected a bulk, got {resp:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn hget_and_hgetall_read_the_hash() {
+        let db = Db::new();
+        let hash = Hash::from(HashMap::from([
+            ("a".to_string(), "1".to_string()),
+            ("b".to_string(), "2".to_string()),
+        ]));
+        db.set("hash".into(), ValueType::Hash(hash), None);
+
+        let resp = HGet::new("hash".into(), "a".into())
+            .apply(&db)
+            .await
+            .unwrap();
+        assert_eq!(bulk(&resp.unwrap()), "1");
+        let resp = HGet::new("hash".into(), "c".into())
+            .apply(&db)
+            .await
+            .unwrap();
+        assert!(matches!(resp, Some(RESP::Null)));
+
+        // field order is unspecified
+        let pairs = match HGetAll::new("hash".into()).apply(&db).await.unwrap() {
+            Some(RESP::Map(pairs)) => pairs
+                .iter()
+                .map(|(field, value)| (bulk(field), bulk(value)))
+                .collect::<HashMap<_, _>>(),
+            resp => panic!("expected a map, got {resp:?}"),
+        };
+        assert_eq!(pairs.len(), 2);
+        assert_eq!(pairs["a"], "1");
+        assert_eq!(pairs["b"], "2");
+
+        let resp = HGetAll::new("missing".into()).apply(&db).await.unwrap();
+        assert!(matches!(resp, Some(RESP::Map(pairs)) if pairs.is_empty()));
+    }
+
+    #[tokio::test]
+    async fn reading_a_string_is_a_wrong_type_error() {
+        let db = Db::new();
+        db.set("key".into(), ValueType::String(Bytes::from("v")), None);
+
+        for result in [
+            HGet::new("key".into(), "a".into()).apply(&db).await,
+            HGetAll::new("key".into()).apply(&db).await,
+        ] {
+            let err = result.unwrap_err();
+            assert!(matches!(
+                err.downcast_ref::<CommandError>(),
+                Some(CommandError::WrongType)
+            ));
+        }
+    }
+}
@@ -0,0 +1,131 @@
+use bytes::Bytes;
+
+use crate::{config::ServerConfig, resp::RESP, CommandError, Db, RespReader, RespReaderError};
+
+#[derive(Debug, Default)]
+pub struct HIncrBy {
+    /// cache lookup key
+    key: String,
+
+    /// field holding the integer
+    field: String,
+
+    /// amount to add to the field
+    increment: i64,
+}
+
+impl HIncrBy {
+    /// contruct new HIncrBy command
+    pub fn new(key: String, field: String, increment: i64) -> Self {
+        HIncrBy {
+            key,
+            field,
+            increment,
+        }
+    }
+
+    /// Construct new HIncrBy command by consuming the RespReader
+    pub fn from_parts(reader: &mut RespReader) -> Result<Self, RespReaderError> {
+        let key = reader.next_string()?;
+        let field = reader.next_string()?;
+        let increment = reader.next_signed_int()?;
+
+        Ok(HIncrBy {
+            key,
+            field,
+            increment,
+        })
+    }
+
+    /// Apply the hincrby command and return the value of the field
+    ///
+    /// A missing field starts out as 0
+    pub async fn apply(self, db: &Db, config: ServerConfig) -> crate::Result<Option<RESP>> {
+        let int = db.hash_op(&self.key, "hincrby", |hash| {
+            let current = match hash.get(&self.field) {
+                Some(value) => match value.parse::<i64>() {
+                    Ok(int) => int,
+                    Err(_) => return (Err(CommandError::HashNotInteger), false),
+                },
+                None => 0,
+            };
+
+            let Some(int) = current.checked_add(self.increment) else {
+                return (Err(CommandError::Overflow), false);
+            };
+
+            hash.insert(self.field, int.to_string(), &config.encoding);
+            (Ok(int), true)
+        })??;
+
+        Ok(Some(RESP::Integer(int)))
+    }
+}
+
+/// Convert HIncrBy command back into an equivalent `RESP`
+impl From<HIncrBy> for RESP {
+    fn from(value: HIncrBy) -> Self {
+        let mut resp = RESP::array();
+        resp.push_bulk(Bytes::from("hincrby"));
+        resp.push_bulk(Bytes::from(value.key));
+        resp.push_bulk(Bytes::from(value.field));
+        resp.push_bulk(Bytes::from(value.increment.to_string()));
+        resp
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::HIncrBy;
+    use crate::{config::ServerConfig, resp::RESP, CommandError, Db, ValueType};
+
+    async fn hincrby(db: &Db, field: &str, increment: i64) -> crate::Result<Option<RESP>> {
+        HIncrBy::new("hash".into(), field.into(), increment)
+            .apply(db, ServerConfig::for_test())
+            .await
+    }
+
+    #[tokio::test]
+    async fn hincrby_creates_the_field_and_goes_negative() {
+        let db = Db::new();
+
+        let resp = hincrby(&db, "count", 5).await.unwrap();
+        assert!(matches!(resp, Some(RESP::Integer(5))));
+
+        let resp = hincrby(&db, "count", -8).await.unwrap();
+        assert!(matches!(resp, Some(RESP::Integer(-3))));
+
+        match db.get("hash") {
+            Some(ValueType::Hash(hash)) => assert_eq!(hash.get("count").unwrap(), "-3"),
+            value => panic!("expected a hash, got {value:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn hincrby_non_numeric_field_is_an_error() {
+        let db = Db::new();
+        db.hash_op("hash", "hset", |hash| {
+            hash.insert(
+                "name".into(),
+                "redis".into(),
+                &ServerConfig::for_test().encoding,
+            );
+            ((), true)
+        })
+        .unwrap();
+
+        let err = hincrby(&db, "name", 1).await.unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<CommandError>(),
+            Some(&CommandError::HashNotInteger)
+        );
+        assert_eq!(err.to_string(), "ERR hash value is not an integer");
+
+        hincrby(&db, "count", i64::MAX).await.unwrap();
+        let err = hincrby(&db, "count", 1).await.unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<CommandError>(),
+            Some(&CommandError::Overflow)
+        );
+    }
+}
@@ -0,0 +1,111 @@
+use bytes::Bytes;
+
+use crate::{config::ServerConfig, resp::RESP, Db, RespReader, RespReaderError};
+
+#[derive(Debug, Default)]
+pub struct HSet {
+    /// cache lookup key
+    key: String,
+
+    /// field-value pairs to set, existing fields are overwritten
+    pairs: Vec<(String, String)>,
+}
+
+impl HSet {
+    /// contruct new HSet command
+    pub fn new(key: String, pairs: Vec<(String, String)>) -> Self {
+        HSet { key, pairs }
+    }
+
+    /// Construct new HSet command by consuming the RespReader
+    pub fn from_parts(reader: &mut RespReader) -> Result<Self, RespReaderError> {
+        let key = reader.next_string()?;
+
+        let mut pairs = vec![(reader.next_string()?, reader.next_string()?)];
+        while let Ok(field) = reader.next_string() {
+            pairs.push((field, reader.next_string()?));
+        }
+
+        Ok(HSet { key, pairs })
+    }
+
+    /// Apply the hset command and return the number of new fields
+    pub async fn apply(self, db: &Db, config: ServerConfig) -> crate::Result<Option<RESP>> {
+        let added = db.hash_op(&self.key, "hset", |hash| {
+            let added = self
+                .pairs
+                .into_iter()
+                .filter(|(field, value)| {
+                    hash.insert(field.clone(), value.clone(), &config.encoding)
+                })
+                .count();
+            (added, true)
+        })?;
+
+        Ok(Some(RESP::Integer(added as i64)))
+    }
+}
+
+/// Convert HSet command back into an equivalent `RESP`
+impl From<HSet> for RESP {
+    fn from(value: HSet) -> Self {
+        let mut resp = RESP::array();
+        resp.push_bulk(Bytes::from("hset"));
+        resp.push_bulk(Bytes::from(value.key));
+        for (field, value) in value.pairs {
+            resp.push_bulk(Bytes::from(field));
+            resp.push_bulk(Bytes::from(value));
+        }
+        resp
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bytes::Bytes;
+
+    use super::HSet;
+    use crate::{config::ServerConfig, resp::RESP, CommandError, Db, ValueType};
+
+    #[tokio::test]
+    async fn hset_counts_new_fields_and_overwrites_existing_ones() {
+        let db = Db::new();
+
+        let pairs = vec![("a".into(), "1".into()), ("b".into(), "2".into())];
+        let resp = HSet::new("hash".into(), pairs)
+            .apply(&db, ServerConfig::for_test())
+            .await
+            .unwrap();
+        assert!(matches!(resp, Some(RESP::Integer(2))));
+
+        let pairs = vec![("b".into(), "3".into()), ("c".into(), "4".into())];
+        let resp = HSet::new("hash".into(), pairs)
+            .apply(&db, ServerConfig::for_test())
+            .await
+            .unwrap();
+        assert!(matches!(resp, Some(RESP::Integer(1))));
+
+        match db.get("hash") {
+            Some(ValueType::Hash(hash)) => {
+                assert_eq!(hash.len(), 3);
+                assert_eq!(hash.get("b").unwrap(), "3");
+            }
+            value => panic!("expected a hash, got {value:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn hset_on_a_string_is_a_wrong_type_error() {
+        let db = Db::new();
+        db.set("key".into(), ValueType::String(Bytes::from("v")), None);
+
+        let err = HSet::new("key".into(), vec![("a".into(), "1".into())])
+            .apply(&db, ServerConfig::for_test())
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<CommandError>(),
+            Some(CommandError::WrongType)
+        ));
+    }
+}
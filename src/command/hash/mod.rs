@@ -0,0 +1,11 @@
+pub mod hdel;
+pub mod hexpire;
+pub mod hget;
+pub mod hincrby;
+pub mod hset;
+
+pub use hdel::HDel;
+pub use hexpire::{HExpire, HTtl};
+pub use hget::{HGet, HGetAll};
+pub use hincrby::HIncrBy;
+pub use hset::HSet;
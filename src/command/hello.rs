@@ -0,0 +1,159 @@
+use bytes::Bytes;
+
+use crate::{
+    config::ServerConfig, connection::Connection, resp::RESP, CommandError, RespReader,
+    RespReaderError,
+};
+
+#[derive(Debug, Default)]
+pub struct Hello {
+    /// protocol version to switch to, the connection keeps
+    /// its current version when omitted
+    protover: Option<String>,
+
+    /// AUTH and SETNAME options, not supported
+    args: Vec<String>,
+}
+
+impl Hello {
+    /// contruct new Hello command
+    pub fn new(protover: Option<String>) -> Self {
+        Hello {
+            protover,
+            args: vec![],
+        }
+    }
+
+    /// Construct new Hello command by consuming the RespReader
+    ///
+    /// The protocol version is validated when the command is applied
+    pub fn from_parts(reader: &mut RespReader) -> Result<Self, RespReaderError> {
+        let protover = match reader.next_string() {
+            Ok(protover) => Some(protover),
+            Err(RespReaderError::EndOfStream) => None,
+            Err(err) => return Err(err),
+        };
+
+        let mut args = vec![];
+        while let Ok(arg) = reader.next_string() {
+            args.push(arg);
+        }
+
+        Ok(Hello { protover, args })
+    }
+
+    /// Apply the hello command, switching the connection to the requested
+    /// protocol version and replying with the server details
+    pub async fn apply(
+        self,
+        dst: &mut Connection,
+        config: ServerConfig,
+    ) -> crate::Result<Option<RESP>> {
+        if let Some(protover) = self.protover {
+            dst.protocol_version = match protover.parse::<u8>() {
+                Ok(version @ (2 | 3)) => version,
+                Ok(_) => return Err(CommandError::NoProto.into()),
+                Err(_) => {
+                    return Ok(Some(RESP::Error(
+                        "ERR Protocol version is not an integer or out of range".to_string(),
+                    )))
+                }
+            };
+        }
+
+        if !self.args.is_empty() {
+            return Err(CommandError::SyntaxError.into());
+        }
+
+        let field = |name: &'static str| RESP::Bulk(Bytes::from(name));
+        let resp = RESP::Map(vec![
+            (field("server"), field("redis")),
+            (field("version"), field(env!("CARGO_PKG_VERSION"))),
+            (field("proto"), RESP::Integer(dst.protocol_version as i64)),
+            (
+                field("role"),
+                RESP::Bulk(Bytes::from(config.role.to_string())),
+            ),
+        ]);
+
+        Ok(Some(resp))
+    }
+}
+
+/// Convert Hello command back into an equivalent `RESP`
+impl From<Hello> for RESP {
+    fn from(value: Hello) -> Self {
+        let mut resp = RESP::array();
+        resp.push_bulk(Bytes::from("hello"));
+        if let Some(protover) = value.protover {
+            resp.push_bulk(Bytes::from(protover));
+        }
+        for arg in value.args {
+            resp.push_bulk(Bytes::from(arg));
+        }
+        resp
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use tokio::io::{duplex, AsyncReadExt};
+
+    use super::Hello;
+    use crate::{config::ServerConfig, connection::Connection, resp::RESP};
+
+    /// Write `resp` to the connection and read back the raw bytes
+    async fn written(
+        connection: &mut Connection,
+        client: &mut tokio::io::DuplexStream,
+        resp: &RESP,
+    ) -> String {
+        connection.write_frame(resp).await.unwrap();
+
+        let mut buf = vec![0; 1024];
+        let len = client.read(&mut buf).await.unwrap();
+        String::from_utf8(buf[..len].to_vec()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn hello_3_switches_to_resp3() {
+        let (mut client, server) = duplex(1024);
+        let mut connection = Connection::from_stream(server, false);
+
+        let resp = Hello::new(Some("3".into()))
+            .apply(&mut connection, ServerConfig::for_test())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(connection.protocol_version, 3);
+
+        let reply = written(&mut connection, &mut client, &resp).await;
+        assert!(reply.starts_with("%4\r\n$6\r\nserver\r\n$5\r\nredis\r\n"));
+        assert!(reply.contains("$5\r\nproto\r\n:3\r\n$4\r\nrole\r\n$6\r\nmaster\r\n"));
+
+        let values = RESP::Array(vec![RESP::Double(1.5), RESP::Boolean(true), RESP::Null]);
+        let reply = written(&mut connection, &mut client, &values).await;
+        assert_eq!(reply, "*3\r\n,1.5\r\n#t\r\n_\r\n");
+    }
+
+    #[tokio::test]
+    async fn resp2_falls_back_for_resp3_types() {
+        let (mut client, server) = duplex(1024);
+        let mut connection = Connection::from_stream(server, false);
+
+        let values = RESP::Array(vec![
+            RESP::Map(vec![(RESP::Bulk("a".into()), RESP::Double(2.0))]),
+            RESP::Boolean(false),
+            RESP::Null,
+        ]);
+        let reply = written(&mut connection, &mut client, &values).await;
+        assert_eq!(reply, "*3\r\n*2\r\n$1\r\na\r\n$1\r\n2\r\n:0\r\n$-1\r\n");
+        assert_eq!(values.encoded_len(), reply.len());
+
+        let resp = Hello::new(Some("4".into()))
+            .apply(&mut connection, ServerConfig::for_test())
+            .await;
+        assert!(resp.is_err());
+        assert_eq!(connection.protocol_version, 2);
+    }
+}
@@ -1,6 +1,6 @@
 use bytes::Bytes;
 
-use crate::{connection::Connection, resp::RESP, Db, RespReader, RespReaderError, ValueType};
+use crate::{resp::RESP, CommandError, Db, RespReader, RespReaderError};
 
 #[derive(Debug, Default)]
 pub struct Incr {
@@ -25,38 +25,94 @@ impl Incr {
         Ok(Incr { key })
     }
 
-    /// Apply the echo command and write to the Tcp connection stream
-    pub async fn apply(self, db: &Db, _dst: &mut Connection) -> crate::Result<Option<RESP>> {
-        let value = db.get(&self.key);
-
-        #[allow(unused_assignments)]
-        let mut resp = RESP::Simple("OKi".into());
-
-        match value {
-            Some(value_type) => match value_type {
-                ValueType::String(value) => {
-                    let int = String::from_utf8(value.to_vec()).unwrap().parse::<u64>();
-                    if let Ok(int) = int {
-                        db.set(
-                            self.key,
-                            ValueType::String(Bytes::from(format!("{}", int + 1))),
-                            None,
-                        );
-                        resp = RESP::Integer(int + 1);
-                    } else {
-                        // unimplemented!("Value exists but it is not a numerical value");
-                        resp = RESP::Error("ERR value is not an integer or out of range".into());
-                    }
-                }
-                ValueType::Stream(_) => unimplemented!("The value is a stream"),
-            },
-            None => {
-                db.set(self.key, ValueType::String(Bytes::from("1")), None);
-                resp = RESP::Integer(1);
-            }
-        }
+    /// Apply the incr command, incrementing the value by one
+    pub async fn apply(self, db: &Db) -> crate::Result<Option<RESP>> {
+        let int = db.incr_by(self.key, 1)?;
+        Ok(Some(RESP::Integer(int)))
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct IncrBy {
+    /// cache lookup key to increment
+    key: String,
+
+    /// amount to add to the value
+    increment: i64,
+}
+
+impl IncrBy {
+    /// contruct new IncrBy command
+    pub fn new(key: String, increment: i64) -> Self {
+        IncrBy { key, increment }
+    }
+
+    /// Construct new IncrBy command by consuming the RespReader
+    pub fn from_parts(reader: &mut RespReader) -> Result<Self, RespReaderError> {
+        let key = reader.next_string()?;
+        let increment = reader.next_signed_int()?;
+        Ok(IncrBy { key, increment })
+    }
+
+    /// Apply the incrby command, incrementing the value by `increment`
+    pub async fn apply(self, db: &Db) -> crate::Result<Option<RESP>> {
+        let int = db.incr_by(self.key, self.increment)?;
+        Ok(Some(RESP::Integer(int)))
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Decr {
+    /// cache lookup key to decrement
+    key: String,
+}
 
-        Ok(Some(resp))
+impl Decr {
+    /// contruct new Decr command
+    pub fn new(key: String) -> Self {
+        Decr { key }
+    }
+
+    /// Construct new Decr command by consuming the RespReader
+    pub fn from_parts(reader: &mut RespReader) -> Result<Self, RespReaderError> {
+        let key = reader.next_string()?;
+        Ok(Decr { key })
+    }
+
+    /// Apply the decr command, decrementing the value by one
+    pub async fn apply(self, db: &Db) -> crate::Result<Option<RESP>> {
+        let int = db.incr_by(self.key, -1)?;
+        Ok(Some(RESP::Integer(int)))
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct DecrBy {
+    /// cache lookup key to decrement
+    key: String,
+
+    /// amount to subtract from the value
+    decrement: i64,
+}
+
+impl DecrBy {
+    /// contruct new DecrBy command
+    pub fn new(key: String, decrement: i64) -> Self {
+        DecrBy { key, decrement }
+    }
+
+    /// Construct new DecrBy command by consuming the RespReader
+    pub fn from_parts(reader: &mut RespReader) -> Result<Self, RespReaderError> {
+        let key = reader.next_string()?;
+        let decrement = reader.next_signed_int()?;
+        Ok(DecrBy { key, decrement })
+    }
+
+    /// Apply the decrby command, decrementing the value by `decrement`
+    pub async fn apply(self, db: &Db) -> crate::Result<Option<RESP>> {
+        let increment = self.decrement.checked_neg().ok_or(CommandError::Overflow)?;
+        let int = db.incr_by(self.key, increment)?;
+        Ok(Some(RESP::Integer(int)))
     }
 }
 
@@ -64,8 +120,156 @@ impl Incr {
 impl From<Incr> for RESP {
     fn from(value: Incr) -> Self {
         let mut resp = RESP::array();
-        resp.push_bulk(Bytes::from("Incr"));
+        resp.push_bulk(Bytes::from("incr"));
         resp.push_bulk(Bytes::from(value.key.into_bytes()));
         resp
     }
 }
+
+/// Convert IncrBy command back into an equivalent `RESP`
+impl From<IncrBy> for RESP {
+    fn from(value: IncrBy) -> Self {
+        let mut resp = RESP::array();
+        resp.push_bulk(Bytes::from("incrby"));
+        resp.push_bulk(Bytes::from(value.key.into_bytes()));
+        resp.push_bulk(Bytes::from(value.increment.to_string()));
+        resp
+    }
+}
+
+/// Convert Decr command back into an equivalent `RESP`
+impl From<Decr> for RESP {
+    fn from(value: Decr) -> Self {
+        let mut resp = RESP::array();
+        resp.push_bulk(Bytes::from("decr"));
+        resp.push_bulk(Bytes::from(value.key.into_bytes()));
+        resp
+    }
+}
+
+/// Convert DecrBy command back into an equivalent `RESP`
+impl From<DecrBy> for RESP {
+    fn from(value: DecrBy) -> Self {
+        let mut resp = RESP::array();
+        resp.push_bulk(Bytes::from("decrby"));
+        resp.push_bulk(Bytes::from(value.key.into_bytes()));
+        resp.push_bulk(Bytes::from(value.decrement.to_string()));
+        resp
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use bytes::Bytes;
+
+    use super::{Decr, DecrBy, Incr, IncrBy};
+    use crate::{resp::RESP, CommandError, Db, ValueType};
+
+    #[tokio::test]
+    async fn incr_non_utf8_value_is_an_error() {
+        let db = Db::new();
+        db.set(
+            "key".into(),
+            ValueType::String(Bytes::from_static(&[0xff, b'1'])),
+            None,
+        );
+
+        let err = Incr::new("key".into()).apply(&db).await.unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<CommandError>(),
+            Some(&CommandError::NotInteger)
+        );
+    }
+
+    #[tokio::test]
+    async fn incrby_decr_and_decrby_go_negative() {
+        let db = Db::new();
+
+        let resp = Decr::new("key".into()).apply(&db).await.unwrap();
+        assert!(matches!(resp, Some(RESP::Integer(-1))));
+
+        let resp = IncrBy::new("key".into(), 10).apply(&db).await.unwrap();
+        assert!(matches!(resp, Some(RESP::Integer(9))));
+
+        let resp = DecrBy::new("key".into(), 20).apply(&db).await.unwrap();
+        assert!(matches!(resp, Some(RESP::Integer(-11))));
+
+        let resp = IncrBy::new("key".into(), -4).apply(&db).await.unwrap();
+        assert!(matches!(resp, Some(RESP::Integer(-15))));
+        assert!(matches!(db.get("key"), Some(ValueType::String(v)) if v == "-15"));
+    }
+
+    #[tokio::test]
+    async fn incrby_overflow_is_an_error() {
+        let db = Db::new();
+        db.set(
+            "key".into(),
+            ValueType::String(Bytes::from(i64::MAX.to_string())),
+            None,
+        );
+
+        let err = Incr::new("key".into()).apply(&db).await.unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<CommandError>(),
+            Some(&CommandError::Overflow)
+        );
+
+        let err = DecrBy::new("other".into(), i64::MIN)
+            .apply(&db)
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<CommandError>(),
+            Some(&CommandError::Overflow)
+        );
+    }
+
+    #[tokio::test]
+    async fn incr_keeps_expiry() {
+        let db = Db::new();
+        db.set(
+            "key".into(),
+            ValueType::String(Bytes::from("1")),
+            Some(Duration::from_secs(100)),
+        );
+
+        let resp = Incr::new("key".into()).apply(&db).await.unwrap();
+        assert!(matches!(resp, Some(RESP::Integer(2))));
+        assert!(db.inner.state.lock().unwrap().next_expiration().is_some());
+    }
+
+    #[tokio::test]
+    async fn concurrent_increments_are_not_lost() {
+        let db = Db::new();
+
+        let tasks: Vec<_> = (0..8)
+            .map(|_| {
+                let db = db.clone();
+                std::thread::spawn(move || {
+                    for _ in 0..1000 {
+                        db.incr_by("key".into(), 1).unwrap();
+                    }
+                })
+            })
+            .collect();
+        for task in tasks {
+            task.join().unwrap();
+        }
+
+        assert!(matches!(db.get("key"), Some(ValueType::String(v)) if v == "8000"));
+    }
+
+    #[tokio::test]
+    async fn incr_on_empty_string_is_an_error() {
+        let db = Db::new();
+        db.set("key".into(), ValueType::String(Bytes::new()), None);
+
+        let err = Incr::new("key".into()).apply(&db).await.unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<CommandError>(),
+            Some(&CommandError::NotInteger)
+        );
+    }
+}
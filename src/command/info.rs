@@ -1,6 +1,12 @@
+use std::sync::atomic::Ordering;
+
 use bytes::Bytes;
+use tokio::sync::RwLock;
 
-use crate::{config::ServerConfig, resp::RESP, Db, RespReader, RespReaderError};
+use crate::{
+    config::ServerConfig, connection::Connection, is_keyword, resp::RESP, Db, RespReader,
+    RespReaderError, Role,
+};
 
 #[derive(Debug, Default)]
 pub struct Info {
@@ -14,13 +20,52 @@ impl Info {
     }
 
     /// Apply the echo command and write to the Tcp connection stream
-    pub async fn apply(self, db: &Db, config: ServerConfig) -> crate::Result<Option<RESP>> {
+    ///
+    /// A master lists its replicas at the address they serve clients on
+    pub async fn apply(
+        self,
+        db: &Db,
+        config: ServerConfig,
+        replicas: &RwLock<Vec<Connection>>,
+    ) -> crate::Result<Option<RESP>> {
         // dbg!(&resp);
         let role = config.role.to_string();
         let mut data: String = "role:".to_owned();
         data.push_str(role.as_str());
         data.push_str("\r\n");
 
+        if matches!(config.role, Role::Slave) {
+            let status = if config.master_link_up.load(Ordering::SeqCst) {
+                "up"
+            } else {
+                "down"
+            };
+            data.push_str("master_link_status:");
+            data.push_str(status);
+            data.push_str("\r\n");
+        }
+
+        if matches!(config.role, Role::Master) {
+            let replicas = replicas.read().await;
+            let replicas = replicas.iter().filter(|connection| connection.is_replica);
+
+            let mut lines = String::new();
+            let mut count = 0;
+            for (idx, replica) in replicas.enumerate() {
+                let (ip, port) = replica.replica_addr().map_or((String::new(), 0), |addr| {
+                    (addr.ip().to_string(), addr.port())
+                });
+                lines.push_str(&format!(
+                    "slave{idx}:ip={ip},port={port},state=online,offset={}\r\n",
+                    replica.acked_offset()
+                ));
+                count += 1;
+            }
+
+            data.push_str(&format!("connected_slaves:{count}\r\n"));
+            data.push_str(&lines);
+        }
+
         let repl_info = db.get_repl_info();
         if repl_info.0.is_some() {
             data.push_str("master_replid:");
@@ -38,7 +83,7 @@ impl Info {
 
     pub fn from_parts(reader: &mut RespReader) -> Result<Self, RespReaderError> {
         let section = match reader.next_string() {
-            Ok(s) if s.to_lowercase() == "replication" => s.to_string(),
+            Ok(s) if is_keyword(&s, "replication") => s.to_string(),
             Ok(invalid_section) => {
                 return Err(RespReaderError::Other(format!(
                     "Info command Invalid section: {}",
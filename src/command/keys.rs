@@ -29,7 +29,15 @@ impl Keys {
     /// Apply the echo command and write to the Tcp connection stream
     pub async fn apply(self, db: &Db, _dst: &mut Connection) -> crate::Result<Option<RESP>> {
         let response = match self.key.as_str() {
-            "*" => db.keys(),
+            "*" => {
+                let mut keys = vec![];
+                for batch in db.key_batches() {
+                    keys.extend(batch);
+                    // give writers queued behind the lock a turn
+                    tokio::task::yield_now().await;
+                }
+                keys
+            }
             _ => {
                 todo!()
             }
@@ -0,0 +1,387 @@
+use std::{future, time::Duration};
+
+use bytes::Bytes;
+use futures::future::select_all;
+use tokio::time;
+
+use crate::{
+    parse_blocking_args, parse_mpop_args, parse_timeout, resp::RESP, Db, MultiPop, RespReader,
+    RespReaderError,
+};
+
+#[derive(Debug, Default)]
+pub struct BLPop {
+    /// keys checked in order, the first non-empty one is popped from
+    keys: Vec<String>,
+
+    /// seconds to block for, 0 blocks until a value is available
+    timeout: f64,
+}
+
+#[derive(Debug, Default)]
+pub struct BRPop {
+    /// keys checked in order, the first non-empty one is popped from
+    keys: Vec<String>,
+
+    /// seconds to block for, 0 blocks until a value is available
+    timeout: f64,
+}
+
+#[derive(Debug, Default)]
+pub struct BLMPop {
+    /// seconds to block for, 0 blocks until a value is available
+    timeout: f64,
+
+    /// keys checked in order, the first non-empty one is popped from
+    keys: Vec<String>,
+
+    /// pop from the tail instead of the head
+    tail: bool,
+
+    /// max number of values to pop
+    count: usize,
+}
+
+/// Keeps a client in the queues of the keys it blocks on, it's taken
+/// out once dropped even if the command never completes
+struct Blocked<'a> {
+    db: &'a Db,
+    keys: &'a [String],
+    waiter: u64,
+}
+
+impl BLPop {
+    /// contruct new BLPop command
+    pub fn new(keys: Vec<String>, timeout: f64) -> Self {
+        BLPop { keys, timeout }
+    }
+
+    /// Construct new BLPop command by consuming the RespReader
+    pub fn from_parts(reader: &mut RespReader) -> Result<Self, RespReaderError> {
+        let (keys, timeout) = parse_blocking_args(reader)?;
+        Ok(BLPop { keys, timeout })
+    }
+
+    /// Apply the blpop command, blocking until a value is available
+    pub async fn apply(self, db: &Db) -> crate::Result<Option<RESP>> {
+        let popped = blocking_pop(db, &self.keys, self.timeout, 1, false).await?;
+        Ok(Some(single_pop_resp(popped)))
+    }
+}
+
+impl BRPop {
+    /// contruct new BRPop command
+    pub fn new(keys: Vec<String>, timeout: f64) -> Self {
+        BRPop { keys, timeout }
+    }
+
+    /// Construct new BRPop command by consuming the RespReader
+    pub fn from_parts(reader: &mut RespReader) -> Result<Self, RespReaderError> {
+        let (keys, timeout) = parse_blocking_args(reader)?;
+        Ok(BRPop { keys, timeout })
+    }
+
+    /// Apply the brpop command, blocking until a value is available
+    pub async fn apply(self, db: &Db) -> crate::Result<Option<RESP>> {
+        let popped = blocking_pop(db, &self.keys, self.timeout, 1, true).await?;
+        Ok(Some(single_pop_resp(popped)))
+    }
+}
+
+impl BLMPop {
+    /// contruct new BLMPop command
+    pub fn new(timeout: f64, keys: Vec<String>, tail: bool, count: usize) -> Self {
+        BLMPop {
+            timeout,
+            keys,
+            tail,
+            count,
+        }
+    }
+
+    /// Construct new BLMPop command by consuming the RespReader
+    ///
+    /// Arguments are `timeout numkeys key [key ...] LEFT|RIGHT [COUNT count]`
+    pub fn from_parts(reader: &mut RespReader) -> Result<Self, RespReaderError> {
+        let timeout = parse_timeout(&reader.next_string()?)?;
+        let (keys, tail, count) = parse_mpop_args(reader, ["left", "right"])?;
+
+        Ok(BLMPop {
+            timeout,
+            keys,
+            tail,
+            count,
+        })
+    }
+
+    /// Whether the values are popped from the tail
+    pub fn tail(&self) -> bool {
+        self.tail
+    }
+
+    /// Apply the blmpop command, blocking until a value is available,
+    /// replies like LMPOP
+    pub async fn apply(self, db: &Db) -> crate::Result<Option<RESP>> {
+        let resp = match blocking_pop(db, &self.keys, self.timeout, self.count, self.tail).await? {
+            Some((key, values)) => RESP::Array(vec![
+                RESP::Bulk(Bytes::from(key)),
+                RESP::Array(values.into_iter().map(RESP::Bulk).collect()),
+            ]),
+            None => RESP::Null,
+        };
+
+        Ok(Some(resp))
+    }
+}
+
+impl Drop for Blocked<'_> {
+    fn drop(&mut self) {
+        self.db.unblock(self.keys, self.waiter);
+        for key in self.keys {
+            self.db.release_key_notifier(key);
+        }
+    }
+}
+
+/// Pop up to `count` values from the first non-empty list in `keys`,
+/// waiting for one to be pushed to until the timeout runs out
+///
+/// Clients blocked on the same key are served in the order they
+/// blocked. Returns the key and values or `None` on timeout
+async fn blocking_pop(
+    db: &Db,
+    keys: &[String],
+    timeout: f64,
+    count: usize,
+    tail: bool,
+) -> crate::Result<MultiPop<Bytes>> {
+    let deadline = (timeout > 0.0).then(|| time::Instant::now() + Duration::from_secs_f64(timeout));
+
+    let blocked = Blocked {
+        db,
+        keys,
+        waiter: db.block(keys),
+    };
+
+    let popped = loop {
+        let notifiers = keys
+            .iter()
+            .map(|key| db.key_notifier(key))
+            .collect::<Vec<_>>();
+        // registered before checking the keys so a push in between isn't missed
+        let written = notifiers
+            .iter()
+            .map(|notify| Box::pin(notify.notified()))
+            .collect::<Vec<_>>();
+
+        let mut popped = None;
+        for key in keys {
+            let values = db.pop_waiting(key, blocked.waiter, count, tail)?;
+            if !values.is_empty() {
+                popped = Some((key.clone(), values));
+                break;
+            }
+        }
+
+        if popped.is_some() {
+            break popped;
+        }
+
+        let timed_out = async {
+            match deadline {
+                Some(deadline) => time::sleep_until(deadline).await,
+                None => future::pending().await,
+            }
+        };
+
+        tokio::select! {
+            _ = select_all(written) => continue,
+            _ = timed_out => break None,
+        }
+    };
+
+    drop(blocked);
+
+    Ok(popped)
+}
+
+/// Reply with the key and the value popped from it, or a null
+fn single_pop_resp(popped: MultiPop<Bytes>) -> RESP {
+    match popped {
+        Some((key, mut values)) => RESP::Array(vec![
+            RESP::Bulk(Bytes::from(key)),
+            RESP::Bulk(values.remove(0)),
+        ]),
+        None => RESP::Null,
+    }
+}
+
+/// Convert BLPop command back into an equivalent `RESP`
+impl From<BLPop> for RESP {
+    fn from(value: BLPop) -> Self {
+        blocking_pop_resp("blpop", value.keys, value.timeout)
+    }
+}
+
+/// Convert BRPop command back into an equivalent `RESP`
+impl From<BRPop> for RESP {
+    fn from(value: BRPop) -> Self {
+        blocking_pop_resp("brpop", value.keys, value.timeout)
+    }
+}
+
+/// Convert BLMPop command back into an equivalent `RESP`
+impl From<BLMPop> for RESP {
+    fn from(value: BLMPop) -> Self {
+        let mut resp = RESP::array();
+        resp.push_bulk(Bytes::from("blmpop"));
+        resp.push_bulk(Bytes::from(value.timeout.to_string()));
+        resp.push_bulk(Bytes::from(value.keys.len().to_string()));
+        for key in value.keys {
+            resp.push_bulk(Bytes::from(key));
+        }
+        resp.push_bulk(Bytes::from(if value.tail { "right" } else { "left" }));
+        resp.push_bulk(Bytes::from("count"));
+        resp.push_bulk(Bytes::from(value.count.to_string()));
+        resp
+    }
+}
+
+fn blocking_pop_resp(name: &'static str, keys: Vec<String>, timeout: f64) -> RESP {
+    let mut resp = RESP::array();
+    resp.push_bulk(Bytes::from(name));
+    for key in keys {
+        resp.push_bulk(Bytes::from(key));
+    }
+    resp.push_bulk(Bytes::from(timeout.to_string()));
+    resp
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use bytes::Bytes;
+
+    use super::{BLMPop, BLPop, BRPop};
+    use crate::{resp::RESP, Db};
+
+    fn strings(resp: Option<RESP>) -> Vec<String> {
+        match resp {
+            Some(RESP::Array(values)) => values
+                .into_iter()
+                .map(|value| match value {
+                    RESP::Bulk(value) => String::from_utf8(value.to_vec()).unwrap(),
+                    value => panic!("unexpected reply {value:?}"),
+                })
+                .collect(),
+            resp => panic!("expected an array, got {resp:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn blpop_wakes_on_push_and_reports_the_key() {
+        let db = Db::new();
+
+        let pop = tokio::spawn({
+            let db = db.clone();
+            async move {
+                BLPop::new(vec!["other".into(), "list".into()], 0.0)
+                    .apply(&db)
+                    .await
+                    .unwrap()
+            }
+        });
+
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(!pop.is_finished());
+        db.push("list".into(), vec![Bytes::from("a")], true)
+            .unwrap();
+
+        let resp = tokio::time::timeout(Duration::from_secs(2), pop)
+            .await
+            .expect("BLPOP was not woken by RPUSH")
+            .unwrap();
+        assert_eq!(strings(resp), ["list", "a"]);
+        assert!(db.get("list").is_none());
+
+        // nothing left to pop, the timeout runs out
+        let resp = BRPop::new(vec!["list".into()], 0.05)
+            .apply(&db)
+            .await
+            .unwrap();
+        assert!(matches!(resp, Some(RESP::Null)));
+    }
+
+    #[tokio::test]
+    async fn blocked_clients_are_served_in_fifo_order() {
+        let db = Db::new();
+
+        let mut pops = vec![];
+        for _ in 0..3 {
+            pops.push(tokio::spawn({
+                let db = db.clone();
+                async move {
+                    BLPop::new(vec!["list".into()], 0.0)
+                        .apply(&db)
+                        .await
+                        .unwrap()
+                }
+            }));
+            // let each client block before the next one does
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        let values = ["a", "b", "c"].map(Bytes::from).to_vec();
+        db.push("list".into(), values, true).unwrap();
+
+        for (pop, expected) in pops.into_iter().zip(["a", "b", "c"]) {
+            let resp = tokio::time::timeout(Duration::from_secs(2), pop)
+                .await
+                .expect("BLPOP was not served")
+                .unwrap();
+            assert_eq!(strings(resp), ["list", expected]);
+        }
+    }
+
+    #[tokio::test]
+    async fn blmpop_returns_once_any_watched_key_is_pushed_to() {
+        let db = Db::new();
+
+        let pop = tokio::spawn({
+            let db = db.clone();
+            async move {
+                BLMPop::new(0.0, vec!["first".into(), "second".into()], false, 2)
+                    .apply(&db)
+                    .await
+                    .unwrap()
+            }
+        });
+
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(!pop.is_finished());
+        let values = ["c", "b", "a"].map(Bytes::from).to_vec();
+        db.push("second".into(), values, false).unwrap();
+
+        let resp = tokio::time::timeout(Duration::from_millis(500), pop)
+            .await
+            .expect("BLMPOP was not woken by LPUSH")
+            .unwrap();
+        match resp {
+            Some(RESP::Array(reply)) => match reply.as_slice() {
+                [RESP::Bulk(key), values @ RESP::Array(_)] => {
+                    assert_eq!(key, "second");
+                    assert_eq!(strings(Some(values.clone())), ["a", "b"]);
+                }
+                reply => panic!("unexpected reply {reply:?}"),
+            },
+            resp => panic!("unexpected reply {resp:?}"),
+        }
+
+        let resp = BLMPop::new(0.05, vec!["first".into()], false, 1)
+            .apply(&db)
+            .await
+            .unwrap();
+        assert!(matches!(resp, Some(RESP::Null)));
+    }
+}
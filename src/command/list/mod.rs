@@ -0,0 +1,11 @@
+pub mod bpop;
+pub mod mpop;
+pub mod pop;
+pub mod push;
+pub mod range;
+
+pub use bpop::{BLMPop, BLPop, BRPop};
+pub use mpop::LMPop;
+pub use pop::{LPop, RPop};
+pub use push::{LPush, RPush};
+pub use range::{LLen, LRange};
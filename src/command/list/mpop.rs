@@ -0,0 +1,166 @@
+use bytes::Bytes;
+
+use crate::{parse_mpop_args, resp::RESP, Db, RespReader, RespReaderError};
+
+#[derive(Debug, Default)]
+pub struct LMPop {
+    /// lists to pop from, the first non-empty one is used
+    keys: Vec<String>,
+
+    /// pop from the tail instead of the head
+    tail: bool,
+
+    /// max number of values to pop
+    count: usize,
+}
+
+impl LMPop {
+    /// contruct new LMPop command
+    pub fn new(keys: Vec<String>, tail: bool, count: usize) -> Self {
+        LMPop { keys, tail, count }
+    }
+
+    /// Construct new LMPop command by consuming the RespReader
+    ///
+    /// Arguments are `numkeys key [key ...] LEFT|RIGHT [COUNT count]`
+    pub fn from_parts(reader: &mut RespReader) -> Result<Self, RespReaderError> {
+        let (keys, tail, count) = parse_mpop_args(reader, ["left", "right"])?;
+        Ok(LMPop { keys, tail, count })
+    }
+
+    /// Apply the lmpop command, replying with the key popped from and
+    /// the values, or a null when every list is empty
+    pub async fn apply(self, db: &Db) -> crate::Result<Option<RESP>> {
+        let resp = match db.mpop(&self.keys, self.count, self.tail)? {
+            Some((key, values)) => RESP::Array(vec![
+                RESP::Bulk(Bytes::from(key)),
+                RESP::Array(values.into_iter().map(RESP::Bulk).collect()),
+            ]),
+            None => RESP::Null,
+        };
+
+        Ok(Some(resp))
+    }
+}
+
+/// Convert LMPop command back into an equivalent `RESP`
+impl From<LMPop> for RESP {
+    fn from(value: LMPop) -> Self {
+        let mut resp = RESP::array();
+        resp.push_bulk(Bytes::from("lmpop"));
+        resp.push_bulk(Bytes::from(value.keys.len().to_string()));
+        for key in value.keys {
+            resp.push_bulk(Bytes::from(key));
+        }
+        resp.push_bulk(Bytes::from(if value.tail { "right" } else { "left" }));
+        resp.push_bulk(Bytes::from("count"));
+        resp.push_bulk(Bytes::from(value.count.to_string()));
+        resp
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::VecDeque;
+
+    use bytes::Bytes;
+
+    use super::LMPop;
+    use crate::{resp::RESP, CommandError, Db, RespReader, RespReaderError, ValueType};
+
+    fn reader(args: &[&str]) -> RespReader {
+        let args = args
+            .iter()
+            .map(|arg| RESP::Bulk(Bytes::from(arg.to_string())))
+            .collect();
+        RespReader::new(RESP::Array(args)).unwrap()
+    }
+
+    fn list(values: &[&str]) -> ValueType {
+        ValueType::List(values.iter().map(|v| Bytes::from(v.to_string())).collect())
+    }
+
+    #[tokio::test]
+    async fn pops_from_the_first_non_empty_list() {
+        let db = Db::new();
+        db.set("empty".into(), ValueType::List(VecDeque::new()), None);
+        db.set("second".into(), list(&["a", "b", "c"]), None);
+
+        let keys = vec!["missing".into(), "empty".into(), "second".into()];
+        let resp = LMPop::new(keys.clone(), true, 2).apply(&db).await.unwrap();
+        match resp {
+            Some(RESP::Array(reply)) => match reply.as_slice() {
+                [RESP::Bulk(key), RESP::Array(values)] => {
+                    assert_eq!(key, "second");
+                    assert!(
+                        matches!(values.as_slice(), [RESP::Bulk(c), RESP::Bulk(b)] if c == "c" && b == "b")
+                    );
+                }
+                reply => panic!("unexpected reply {reply:?}"),
+            },
+            resp => panic!("unexpected reply {resp:?}"),
+        }
+
+        // popping more than is left empties and deletes the list
+        LMPop::new(keys.clone(), false, 10)
+            .apply(&db)
+            .await
+            .unwrap();
+        assert!(db.get("second").is_none());
+
+        let resp = LMPop::new(keys, false, 1).apply(&db).await.unwrap();
+        assert!(matches!(resp, Some(RESP::Null)));
+    }
+
+    #[tokio::test]
+    async fn a_non_list_key_before_a_list_is_a_wrong_type_error() {
+        let db = Db::new();
+        db.set("string".into(), ValueType::String(Bytes::from("v")), None);
+        db.set("list".into(), list(&["a"]), None);
+
+        let err = LMPop::new(vec!["string".into(), "list".into()], false, 1)
+            .apply(&db)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<CommandError>(),
+            Some(CommandError::WrongType)
+        ));
+    }
+
+    #[test]
+    fn parses_numkeys_direction_and_count() {
+        let lmpop =
+            LMPop::from_parts(&mut reader(&["2", "a", "b", "RIGHT", "COUNT", "3"])).unwrap();
+        assert_eq!(lmpop.keys, vec!["a", "b"]);
+        assert!(lmpop.tail);
+        assert_eq!(lmpop.count, 3);
+
+        let lmpop = LMPop::from_parts(&mut reader(&["1", "a", "left"])).unwrap();
+        assert!(!lmpop.tail);
+        assert_eq!(lmpop.count, 1);
+
+        for (args, expected) in [
+            (
+                &["0", "a", "left"][..],
+                "ERR numkeys should be greater than 0",
+            ),
+            (&["1", "a", "up"][..], "ERR syntax error"),
+            (
+                &["1", "a", "left", "count", "0"][..],
+                "ERR count should be greater than 0",
+            ),
+            (&["1", "a", "left", "limit", "1"][..], "ERR syntax error"),
+        ] {
+            assert!(matches!(
+                LMPop::from_parts(&mut reader(args)),
+                Err(RespReaderError::Command(err)) if err.to_string() == expected
+            ));
+        }
+        // fewer keys than numkeys leaves no direction
+        assert!(matches!(
+            LMPop::from_parts(&mut reader(&["2", "a", "left"])),
+            Err(RespReaderError::EndOfStream)
+        ));
+    }
+}
@@ -0,0 +1,185 @@
+use bytes::Bytes;
+
+use crate::{resp::RESP, Db, RespReader, RespReaderError};
+
+#[derive(Debug, Default)]
+pub struct LPop {
+    /// cache lookup key
+    key: String,
+
+    /// number of values to pop, a single value is replied without
+    /// wrapping it when the count is omitted
+    count: Option<u64>,
+}
+
+#[derive(Debug, Default)]
+pub struct RPop {
+    /// cache lookup key
+    key: String,
+
+    /// number of values to pop
+    count: Option<u64>,
+}
+
+impl LPop {
+    /// contruct new LPop command
+    pub fn new(key: String, count: Option<u64>) -> Self {
+        LPop { key, count }
+    }
+
+    /// Construct new LPop command by consuming the RespReader
+    pub fn from_parts(reader: &mut RespReader) -> Result<Self, RespReaderError> {
+        let (key, count) = parse_pop(reader)?;
+        Ok(LPop { key, count })
+    }
+
+    /// Apply the lpop command, popping from the head of the list
+    pub async fn apply(self, db: &Db) -> crate::Result<Option<RESP>> {
+        pop(db, &self.key, self.count, false)
+    }
+}
+
+impl RPop {
+    /// contruct new RPop command
+    pub fn new(key: String, count: Option<u64>) -> Self {
+        RPop { key, count }
+    }
+
+    /// Construct new RPop command by consuming the RespReader
+    pub fn from_parts(reader: &mut RespReader) -> Result<Self, RespReaderError> {
+        let (key, count) = parse_pop(reader)?;
+        Ok(RPop { key, count })
+    }
+
+    /// Apply the rpop command, popping from the tail of the list
+    pub async fn apply(self, db: &Db) -> crate::Result<Option<RESP>> {
+        pop(db, &self.key, self.count, true)
+    }
+}
+
+/// Parse `key [count]`
+fn parse_pop(reader: &mut RespReader) -> Result<(String, Option<u64>), RespReaderError> {
+    let key = reader.next_string()?;
+
+    let count = match reader.next_int() {
+        Ok(count) => Some(count),
+        Err(RespReaderError::EndOfStream) => None,
+        Err(err) => return Err(err),
+    };
+
+    Ok((key, count))
+}
+
+/// Pop from a list, replying with a single bulk without a count and
+/// an array with one. Nothing to pop is a null, a null array with a
+/// count, but a count of 0 on an existing list is an empty array
+fn pop(db: &Db, key: &str, count: Option<u64>, tail: bool) -> crate::Result<Option<RESP>> {
+    let mut popped = db.pop(key, count.map_or(1, |count| count as usize), tail)?;
+
+    let resp = match count {
+        Some(0) if db.count_existing(&[key.to_string()]) > 0 => RESP::array(),
+        Some(_) if popped.is_empty() => RESP::NullArray,
+        None if popped.is_empty() => RESP::Null,
+        Some(_) => RESP::Array(popped.into_iter().map(RESP::Bulk).collect()),
+        None => RESP::Bulk(popped.remove(0)),
+    };
+
+    Ok(Some(resp))
+}
+
+/// Convert LPop command back into an equivalent `RESP`
+impl From<LPop> for RESP {
+    fn from(value: LPop) -> Self {
+        pop_resp("lpop", value.key, value.count)
+    }
+}
+
+/// Convert RPop command back into an equivalent `RESP`
+impl From<RPop> for RESP {
+    fn from(value: RPop) -> Self {
+        pop_resp("rpop", value.key, value.count)
+    }
+}
+
+fn pop_resp(name: &'static str, key: String, count: Option<u64>) -> RESP {
+    let mut resp = RESP::array();
+    resp.push_bulk(Bytes::from(name));
+    resp.push_bulk(Bytes::from(key));
+    if let Some(count) = count {
+        resp.push_bulk(Bytes::from(count.to_string()));
+    }
+    resp
+}
+
+#[cfg(test)]
+mod test {
+    use bytes::Bytes;
+
+    use super::{LPop, RPop};
+    use crate::{resp::RESP, CommandError, Db, ValueType};
+
+    #[tokio::test]
+    async fn popping_the_last_value_deletes_the_key() {
+        let db = Db::new();
+        db.push(
+            "list".into(),
+            vec![Bytes::from("a"), Bytes::from("b"), Bytes::from("c")],
+            true,
+        )
+        .unwrap();
+
+        let resp = LPop::new("list".into(), None).apply(&db).await.unwrap();
+        assert!(matches!(resp, Some(RESP::Bulk(value)) if value == "a"));
+
+        let resp = RPop::new("list".into(), Some(5)).apply(&db).await.unwrap();
+        match resp {
+            Some(RESP::Array(values)) => assert!(matches!(
+                values.as_slice(),
+                [RESP::Bulk(c), RESP::Bulk(b)] if c == "c" && b == "b"
+            )),
+            resp => panic!("expected an array, got {resp:?}"),
+        }
+
+        // the empty list is gone, further pops find nothing
+        assert!(db.get("list").is_none());
+        let resp = LPop::new("list".into(), None).apply(&db).await.unwrap();
+        assert!(matches!(resp, Some(RESP::Null)));
+        let resp = RPop::new("list".into(), Some(2)).apply(&db).await.unwrap();
+        assert!(matches!(resp, Some(RESP::NullArray)));
+    }
+
+    #[tokio::test]
+    async fn a_zero_count_pops_nothing() {
+        let db = Db::new();
+        db.push("list".into(), vec![Bytes::from("a")], true)
+            .unwrap();
+
+        // an empty array for a list, a null array when there's none
+        let resp = LPop::new("list".into(), Some(0)).apply(&db).await.unwrap();
+        assert!(matches!(resp, Some(RESP::Array(values)) if values.is_empty()));
+        let resp = RPop::new("missing".into(), Some(0))
+            .apply(&db)
+            .await
+            .unwrap();
+        assert!(matches!(resp, Some(RESP::NullArray)));
+
+        assert!(matches!(db.get("list"), Some(ValueType::List(list)) if list.len() == 1));
+    }
+
+    #[tokio::test]
+    async fn popping_from_a_string_is_a_wrong_type_error() {
+        let db = Db::new();
+        db.set("key".into(), ValueType::String(Bytes::from("v")), None);
+
+        for result in [
+            LPop::new("key".into(), None).apply(&db).await,
+            RPop::new("key".into(), None).apply(&db).await,
+        ] {
+            let err = result.unwrap_err();
+            assert!(matches!(
+                err.downcast_ref::<CommandError>(),
+                Some(CommandError::WrongType)
+            ));
+        }
+    }
+}
@@ -0,0 +1,149 @@
+use bytes::Bytes;
+
+use crate::{resp::RESP, Db, RespReader, RespReaderError};
+
+#[derive(Debug, Default)]
+pub struct LPush {
+    /// cache lookup key
+    key: String,
+
+    /// values pushed one after the other onto the head, so the last
+    /// one ends up first
+    values: Vec<Bytes>,
+}
+
+#[derive(Debug, Default)]
+pub struct RPush {
+    /// cache lookup key
+    key: String,
+
+    /// values appended to the tail in order
+    values: Vec<Bytes>,
+}
+
+impl LPush {
+    /// contruct new LPush command
+    pub fn new(key: String, values: Vec<Bytes>) -> Self {
+        LPush { key, values }
+    }
+
+    /// Construct new LPush command by consuming the RespReader
+    pub fn from_parts(reader: &mut RespReader) -> Result<Self, RespReaderError> {
+        let (key, values) = parse_push(reader)?;
+        Ok(LPush { key, values })
+    }
+
+    /// Apply the lpush command and return the length of the list
+    pub async fn apply(self, db: &Db) -> crate::Result<Option<RESP>> {
+        let len = db.push(self.key, self.values, false)?;
+
+        Ok(Some(RESP::Integer(len as i64)))
+    }
+}
+
+impl RPush {
+    /// contruct new RPush command
+    pub fn new(key: String, values: Vec<Bytes>) -> Self {
+        RPush { key, values }
+    }
+
+    /// Construct new RPush command by consuming the RespReader
+    pub fn from_parts(reader: &mut RespReader) -> Result<Self, RespReaderError> {
+        let (key, values) = parse_push(reader)?;
+        Ok(RPush { key, values })
+    }
+
+    /// Apply the rpush command and return the length of the list
+    pub async fn apply(self, db: &Db) -> crate::Result<Option<RESP>> {
+        let len = db.push(self.key, self.values, true)?;
+
+        Ok(Some(RESP::Integer(len as i64)))
+    }
+}
+
+/// Parse `key element [element ...]`
+fn parse_push(reader: &mut RespReader) -> Result<(String, Vec<Bytes>), RespReaderError> {
+    let key = reader.next_string()?;
+
+    let mut values = vec![reader.next_byte()?];
+    while let Ok(value) = reader.next_byte() {
+        values.push(value);
+    }
+
+    Ok((key, values))
+}
+
+/// Convert LPush command back into an equivalent `RESP`
+impl From<LPush> for RESP {
+    fn from(value: LPush) -> Self {
+        push_resp("lpush", value.key, value.values)
+    }
+}
+
+/// Convert RPush command back into an equivalent `RESP`
+impl From<RPush> for RESP {
+    fn from(value: RPush) -> Self {
+        push_resp("rpush", value.key, value.values)
+    }
+}
+
+fn push_resp(name: &'static str, key: String, values: Vec<Bytes>) -> RESP {
+    let mut resp = RESP::array();
+    resp.push_bulk(Bytes::from(name));
+    resp.push_bulk(Bytes::from(key));
+    for value in values {
+        resp.push_bulk(value);
+    }
+    resp
+}
+
+#[cfg(test)]
+mod test {
+    use bytes::Bytes;
+
+    use super::{LPush, RPush};
+    use crate::{resp::RESP, CommandError, Db, ValueType};
+
+    #[tokio::test]
+    async fn pushes_create_the_list_and_reply_with_its_length() {
+        let db = Db::new();
+
+        let resp = LPush::new("list".into(), vec![Bytes::from("b"), Bytes::from("a")])
+            .apply(&db)
+            .await
+            .unwrap();
+        assert!(matches!(resp, Some(RESP::Integer(2))));
+
+        let resp = RPush::new("list".into(), vec![Bytes::from("c")])
+            .apply(&db)
+            .await
+            .unwrap();
+        assert!(matches!(resp, Some(RESP::Integer(3))));
+
+        match db.get("list") {
+            Some(ValueType::List(list)) => assert_eq!(list, ["a", "b", "c"]),
+            value => panic!("expected a list, got {value:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn pushing_onto_a_string_is_a_wrong_type_error() {
+        let db = Db::new();
+        db.set("key".into(), ValueType::String(Bytes::from("v")), None);
+
+        for result in [
+            LPush::new("key".into(), vec![Bytes::from("a")])
+                .apply(&db)
+                .await,
+            RPush::new("key".into(), vec![Bytes::from("a")])
+                .apply(&db)
+                .await,
+        ] {
+            let err = result.unwrap_err();
+            assert!(matches!(
+                err.downcast_ref::<CommandError>(),
+                Some(CommandError::WrongType)
+            ));
+        }
+    }
+}
@@ -0,0 +1,169 @@
+use bytes::Bytes;
+
+use crate::{
+    range::index_range, resp::RESP, CommandError, Db, RespReader, RespReaderError, ValueType,
+};
+
+#[derive(Debug, Default)]
+pub struct LRange {
+    /// cache lookup key
+    key: String,
+
+    /// first index, negative indexes count from the tail
+    start: i64,
+
+    /// last index (inclusive), negative indexes count from the tail
+    end: i64,
+}
+
+#[derive(Debug, Default)]
+pub struct LLen {
+    /// cache lookup key
+    key: String,
+}
+
+impl LRange {
+    /// contruct new LRange command
+    pub fn new(key: String, start: i64, end: i64) -> Self {
+        LRange { key, start, end }
+    }
+
+    /// Construct new LRange command by consuming the RespReader
+    pub fn from_parts(reader: &mut RespReader) -> Result<Self, RespReaderError> {
+        let key = reader.next_string()?;
+        let start = reader.next_signed_int()?;
+        let end = reader.next_signed_int()?;
+
+        Ok(LRange { key, start, end })
+    }
+
+    /// Apply the lrange command and return the elements in range
+    pub async fn apply(self, db: &Db) -> crate::Result<Option<RESP>> {
+        let list = match db.get(&self.key) {
+            Some(ValueType::List(list)) => list,
+            Some(_) => return Err(CommandError::WrongType.into()),
+            None => return Ok(Some(RESP::array())),
+        };
+
+        let range = index_range(list.len(), self.start, self.end);
+
+        Ok(Some(RESP::Array(
+            list.range(range).cloned().map(RESP::Bulk).collect(),
+        )))
+    }
+}
+
+impl LLen {
+    /// contruct new LLen command
+    pub fn new(key: String) -> Self {
+        LLen { key }
+    }
+
+    /// Construct new LLen command by consuming the RespReader
+    pub fn from_parts(reader: &mut RespReader) -> Result<Self, RespReaderError> {
+        let key = reader.next_string()?;
+
+        Ok(LLen { key })
+    }
+
+    /// Apply the llen command and return the length of the list
+    pub async fn apply(self, db: &Db) -> crate::Result<Option<RESP>> {
+        let len = match db.get(&self.key) {
+            Some(ValueType::List(list)) => list.len(),
+            Some(_) => return Err(CommandError::WrongType.into()),
+            None => 0,
+        };
+
+        Ok(Some(RESP::Integer(len as i64)))
+    }
+}
+
+/// Convert LRange command back into an equivalent `RESP`
+impl From<LRange> for RESP {
+    fn from(value: LRange) -> Self {
+        let mut resp = RESP::array();
+        resp.push_bulk(Bytes::from("lrange"));
+        resp.push_bulk(Bytes::from(value.key));
+        resp.push_bulk(Bytes::from(value.start.to_string()));
+        resp.push_bulk(Bytes::from(value.end.to_string()));
+        resp
+    }
+}
+
+/// Convert LLen command back into an equivalent `RESP`
+impl From<LLen> for RESP {
+    fn from(value: LLen) -> Self {
+        let mut resp = RESP::array();
+        resp.push_bulk(Bytes::from("llen"));
+        resp.push_bulk(Bytes::from(value.key));
+        resp
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bytes::Bytes;
+
+    use super::{LLen, LRange};
+    use crate::{resp::RESP, CommandError, Db, ValueType};
+
+    fn strings(resp: Option<RESP>) -> Vec<String> {
+        match resp {
+            Some(RESP::Array(values)) => values
+                .into_iter()
+                .map(|value| match value {
+                    RESP::Bulk(value) => String::from_utf8(value.to_vec()).unwrap(),
+                    value => panic!("unexpected reply {value:?}"),
+                })
+                .collect(),
+            resp => panic!("expected an array, got {resp:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn lrange_clamps_and_resolves_negative_indexes() {
+        let db = Db::new();
+        let values = ["a", "b", "c", "d"].map(Bytes::from).to_vec();
+        db.push("list".into(), values, true).unwrap();
+
+        let lrange = |start, end| LRange::new("list".into(), start, end).apply(&db);
+        assert_eq!(strings(lrange(0, -1).await.unwrap()), ["a", "b", "c", "d"]);
+        assert_eq!(strings(lrange(-2, 100).await.unwrap()), ["c", "d"]);
+        assert!(strings(lrange(3, 1).await.unwrap()).is_empty());
+        assert!(strings(lrange(10, 20).await.unwrap()).is_empty());
+
+        let resp = LRange::new("missing".into(), 0, -1)
+            .apply(&db)
+            .await
+            .unwrap();
+        assert!(strings(resp).is_empty());
+    }
+
+    #[tokio::test]
+    async fn llen_counts_elements_and_rejects_other_types() {
+        let db = Db::new();
+        db.push(
+            "list".into(),
+            vec![Bytes::from("a"), Bytes::from("b")],
+            false,
+        )
+        .unwrap();
+        db.set("key".into(), ValueType::String(Bytes::from("v")), None);
+
+        let resp = LLen::new("list".into()).apply(&db).await.unwrap();
+        assert!(matches!(resp, Some(RESP::Integer(2))));
+        let resp = LLen::new("missing".into()).apply(&db).await.unwrap();
+        assert!(matches!(resp, Some(RESP::Integer(0))));
+
+        for result in [
+            LLen::new("key".into()).apply(&db).await,
+            LRange::new("key".into(), 0, -1).apply(&db).await,
+        ] {
+            let err = result.unwrap_err();
+            assert!(matches!(
+                err.downcast_ref::<CommandError>(),
+                Some(CommandError::WrongType)
+            ));
+        }
+    }
+}
@@ -1,44 +1,96 @@
+pub mod append;
+pub mod bitfield;
+pub mod client;
 pub mod config;
+pub mod debug;
+pub mod del;
 pub mod discard;
+pub mod docs;
 pub mod echo;
+pub mod error;
 pub mod exec;
+pub mod exists;
+pub mod expire;
 pub mod get;
+pub mod getset;
+pub mod hash;
+pub mod hello;
 pub mod incr;
 pub mod info;
 pub mod keys;
+pub mod list;
+pub mod mset;
 pub mod multi;
+pub mod object;
+pub mod persist;
 pub mod ping;
 pub mod psync;
+pub mod range;
+pub mod registry;
+pub mod rename;
 pub mod replconf;
+pub mod save;
+pub mod select;
 pub mod set;
+pub mod setnx;
+pub mod sets;
 pub mod stream;
+pub mod ttl;
 pub mod types;
 pub mod unknown;
 pub mod wait;
+pub mod zset;
 
 use std::{
     sync::{atomic::AtomicUsize, Arc},
     vec,
 };
 
+use append::Append;
+use bitfield::BitField;
 use bytes::Bytes;
+use client::Client;
 use config::Config;
+use debug::Debug;
+use del::Del;
 use discard::Discard;
 use echo::Echo;
+pub use error::CommandError;
 use exec::Exec;
+use exists::Exists;
+use expire::{Expire, Pexpire};
 use get::Get;
-use incr::Incr;
+use getset::GetSet;
+use hash::{HDel, HExpire, HGet, HGetAll, HIncrBy, HSet, HTtl};
+use hello::Hello;
+use incr::{Decr, DecrBy, Incr, IncrBy};
 use info::Info;
 use keys::Keys;
+use list::{BLMPop, BLPop, BRPop, LLen, LMPop, LPop, LPush, LRange, RPop, RPush};
+use mset::{MGet, MSet};
 use multi::Multi;
+use object::Object;
+use persist::Persist;
 use ping::Ping;
 pub use psync::PSync;
+use range::{GetRange, SetRange};
+use registry::{CommandSpec, Introspect};
+use rename::{Rename, RenameNx};
 pub use replconf::Replconf;
+use save::{BgSave, Save, ShutdownServer};
+use select::Select;
 use set::Set;
-use stream::{XAdd, XRange, XRead};
+use setnx::SetNx;
+use sets::{SAdd, SCard, SDiff, SInter, SIsMember, SMembers, SRem, SUnion};
+use stream::{XAdd, XDel, XRange, XRead};
 use tokio::sync::RwLock;
+use ttl::{Pttl, Ttl};
 use unknown::Unknown;
 use wait::Wait;
+use zset::{
+    BZMPop, BZPopMax, BZPopMin, ZAdd, ZMPop, ZPopMax, ZPopMin, ZRange, ZRangeByLex, ZRangeByScore,
+    ZRank, ZScore,
+};
 
 use crate::{config::ServerConfig, connection::Connection, resp::RESP, Db};
 
@@ -58,12 +110,79 @@ pub enum Command {
     Keys(Keys),
     Type(types::Type),
     XAdd(XAdd),
+    XDel(XDel),
     XRange(XRange),
     XRead(XRead),
     Incr(Incr),
     Multi(Multi),
     Exec(Exec),
     Discard(Discard),
+    Object(Object),
+    Del(Del),
+    Exists(Exists),
+    Debug(Debug),
+    Expire(Expire),
+    Pexpire(Pexpire),
+    Persist(Persist),
+    IncrBy(IncrBy),
+    Decr(Decr),
+    DecrBy(DecrBy),
+    Append(Append),
+    GetSet(GetSet),
+    SetNx(SetNx),
+    MSet(MSet),
+    MGet(MGet),
+    GetRange(GetRange),
+    SetRange(SetRange),
+    Select(Select),
+    Client(Client),
+    Introspect(Introspect),
+    Rename(Rename),
+    RenameNx(RenameNx),
+    BitField(BitField),
+    ZRangeByLex(ZRangeByLex),
+    ZPopMin(ZPopMin),
+    ZPopMax(ZPopMax),
+    BZPopMin(BZPopMin),
+    BZPopMax(BZPopMax),
+    Hello(Hello),
+    Ttl(Ttl),
+    Pttl(Pttl),
+    Save(Save),
+    BgSave(BgSave),
+    Shutdown(ShutdownServer),
+    LPush(LPush),
+    RPush(RPush),
+    LPop(LPop),
+    RPop(RPop),
+    ZScore(ZScore),
+    ZAdd(ZAdd),
+    ZRank(ZRank),
+    LMPop(LMPop),
+    ZMPop(ZMPop),
+    ZRange(ZRange),
+    ZRangeByScore(ZRangeByScore),
+    BLMPop(BLMPop),
+    BZMPop(BZMPop),
+    LRange(LRange),
+    LLen(LLen),
+    BLPop(BLPop),
+    BRPop(BRPop),
+    HSet(HSet),
+    HGet(HGet),
+    HDel(HDel),
+    HExpire(HExpire),
+    HTtl(HTtl),
+    HGetAll(HGetAll),
+    HIncrBy(HIncrBy),
+    SAdd(SAdd),
+    SRem(SRem),
+    SMembers(SMembers),
+    SIsMember(SIsMember),
+    SCard(SCard),
+    SInter(SInter),
+    SUnion(SUnion),
+    SDiff(SDiff),
 }
 
 impl Command {
@@ -76,31 +195,32 @@ impl Command {
 
         let command_name = resp_reader.next_string()?.to_lowercase();
 
-        let command = match command_name.as_str() {
-            "echo" => Command::Echo(Echo::from_parts(&mut resp_reader)?),
-            "config" => Command::Config(Config::from_parts(&mut resp_reader)?),
-            "ping" => Command::Ping(Ping::from_parts(&mut resp_reader)?),
-            "set" => Command::Set(Set::from_parts(&mut resp_reader)?),
-            "incr" => Command::Incr(Incr::from_parts(&mut resp_reader)?),
-            "get" => Command::Get(Get::from_parts(&mut resp_reader)?),
-            "info" => Command::Info(Info::from_parts(&mut resp_reader)?),
-            "replconf" => Command::Replconf(Replconf::from_parts(&mut resp_reader)?),
-            "psync" => Command::PSync(PSync::from_parts(&mut resp_reader)?),
-            "wait" => Command::Wait(Wait::from_parts(&mut resp_reader)?),
-            "keys" => Command::Keys(Keys::from_parts(&mut resp_reader)?),
-            "type" => Command::Type(types::Type::from_parts(&mut resp_reader)?),
-            "xadd" => Command::XAdd(XAdd::from_parts(&mut resp_reader)?),
-            "xrange" => Command::XRange(XRange::from_parts(&mut resp_reader)?),
-            "xread" => Command::XRead(XRead::from_parts(&mut resp_reader)?),
-            "multi" => Command::Multi(Multi::from_parts(&mut resp_reader)?),
-            "exec" => Command::Exec(Exec::from_parts(&mut resp_reader)?),
-            "discard" => Command::Discard(Discard::from_parts(&mut resp_reader)?),
-            _ => panic!("Unexpected command"),
+        // Running out of arguments, or having some left over once the
+        // command is built, means the command was called with the wrong arity
+        let command = match Self::from_parts(&command_name, &mut resp_reader) {
+            Err(RespReaderError::EndOfStream) => {
+                return Err(CommandError::WrongArity(command_name).into())
+            }
+            Err(RespReaderError::Command(err)) => return Err(err.into()),
+            command => command?,
         };
 
-        // Check if reader has been consumed, if not return an Error
-        // to alert protocol of unexpected resp format
-        resp_reader.finish()?;
+        if resp_reader.finish().is_err() {
+            return Err(CommandError::WrongArity(command_name).into());
+        }
+
+        Ok(command)
+    }
+
+    /// Construct the named command from the remaining RESP entries
+    fn from_parts(
+        command_name: &str,
+        resp_reader: &mut RespReader,
+    ) -> Result<Command, RespReaderError> {
+        let command = match registry::lookup(command_name) {
+            Some(spec) => (spec.parse)(resp_reader)?,
+            None => Command::Unknown(Unknown::from_parts(command_name.to_string(), resp_reader)?),
+        };
 
         Ok(command)
     }
@@ -108,6 +228,7 @@ impl Command {
     /// Apply the command
     ///
     /// The response is written to the dst connection.
+    /// A `CommandError` is turned into a `RESP::Error` reply
     pub async fn apply(
         self,
         dst: &mut Connection,
@@ -118,65 +239,138 @@ impl Command {
     ) -> crate::Result<Option<RESP>> {
         use Command::*;
 
-        match self {
+        let result = match self {
             Config(cmd) => cmd.apply(config).await,
             Echo(cmd) => cmd.apply(dst).await,
             Ping(cmd) => cmd.apply(dst).await,
             Unknown(cmd) => cmd.apply(dst).await,
-            Set(cmd) => cmd.apply(&db, dst).await,
-            Incr(cmd) => cmd.apply(&db, dst).await,
-            Get(cmd) => cmd.apply(&db, dst).await,
+            Set(cmd) => cmd.apply(db).await,
+            Incr(cmd) => cmd.apply(db).await,
+            Get(cmd) => cmd.apply(db).await,
             Keys(cmd) => cmd.apply(&db, dst).await,
             Type(cmd) => cmd.apply(&db, dst).await,
-            Info(cmd) => cmd.apply(&db, config).await,
+            Info(cmd) => cmd.apply(&db, config, &replicas).await,
             Replconf(cmd) => cmd.apply(dst, offset).await,
             PSync(cmd) => cmd.apply(&db, dst).await,
             Wait(cmd) => cmd.apply(dst, offset, replicas, config).await,
             XAdd(cmd) => cmd.apply(&db).await,
+            XDel(cmd) => cmd.apply(db).await,
             XRange(cmd) => cmd.apply(&db).await,
             XRead(cmd) => cmd.apply(&db).await,
             Multi(cmd) => cmd.apply().await,
             Exec(cmd) => cmd.apply().await,
             Discard(cmd) => cmd.apply().await,
+            Object(cmd) => cmd.apply(db).await,
+            Del(cmd) => cmd.apply(db).await,
+            Exists(cmd) => cmd.apply(db).await,
+            Debug(cmd) => cmd.apply(db).await,
+            Expire(cmd) => cmd.apply(db).await,
+            Pexpire(cmd) => cmd.apply(db).await,
+            Persist(cmd) => cmd.apply(db).await,
+            IncrBy(cmd) => cmd.apply(db).await,
+            Decr(cmd) => cmd.apply(db).await,
+            DecrBy(cmd) => cmd.apply(db).await,
+            Append(cmd) => cmd.apply(db, config).await,
+            GetSet(cmd) => cmd.apply(db).await,
+            SetNx(cmd) => cmd.apply(db).await,
+            MSet(cmd) => cmd.apply(db).await,
+            MGet(cmd) => cmd.apply(db).await,
+            GetRange(cmd) => cmd.apply(db).await,
+            SetRange(cmd) => cmd.apply(db, config).await,
+            Select(cmd) => cmd.apply(db).await,
+            Client(cmd) => cmd.apply(dst, db, config).await,
+            Introspect(cmd) => cmd.apply().await,
+            Rename(cmd) => cmd.apply(db).await,
+            RenameNx(cmd) => cmd.apply(db).await,
+            BitField(cmd) => cmd.apply(db, config).await,
+            ZRangeByLex(cmd) => cmd.apply(db).await,
+            ZPopMin(cmd) => cmd.apply(db).await,
+            ZPopMax(cmd) => cmd.apply(db).await,
+            BZPopMin(cmd) => cmd.apply(db).await,
+            BZPopMax(cmd) => cmd.apply(db).await,
+            Hello(cmd) => cmd.apply(dst, config).await,
+            Ttl(cmd) => cmd.apply(db).await,
+            Pttl(cmd) => cmd.apply(db).await,
+            Save(cmd) => cmd.apply(db, config).await,
+            BgSave(cmd) => cmd.apply(db, config).await,
+            Shutdown(cmd) => cmd.apply(config).await,
+            LPush(cmd) => cmd.apply(db).await,
+            RPush(cmd) => cmd.apply(db).await,
+            LPop(cmd) => cmd.apply(db).await,
+            RPop(cmd) => cmd.apply(db).await,
+            ZScore(cmd) => cmd.apply(db).await,
+            ZAdd(cmd) => cmd.apply(db).await,
+            ZRank(cmd) => cmd.apply(db).await,
+            LMPop(cmd) => cmd.apply(db).await,
+            ZMPop(cmd) => cmd.apply(db).await,
+            ZRange(cmd) => cmd.apply(db).await,
+            ZRangeByScore(cmd) => cmd.apply(db).await,
+            BLMPop(cmd) => cmd.apply(db).await,
+            BZMPop(cmd) => cmd.apply(db).await,
+            LRange(cmd) => cmd.apply(db).await,
+            LLen(cmd) => cmd.apply(db).await,
+            BLPop(cmd) => cmd.apply(db).await,
+            BRPop(cmd) => cmd.apply(db).await,
+            HSet(cmd) => cmd.apply(db, config).await,
+            HGet(cmd) => cmd.apply(db).await,
+            HDel(cmd) => cmd.apply(db).await,
+            HExpire(cmd) => cmd.apply(db).await,
+            HTtl(cmd) => cmd.apply(db).await,
+            HGetAll(cmd) => cmd.apply(db).await,
+            HIncrBy(cmd) => cmd.apply(db, config).await,
+            SAdd(cmd) => cmd.apply(db, config).await,
+            SRem(cmd) => cmd.apply(db).await,
+            SMembers(cmd) => cmd.apply(db).await,
+            SIsMember(cmd) => cmd.apply(db).await,
+            SCard(cmd) => cmd.apply(db).await,
+            SInter(cmd) => cmd.apply(db).await,
+            SUnion(cmd) => cmd.apply(db).await,
+            SDiff(cmd) => cmd.apply(db).await,
+        };
+
+        match result {
+            Err(err) => match err.downcast::<CommandError>() {
+                Ok(err) => Ok(Some(RESP::Error(err.to_string()))),
+                Err(err) => Err(err),
+            },
+            resp => resp,
         }
     }
 
+    /// The registry entry of the command, `None` for an unknown command
+    pub fn spec(&self) -> Option<&'static CommandSpec> {
+        registry::COMMANDS.iter().find(|spec| (spec.is)(self))
+    }
+
     pub fn get_name(&self) -> String {
-        match self {
-            Command::Config(_) => "config".to_string(),
-            Command::Echo(_) => "echo".to_string(),
-            Command::Ping(_) => "ping".to_string(),
-            Command::Set(_) => "set".to_string(),
-            Command::Get(_) => "get".to_string(),
-            Command::Info(_) => "info".to_string(),
-            Command::Replconf(_) => "replconf".to_string(),
-            Command::PSync(_) => "psync".to_string(),
-            Command::Wait(_) => "wait".to_string(),
-            Command::Keys(_) => "keys".to_string(),
-            Command::Type(_) => "type".to_string(),
-            Command::XAdd(_) => "xadd".to_string(),
-            Command::XRange(_) => "xrange".to_string(),
-            Command::XRead(_) => "xread".to_string(),
-            Command::Incr(_) => "incr".to_string(),
-            Command::Multi(_) => "multi".to_string(),
-            Command::Exec(_) => "exec".to_string(),
-            Command::Discard(_) => "discard".to_string(),
-            Command::Unknown(_) => "unknown".into(),
+        match self.spec() {
+            Some(spec) => spec.name.to_string(),
+            None => match self {
+                Command::Unknown(cmd) => cmd.get_name().to_string(),
+                command => unreachable!("{command:?} isn't registered"),
+            },
         }
     }
 
+    /// Whether the command is flagged `write` in the registry
+    pub fn is_write(&self) -> bool {
+        self.has_flag("write")
+    }
+
+    /// Whether the request is sent to replicas as is. Blocking pops
+    /// and XADD are sent as the write they ended up doing instead,
+    /// it depends on when they ran
     pub fn is_replicable_command(&self) -> bool {
-        match self {
-            Command::Set(_) => true,
-            _ => false,
-        }
+        self.is_write() && !self.has_flag("blocking") && !matches!(self, Command::XAdd(_))
+    }
+
+    /// Whether the registry lists `flag` for the command
+    fn has_flag(&self, flag: &str) -> bool {
+        self.spec().is_some_and(|spec| spec.flags.contains(&flag))
     }
 
     pub fn affects_offset(&self) -> bool {
-        match self {
-            Command::Set(_) => true,
-            _ => false,
-        }
+        self.is_replicable_command()
     }
 }
 
@@ -189,6 +383,9 @@ pub struct RespReader {
 #[derive(Debug)]
 pub enum RespReaderError {
     EndOfStream,
+    /// An argument the client gets an error reply for, rather than
+    /// losing the connection
+    Command(CommandError),
     Other(String),
 }
 
@@ -244,18 +441,41 @@ impl RespReader {
     /// Return the next entry as an integer
     ///
     /// Only `Integer`, `Bulk`, and `Simple` are allowed to be
-    /// converted to u64 before returned
+    /// converted to u64 before returned, one that doesn't parse is a
+    /// `CommandError::NotInteger` the client is replied with
     pub fn next_int(&mut self) -> Result<u64, RespReaderError> {
+        match self.next()? {
+            RESP::Integer(int) => u64::try_from(int).map_err(|_| CommandError::NotInteger.into()),
+            RESP::Simple(s) => {
+                convert_string_to_u64(s).map_err(|_| CommandError::NotInteger.into())
+            }
+            RESP::Bulk(data) => {
+                convert_bytes_to_u64(data).map_err(|_| CommandError::NotInteger.into())
+            }
+            other => {
+                return Err(
+                    format!("Expected `RESP::Simple` or `RESP::Bulk but got {:?}", other).into(),
+                )
+            }
+        }
+    }
+
+    /// Return the next entry as a signed integer
+    ///
+    /// Only `Integer`, `Bulk`, and `Simple` are allowed to be
+    /// converted to i64 before returned
+    pub fn next_signed_int(&mut self) -> Result<i64, RespReaderError> {
         match self.next()? {
             RESP::Integer(int) => Ok(int),
-            RESP::Simple(s) => convert_string_to_u64(s).map_err(|_| "Invalid integer".into()),
-            RESP::Bulk(data) => convert_bytes_to_u64(data).map_err(|_| "Invalid integer".into()),
+            RESP::Simple(s) => convert_bytes_to_i64(Bytes::from(s)),
+            RESP::Bulk(data) => convert_bytes_to_i64(data),
             other => {
                 return Err(
                     format!("Expected `RESP::Simple` or `RESP::Bulk but got {:?}", other).into(),
                 )
             }
         }
+        .map_err(|_| CommandError::NotInteger.into())
     }
 
     /// Check if RESP has been exhausted from the reader
@@ -277,10 +497,83 @@ pub fn convert_bytes_to_u64(bytes: bytes::Bytes) -> Result<u64, String> {
     Ok(int)
 }
 
+pub fn convert_bytes_to_i64(bytes: bytes::Bytes) -> Result<i64, String> {
+    std::str::from_utf8(&bytes)
+        .ok()
+        .and_then(|string| string.parse::<i64>().ok())
+        .ok_or_else(|| "Cannot parse i64 from bytes".to_string())
+}
+
 pub fn convert_string_to_u64(string: String) -> Result<u64, String> {
     convert_bytes_to_u64(bytes::Bytes::from(string))
 }
 
+/// Match an option or subcommand argument against `keyword`, ignoring
+/// ASCII case the way redis does so `PX`, `Px` and `px` are the same
+pub fn is_keyword(arg: &str, keyword: &str) -> bool {
+    arg.eq_ignore_ascii_case(keyword)
+}
+
+/// Parse the `key [key ...] timeout` arguments of a blocking pop, the
+/// timeout is the last argument
+pub fn parse_blocking_args(reader: &mut RespReader) -> Result<(Vec<String>, f64), RespReaderError> {
+    let mut keys = vec![reader.next_string()?, reader.next_string()?];
+    while let Ok(key) = reader.next_string() {
+        keys.push(key);
+    }
+
+    let timeout = keys.pop().expect("at least two arguments were read");
+
+    Ok((keys, parse_timeout(&timeout)?))
+}
+
+/// Parse the timeout of a blocking command, seconds as a non-negative
+/// float where 0 blocks forever
+pub fn parse_timeout(timeout: &str) -> Result<f64, RespReaderError> {
+    match timeout.parse::<f64>() {
+        Ok(timeout) if timeout >= 0.0 && timeout.is_finite() => Ok(timeout),
+        _ => Err(CommandError::Timeout.into()),
+    }
+}
+
+/// Parse the `numkeys key [key ...] <from> [COUNT count]` arguments of
+/// a multi-key pop, `from` is one of the two `directions`
+///
+/// Returns the keys, whether the pop is from the second direction and
+/// the count, which defaults to 1
+pub fn parse_mpop_args(
+    reader: &mut RespReader,
+    directions: [&str; 2],
+) -> Result<(Vec<String>, bool, usize), RespReaderError> {
+    let numkeys = match reader.next_string()?.parse::<usize>() {
+        Ok(numkeys) if numkeys > 0 => numkeys,
+        _ => return Err(CommandError::NumKeys.into()),
+    };
+
+    let keys = (0..numkeys)
+        .map(|_| reader.next_string())
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let from = reader.next_string()?;
+    let second = match directions {
+        [first, _] if is_keyword(&from, first) => false,
+        [_, second] if is_keyword(&from, second) => true,
+        _ => return Err(CommandError::SyntaxError.into()),
+    };
+
+    let count = match reader.next_string() {
+        Ok(option) if is_keyword(&option, "count") => match reader.next_string()?.parse() {
+            Ok(count) if count > 0 => count,
+            _ => return Err(CommandError::Count.into()),
+        },
+        Ok(_) => return Err(CommandError::SyntaxError.into()),
+        Err(RespReaderError::EndOfStream) => 1,
+        Err(err) => return Err(err),
+    };
+
+    Ok((keys, second, count))
+}
+
 // Implement standard error
 impl std::error::Error for RespReaderError {}
 
@@ -290,6 +583,12 @@ impl From<String> for RespReaderError {
     }
 }
 
+impl From<CommandError> for RespReaderError {
+    fn from(value: CommandError) -> Self {
+        RespReaderError::Command(value)
+    }
+}
+
 impl From<&str> for RespReaderError {
     fn from(value: &str) -> Self {
         value.to_string().into()
@@ -300,6 +599,7 @@ impl std::fmt::Display for RespReaderError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             RespReaderError::EndOfStream => "RespReader reached end of stream".fmt(f),
+            RespReaderError::Command(err) => err.fmt(f),
             RespReaderError::Other(reason) => reason.fmt(f),
         }
     }
@@ -307,9 +607,32 @@ impl std::fmt::Display for RespReaderError {
 
 #[cfg(test)]
 mod test {
+    use bytes::Bytes;
+
+    use super::{RespReader, RespReaderError};
+    use crate::resp::RESP;
+
     // write tests for the RespReader
     #[test]
     fn create_reader() {
         todo!()
     }
+
+    #[test]
+    fn reader_walks_the_array_in_order() {
+        let resp = RESP::Array(vec![
+            RESP::Bulk(Bytes::from("SET")),
+            RESP::Simple("key".into()),
+            RESP::Integer(10),
+        ]);
+
+        let mut reader = RespReader::new(resp).unwrap();
+        assert_eq!(reader.next_string().unwrap(), "SET");
+        assert_eq!(reader.next_byte().unwrap(), Bytes::from("key"));
+        assert_eq!(reader.next_int().unwrap(), 10);
+        assert!(matches!(reader.next(), Err(RespReaderError::EndOfStream)));
+        assert!(reader.finish().is_ok());
+
+        assert!(RespReader::new(RESP::Simple("PING".into())).is_err());
+    }
 }
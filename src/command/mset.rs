@@ -0,0 +1,183 @@
+use bytes::Bytes;
+
+use crate::{resp::RESP, Db, RespReader, RespReaderError, ValueType};
+
+#[derive(Debug, Default)]
+pub struct MSet {
+    /// key value pairs to store in db
+    pairs: Vec<(String, Bytes)>,
+}
+
+#[derive(Debug, Default)]
+pub struct MGet {
+    /// cache lookup keys
+    keys: Vec<String>,
+}
+
+impl MSet {
+    /// contruct new MSet command
+    pub fn new(pairs: Vec<(String, Bytes)>) -> Self {
+        MSet { pairs }
+    }
+
+    /// Construct new MSet command by consuming the RespReader
+    ///
+    /// Entries are read as alternating keys and values, a key
+    /// without a value returns `RespReaderError::EndOfStream`
+    pub fn from_parts(reader: &mut RespReader) -> Result<Self, RespReaderError> {
+        let mut pairs = vec![(reader.next_string()?, reader.next_byte()?)];
+
+        while let Ok(key) = reader.next_string() {
+            let value = reader.next_byte()?;
+            pairs.push((key, value));
+        }
+
+        Ok(MSet { pairs })
+    }
+
+    /// Apply the mset command setting all the keys at once
+    pub async fn apply(self, db: &Db) -> crate::Result<Option<RESP>> {
+        let pairs = self
+            .pairs
+            .into_iter()
+            .map(|(key, value)| (key, ValueType::String(value)))
+            .collect();
+
+        db.set_all(pairs);
+
+        Ok(Some(RESP::Simple("OK".into())))
+    }
+}
+
+impl MGet {
+    /// contruct new MGet command
+    pub fn new(keys: Vec<String>) -> Self {
+        MGet { keys }
+    }
+
+    /// Construct new MGet command by consuming the RespReader
+    ///
+    /// Every remaining entry in the reader is a key to look up
+    pub fn from_parts(reader: &mut RespReader) -> Result<Self, RespReaderError> {
+        let mut keys = vec![reader.next_string()?];
+
+        while let Ok(key) = reader.next_string() {
+            keys.push(key);
+        }
+
+        Ok(MGet { keys })
+    }
+
+    /// Apply the mget command, keys that don't hold a string are `Null`
+    pub async fn apply(self, db: &Db) -> crate::Result<Option<RESP>> {
+        let values = db
+            .get_all(&self.keys)
+            .into_iter()
+            .map(|value| match value {
+                Some(ValueType::String(bytes)) => RESP::Bulk(bytes),
+                _ => RESP::Null,
+            })
+            .collect();
+
+        Ok(Some(RESP::Array(values)))
+    }
+}
+
+/// Convert MSet command back into an equivalent `RESP`
+impl From<MSet> for RESP {
+    fn from(value: MSet) -> Self {
+        let mut resp = RESP::array();
+        resp.push_bulk(Bytes::from("mset"));
+        for (key, value) in value.pairs {
+            resp.push_bulk(Bytes::from(key));
+            resp.push_bulk(value);
+        }
+        resp
+    }
+}
+
+/// Convert MGet command back into an equivalent `RESP`
+impl From<MGet> for RESP {
+    fn from(value: MGet) -> Self {
+        let mut resp = RESP::array();
+        resp.push_bulk(Bytes::from("mget"));
+        for key in value.keys {
+            resp.push_bulk(Bytes::from(key));
+        }
+        resp
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bytes::Bytes;
+
+    use super::{MGet, MSet};
+    use crate::{
+        resp::RESP, Command, CommandError, Db, RespReader, RespReaderError, Stream, ValueType,
+    };
+
+    fn reader(args: &[&'static str]) -> RespReader {
+        let parts = args.iter().map(|arg| RESP::Bulk(Bytes::from(*arg)));
+        RespReader::new(RESP::Array(parts.collect())).unwrap()
+    }
+
+    #[test]
+    fn mset_rejects_odd_arguments() {
+        assert!(matches!(
+            MSet::from_parts(&mut reader(&["a"])),
+            Err(RespReaderError::EndOfStream)
+        ));
+        assert!(matches!(
+            MSet::from_parts(&mut reader(&["a", "1", "b"])),
+            Err(RespReaderError::EndOfStream)
+        ));
+        assert!(MSet::from_parts(&mut reader(&["a", "1", "b", "2"])).is_ok());
+
+        let err = Command::from_resp(RESP::Array(vec![
+            RESP::Bulk(Bytes::from("MSET")),
+            RESP::Bulk(Bytes::from("a")),
+        ]))
+        .unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<CommandError>(),
+            Some(&CommandError::WrongArity("mset".into()))
+        );
+    }
+
+    #[tokio::test]
+    async fn mset_then_mget() {
+        let db = Db::new();
+        db.set("stream".into(), ValueType::Stream(Stream::default()), None);
+
+        MSet::new(vec![
+            ("a".into(), Bytes::from("1")),
+            ("b".into(), Bytes::from("2")),
+        ])
+        .apply(&db)
+        .await
+        .unwrap();
+
+        let resp = MGet::new(vec![
+            "a".into(),
+            "missing".into(),
+            "stream".into(),
+            "b".into(),
+        ])
+        .apply(&db)
+        .await
+        .unwrap();
+
+        match resp {
+            Some(RESP::Array(values)) => {
+                assert!(matches!(&values[..], [
+                    RESP::Bulk(a),
+                    RESP::Null,
+                    RESP::Null,
+                    RESP::Bulk(b),
+                ] if a == "1" && b == "2"));
+            }
+            other => panic!("Expected array reply, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,57 @@
+use bytes::Bytes;
+
+use crate::{resp::RESP, CommandError, Db, RespReader, RespReaderError};
+
+#[derive(Debug, Default)]
+pub struct Object {
+    subcommand: String,
+    key: Option<String>,
+}
+
+impl Object {
+    /// contruct new Object command
+    pub fn new(subcommand: String, key: Option<String>) -> Self {
+        Object { subcommand, key }
+    }
+
+    /// Construct new Object command by consuming the RespReader
+    ///
+    /// The key is optional since subcommands like `HELP` don't take one
+    pub fn from_parts(reader: &mut RespReader) -> Result<Self, RespReaderError> {
+        let subcommand = reader.next_string()?;
+
+        let key = match reader.next_string() {
+            Ok(key) => Some(key),
+            Err(RespReaderError::EndOfStream) => None,
+            Err(err) => return Err(err),
+        };
+
+        Ok(Object { subcommand, key })
+    }
+
+    /// Apply the object command and return the introspected value
+    pub async fn apply(self, db: &Db) -> crate::Result<Option<RESP>> {
+        let resp = match (self.subcommand.to_lowercase().as_str(), self.key) {
+            ("encoding", Some(key)) => match db.get(&key) {
+                Some(value) => RESP::Bulk(Bytes::from(value.encoding())),
+                None => RESP::Null,
+            },
+            _ => return Err(CommandError::UnknownSubcommand("OBJECT", self.subcommand).into()),
+        };
+
+        Ok(Some(resp))
+    }
+}
+
+/// Convert Object command back into an equivalent `RESP`
+impl From<Object> for RESP {
+    fn from(value: Object) -> Self {
+        let mut resp = RESP::array();
+        resp.push_bulk(Bytes::from("object"));
+        resp.push_bulk(Bytes::from(value.subcommand));
+        if let Some(key) = value.key {
+            resp.push_bulk(Bytes::from(key));
+        }
+        resp
+    }
+}
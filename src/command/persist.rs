@@ -0,0 +1,75 @@
+use bytes::Bytes;
+
+use crate::{resp::RESP, Db, RespReader, RespReaderError};
+
+#[derive(Debug, Default)]
+pub struct Persist {
+    /// cache lookup key
+    key: String,
+}
+
+impl Persist {
+    /// contruct new Persist command
+    pub fn new(key: String) -> Self {
+        Persist { key }
+    }
+
+    /// Construct new Persist command by consuming the RespReader
+    pub fn from_parts(reader: &mut RespReader) -> Result<Self, RespReaderError> {
+        let key = reader.next_string()?;
+
+        Ok(Persist { key })
+    }
+
+    /// Apply the persist command, returns 1 if an expiry was removed
+    pub async fn apply(self, db: &Db) -> crate::Result<Option<RESP>> {
+        if db.persist(&self.key) {
+            Ok(Some(RESP::Integer(1)))
+        } else {
+            Ok(Some(RESP::Integer(0)))
+        }
+    }
+}
+
+/// Convert Persist command back into an equivalent `RESP`
+impl From<Persist> for RESP {
+    fn from(value: Persist) -> Self {
+        let mut resp = RESP::array();
+        resp.push_bulk(Bytes::from("persist"));
+        resp.push_bulk(Bytes::from(value.key));
+        resp
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bytes::Bytes;
+    use tokio::time::Duration;
+
+    use super::Persist;
+    use crate::{resp::RESP, Db, ValueType};
+
+    #[tokio::test]
+    async fn persist_strips_expiry() {
+        let db = Db::new();
+        db.set(
+            "key".into(),
+            ValueType::String(Bytes::from("1")),
+            Some(Duration::from_millis(10)),
+        );
+
+        let resp = Persist::new("key".into()).apply(&db).await.unwrap();
+        assert!(matches!(resp, Some(RESP::Integer(1))));
+        assert!(db.inner.state.lock().unwrap().next_expiration().is_none());
+
+        // the purge task must leave the now persistent key alone
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(db.get("key").is_some());
+
+        let resp = Persist::new("key".into()).apply(&db).await.unwrap();
+        assert!(matches!(resp, Some(RESP::Integer(0))));
+
+        let resp = Persist::new("missing".into()).apply(&db).await.unwrap();
+        assert!(matches!(resp, Some(RESP::Integer(0))));
+    }
+}
@@ -1,4 +1,4 @@
-use crate::{connection::Connection, resp::RESP, Db, RespReader, RespReaderError};
+use crate::{connection::Connection, rdb::encode_rdb, resp::RESP, Db, RespReader, RespReaderError};
 use bytes::Bytes;
 
 #[allow(unused_imports)]
@@ -14,15 +14,6 @@ pub struct PSync {
 
 pub const EMPTY_DB_FILE: &str = "524544495330303131fa0972656469732d76657205372e322e30fa0a72656469732d62697473c040fa056374696d65c26d08bc65fa08757365642d6d656dc2b0c41000fa08616f662d62617365c000fff06e3bfec0ff5aa2";
 
-static EMPTY_RDB_FILE_: [u8; 88] = [
-    0x52, 0x45, 0x44, 0x49, 0x53, 0x30, 0x30, 0x31, 0x31, 0xfa, 0x09, 0x72, 0x65, 0x64, 0x69, 0x73,
-    0x2d, 0x76, 0x65, 0x72, 0x05, 0x37, 0x2e, 0x32, 0x2e, 0x30, 0xfa, 0x0a, 0x72, 0x65, 0x64, 0x69,
-    0x73, 0x2d, 0x62, 0x69, 0x74, 0x73, 0xc0, 0x40, 0xfa, 0x05, 0x63, 0x74, 0x69, 0x6d, 0x65, 0xc2,
-    0x6d, 0x08, 0xbc, 0x65, 0xfa, 0x08, 0x75, 0x73, 0x65, 0x64, 0x2d, 0x6d, 0x65, 0x6d, 0xc2, 0xb0,
-    0xc4, 0x10, 0x00, 0xfa, 0x08, 0x61, 0x6f, 0x66, 0x2d, 0x62, 0x61, 0x73, 0x65, 0xc0, 0x00, 0xff,
-    0xf0, 0x6e, 0x3b, 0xfe, 0xc0, 0xff, 0x5a, 0xa2,
-];
-
 #[allow(unused)]
 pub(crate) fn empty_rdb_file() -> Vec<u8> {
     let rdb_bytes: Vec<u8> = (0..EMPTY_DB_FILE.len())
@@ -55,7 +46,7 @@ impl PSync {
         Ok(PSync { key, value })
     }
 
-    /// Apply the echo command and write to the Tcp connection stream
+    /// Reply with FULLRESYNC followed by an RDB snapshot of the dataset
     pub async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<Option<RESP>> {
         let (replid, _) = db.get_repl_info();
         let replid = replid.unwrap();
@@ -63,9 +54,8 @@ impl PSync {
         println!("Write full sync 1");
         dst.write_frame(&resp).await?;
 
-        // write empty rdb file
-        dst.write_frame(&RESP::File(EMPTY_RDB_FILE_.as_slice().into()))
-            .await?;
+        // the replica starts out with a snapshot of every database
+        dst.write_rdb_file(&encode_rdb(&db.snapshot())).await?;
 
         println!("RDB file sent!!!");
 
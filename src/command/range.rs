@@ -0,0 +1,234 @@
+use bytes::Bytes;
+
+use crate::{
+    config::ServerConfig, resp::RESP, CommandError, Db, RespReader, RespReaderError, ValueType,
+};
+
+#[derive(Debug, Default)]
+pub struct GetRange {
+    /// cache lookup key
+    key: String,
+
+    /// first byte offset, negative offsets count from the end
+    start: i64,
+
+    /// last byte offset (inclusive), negative offsets count from the end
+    end: i64,
+}
+
+#[derive(Debug, Default)]
+pub struct SetRange {
+    /// cache lookup key
+    key: String,
+
+    /// byte offset to start writing at
+    offset: u64,
+
+    /// bytes to write
+    value: Bytes,
+}
+
+impl GetRange {
+    /// contruct new GetRange command
+    pub fn new(key: String, start: i64, end: i64) -> Self {
+        GetRange { key, start, end }
+    }
+
+    /// Construct new GetRange command by consuming the RespReader
+    pub fn from_parts(reader: &mut RespReader) -> Result<Self, RespReaderError> {
+        let key = reader.next_string()?;
+        let start = reader.next_signed_int()?;
+        let end = reader.next_signed_int()?;
+
+        Ok(GetRange { key, start, end })
+    }
+
+    /// Apply the getrange command and return the substring
+    pub async fn apply(self, db: &Db) -> crate::Result<Option<RESP>> {
+        let bytes = match db.get(&self.key) {
+            Some(ValueType::String(bytes)) => bytes,
+            Some(_) => return Err(CommandError::WrongType.into()),
+            None => Bytes::new(),
+        };
+
+        let range = index_range(bytes.len(), self.start, self.end);
+
+        Ok(Some(RESP::Bulk(bytes.slice(range))))
+    }
+}
+
+/// Resolve inclusive, possibly negative, offsets into a range of a
+/// string of `len` bytes, or a list of `len` elements, the way redis
+/// does, out of range offsets are clamped and an inverted range is empty
+pub fn index_range(len: usize, start: i64, end: i64) -> std::ops::Range<usize> {
+    let len = len as i64;
+
+    if len == 0 || (start < 0 && end < 0 && start > end) {
+        return 0..0;
+    }
+
+    let start = if start < 0 {
+        (len + start).max(0)
+    } else {
+        start
+    };
+    let end = if end < 0 {
+        (len + end).max(0)
+    } else {
+        end.min(len - 1)
+    };
+
+    if start > end {
+        return 0..0;
+    }
+
+    start as usize..end as usize + 1
+}
+
+impl SetRange {
+    /// contruct new SetRange command
+    pub fn new(key: String, offset: u64, value: Bytes) -> Self {
+        SetRange { key, offset, value }
+    }
+
+    /// Construct new SetRange command by consuming the RespReader
+    pub fn from_parts(reader: &mut RespReader) -> Result<Self, RespReaderError> {
+        let key = reader.next_string()?;
+        let offset = reader.next_int()?;
+        let value = reader.next_byte()?;
+
+        Ok(SetRange { key, offset, value })
+    }
+
+    /// Apply the setrange command and return the length of the string,
+    /// the string can't grow past `proto-max-bulk-len`
+    pub async fn apply(self, db: &Db, config: ServerConfig) -> crate::Result<Option<RESP>> {
+        // an empty write never creates or pads the string
+        if self.value.is_empty() {
+            let len = match db.get(&self.key) {
+                Some(ValueType::String(bytes)) => bytes.len(),
+                Some(_) => return Err(CommandError::WrongType.into()),
+                None => 0,
+            };
+            return Ok(Some(RESP::Integer(len as i64)));
+        }
+
+        if self.offset.saturating_add(self.value.len() as u64) > config.proto_max_bulk_len as u64 {
+            return Err(CommandError::StringTooLong.into());
+        }
+
+        let len = db.set_range(self.key, self.offset as usize, &self.value)?;
+
+        Ok(Some(RESP::Integer(len as i64)))
+    }
+}
+
+/// Convert GetRange command back into an equivalent `RESP`
+impl From<GetRange> for RESP {
+    fn from(value: GetRange) -> Self {
+        let mut resp = RESP::array();
+        resp.push_bulk(Bytes::from("getrange"));
+        resp.push_bulk(Bytes::from(value.key));
+        resp.push_bulk(Bytes::from(value.start.to_string()));
+        resp.push_bulk(Bytes::from(value.end.to_string()));
+        resp
+    }
+}
+
+/// Convert SetRange command back into an equivalent `RESP`
+impl From<SetRange> for RESP {
+    fn from(value: SetRange) -> Self {
+        let mut resp = RESP::array();
+        resp.push_bulk(Bytes::from("setrange"));
+        resp.push_bulk(Bytes::from(value.key));
+        resp.push_bulk(Bytes::from(value.offset.to_string()));
+        resp.push_bulk(value.value);
+        resp
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bytes::Bytes;
+
+    use super::{index_range, GetRange, SetRange};
+    use crate::{config::ServerConfig, resp::RESP, CommandError, Db, ValueType};
+
+    #[test]
+    fn index_range_matches_redis() {
+        // "This is a string"
+        assert_eq!(index_range(16, 0, 3), 0..4);
+        assert_eq!(index_range(16, -3, -1), 13..16);
+        assert_eq!(index_range(16, 0, -1), 0..16);
+        assert_eq!(index_range(16, 10, 100), 10..16);
+        assert_eq!(index_range(16, -100, 2), 0..3);
+        assert_eq!(index_range(16, 5, 2), 0..0);
+        assert_eq!(index_range(16, -1, -5), 0..0);
+        assert_eq!(index_range(0, 0, -1), 0..0);
+    }
+
+    #[tokio::test]
+    async fn getrange_binary_and_missing_key() {
+        let db = Db::new();
+        db.set(
+            "key".into(),
+            ValueType::String(Bytes::from_static(&[0x00, 0xff, b'\r', b'\n'])),
+            None,
+        );
+
+        let resp = GetRange::new("key".into(), 1, -2).apply(&db).await.unwrap();
+        assert!(matches!(resp, Some(RESP::Bulk(b)) if b == [0xff, b'\r'][..]));
+
+        let resp = GetRange::new("missing".into(), 0, -1)
+            .apply(&db)
+            .await
+            .unwrap();
+        assert!(matches!(resp, Some(RESP::Bulk(b)) if b.is_empty()));
+    }
+
+    #[tokio::test]
+    async fn setrange_pads_and_overwrites() {
+        let db = Db::new();
+
+        let resp = SetRange::new("key".into(), 3, Bytes::from_static(&[0xff]))
+            .apply(&db, ServerConfig::for_test())
+            .await
+            .unwrap();
+        assert!(matches!(resp, Some(RESP::Integer(4))));
+        assert!(matches!(db.get("key"), Some(ValueType::String(v)) if v == [0, 0, 0, 0xff][..]));
+
+        let resp = SetRange::new("key".into(), 1, Bytes::from("ab"))
+            .apply(&db, ServerConfig::for_test())
+            .await
+            .unwrap();
+        assert!(matches!(resp, Some(RESP::Integer(4))));
+        assert!(
+            matches!(db.get("key"), Some(ValueType::String(v)) if v == [0, b'a', b'b', 0xff][..])
+        );
+
+        // an empty write leaves a missing key alone
+        let resp = SetRange::new("missing".into(), 10, Bytes::new())
+            .apply(&db, ServerConfig::for_test())
+            .await
+            .unwrap();
+        assert!(matches!(resp, Some(RESP::Integer(0))));
+        assert!(db.get("missing").is_none());
+    }
+
+    #[tokio::test]
+    async fn setrange_past_proto_max_bulk_len_is_an_error() {
+        let db = Db::new();
+        let mut config = ServerConfig::for_test();
+        config.proto_max_bulk_len = 4;
+
+        let err = SetRange::new("key".into(), 3, Bytes::from("ab"))
+            .apply(&db, config)
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<CommandError>(),
+            Some(&CommandError::StringTooLong)
+        );
+        assert!(db.get("key").is_none());
+    }
+}
@@ -0,0 +1,1513 @@
+use bytes::Bytes;
+
+use super::{
+    docs::{arg, multiple, optional, CommandDoc},
+    *,
+};
+use crate::{resp::RESP, RespReader, RespReaderError};
+
+/// Builds a command from the arguments following its name
+pub type Parse = fn(&mut RespReader) -> Result<Command, RespReaderError>;
+
+/// A supported command, what COMMAND reports about it and how
+/// it's built from a request
+#[derive(Debug)]
+pub struct CommandSpec {
+    pub name: &'static str,
+    /// number of arguments including the name, negative when it's
+    /// the minimum number of arguments
+    pub arity: i64,
+    pub flags: &'static [&'static str],
+    /// position of the first key, the last key and the step between keys
+    pub keys: (i64, i64, i64),
+    pub parse: Parse,
+    /// Whether a built command is this one
+    pub is: fn(&Command) -> bool,
+    pub doc: CommandDoc,
+}
+
+/// Every supported command, sorted by name
+pub const COMMANDS: &[CommandSpec] = &[
+    CommandSpec {
+        name: "append",
+        arity: 3,
+        flags: &["write", "denyoom"],
+        keys: (1, 1, 1),
+        parse: |reader| Ok(Command::Append(Append::from_parts(reader)?)),
+        is: |command| matches!(command, Command::Append(_)),
+        doc: CommandDoc {
+            summary: "Appends a string to the value of a key. Creates the key if it doesn't exist.",
+            since: "2.0.0",
+            group: "string",
+            arguments: &[arg("key", "key"), arg("value", "string")],
+        },
+    },
+    CommandSpec {
+        name: "bgsave",
+        arity: -1,
+        flags: &["admin", "noscript", "no_async_loading", "no_multi"],
+        keys: (0, 0, 0),
+        parse: |reader| Ok(Command::BgSave(BgSave::from_parts(reader)?)),
+        is: |command| matches!(command, Command::BgSave(_)),
+        doc: CommandDoc {
+            summary: "Asynchronously saves the database(s) to disk.",
+            since: "1.0.0",
+            group: "server",
+            arguments: &[],
+        },
+    },
+    CommandSpec {
+        name: "bitfield",
+        arity: -2,
+        flags: &["write", "denyoom"],
+        keys: (1, 1, 1),
+        parse: |reader| Ok(Command::BitField(BitField::from_parts(reader)?)),
+        is: |command| matches!(command, Command::BitField(_)),
+        doc: CommandDoc {
+            summary: "Performs arbitrary bitfield integer operations on strings.",
+            since: "3.2.0",
+            group: "bitmap",
+            arguments: &[arg("key", "key"), multiple("operation", "oneof")],
+        },
+    },
+    CommandSpec {
+        name: "blmpop",
+        arity: -5,
+        flags: &["write", "blocking", "movablekeys"],
+        keys: (0, 0, 0),
+        parse: |reader| Ok(Command::BLMPop(BLMPop::from_parts(reader)?)),
+        is: |command| matches!(command, Command::BLMPop(_)),
+        doc: CommandDoc {
+            summary: "Pops the first element from one of multiple lists. Blocks until an element is available otherwise. Deletes the list if the last element was popped.",
+            since: "7.0.0",
+            group: "list",
+            arguments: &[
+                arg("timeout", "double"),
+                arg("numkeys", "integer"),
+                multiple("key", "key"),
+                arg("where", "oneof"),
+                optional("count", "integer"),
+            ],
+        },
+    },
+    CommandSpec {
+        name: "blpop",
+        arity: -3,
+        flags: &["write", "blocking"],
+        keys: (1, -2, 1),
+        parse: |reader| Ok(Command::BLPop(BLPop::from_parts(reader)?)),
+        is: |command| matches!(command, Command::BLPop(_)),
+        doc: CommandDoc {
+            summary: "Removes and returns the first element in a list. Blocks until an element is available otherwise. Deletes the list if the last element was popped.",
+            since: "2.0.0",
+            group: "list",
+            arguments: &[multiple("key", "key"), arg("timeout", "double")],
+        },
+    },
+    CommandSpec {
+        name: "brpop",
+        arity: -3,
+        flags: &["write", "blocking"],
+        keys: (1, -2, 1),
+        parse: |reader| Ok(Command::BRPop(BRPop::from_parts(reader)?)),
+        is: |command| matches!(command, Command::BRPop(_)),
+        doc: CommandDoc {
+            summary: "Removes and returns the last element in a list. Blocks until an element is available otherwise. Deletes the list if the last element was popped.",
+            since: "2.0.0",
+            group: "list",
+            arguments: &[multiple("key", "key"), arg("timeout", "double")],
+        },
+    },
+    CommandSpec {
+        name: "bzmpop",
+        arity: -5,
+        flags: &["write", "blocking", "movablekeys"],
+        keys: (0, 0, 0),
+        parse: |reader| Ok(Command::BZMPop(BZMPop::from_parts(reader)?)),
+        is: |command| matches!(command, Command::BZMPop(_)),
+        doc: CommandDoc {
+            summary: "Removes and returns a member by score from one or more sorted sets. Blocks until a member is available otherwise. Deletes the sorted set if the last element was popped.",
+            since: "7.0.0",
+            group: "sorted-set",
+            arguments: &[
+                arg("timeout", "double"),
+                arg("numkeys", "integer"),
+                multiple("key", "key"),
+                arg("where", "oneof"),
+                optional("count", "integer"),
+            ],
+        },
+    },
+    CommandSpec {
+        name: "bzpopmax",
+        arity: -3,
+        flags: &["write", "blocking"],
+        keys: (1, -2, 1),
+        parse: |reader| Ok(Command::BZPopMax(BZPopMax::from_parts(reader)?)),
+        is: |command| matches!(command, Command::BZPopMax(_)),
+        doc: CommandDoc {
+            summary: "Removes and returns the member with the highest score from one or more sorted sets. Blocks until a member is available otherwise.",
+            since: "5.0.0",
+            group: "sorted-set",
+            arguments: &[multiple("key", "key"), arg("timeout", "double")],
+        },
+    },
+    CommandSpec {
+        name: "bzpopmin",
+        arity: -3,
+        flags: &["write", "blocking"],
+        keys: (1, -2, 1),
+        parse: |reader| Ok(Command::BZPopMin(BZPopMin::from_parts(reader)?)),
+        is: |command| matches!(command, Command::BZPopMin(_)),
+        doc: CommandDoc {
+            summary: "Removes and returns the member with the lowest score from one or more sorted sets. Blocks until a member is available otherwise.",
+            since: "5.0.0",
+            group: "sorted-set",
+            arguments: &[multiple("key", "key"), arg("timeout", "double")],
+        },
+    },
+    CommandSpec {
+        name: "client",
+        arity: -2,
+        flags: &["admin", "stale"],
+        keys: (0, 0, 0),
+        parse: |reader| Ok(Command::Client(Client::from_parts(reader)?)),
+        is: |command| matches!(command, Command::Client(_)),
+        doc: CommandDoc {
+            summary: "A container for client connection commands.",
+            since: "2.4.0",
+            group: "connection",
+            arguments: &[arg("subcommand", "string"), optional("args", "string")],
+        },
+    },
+    CommandSpec {
+        name: "command",
+        arity: -1,
+        flags: &["loading", "stale"],
+        keys: (0, 0, 0),
+        parse: |reader| Ok(Command::Introspect(Introspect::from_parts(reader)?)),
+        is: |command| matches!(command, Command::Introspect(_)),
+        doc: CommandDoc {
+            summary: "Returns detailed information about all commands.",
+            since: "2.8.13",
+            group: "server",
+            arguments: &[
+                optional("subcommand", "string"),
+                multiple("command-name", "string"),
+            ],
+        },
+    },
+    CommandSpec {
+        name: "config",
+        arity: -2,
+        flags: &["admin", "stale"],
+        keys: (0, 0, 0),
+        parse: |reader| Ok(Command::Config(Config::from_parts(reader)?)),
+        is: |command| matches!(command, Command::Config(_)),
+        doc: CommandDoc {
+            summary: "A container for server configuration commands.",
+            since: "2.0.0",
+            group: "server",
+            arguments: &[arg("subcommand", "string"), arg("parameter", "string")],
+        },
+    },
+    CommandSpec {
+        name: "debug",
+        arity: -2,
+        flags: &["admin"],
+        keys: (0, 0, 0),
+        parse: |reader| Ok(Command::Debug(Debug::from_parts(reader)?)),
+        is: |command| matches!(command, Command::Debug(_)),
+        doc: CommandDoc {
+            summary: "A container for debugging commands.",
+            since: "1.0.0",
+            group: "server",
+            arguments: &[arg("subcommand", "string"), optional("args", "string")],
+        },
+    },
+    CommandSpec {
+        name: "decr",
+        arity: 2,
+        flags: &["write", "denyoom", "fast"],
+        keys: (1, 1, 1),
+        parse: |reader| Ok(Command::Decr(Decr::from_parts(reader)?)),
+        is: |command| matches!(command, Command::Decr(_)),
+        doc: CommandDoc {
+            summary: "Decrements the integer value of a key by one. Uses 0 as initial value if the key doesn't exist.",
+            since: "1.0.0",
+            group: "string",
+            arguments: &[arg("key", "key")],
+        },
+    },
+    CommandSpec {
+        name: "decrby",
+        arity: 3,
+        flags: &["write", "denyoom", "fast"],
+        keys: (1, 1, 1),
+        parse: |reader| Ok(Command::DecrBy(DecrBy::from_parts(reader)?)),
+        is: |command| matches!(command, Command::DecrBy(_)),
+        doc: CommandDoc {
+            summary: "Decrements a number from the integer value of a key. Uses 0 as initial value if the key doesn't exist.",
+            since: "1.0.0",
+            group: "string",
+            arguments: &[arg("key", "key"), arg("decrement", "integer")],
+        },
+    },
+    CommandSpec {
+        name: "del",
+        arity: -2,
+        flags: &["write"],
+        keys: (1, -1, 1),
+        parse: |reader| Ok(Command::Del(Del::from_parts(reader)?)),
+        is: |command| matches!(command, Command::Del(_)),
+        doc: CommandDoc {
+            summary: "Deletes one or more keys.",
+            since: "1.0.0",
+            group: "generic",
+            arguments: &[multiple("key", "key")],
+        },
+    },
+    CommandSpec {
+        name: "discard",
+        arity: 1,
+        flags: &["fast"],
+        keys: (0, 0, 0),
+        parse: |reader| Ok(Command::Discard(Discard::from_parts(reader)?)),
+        is: |command| matches!(command, Command::Discard(_)),
+        doc: CommandDoc {
+            summary: "Discards a transaction.",
+            since: "2.0.0",
+            group: "transactions",
+            arguments: &[],
+        },
+    },
+    CommandSpec {
+        name: "echo",
+        arity: 2,
+        flags: &["fast"],
+        keys: (0, 0, 0),
+        parse: |reader| Ok(Command::Echo(Echo::from_parts(reader)?)),
+        is: |command| matches!(command, Command::Echo(_)),
+        doc: CommandDoc {
+            summary: "Returns the given string.",
+            since: "1.0.0",
+            group: "connection",
+            arguments: &[arg("message", "string")],
+        },
+    },
+    CommandSpec {
+        name: "exec",
+        arity: 1,
+        flags: &["loading", "stale"],
+        keys: (0, 0, 0),
+        parse: |reader| Ok(Command::Exec(Exec::from_parts(reader)?)),
+        is: |command| matches!(command, Command::Exec(_)),
+        doc: CommandDoc {
+            summary: "Executes all commands in a transaction.",
+            since: "1.2.0",
+            group: "transactions",
+            arguments: &[],
+        },
+    },
+    CommandSpec {
+        name: "exists",
+        arity: -2,
+        flags: &["readonly", "fast"],
+        keys: (1, -1, 1),
+        parse: |reader| Ok(Command::Exists(Exists::from_parts(reader)?)),
+        is: |command| matches!(command, Command::Exists(_)),
+        doc: CommandDoc {
+            summary: "Determines whether one or more keys exist.",
+            since: "1.0.0",
+            group: "generic",
+            arguments: &[multiple("key", "key")],
+        },
+    },
+    CommandSpec {
+        name: "expire",
+        arity: -3,
+        flags: &["write", "fast"],
+        keys: (1, 1, 1),
+        parse: |reader| Ok(Command::Expire(Expire::from_parts(reader)?)),
+        is: |command| matches!(command, Command::Expire(_)),
+        doc: CommandDoc {
+            summary: "Sets the expiration time of a key in seconds.",
+            since: "1.0.0",
+            group: "generic",
+            arguments: &[arg("key", "key"), arg("seconds", "integer")],
+        },
+    },
+    CommandSpec {
+        name: "get",
+        arity: 2,
+        flags: &["readonly", "fast"],
+        keys: (1, 1, 1),
+        parse: |reader| Ok(Command::Get(Get::from_parts(reader)?)),
+        is: |command| matches!(command, Command::Get(_)),
+        doc: CommandDoc {
+            summary: "Returns the string value of a key.",
+            since: "1.0.0",
+            group: "string",
+            arguments: &[arg("key", "key")],
+        },
+    },
+    CommandSpec {
+        name: "getrange",
+        arity: 4,
+        flags: &["readonly"],
+        keys: (1, 1, 1),
+        parse: |reader| Ok(Command::GetRange(GetRange::from_parts(reader)?)),
+        is: |command| matches!(command, Command::GetRange(_)),
+        doc: CommandDoc {
+            summary: "Returns a substring of the string stored at a key.",
+            since: "2.4.0",
+            group: "string",
+            arguments: &[
+                arg("key", "key"),
+                arg("start", "integer"),
+                arg("end", "integer"),
+            ],
+        },
+    },
+    CommandSpec {
+        name: "getset",
+        arity: 3,
+        flags: &["write", "denyoom", "fast"],
+        keys: (1, 1, 1),
+        parse: |reader| Ok(Command::GetSet(GetSet::from_parts(reader)?)),
+        is: |command| matches!(command, Command::GetSet(_)),
+        doc: CommandDoc {
+            summary: "Returns the previous string value of a key after setting it to a new value.",
+            since: "1.0.0",
+            group: "string",
+            arguments: &[arg("key", "key"), arg("value", "string")],
+        },
+    },
+    CommandSpec {
+        name: "hdel",
+        arity: -3,
+        flags: &["write", "fast"],
+        keys: (1, 1, 1),
+        parse: |reader| Ok(Command::HDel(HDel::from_parts(reader)?)),
+        is: |command| matches!(command, Command::HDel(_)),
+        doc: CommandDoc {
+            summary: "Deletes one or more fields and their values from a hash. Deletes the hash if no fields remain.",
+            since: "2.0.0",
+            group: "hash",
+            arguments: &[arg("key", "key"), multiple("field", "string")],
+        },
+    },
+    CommandSpec {
+        name: "hello",
+        arity: -1,
+        flags: &["fast", "loading", "stale"],
+        keys: (0, 0, 0),
+        parse: |reader| Ok(Command::Hello(Hello::from_parts(reader)?)),
+        is: |command| matches!(command, Command::Hello(_)),
+        doc: CommandDoc {
+            summary: "Handshakes with the Redis server.",
+            since: "6.0.0",
+            group: "connection",
+            arguments: &[optional("protover", "integer"), optional("args", "string")],
+        },
+    },
+    CommandSpec {
+        name: "hexpire",
+        arity: -6,
+        flags: &["write", "denyoom", "fast"],
+        keys: (1, 1, 1),
+        parse: |reader| Ok(Command::HExpire(HExpire::from_parts(reader)?)),
+        is: |command| matches!(command, Command::HExpire(_)),
+        doc: CommandDoc {
+            summary: "Set expiry for hash field using relative time to expire (seconds)",
+            since: "7.4.0",
+            group: "hash",
+            arguments: &[
+                arg("key", "key"),
+                arg("seconds", "integer"),
+                arg("fields", "block"),
+            ],
+        },
+    },
+    CommandSpec {
+        name: "hget",
+        arity: 3,
+        flags: &["readonly", "fast"],
+        keys: (1, 1, 1),
+        parse: |reader| Ok(Command::HGet(HGet::from_parts(reader)?)),
+        is: |command| matches!(command, Command::HGet(_)),
+        doc: CommandDoc {
+            summary: "Returns the value of a field in a hash.",
+            since: "2.0.0",
+            group: "hash",
+            arguments: &[arg("key", "key"), arg("field", "string")],
+        },
+    },
+    CommandSpec {
+        name: "hgetall",
+        arity: 2,
+        flags: &["readonly"],
+        keys: (1, 1, 1),
+        parse: |reader| Ok(Command::HGetAll(HGetAll::from_parts(reader)?)),
+        is: |command| matches!(command, Command::HGetAll(_)),
+        doc: CommandDoc {
+            summary: "Returns all fields and values in a hash.",
+            since: "2.0.0",
+            group: "hash",
+            arguments: &[arg("key", "key")],
+        },
+    },
+    CommandSpec {
+        name: "hincrby",
+        arity: 4,
+        flags: &["write", "denyoom", "fast"],
+        keys: (1, 1, 1),
+        parse: |reader| Ok(Command::HIncrBy(HIncrBy::from_parts(reader)?)),
+        is: |command| matches!(command, Command::HIncrBy(_)),
+        doc: CommandDoc {
+            summary: "Increments the integer value of a field in a hash by a number. Uses 0 as initial value if the field doesn't exist.",
+            since: "2.0.0",
+            group: "hash",
+            arguments: &[arg("key", "key"), arg("field", "string"), arg("increment", "integer")],
+        },
+    },
+    CommandSpec {
+        name: "hset",
+        arity: -4,
+        flags: &["write", "denyoom", "fast"],
+        keys: (1, 1, 1),
+        parse: |reader| Ok(Command::HSet(HSet::from_parts(reader)?)),
+        is: |command| matches!(command, Command::HSet(_)),
+        doc: CommandDoc {
+            summary: "Creates or modifies the value of a field in a hash.",
+            since: "2.0.0",
+            group: "hash",
+            arguments: &[arg("key", "key"), multiple("data", "block")],
+        },
+    },
+    CommandSpec {
+        name: "httl",
+        arity: -5,
+        flags: &["readonly", "fast"],
+        keys: (1, 1, 1),
+        parse: |reader| Ok(Command::HTtl(HTtl::from_parts(reader)?)),
+        is: |command| matches!(command, Command::HTtl(_)),
+        doc: CommandDoc {
+            summary: "Returns the TTL in seconds of a hash field.",
+            since: "7.4.0",
+            group: "hash",
+            arguments: &[arg("key", "key"), arg("fields", "block")],
+        },
+    },
+    CommandSpec {
+        name: "incr",
+        arity: 2,
+        flags: &["write", "denyoom", "fast"],
+        keys: (1, 1, 1),
+        parse: |reader| Ok(Command::Incr(Incr::from_parts(reader)?)),
+        is: |command| matches!(command, Command::Incr(_)),
+        doc: CommandDoc {
+            summary: "Increments the integer value of a key by one. Uses 0 as initial value if the key doesn't exist.",
+            since: "1.0.0",
+            group: "string",
+            arguments: &[arg("key", "key")],
+        },
+    },
+    CommandSpec {
+        name: "incrby",
+        arity: 3,
+        flags: &["write", "denyoom", "fast"],
+        keys: (1, 1, 1),
+        parse: |reader| Ok(Command::IncrBy(IncrBy::from_parts(reader)?)),
+        is: |command| matches!(command, Command::IncrBy(_)),
+        doc: CommandDoc {
+            summary: "Increments the integer value of a key by a number. Uses 0 as initial value if the key doesn't exist.",
+            since: "1.0.0",
+            group: "string",
+            arguments: &[arg("key", "key"), arg("increment", "integer")],
+        },
+    },
+    CommandSpec {
+        name: "info",
+        arity: -1,
+        flags: &["loading", "stale"],
+        keys: (0, 0, 0),
+        parse: |reader| Ok(Command::Info(Info::from_parts(reader)?)),
+        is: |command| matches!(command, Command::Info(_)),
+        doc: CommandDoc {
+            summary: "Returns information and statistics about the server.",
+            since: "1.0.0",
+            group: "server",
+            arguments: &[multiple("section", "string")],
+        },
+    },
+    CommandSpec {
+        name: "keys",
+        arity: 2,
+        flags: &["readonly"],
+        keys: (0, 0, 0),
+        parse: |reader| Ok(Command::Keys(Keys::from_parts(reader)?)),
+        is: |command| matches!(command, Command::Keys(_)),
+        doc: CommandDoc {
+            summary: "Returns all key names that match a pattern.",
+            since: "1.0.0",
+            group: "generic",
+            arguments: &[arg("pattern", "pattern")],
+        },
+    },
+    CommandSpec {
+        name: "llen",
+        arity: 2,
+        flags: &["readonly", "fast"],
+        keys: (1, 1, 1),
+        parse: |reader| Ok(Command::LLen(LLen::from_parts(reader)?)),
+        is: |command| matches!(command, Command::LLen(_)),
+        doc: CommandDoc {
+            summary: "Returns the length of a list.",
+            since: "1.0.0",
+            group: "list",
+            arguments: &[arg("key", "key")],
+        },
+    },
+    CommandSpec {
+        name: "lmpop",
+        arity: -4,
+        flags: &["write", "movablekeys"],
+        keys: (0, 0, 0),
+        parse: |reader| Ok(Command::LMPop(LMPop::from_parts(reader)?)),
+        is: |command| matches!(command, Command::LMPop(_)),
+        doc: CommandDoc {
+            summary: "Returns multiple elements from a list after removing them. Deletes the list if the last element was popped.",
+            since: "7.0.0",
+            group: "list",
+            arguments: &[
+                arg("numkeys", "integer"),
+                multiple("key", "key"),
+                arg("where", "oneof"),
+                optional("count", "integer"),
+            ],
+        },
+    },
+    CommandSpec {
+        name: "lpop",
+        arity: -2,
+        flags: &["write", "fast"],
+        keys: (1, 1, 1),
+        parse: |reader| Ok(Command::LPop(LPop::from_parts(reader)?)),
+        is: |command| matches!(command, Command::LPop(_)),
+        doc: CommandDoc {
+            summary: "Returns the first elements in a list after removing it. Deletes the list if the last element was popped.",
+            since: "1.0.0",
+            group: "list",
+            arguments: &[arg("key", "key"), optional("count", "integer")],
+        },
+    },
+    CommandSpec {
+        name: "lpush",
+        arity: -3,
+        flags: &["write", "denyoom", "fast"],
+        keys: (1, 1, 1),
+        parse: |reader| Ok(Command::LPush(LPush::from_parts(reader)?)),
+        is: |command| matches!(command, Command::LPush(_)),
+        doc: CommandDoc {
+            summary: "Prepends one or more elements to a list. Creates the key if it doesn't exist.",
+            since: "1.0.0",
+            group: "list",
+            arguments: &[arg("key", "key"), multiple("element", "string")],
+        },
+    },
+    CommandSpec {
+        name: "lrange",
+        arity: 4,
+        flags: &["readonly"],
+        keys: (1, 1, 1),
+        parse: |reader| Ok(Command::LRange(LRange::from_parts(reader)?)),
+        is: |command| matches!(command, Command::LRange(_)),
+        doc: CommandDoc {
+            summary: "Returns a range of elements from a list.",
+            since: "1.0.0",
+            group: "list",
+            arguments: &[arg("key", "key"), arg("start", "integer"), arg("stop", "integer")],
+        },
+    },
+    CommandSpec {
+        name: "mget",
+        arity: -2,
+        flags: &["readonly", "fast"],
+        keys: (1, -1, 1),
+        parse: |reader| Ok(Command::MGet(MGet::from_parts(reader)?)),
+        is: |command| matches!(command, Command::MGet(_)),
+        doc: CommandDoc {
+            summary: "Atomically returns the string values of one or more keys.",
+            since: "1.0.0",
+            group: "string",
+            arguments: &[multiple("key", "key")],
+        },
+    },
+    CommandSpec {
+        name: "mset",
+        arity: -3,
+        flags: &["write", "denyoom"],
+        keys: (1, -1, 2),
+        parse: |reader| Ok(Command::MSet(MSet::from_parts(reader)?)),
+        is: |command| matches!(command, Command::MSet(_)),
+        doc: CommandDoc {
+            summary: "Atomically creates or modifies the string values of one or more keys.",
+            since: "1.0.1",
+            group: "string",
+            arguments: &[multiple("data", "block")],
+        },
+    },
+    CommandSpec {
+        name: "multi",
+        arity: 1,
+        flags: &["fast", "loading", "stale"],
+        keys: (0, 0, 0),
+        parse: |reader| Ok(Command::Multi(Multi::from_parts(reader)?)),
+        is: |command| matches!(command, Command::Multi(_)),
+        doc: CommandDoc {
+            summary: "Starts a transaction.",
+            since: "1.2.0",
+            group: "transactions",
+            arguments: &[],
+        },
+    },
+    CommandSpec {
+        name: "object",
+        arity: -2,
+        flags: &["readonly"],
+        keys: (2, 2, 1),
+        parse: |reader| Ok(Command::Object(Object::from_parts(reader)?)),
+        is: |command| matches!(command, Command::Object(_)),
+        doc: CommandDoc {
+            summary: "A container for object introspection commands.",
+            since: "2.2.3",
+            group: "generic",
+            arguments: &[arg("subcommand", "string"), optional("key", "key")],
+        },
+    },
+    CommandSpec {
+        name: "persist",
+        arity: 2,
+        flags: &["write", "fast"],
+        keys: (1, 1, 1),
+        parse: |reader| Ok(Command::Persist(Persist::from_parts(reader)?)),
+        is: |command| matches!(command, Command::Persist(_)),
+        doc: CommandDoc {
+            summary: "Removes the expiration time of a key.",
+            since: "2.2.0",
+            group: "generic",
+            arguments: &[arg("key", "key")],
+        },
+    },
+    CommandSpec {
+        name: "pexpire",
+        arity: -3,
+        flags: &["write", "fast"],
+        keys: (1, 1, 1),
+        parse: |reader| Ok(Command::Pexpire(Pexpire::from_parts(reader)?)),
+        is: |command| matches!(command, Command::Pexpire(_)),
+        doc: CommandDoc {
+            summary: "Sets the expiration time of a key in milliseconds.",
+            since: "2.6.0",
+            group: "generic",
+            arguments: &[arg("key", "key"), arg("milliseconds", "integer")],
+        },
+    },
+    CommandSpec {
+        name: "ping",
+        arity: -1,
+        flags: &["fast", "stale"],
+        keys: (0, 0, 0),
+        parse: |reader| Ok(Command::Ping(Ping::from_parts(reader)?)),
+        is: |command| matches!(command, Command::Ping(_)),
+        doc: CommandDoc {
+            summary: "Returns the server's liveliness response.",
+            since: "1.0.0",
+            group: "connection",
+            arguments: &[optional("message", "string")],
+        },
+    },
+    CommandSpec {
+        name: "psync",
+        arity: -3,
+        flags: &["admin", "noscript"],
+        keys: (0, 0, 0),
+        parse: |reader| Ok(Command::PSync(PSync::from_parts(reader)?)),
+        is: |command| matches!(command, Command::PSync(_)),
+        doc: CommandDoc {
+            summary: "An internal command used in replication.",
+            since: "2.8.0",
+            group: "server",
+            arguments: &[arg("replicationid", "string"), arg("offset", "integer")],
+        },
+    },
+    CommandSpec {
+        name: "pttl",
+        arity: 2,
+        flags: &["readonly", "fast"],
+        keys: (1, 1, 1),
+        parse: |reader| Ok(Command::Pttl(Pttl::from_parts(reader)?)),
+        is: |command| matches!(command, Command::Pttl(_)),
+        doc: CommandDoc {
+            summary: "Returns the expiration time in milliseconds of a key.",
+            since: "2.6.0",
+            group: "generic",
+            arguments: &[arg("key", "key")],
+        },
+    },
+    CommandSpec {
+        name: "rename",
+        arity: 3,
+        flags: &["write"],
+        keys: (1, 2, 1),
+        parse: |reader| Ok(Command::Rename(Rename::from_parts(reader)?)),
+        is: |command| matches!(command, Command::Rename(_)),
+        doc: CommandDoc {
+            summary: "Renames a key and overwrites the destination.",
+            since: "1.0.0",
+            group: "generic",
+            arguments: &[arg("key", "key"), arg("newkey", "key")],
+        },
+    },
+    CommandSpec {
+        name: "renamenx",
+        arity: 3,
+        flags: &["write", "fast"],
+        keys: (1, 2, 1),
+        parse: |reader| Ok(Command::RenameNx(RenameNx::from_parts(reader)?)),
+        is: |command| matches!(command, Command::RenameNx(_)),
+        doc: CommandDoc {
+            summary: "Renames a key only when the target key name doesn't exist.",
+            since: "1.0.0",
+            group: "generic",
+            arguments: &[arg("key", "key"), arg("newkey", "key")],
+        },
+    },
+    CommandSpec {
+        name: "replconf",
+        arity: -1,
+        flags: &["admin", "loading", "stale"],
+        keys: (0, 0, 0),
+        parse: |reader| Ok(Command::Replconf(Replconf::from_parts(reader)?)),
+        is: |command| matches!(command, Command::Replconf(_)),
+        doc: CommandDoc {
+            summary: "An internal command for configuring the replication stream.",
+            since: "3.0.0",
+            group: "server",
+            arguments: &[multiple("args", "string")],
+        },
+    },
+    CommandSpec {
+        name: "rpop",
+        arity: -2,
+        flags: &["write", "fast"],
+        keys: (1, 1, 1),
+        parse: |reader| Ok(Command::RPop(RPop::from_parts(reader)?)),
+        is: |command| matches!(command, Command::RPop(_)),
+        doc: CommandDoc {
+            summary: "Returns and removes the last elements of the list. Deletes the list if the last element was popped.",
+            since: "1.0.0",
+            group: "list",
+            arguments: &[arg("key", "key"), optional("count", "integer")],
+        },
+    },
+    CommandSpec {
+        name: "rpush",
+        arity: -3,
+        flags: &["write", "denyoom", "fast"],
+        keys: (1, 1, 1),
+        parse: |reader| Ok(Command::RPush(RPush::from_parts(reader)?)),
+        is: |command| matches!(command, Command::RPush(_)),
+        doc: CommandDoc {
+            summary: "Appends one or more elements to a list. Creates the key if it doesn't exist.",
+            since: "1.0.0",
+            group: "list",
+            arguments: &[arg("key", "key"), multiple("element", "string")],
+        },
+    },
+    CommandSpec {
+        name: "sadd",
+        arity: -3,
+        flags: &["write", "denyoom", "fast"],
+        keys: (1, 1, 1),
+        parse: |reader| Ok(Command::SAdd(SAdd::from_parts(reader)?)),
+        is: |command| matches!(command, Command::SAdd(_)),
+        doc: CommandDoc {
+            summary: "Adds one or more members to a set. Creates the key if it doesn't exist.",
+            since: "1.0.0",
+            group: "set",
+            arguments: &[arg("key", "key"), multiple("member", "string")],
+        },
+    },
+    CommandSpec {
+        name: "save",
+        arity: 1,
+        flags: &["admin", "noscript", "no_async_loading", "no_multi"],
+        keys: (0, 0, 0),
+        parse: |reader| Ok(Command::Save(Save::from_parts(reader)?)),
+        is: |command| matches!(command, Command::Save(_)),
+        doc: CommandDoc {
+            summary: "Synchronously saves the database(s) to disk.",
+            since: "1.0.0",
+            group: "server",
+            arguments: &[],
+        },
+    },
+    CommandSpec {
+        name: "scard",
+        arity: 2,
+        flags: &["readonly", "fast"],
+        keys: (1, 1, 1),
+        parse: |reader| Ok(Command::SCard(SCard::from_parts(reader)?)),
+        is: |command| matches!(command, Command::SCard(_)),
+        doc: CommandDoc {
+            summary: "Returns the number of members in a set.",
+            since: "1.0.0",
+            group: "set",
+            arguments: &[arg("key", "key")],
+        },
+    },
+    CommandSpec {
+        name: "sdiff",
+        arity: -2,
+        flags: &["readonly"],
+        keys: (1, -1, 1),
+        parse: |reader| Ok(Command::SDiff(SDiff::from_parts(reader)?)),
+        is: |command| matches!(command, Command::SDiff(_)),
+        doc: CommandDoc {
+            summary: "Returns the difference of multiple sets.",
+            since: "1.0.0",
+            group: "set",
+            arguments: &[multiple("key", "key")],
+        },
+    },
+    CommandSpec {
+        name: "select",
+        arity: 2,
+        flags: &["fast", "loading", "stale"],
+        keys: (0, 0, 0),
+        parse: |reader| Ok(Command::Select(Select::from_parts(reader)?)),
+        is: |command| matches!(command, Command::Select(_)),
+        doc: CommandDoc {
+            summary: "Changes the selected database.",
+            since: "1.0.0",
+            group: "connection",
+            arguments: &[arg("index", "integer")],
+        },
+    },
+    CommandSpec {
+        name: "set",
+        arity: -3,
+        flags: &["write", "denyoom"],
+        keys: (1, 1, 1),
+        parse: |reader| Ok(Command::Set(Set::from_parts(reader)?)),
+        is: |command| matches!(command, Command::Set(_)),
+        doc: CommandDoc {
+            summary: "Sets the string value of a key, ignoring its type. The key is created if it doesn't exist.",
+            since: "1.0.0",
+            group: "string",
+            arguments: &[
+                arg("key", "key"),
+                arg("value", "string"),
+                optional("expiration", "oneof"),
+            ],
+        },
+    },
+    CommandSpec {
+        name: "setnx",
+        arity: 3,
+        flags: &["write", "denyoom", "fast"],
+        keys: (1, 1, 1),
+        parse: |reader| Ok(Command::SetNx(SetNx::from_parts(reader)?)),
+        is: |command| matches!(command, Command::SetNx(_)),
+        doc: CommandDoc {
+            summary: "Set the string value of a key only when the key doesn't exist.",
+            since: "1.0.0",
+            group: "string",
+            arguments: &[arg("key", "key"), arg("value", "string")],
+        },
+    },
+    CommandSpec {
+        name: "setrange",
+        arity: 4,
+        flags: &["write", "denyoom"],
+        keys: (1, 1, 1),
+        parse: |reader| Ok(Command::SetRange(SetRange::from_parts(reader)?)),
+        is: |command| matches!(command, Command::SetRange(_)),
+        doc: CommandDoc {
+            summary: "Overwrites a part of a string value with another by an offset. Creates the key if it doesn't exist.",
+            since: "2.2.0",
+            group: "string",
+            arguments: &[
+                arg("key", "key"),
+                arg("offset", "integer"),
+                arg("value", "string"),
+            ],
+        },
+    },
+    CommandSpec {
+        name: "shutdown",
+        arity: -1,
+        flags: &["admin", "noscript", "loading", "stale", "no_multi"],
+        keys: (0, 0, 0),
+        parse: |reader| Ok(Command::Shutdown(ShutdownServer::from_parts(reader)?)),
+        is: |command| matches!(command, Command::Shutdown(_)),
+        doc: CommandDoc {
+            summary: "Synchronously saves the database(s) to disk and shuts down the Redis server.",
+            since: "1.0.0",
+            group: "server",
+            arguments: &[optional("save-selector", "oneof")],
+        },
+    },
+    CommandSpec {
+        name: "sinter",
+        arity: -2,
+        flags: &["readonly"],
+        keys: (1, -1, 1),
+        parse: |reader| Ok(Command::SInter(SInter::from_parts(reader)?)),
+        is: |command| matches!(command, Command::SInter(_)),
+        doc: CommandDoc {
+            summary: "Returns the intersect of multiple sets.",
+            since: "1.0.0",
+            group: "set",
+            arguments: &[multiple("key", "key")],
+        },
+    },
+    CommandSpec {
+        name: "sismember",
+        arity: 3,
+        flags: &["readonly", "fast"],
+        keys: (1, 1, 1),
+        parse: |reader| Ok(Command::SIsMember(SIsMember::from_parts(reader)?)),
+        is: |command| matches!(command, Command::SIsMember(_)),
+        doc: CommandDoc {
+            summary: "Determines whether a member belongs to a set.",
+            since: "1.0.0",
+            group: "set",
+            arguments: &[arg("key", "key"), arg("member", "string")],
+        },
+    },
+    CommandSpec {
+        name: "smembers",
+        arity: 2,
+        flags: &["readonly"],
+        keys: (1, 1, 1),
+        parse: |reader| Ok(Command::SMembers(SMembers::from_parts(reader)?)),
+        is: |command| matches!(command, Command::SMembers(_)),
+        doc: CommandDoc {
+            summary: "Returns all members of a set.",
+            since: "1.0.0",
+            group: "set",
+            arguments: &[arg("key", "key")],
+        },
+    },
+    CommandSpec {
+        name: "srem",
+        arity: -3,
+        flags: &["write", "fast"],
+        keys: (1, 1, 1),
+        parse: |reader| Ok(Command::SRem(SRem::from_parts(reader)?)),
+        is: |command| matches!(command, Command::SRem(_)),
+        doc: CommandDoc {
+            summary: "Removes one or more members from a set. Deletes the set if the last member was removed.",
+            since: "1.0.0",
+            group: "set",
+            arguments: &[arg("key", "key"), multiple("member", "string")],
+        },
+    },
+    CommandSpec {
+        name: "sunion",
+        arity: -2,
+        flags: &["readonly"],
+        keys: (1, -1, 1),
+        parse: |reader| Ok(Command::SUnion(SUnion::from_parts(reader)?)),
+        is: |command| matches!(command, Command::SUnion(_)),
+        doc: CommandDoc {
+            summary: "Returns the union of multiple sets.",
+            since: "1.0.0",
+            group: "set",
+            arguments: &[multiple("key", "key")],
+        },
+    },
+    CommandSpec {
+        name: "ttl",
+        arity: 2,
+        flags: &["readonly", "fast"],
+        keys: (1, 1, 1),
+        parse: |reader| Ok(Command::Ttl(Ttl::from_parts(reader)?)),
+        is: |command| matches!(command, Command::Ttl(_)),
+        doc: CommandDoc {
+            summary: "Returns the expiration time in seconds of a key.",
+            since: "1.0.0",
+            group: "generic",
+            arguments: &[arg("key", "key")],
+        },
+    },
+    CommandSpec {
+        name: "type",
+        arity: 2,
+        flags: &["readonly", "fast"],
+        keys: (1, 1, 1),
+        parse: |reader| Ok(Command::Type(types::Type::from_parts(reader)?)),
+        is: |command| matches!(command, Command::Type(_)),
+        doc: CommandDoc {
+            summary: "Determines the type of value stored at a key.",
+            since: "1.0.0",
+            group: "generic",
+            arguments: &[arg("key", "key")],
+        },
+    },
+    CommandSpec {
+        name: "wait",
+        arity: 3,
+        flags: &["noscript"],
+        keys: (0, 0, 0),
+        parse: |reader| Ok(Command::Wait(Wait::from_parts(reader)?)),
+        is: |command| matches!(command, Command::Wait(_)),
+        doc: CommandDoc {
+            summary: "Blocks until the asynchronous replication of all preceding write commands sent by the connection is completed.",
+            since: "3.0.0",
+            group: "generic",
+            arguments: &[arg("numreplicas", "integer"), arg("timeout", "integer")],
+        },
+    },
+    CommandSpec {
+        name: "xadd",
+        arity: -5,
+        flags: &["write", "denyoom", "fast"],
+        keys: (1, 1, 1),
+        parse: |reader| Ok(Command::XAdd(XAdd::from_parts(reader)?)),
+        is: |command| matches!(command, Command::XAdd(_)),
+        doc: CommandDoc {
+            summary: "Appends a new message to a stream. Creates the key if it doesn't exist.",
+            since: "5.0.0",
+            group: "stream",
+            arguments: &[
+                arg("key", "key"),
+                optional("nomkstream", "pure-token"),
+                optional("trim", "block"),
+                arg("id", "string"),
+                multiple("data", "block"),
+            ],
+        },
+    },
+    CommandSpec {
+        name: "xdel",
+        arity: -3,
+        flags: &["write", "fast"],
+        keys: (1, 1, 1),
+        parse: |reader| Ok(Command::XDel(XDel::from_parts(reader)?)),
+        is: |command| matches!(command, Command::XDel(_)),
+        doc: CommandDoc {
+            summary: "Returns the number of messages after removing them from a stream.",
+            since: "5.0.0",
+            group: "stream",
+            arguments: &[arg("key", "key"), multiple("id", "string")],
+        },
+    },
+    CommandSpec {
+        name: "xrange",
+        arity: -4,
+        flags: &["readonly"],
+        keys: (1, 1, 1),
+        parse: |reader| Ok(Command::XRange(XRange::from_parts(reader)?)),
+        is: |command| matches!(command, Command::XRange(_)),
+        doc: CommandDoc {
+            summary: "Returns the messages from a stream within a range of IDs.",
+            since: "5.0.0",
+            group: "stream",
+            arguments: &[arg("key", "key"), arg("start", "string"), arg("end", "string")],
+        },
+    },
+    CommandSpec {
+        name: "xread",
+        arity: -4,
+        flags: &["readonly", "blocking"],
+        keys: (0, 0, 0),
+        parse: |reader| Ok(Command::XRead(XRead::from_parts(reader)?)),
+        is: |command| matches!(command, Command::XRead(_)),
+        doc: CommandDoc {
+            summary: "Returns messages from multiple streams with IDs greater than the ones requested. Blocks until a message is available otherwise.",
+            since: "5.0.0",
+            group: "stream",
+            arguments: &[
+                optional("count", "integer"),
+                optional("milliseconds", "integer"),
+                arg("streams", "block"),
+            ],
+        },
+    },
+    CommandSpec {
+        name: "zadd",
+        arity: -4,
+        flags: &["write", "denyoom", "fast"],
+        keys: (1, 1, 1),
+        parse: |reader| Ok(Command::ZAdd(ZAdd::from_parts(reader)?)),
+        is: |command| matches!(command, Command::ZAdd(_)),
+        doc: CommandDoc {
+            summary: "Adds one or more members to a sorted set, or updates their scores. Creates the key if it doesn't exist.",
+            since: "1.2.0",
+            group: "sorted-set",
+            arguments: &[arg("key", "key"), multiple("data", "block")],
+        },
+    },
+    CommandSpec {
+        name: "zmpop",
+        arity: -4,
+        flags: &["write", "movablekeys"],
+        keys: (0, 0, 0),
+        parse: |reader| Ok(Command::ZMPop(ZMPop::from_parts(reader)?)),
+        is: |command| matches!(command, Command::ZMPop(_)),
+        doc: CommandDoc {
+            summary: "Returns the highest- or lowest-scoring members from one or more sorted sets after removing them. Deletes the sorted set if the last member was popped.",
+            since: "7.0.0",
+            group: "sorted-set",
+            arguments: &[
+                arg("numkeys", "integer"),
+                multiple("key", "key"),
+                arg("where", "oneof"),
+                optional("count", "integer"),
+            ],
+        },
+    },
+    CommandSpec {
+        name: "zpopmax",
+        arity: -2,
+        flags: &["write", "fast"],
+        keys: (1, 1, 1),
+        parse: |reader| Ok(Command::ZPopMax(ZPopMax::from_parts(reader)?)),
+        is: |command| matches!(command, Command::ZPopMax(_)),
+        doc: CommandDoc {
+            summary: "Returns the highest-scoring members from a sorted set after removing them.",
+            since: "5.0.0",
+            group: "sorted-set",
+            arguments: &[arg("key", "key"), optional("count", "integer")],
+        },
+    },
+    CommandSpec {
+        name: "zpopmin",
+        arity: -2,
+        flags: &["write", "fast"],
+        keys: (1, 1, 1),
+        parse: |reader| Ok(Command::ZPopMin(ZPopMin::from_parts(reader)?)),
+        is: |command| matches!(command, Command::ZPopMin(_)),
+        doc: CommandDoc {
+            summary: "Returns the lowest-scoring members from a sorted set after removing them.",
+            since: "5.0.0",
+            group: "sorted-set",
+            arguments: &[arg("key", "key"), optional("count", "integer")],
+        },
+    },
+    CommandSpec {
+        name: "zrange",
+        arity: -4,
+        flags: &["readonly"],
+        keys: (1, 1, 1),
+        parse: |reader| Ok(Command::ZRange(ZRange::from_parts(reader)?)),
+        is: |command| matches!(command, Command::ZRange(_)),
+        doc: CommandDoc {
+            summary: "Returns members in a sorted set within a range of indexes.",
+            since: "1.2.0",
+            group: "sorted-set",
+            arguments: &[
+                arg("key", "key"),
+                arg("start", "integer"),
+                arg("stop", "integer"),
+                optional("withscores", "pure-token"),
+            ],
+        },
+    },
+    CommandSpec {
+        name: "zrangebylex",
+        arity: -4,
+        flags: &["readonly"],
+        keys: (1, 1, 1),
+        parse: |reader| Ok(Command::ZRangeByLex(ZRangeByLex::from_parts(reader)?)),
+        is: |command| matches!(command, Command::ZRangeByLex(_)),
+        doc: CommandDoc {
+            summary: "Returns members in a sorted set within a lexicographical range.",
+            since: "2.8.9",
+            group: "sorted-set",
+            arguments: &[
+                arg("key", "key"),
+                arg("min", "string"),
+                arg("max", "string"),
+                optional("limit", "block"),
+            ],
+        },
+    },
+    CommandSpec {
+        name: "zrangebyscore",
+        arity: -4,
+        flags: &["readonly"],
+        keys: (1, 1, 1),
+        parse: |reader| Ok(Command::ZRangeByScore(ZRangeByScore::from_parts(reader)?)),
+        is: |command| matches!(command, Command::ZRangeByScore(_)),
+        doc: CommandDoc {
+            summary: "Returns members in a sorted set within a range of scores.",
+            since: "1.0.5",
+            group: "sorted-set",
+            arguments: &[
+                arg("key", "key"),
+                arg("min", "double"),
+                arg("max", "double"),
+                optional("withscores", "pure-token"),
+            ],
+        },
+    },
+    CommandSpec {
+        name: "zrank",
+        arity: 3,
+        flags: &["readonly", "fast"],
+        keys: (1, 1, 1),
+        parse: |reader| Ok(Command::ZRank(ZRank::from_parts(reader)?)),
+        is: |command| matches!(command, Command::ZRank(_)),
+        doc: CommandDoc {
+            summary: "Returns the index of a member in a sorted set ordered by ascending scores.",
+            since: "2.0.0",
+            group: "sorted-set",
+            arguments: &[arg("key", "key"), arg("member", "string")],
+        },
+    },
+    CommandSpec {
+        name: "zscore",
+        arity: 3,
+        flags: &["readonly", "fast"],
+        keys: (1, 1, 1),
+        parse: |reader| Ok(Command::ZScore(ZScore::from_parts(reader)?)),
+        is: |command| matches!(command, Command::ZScore(_)),
+        doc: CommandDoc {
+            summary: "Returns the score of a member in a sorted set.",
+            since: "1.2.0",
+            group: "sorted-set",
+            arguments: &[arg("key", "key"), arg("member", "string")],
+        },
+    },
+];
+
+/// Look up a command by its lowercase name
+pub fn lookup(name: &str) -> Option<&'static CommandSpec> {
+    COMMANDS
+        .binary_search_by(|spec| spec.name.cmp(name))
+        .ok()
+        .map(|idx| &COMMANDS[idx])
+}
+
+/// The COMMAND command, introspection over the registered commands
+#[derive(Debug, Default)]
+pub struct Introspect {
+    subcommand: Option<String>,
+    names: Vec<String>,
+}
+
+impl Introspect {
+    /// contruct new Introspect command
+    pub fn new(subcommand: Option<String>, names: Vec<String>) -> Self {
+        Introspect { subcommand, names }
+    }
+
+    /// Construct new Introspect command by consuming the RespReader
+    pub fn from_parts(reader: &mut RespReader) -> Result<Self, RespReaderError> {
+        let subcommand = match reader.next_string() {
+            Ok(subcommand) => Some(subcommand),
+            Err(RespReaderError::EndOfStream) => None,
+            Err(err) => return Err(err),
+        };
+
+        let mut names = vec![];
+        while let Ok(name) = reader.next_string() {
+            names.push(name);
+        }
+
+        Ok(Introspect { subcommand, names })
+    }
+
+    /// Apply the command command
+    ///
+    /// COUNT and LIST reply with the number and names of the registered
+    /// commands. INFO and DOCS reply with the details of the requested
+    /// commands, or every command if none is given, and a bare COMMAND
+    /// is the same as COMMAND INFO
+    pub async fn apply(self) -> crate::Result<Option<RESP>> {
+        let subcommand = self.subcommand.as_deref().unwrap_or("info").to_lowercase();
+
+        let resp = match (subcommand.as_str(), self.names.is_empty()) {
+            ("count", true) => RESP::Integer(COMMANDS.len() as i64),
+            ("list", true) => RESP::Array(
+                COMMANDS
+                    .iter()
+                    .map(|spec| RESP::Bulk(Bytes::from(spec.name)))
+                    .collect(),
+            ),
+            ("info", true) => RESP::Array(COMMANDS.iter().map(RESP::from).collect()),
+            // unknown commands are a null in the reply
+            ("info", false) => RESP::Array(
+                self.names
+                    .iter()
+                    .map(|name| lookup(&name.to_lowercase()).map_or(RESP::Null, RESP::from))
+                    .collect(),
+            ),
+            ("docs", _) => {
+                let specs: Vec<&CommandSpec> = if self.names.is_empty() {
+                    COMMANDS.iter().collect()
+                } else {
+                    // unknown commands are left out
+                    self.names
+                        .iter()
+                        .filter_map(|name| lookup(&name.to_lowercase()))
+                        .collect()
+                };
+
+                RESP::Map(
+                    specs
+                        .into_iter()
+                        .map(|spec| (RESP::Bulk(Bytes::from(spec.name)), (&spec.doc).into()))
+                        .collect(),
+                )
+            }
+            _ => {
+                let subcommand = self.subcommand.unwrap_or_default();
+                return Err(CommandError::UnknownSubcommand("COMMAND", subcommand).into());
+            }
+        };
+
+        Ok(Some(resp))
+    }
+}
+
+/// Encode a command as a COMMAND INFO entry
+impl From<&CommandSpec> for RESP {
+    fn from(spec: &CommandSpec) -> Self {
+        let (first, last, step) = spec.keys;
+
+        let mut resp = RESP::array();
+        resp.push_bulk(Bytes::from(spec.name));
+        resp.push_int(spec.arity);
+        resp.push(RESP::Array(
+            spec.flags
+                .iter()
+                .map(|flag| RESP::Simple(flag.to_string()))
+                .collect(),
+        ));
+        resp.push_int(first);
+        resp.push_int(last);
+        resp.push_int(step);
+        resp
+    }
+}
+
+/// Convert Introspect command back into an equivalent `RESP`
+impl From<Introspect> for RESP {
+    fn from(value: Introspect) -> Self {
+        let mut resp = RESP::array();
+        resp.push_bulk(Bytes::from("command"));
+        if let Some(subcommand) = value.subcommand {
+            resp.push_bulk(Bytes::from(subcommand));
+        }
+        for name in value.names {
+            resp.push_bulk(Bytes::from(name));
+        }
+        resp
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use tokio::io::{duplex, AsyncReadExt};
+
+    use super::{lookup, Introspect, COMMANDS};
+    use crate::{connection::Connection, resp::RESP, Command};
+
+    #[test]
+    fn commands_are_sorted() {
+        assert!(COMMANDS.windows(2).all(|w| w[0].name < w[1].name));
+    }
+
+    #[tokio::test]
+    async fn count_matches_the_registry_and_every_name_dispatches() {
+        let resp = Introspect::new(Some("COUNT".into()), vec![])
+            .apply()
+            .await
+            .unwrap();
+        assert!(matches!(resp, Some(RESP::Integer(count)) if count == COMMANDS.len() as i64));
+
+        let names = match Introspect::new(Some("LIST".into()), vec![]).apply().await {
+            Ok(Some(RESP::Array(names))) => names,
+            resp => panic!("expected an array, got {resp:?}"),
+        };
+        assert_eq!(names.len(), COMMANDS.len());
+
+        for name in names {
+            let name = match name {
+                RESP::Bulk(name) => String::from_utf8(name.to_vec()).unwrap(),
+                name => panic!("expected a bulk name, got {name:?}"),
+            };
+            assert!(lookup(&name).is_some());
+
+            // arity errors are fine, only an unknown command isn't
+            let request = RESP::Array(vec![RESP::Bulk(name.clone().into())]);
+            if let Ok(command) = Command::from_resp(request) {
+                assert!(
+                    !matches!(command, Command::Unknown(_)),
+                    "{name} isn't dispatched"
+                );
+                assert_eq!(command.get_name(), name);
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn command_docs_get_has_a_summary() {
+        let docs = Introspect::new(Some("DOCS".into()), vec!["get".into(), "nosuch".into()]);
+
+        let resp = docs.apply().await.unwrap().unwrap();
+        let entries = match resp {
+            RESP::Map(entries) => entries,
+            resp => panic!("expected a map, got {resp:?}"),
+        };
+        assert_eq!(entries.len(), 1);
+        assert!(matches!(&entries[0].0, RESP::Bulk(name) if name == "get"));
+
+        let doc = match &entries[0].1 {
+            RESP::Map(doc) => doc,
+            resp => panic!("expected a doc map, got {resp:?}"),
+        };
+        let field = |name: &str| {
+            doc.iter()
+                .find(|(field, _)| matches!(field, RESP::Bulk(field) if field == name))
+                .map(|(_, value)| value)
+        };
+        assert!(matches!(
+            field("summary"),
+            Some(RESP::Bulk(summary)) if summary == "Returns the string value of a key."
+        ));
+        assert!(matches!(
+            field("arguments"),
+            Some(RESP::Array(arguments)) if matches!(&arguments[0], RESP::Map(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn command_docs_are_maps_in_resp3_and_flat_in_resp2() {
+        let docs = Introspect::new(Some("DOCS".into()), vec!["get".into()])
+            .apply()
+            .await
+            .unwrap()
+            .unwrap();
+
+        let (mut client, server) = duplex(4096);
+        let mut connection = Connection::from_stream(server, false);
+        let mut buf = vec![0; 4096];
+
+        connection.protocol_version = 3;
+        connection.write_frame(&docs).await.unwrap();
+        let len = client.read(&mut buf).await.unwrap();
+        assert!(buf[..len].starts_with(b"%1\r\n$3\r\nget\r\n%4\r\n$7\r\nsummary\r\n"));
+
+        connection.protocol_version = 2;
+        connection.write_frame(&docs).await.unwrap();
+        let len = client.read(&mut buf).await.unwrap();
+        assert!(buf[..len].starts_with(b"*2\r\n$3\r\nget\r\n*8\r\n$7\r\nsummary\r\n"));
+    }
+}
@@ -0,0 +1,159 @@
+use bytes::Bytes;
+
+use crate::{resp::RESP, Db, RespReader, RespReaderError};
+
+#[derive(Debug, Default)]
+pub struct Rename {
+    /// key to rename
+    key: String,
+
+    /// new name of the key
+    new_key: String,
+}
+
+impl Rename {
+    /// contruct new Rename command
+    pub fn new(key: String, new_key: String) -> Self {
+        Rename { key, new_key }
+    }
+
+    /// Construct new Rename command by consuming the RespReader
+    pub fn from_parts(reader: &mut RespReader) -> Result<Self, RespReaderError> {
+        let key = reader.next_string()?;
+        let new_key = reader.next_string()?;
+
+        Ok(Rename { key, new_key })
+    }
+
+    /// Apply the rename command, overwriting any value at the new key
+    pub async fn apply(self, db: &Db) -> crate::Result<Option<RESP>> {
+        db.rename(&self.key, &self.new_key, false)?;
+
+        Ok(Some(RESP::Simple("OK".to_string())))
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct RenameNx {
+    /// key to rename
+    key: String,
+
+    /// new name of the key
+    new_key: String,
+}
+
+impl RenameNx {
+    /// contruct new RenameNx command
+    pub fn new(key: String, new_key: String) -> Self {
+        RenameNx { key, new_key }
+    }
+
+    /// Construct new RenameNx command by consuming the RespReader
+    pub fn from_parts(reader: &mut RespReader) -> Result<Self, RespReaderError> {
+        let key = reader.next_string()?;
+        let new_key = reader.next_string()?;
+
+        Ok(RenameNx { key, new_key })
+    }
+
+    /// Apply the renamenx command, returns 1 if the key was renamed
+    pub async fn apply(self, db: &Db) -> crate::Result<Option<RESP>> {
+        // renaming a key to itself always counts as the new key existing
+        if db.rename(&self.key, &self.new_key, true)? && self.key != self.new_key {
+            Ok(Some(RESP::Integer(1)))
+        } else {
+            Ok(Some(RESP::Integer(0)))
+        }
+    }
+}
+
+/// Convert Rename command back into an equivalent `RESP`
+impl From<Rename> for RESP {
+    fn from(value: Rename) -> Self {
+        let mut resp = RESP::array();
+        resp.push_bulk(Bytes::from("rename"));
+        resp.push_bulk(Bytes::from(value.key));
+        resp.push_bulk(Bytes::from(value.new_key));
+        resp
+    }
+}
+
+/// Convert RenameNx command back into an equivalent `RESP`
+impl From<RenameNx> for RESP {
+    fn from(value: RenameNx) -> Self {
+        let mut resp = RESP::array();
+        resp.push_bulk(Bytes::from("renamenx"));
+        resp.push_bulk(Bytes::from(value.key));
+        resp.push_bulk(Bytes::from(value.new_key));
+        resp
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use bytes::Bytes;
+
+    use super::{Rename, RenameNx};
+    use crate::{resp::RESP, CommandError, Db, ValueType};
+
+    #[tokio::test]
+    async fn rename_moves_value_and_expiry() {
+        let db = Db::new();
+        db.set(
+            "src".into(),
+            ValueType::String(Bytes::from("1")),
+            Some(Duration::from_secs(60)),
+        );
+        db.set("dst".into(), ValueType::String(Bytes::from("2")), None);
+        let expires_at = db.lookup("src").unwrap().expires_at;
+
+        let resp = Rename::new("src".into(), "dst".into())
+            .apply(&db)
+            .await
+            .unwrap();
+        assert!(matches!(resp, Some(RESP::Simple(ok)) if ok == "OK"));
+        assert!(db.get("src").is_none());
+
+        let renamed = db.lookup("dst").unwrap();
+        assert!(matches!(renamed.data, ValueType::String(v) if v == "1"));
+        assert_eq!(renamed.expires_at, expires_at);
+
+        // the tracker moved along, expiring it purges the new key
+        assert!(db.expire_now("dst"));
+        assert!(db.keys().is_empty());
+        assert!(db.inner.state.lock().unwrap().next_expiration().is_none());
+
+        let err = Rename::new("src".into(), "dst".into())
+            .apply(&db)
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<CommandError>(),
+            Some(&CommandError::NoSuchKey)
+        );
+    }
+
+    #[tokio::test]
+    async fn renamenx_keeps_an_existing_destination() {
+        let db = Db::new();
+        db.set("a".into(), ValueType::String(Bytes::from("1")), None);
+        db.set("b".into(), ValueType::String(Bytes::from("2")), None);
+
+        let resp = RenameNx::new("a".into(), "b".into())
+            .apply(&db)
+            .await
+            .unwrap();
+        assert!(matches!(resp, Some(RESP::Integer(0))));
+        assert!(matches!(db.get("b"), Some(ValueType::String(v)) if v == "2"));
+
+        let resp = RenameNx::new("a".into(), "c".into())
+            .apply(&db)
+            .await
+            .unwrap();
+        assert!(matches!(resp, Some(RESP::Integer(1))));
+        assert!(db.get("a").is_none());
+        assert!(matches!(db.get("c"), Some(ValueType::String(v)) if v == "1"));
+    }
+}
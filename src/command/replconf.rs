@@ -2,7 +2,9 @@ use std::sync::atomic::{AtomicUsize, Ordering};
 
 use bytes::Bytes;
 
-use crate::{connection::Connection, resp::RESP, RespReader, RespReaderError};
+use crate::{
+    connection::Connection, is_keyword, resp::RESP, CommandError, RespReader, RespReaderError,
+};
 
 #[derive(Debug, Default)]
 pub struct Replconf {
@@ -19,6 +21,30 @@ impl Replconf {
         "REPLCONF"
     }
 
+    /// Whether this is the `GETACK *` a master sends to learn the
+    /// replication offset of its replicas
+    pub fn is_getack(&self) -> bool {
+        matches!(self.values.as_slice(), [key, cmd] if is_keyword(key, "getack") && cmd == "*")
+    }
+
+    /// `REPLCONF GETACK *`, asking a replica for its offset
+    pub fn getack() -> RESP {
+        RESP::Array(vec![
+            RESP::Bulk(Bytes::from("REPLCONF".as_bytes())),
+            RESP::Bulk(Bytes::from("GETACK".as_bytes())),
+            RESP::Bulk(Bytes::from("*".as_bytes())),
+        ])
+    }
+
+    /// Reply to a `GETACK`, acknowledging `offset` bytes processed
+    pub fn ack(offset: usize) -> RESP {
+        RESP::Array(vec![
+            RESP::Bulk(Bytes::from("REPLCONF".as_bytes())),
+            RESP::Bulk(Bytes::from("ACK".as_bytes())),
+            RESP::Bulk(Bytes::from(offset.to_string())),
+        ])
+    }
+
     /// Construct new REPLCONF command by consuming the RespReader
     ///
     /// Parse next_string()? to get the config key
@@ -37,7 +63,11 @@ impl Replconf {
         Ok(Replconf { values })
     }
 
-    /// Apply the echo command and write to the Tcp connection stream
+    /// Apply the replconf command and write to the Tcp connection stream
+    ///
+    /// A master records the `listening-port` and `capa` options a
+    /// replica announces on the connection, other options are accepted
+    /// and ignored
     pub async fn apply(
         self,
         dst: &mut Connection,
@@ -52,17 +82,29 @@ impl Replconf {
         let cmd = value_iter.next();
 
         match (key, cmd) {
-            (Some(key), Some(cmd))
-                if key.to_lowercase() == "getack" && cmd.to_lowercase() == "*" =>
-            {
-                let offset_bytes = offset.unwrap().load(Ordering::SeqCst).to_string();
-                resp = RESP::Array(vec![
-                    RESP::Bulk(Bytes::from("REPLCONF".as_bytes())),
-                    RESP::Bulk(Bytes::from("ACK".as_bytes())),
-                    RESP::Bulk(Bytes::from(offset_bytes)),
-                ]);
+            (Some(key), Some(cmd)) if is_keyword(key, "getack") && cmd == "*" => {
+                resp = Replconf::ack(offset.unwrap().load(Ordering::SeqCst));
+            }
+            _ if !self.values.len().is_multiple_of(2) => {
+                resp = RESP::Error(CommandError::SyntaxError.to_string());
+            }
+            _ => {
+                for option in self.values.chunks(2) {
+                    let [key, value] = option else { continue };
+
+                    if is_keyword(key, "listening-port") {
+                        match value.parse() {
+                            Ok(port) => dst.listening_port = Some(port),
+                            Err(_) => {
+                                resp = RESP::Error(CommandError::NotInteger.to_string());
+                                break;
+                            }
+                        }
+                    } else if is_keyword(key, "capa") {
+                        dst.capabilities.push(value.to_lowercase());
+                    }
+                }
             }
-            _ => (),
         }
 
         // println!("Write RESPONSE {:?}", &resp);
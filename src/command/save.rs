@@ -0,0 +1,330 @@
+use std::{
+    fs::File,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    sync::atomic::Ordering,
+};
+
+use bytes::Bytes;
+
+use crate::{
+    config::ServerConfig, is_keyword, rdb, resp::RESP, CommandError, Db, RespReader,
+    RespReaderError,
+};
+
+/// File names used when the server wasn't started with `--dir`
+/// and `--dbfilename`, same as redis
+const DEFAULT_DIR: &str = ".";
+const DEFAULT_DBFILENAME: &str = "dump.rdb";
+
+#[derive(Debug, Default)]
+pub struct Save;
+
+impl Save {
+    /// contruct new Save command
+    pub fn new() -> Self {
+        Save {}
+    }
+
+    /// Construct new Save command by consuming the RespReader
+    pub fn from_parts(_reader: &mut RespReader) -> Result<Self, RespReaderError> {
+        Ok(Save {})
+    }
+
+    /// Apply the save command, writing a snapshot of every database
+    /// to `dir/dbfilename`
+    ///
+    /// The snapshot is taken and written synchronously like redis' SAVE,
+    /// it's refused while a background save is writing the same file
+    pub async fn apply(self, db: &Db, config: ServerConfig) -> crate::Result<Option<RESP>> {
+        if db.inner.save_in_progress.swap(true, Ordering::SeqCst) {
+            return Ok(Some(RESP::Error(
+                "ERR Background save already in progress".to_string(),
+            )));
+        }
+
+        let path = rdb_path(&config);
+
+        let rdb = rdb::encode_rdb(&db.snapshot());
+        let resp = match write_rdb(&path, &rdb) {
+            Ok(()) => RESP::Simple("OK".to_string()),
+            Err(err) => RESP::Error(format!("ERR {}: {err}", path.display())),
+        };
+        db.inner.save_in_progress.store(false, Ordering::SeqCst);
+
+        Ok(Some(resp))
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct BgSave;
+
+impl BgSave {
+    /// contruct new BgSave command
+    pub fn new() -> Self {
+        BgSave {}
+    }
+
+    /// Construct new BgSave command by consuming the RespReader
+    pub fn from_parts(_reader: &mut RespReader) -> Result<Self, RespReaderError> {
+        Ok(BgSave {})
+    }
+
+    /// Apply the bgsave command, writing a snapshot of every database
+    /// to `dir/dbfilename` on a separate task
+    ///
+    /// The entries are cloned under the state lock before the task is
+    /// spawned, so the snapshot is consistent and the lock isn't held
+    /// while it's written. Only one save, background or not, runs at
+    /// a time
+    pub async fn apply(self, db: &Db, config: ServerConfig) -> crate::Result<Option<RESP>> {
+        if db.inner.save_in_progress.swap(true, Ordering::SeqCst) {
+            return Ok(Some(RESP::Error(
+                "ERR Background save already in progress".to_string(),
+            )));
+        }
+
+        let path = rdb_path(&config);
+        let snapshot = db.snapshot();
+        let shared = db.inner.clone();
+
+        tokio::spawn(async move {
+            let rdb = rdb::encode_rdb(&snapshot);
+            let write = {
+                let path = path.clone();
+                tokio::task::spawn_blocking(move || write_rdb(&path, &rdb))
+            };
+            if let Err(err) = write.await.map_err(io::Error::other).and_then(|res| res) {
+                println!("Background save to {} failed: {err}", path.display());
+            }
+            shared.save_in_progress.store(false, Ordering::SeqCst);
+        });
+
+        Ok(Some(RESP::Simple("Background saving started".to_string())))
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct ShutdownServer {
+    /// `SAVE` or `NOSAVE`, the save points decide without one
+    modifier: Option<String>,
+}
+
+impl ShutdownServer {
+    /// contruct new ShutdownServer command
+    pub fn new(modifier: Option<String>) -> Self {
+        ShutdownServer { modifier }
+    }
+
+    /// Construct new ShutdownServer command by consuming the RespReader
+    ///
+    /// The modifier is optional
+    pub fn from_parts(reader: &mut RespReader) -> Result<Self, RespReaderError> {
+        let modifier = match reader.next_string() {
+            Ok(modifier) => Some(modifier),
+            Err(RespReaderError::EndOfStream) => None,
+            Err(err) => return Err(err),
+        };
+
+        Ok(ShutdownServer { modifier })
+    }
+
+    /// Apply the shutdown command, asking the server to stop
+    ///
+    /// Nothing is replied, the connection is closed with every other
+    /// one once the server shuts down
+    pub async fn apply(self, config: ServerConfig) -> crate::Result<Option<RESP>> {
+        let save = match self.modifier {
+            None => None,
+            Some(modifier) if is_keyword(&modifier, "save") => Some(true),
+            Some(modifier) if is_keyword(&modifier, "nosave") => Some(false),
+            Some(_) => return Err(CommandError::SyntaxError.into()),
+        };
+
+        config.shutdown_request.request(save);
+
+        Ok(None)
+    }
+}
+
+/// Where SAVE and BGSAVE write the snapshot
+fn rdb_path(config: &ServerConfig) -> PathBuf {
+    Path::new(config.dir.as_deref().unwrap_or(DEFAULT_DIR))
+        .join(config.dbfilename.as_deref().unwrap_or(DEFAULT_DBFILENAME))
+}
+
+/// Write `rdb` to a temporary file next to `path` and rename it over
+/// `path`, so a save that fails midway leaves the previous file intact
+fn write_rdb(path: &Path, rdb: &[u8]) -> io::Result<()> {
+    let temp = path.with_file_name(format!("temp-{}.rdb", std::process::id()));
+
+    let result = File::create(&temp)
+        .and_then(|mut file| {
+            file.write_all(rdb)?;
+            file.sync_all()
+        })
+        .and_then(|()| std::fs::rename(&temp, path));
+    if result.is_err() {
+        let _ = std::fs::remove_file(&temp);
+    }
+
+    result
+}
+
+/// Convert Save command back into an equivalent `RESP`
+impl From<Save> for RESP {
+    fn from(_value: Save) -> Self {
+        let mut resp = RESP::array();
+        resp.push_bulk(Bytes::from("save"));
+        resp
+    }
+}
+
+/// Convert BgSave command back into an equivalent `RESP`
+impl From<BgSave> for RESP {
+    fn from(_value: BgSave) -> Self {
+        let mut resp = RESP::array();
+        resp.push_bulk(Bytes::from("bgsave"));
+        resp
+    }
+}
+
+/// Convert ShutdownServer command back into an equivalent `RESP`
+impl From<ShutdownServer> for RESP {
+    fn from(value: ShutdownServer) -> Self {
+        let mut resp = RESP::array();
+        resp.push_bulk(Bytes::from("shutdown"));
+        if let Some(modifier) = value.modifier {
+            resp.push_bulk(Bytes::from(modifier));
+        }
+        resp
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{path::Path, sync::atomic::Ordering, time::Duration};
+
+    use bytes::Bytes;
+
+    use super::{BgSave, Save};
+    use crate::{
+        config::ServerConfig,
+        rdb::{read_db_file, DefaultFilter, RdbBuilder, RdbParser},
+        resp::RESP,
+        Db, ValueType,
+    };
+
+    fn config(dir: &Path, dbfilename: &str) -> ServerConfig {
+        ServerConfig {
+            dir: Some(dir.to_string_lossy().into_owned()),
+            dbfilename: Some(dbfilename.into()),
+            ..ServerConfig::for_test()
+        }
+    }
+
+    #[tokio::test]
+    async fn save_writes_to_dir_and_dbfilename() {
+        let dir = std::env::temp_dir().join(format!("save-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let db = Db::new();
+        db.set("key".into(), ValueType::String(Bytes::from("value")), None);
+
+        let resp = Save::new()
+            .apply(&db, config(&dir, "saved.rdb"))
+            .await
+            .unwrap();
+        assert!(matches!(resp, Some(RESP::Simple(ok)) if ok == "OK"));
+
+        let rdb = read_db_file(dir.join("saved.rdb")).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        let mut parser = RdbParser::new(DefaultFilter::new(), RdbBuilder::default(), rdb);
+        let database = parser.parse().unwrap().unwrap();
+        assert!(matches!(&database.entries["key"].data, ValueType::String(v) if v == "value"));
+    }
+
+    #[tokio::test]
+    async fn bgsave_writes_the_file_once_the_task_completes() {
+        let dir = std::env::temp_dir().join(format!("bgsave-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let db = Db::new();
+        db.set("key".into(), ValueType::String(Bytes::from("value")), None);
+
+        // only one background save at a time
+        db.inner.save_in_progress.store(true, Ordering::SeqCst);
+        let resp = BgSave::new()
+            .apply(&db, config(&dir, "bg.rdb"))
+            .await
+            .unwrap();
+        assert!(matches!(resp, Some(RESP::Error(err)) if err.contains("in progress")));
+        db.inner.save_in_progress.store(false, Ordering::SeqCst);
+
+        let resp = BgSave::new()
+            .apply(&db, config(&dir, "bg.rdb"))
+            .await
+            .unwrap();
+        assert!(matches!(resp, Some(RESP::Simple(s)) if s == "Background saving started"));
+
+        tokio::time::timeout(Duration::from_secs(2), async {
+            while db.inner.save_in_progress.load(Ordering::SeqCst) {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+        })
+        .await
+        .expect("background save didn't finish");
+
+        let rdb = read_db_file(dir.join("bg.rdb")).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        let mut parser = RdbParser::new(DefaultFilter::new(), RdbBuilder::default(), rdb);
+        let database = parser.parse().unwrap().unwrap();
+        assert!(database.entries.contains_key("key"));
+    }
+
+    #[tokio::test]
+    async fn save_replaces_the_file_without_leaving_temp_files() {
+        let dir = std::env::temp_dir().join(format!("save-replace-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("dump.rdb"), "stale").unwrap();
+
+        let db = Db::new();
+        db.set("key".into(), ValueType::String(Bytes::from("value")), None);
+
+        let resp = Save::new()
+            .apply(&db, config(&dir, "dump.rdb"))
+            .await
+            .unwrap();
+        assert!(matches!(resp, Some(RESP::Simple(ok)) if ok == "OK"));
+
+        let files = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect::<Vec<_>>();
+        let rdb = read_db_file(dir.join("dump.rdb")).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(files, ["dump.rdb"]);
+        let mut parser = RdbParser::new(DefaultFilter::new(), RdbBuilder::default(), rdb);
+        assert!(parser.parse().unwrap().unwrap().entries.contains_key("key"));
+    }
+
+    #[tokio::test]
+    async fn save_is_refused_during_a_background_save() {
+        let dir = std::env::temp_dir().join(format!("save-refused-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let db = Db::new();
+        db.inner.save_in_progress.store(true, Ordering::SeqCst);
+
+        let resp = Save::new()
+            .apply(&db, config(&dir, "dump.rdb"))
+            .await
+            .unwrap();
+        let written = dir.join("dump.rdb").exists();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(matches!(resp, Some(RESP::Error(err)) if err.contains("in progress")));
+        assert!(!written);
+        // the background save still owns the flag
+        assert!(db.inner.save_in_progress.load(Ordering::SeqCst));
+    }
+}
@@ -0,0 +1,74 @@
+use bytes::Bytes;
+
+use crate::{resp::RESP, Db, RespReader, RespReaderError};
+
+#[derive(Debug, Default)]
+pub struct Select {
+    /// index of the logical database to switch to
+    index: u64,
+}
+
+impl Select {
+    /// contruct new Select command
+    pub fn new(index: u64) -> Self {
+        Select { index }
+    }
+
+    /// Construct new Select command by consuming the RespReader
+    pub fn from_parts(reader: &mut RespReader) -> Result<Self, RespReaderError> {
+        let index = reader.next_int()?;
+
+        Ok(Select { index })
+    }
+
+    /// Index of the selected database
+    pub fn index(&self) -> u64 {
+        self.index
+    }
+
+    /// Apply the select command, only checks the index is in range
+    ///
+    /// The connection handler keeps track of the selected database
+    pub async fn apply(self, db: &Db) -> crate::Result<Option<RESP>> {
+        if self.index < db.databases() as u64 {
+            Ok(Some(RESP::Simple("OK".to_string())))
+        } else {
+            Ok(Some(RESP::Error(
+                "ERR DB index is out of range".to_string(),
+            )))
+        }
+    }
+}
+
+/// Convert Select command back into an equivalent `RESP`
+impl From<Select> for RESP {
+    fn from(value: Select) -> Self {
+        let mut resp = RESP::array();
+        resp.push_bulk(Bytes::from("select"));
+        resp.push_bulk(Bytes::from(value.index.to_string()));
+        resp
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Select;
+    use crate::{resp::RESP, Db, DEFAULT_DATABASES};
+
+    #[tokio::test]
+    async fn select_out_of_range_is_an_error() {
+        let db = Db::new();
+
+        let resp = Select::new(DEFAULT_DATABASES as u64 - 1)
+            .apply(&db)
+            .await
+            .unwrap();
+        assert!(matches!(resp, Some(RESP::Simple(ok)) if ok == "OK"));
+
+        let resp = Select::new(DEFAULT_DATABASES as u64)
+            .apply(&db)
+            .await
+            .unwrap();
+        assert!(matches!(resp, Some(RESP::Error(err)) if err == "ERR DB index is out of range"));
+    }
+}
@@ -2,7 +2,7 @@ use tokio::time::Duration;
 
 use bytes::Bytes;
 
-use crate::{connection::Connection, resp::RESP, Db, RespReader, RespReaderError, ValueType};
+use crate::{is_keyword, resp::RESP, Db, RespReader, RespReaderError, ValueType};
 
 #[derive(Debug, Default)]
 pub struct Set {
@@ -36,13 +36,13 @@ impl Set {
 
         match reader.next_string() {
             // parse PX argument to SET command
-            Ok(s) if s.to_lowercase() == "px" => {
+            Ok(s) if is_keyword(&s, "px") => {
                 let duration = reader.next_int().map(|dur| Duration::from_millis(dur))?;
                 expire = Some(duration);
             }
 
             // parse EX argument to SET command
-            Ok(s) if s.to_lowercase() == "ex" => {
+            Ok(s) if is_keyword(&s, "ex") => {
                 let duration = reader.next_int().map(|dur| Duration::from_secs(dur))?;
                 expire = Some(duration);
             }
@@ -59,7 +59,7 @@ impl Set {
     }
 
     /// Apply the echo command and write to the Tcp connection stream
-    pub async fn apply(self, db: &Db, _dst: &mut Connection) -> crate::Result<Option<RESP>> {
+    pub async fn apply(self, db: &Db) -> crate::Result<Option<RESP>> {
         // set the value in the shared cache.
         let value = ValueType::String(self.value);
         db.set(self.key, value, self.expire);
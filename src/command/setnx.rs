@@ -0,0 +1,82 @@
+use bytes::Bytes;
+
+use crate::{resp::RESP, Db, RespReader, RespReaderError, ValueType};
+
+#[derive(Debug, Default)]
+pub struct SetNx {
+    /// cache lookup key
+    key: String,
+
+    /// value to store in db
+    value: Bytes,
+}
+
+impl SetNx {
+    /// contruct new SetNx command
+    pub fn new(key: String, value: Bytes) -> Self {
+        SetNx { key, value }
+    }
+
+    /// Construct new SetNx command by consuming the RespReader
+    pub fn from_parts(reader: &mut RespReader) -> Result<Self, RespReaderError> {
+        let key = reader.next_string()?;
+        let value = reader.next_byte()?;
+
+        Ok(SetNx { key, value })
+    }
+
+    /// Apply the setnx command, returns 1 if the key was set
+    pub async fn apply(self, db: &Db) -> crate::Result<Option<RESP>> {
+        if db.set_nx(self.key, ValueType::String(self.value)) {
+            Ok(Some(RESP::Integer(1)))
+        } else {
+            Ok(Some(RESP::Integer(0)))
+        }
+    }
+}
+
+/// Convert SetNx command back into an equivalent `RESP`
+impl From<SetNx> for RESP {
+    fn from(value: SetNx) -> Self {
+        let mut resp = RESP::array();
+        resp.push_bulk(Bytes::from("setnx"));
+        resp.push_bulk(Bytes::from(value.key));
+        resp.push_bulk(value.value);
+        resp
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bytes::Bytes;
+
+    use super::SetNx;
+    use crate::{resp::RESP, Db, ValueType};
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn setnx_sets_only_once_across_tasks() {
+        let db = Db::new();
+
+        let tasks = (0..16)
+            .map(|i| {
+                let db = db.clone();
+                tokio::spawn(async move {
+                    SetNx::new("key".into(), Bytes::from(i.to_string()))
+                        .apply(&db)
+                        .await
+                        .unwrap()
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let mut set = 0;
+        for task in tasks {
+            if let Some(RESP::Integer(1)) = task.await.unwrap() {
+                set += 1;
+            }
+        }
+
+        assert_eq!(set, 1);
+        assert!(matches!(db.get("key"), Some(ValueType::String(_))));
+    }
+}
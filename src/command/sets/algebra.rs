@@ -0,0 +1,261 @@
+use std::collections::HashSet;
+
+use bytes::Bytes;
+
+use crate::{resp::RESP, CommandError, Db, RespReader, RespReaderError, ValueType};
+
+#[derive(Debug, Default)]
+pub struct SInter {
+    /// keys of the sets to intersect
+    keys: Vec<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct SUnion {
+    /// keys of the sets to join
+    keys: Vec<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct SDiff {
+    /// the first set is reduced by the sets of the other keys
+    keys: Vec<String>,
+}
+
+impl SInter {
+    /// contruct new SInter command
+    pub fn new(keys: Vec<String>) -> Self {
+        SInter { keys }
+    }
+
+    /// Construct new SInter command by consuming the RespReader
+    pub fn from_parts(reader: &mut RespReader) -> Result<Self, RespReaderError> {
+        Ok(SInter {
+            keys: parse_keys(reader)?,
+        })
+    }
+
+    /// Apply the sinter command and return the members found in every set
+    pub async fn apply(self, db: &Db) -> crate::Result<Option<RESP>> {
+        let mut sets = snapshot(db, &self.keys)?.into_iter();
+        let first = sets.next().unwrap_or_default();
+
+        let members = sets.fold(first, |acc, set| &acc & &set);
+        Ok(Some(members_resp(members)))
+    }
+}
+
+impl SUnion {
+    /// contruct new SUnion command
+    pub fn new(keys: Vec<String>) -> Self {
+        SUnion { keys }
+    }
+
+    /// Construct new SUnion command by consuming the RespReader
+    pub fn from_parts(reader: &mut RespReader) -> Result<Self, RespReaderError> {
+        Ok(SUnion {
+            keys: parse_keys(reader)?,
+        })
+    }
+
+    /// Apply the sunion command and return the members found in any set
+    pub async fn apply(self, db: &Db) -> crate::Result<Option<RESP>> {
+        let members = snapshot(db, &self.keys)?.into_iter().flatten().collect();
+        Ok(Some(members_resp(members)))
+    }
+}
+
+impl SDiff {
+    /// contruct new SDiff command
+    pub fn new(keys: Vec<String>) -> Self {
+        SDiff { keys }
+    }
+
+    /// Construct new SDiff command by consuming the RespReader
+    pub fn from_parts(reader: &mut RespReader) -> Result<Self, RespReaderError> {
+        Ok(SDiff {
+            keys: parse_keys(reader)?,
+        })
+    }
+
+    /// Apply the sdiff command and return the members of the first set
+    /// that aren't in any of the other sets
+    pub async fn apply(self, db: &Db) -> crate::Result<Option<RESP>> {
+        let mut sets = snapshot(db, &self.keys)?.into_iter();
+        let first = sets.next().unwrap_or_default();
+
+        let members = sets.fold(first, |acc, set| &acc - &set);
+        Ok(Some(members_resp(members)))
+    }
+}
+
+fn parse_keys(reader: &mut RespReader) -> Result<Vec<String>, RespReaderError> {
+    let mut keys = vec![reader.next_string()?];
+
+    while let Ok(key) = reader.next_string() {
+        keys.push(key);
+    }
+
+    Ok(keys)
+}
+
+/// Copy the members of every set in `keys` under a single lock, in the
+/// order of `keys`
+///
+/// Missing keys are empty sets
+fn snapshot(db: &Db, keys: &[String]) -> crate::Result<Vec<HashSet<String>>> {
+    db.get_all(keys)
+        .into_iter()
+        .map(|value| match value {
+            Some(ValueType::Set(set)) => Ok(set.iter().collect()),
+            Some(_) => Err(CommandError::WrongType.into()),
+            None => Ok(HashSet::new()),
+        })
+        .collect()
+}
+
+fn members_resp(members: HashSet<String>) -> RESP {
+    RESP::Array(
+        members
+            .into_iter()
+            .map(|member| RESP::Bulk(Bytes::from(member)))
+            .collect(),
+    )
+}
+
+fn keys_resp(name: &'static str, keys: Vec<String>) -> RESP {
+    let mut resp = RESP::array();
+    resp.push_bulk(Bytes::from(name));
+    for key in keys {
+        resp.push_bulk(Bytes::from(key));
+    }
+    resp
+}
+
+/// Convert SInter command back into an equivalent `RESP`
+impl From<SInter> for RESP {
+    fn from(value: SInter) -> Self {
+        keys_resp("sinter", value.keys)
+    }
+}
+
+/// Convert SUnion command back into an equivalent `RESP`
+impl From<SUnion> for RESP {
+    fn from(value: SUnion) -> Self {
+        keys_resp("sunion", value.keys)
+    }
+}
+
+/// Convert SDiff command back into an equivalent `RESP`
+impl From<SDiff> for RESP {
+    fn from(value: SDiff) -> Self {
+        keys_resp("sdiff", value.keys)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashSet;
+
+    use bytes::Bytes;
+
+    use super::{SDiff, SInter, SUnion};
+    use crate::{resp::RESP, CommandError, Db, Set, ValueType};
+
+    fn db() -> Db {
+        let db = Db::new();
+        for (key, members) in [
+            ("a", ["1", "2", "3"]),
+            ("b", ["2", "3", "4"]),
+            ("c", ["3", "5", "6"]),
+        ] {
+            let members = members
+                .into_iter()
+                .map(String::from)
+                .collect::<HashSet<_>>();
+            db.set(key.into(), ValueType::Set(Set::from(members)), None);
+        }
+        db
+    }
+
+    fn keys(keys: &[&str]) -> Vec<String> {
+        keys.iter().map(|key| key.to_string()).collect()
+    }
+
+    /// Members of an array reply, the order is unspecified
+    fn members(resp: crate::Result<Option<RESP>>) -> HashSet<String> {
+        match resp.unwrap() {
+            Some(RESP::Array(members)) => members
+                .into_iter()
+                .map(|member| match member {
+                    RESP::Bulk(member) => String::from_utf8(member.to_vec()).unwrap(),
+                    member => panic!("unexpected reply {member:?}"),
+                })
+                .collect(),
+            resp => panic!("expected an array, got {resp:?}"),
+        }
+    }
+
+    fn set(members: &[&str]) -> HashSet<String> {
+        members.iter().map(|member| member.to_string()).collect()
+    }
+
+    #[tokio::test]
+    async fn sinter() {
+        let db = db();
+
+        let inter = |k: &[&str]| SInter::new(keys(k)).apply(&db);
+        assert_eq!(members(inter(&["a", "b"]).await), set(&["2", "3"]));
+        assert_eq!(members(inter(&["a", "b", "c"]).await), set(&["3"]));
+        assert_eq!(members(inter(&["a"]).await), set(&["1", "2", "3"]));
+        // a missing key is an empty set
+        assert_eq!(members(inter(&["a", "missing"]).await), set(&[]));
+    }
+
+    #[tokio::test]
+    async fn sunion() {
+        let db = db();
+
+        let union = |k: &[&str]| SUnion::new(keys(k)).apply(&db);
+        assert_eq!(
+            members(union(&["a", "b", "missing"]).await),
+            set(&["1", "2", "3", "4"])
+        );
+        assert_eq!(members(union(&["c"]).await), set(&["3", "5", "6"]));
+        assert_eq!(members(union(&["missing"]).await), set(&[]));
+    }
+
+    #[tokio::test]
+    async fn sdiff_keeps_the_argument_order() {
+        let db = db();
+
+        let diff = |k: &[&str]| SDiff::new(keys(k)).apply(&db);
+        assert_eq!(members(diff(&["a", "b"]).await), set(&["1"]));
+        assert_eq!(members(diff(&["b", "a"]).await), set(&["4"]));
+        assert_eq!(members(diff(&["a", "b", "c"]).await), set(&["1"]));
+        assert_eq!(
+            members(diff(&["a", "missing"]).await),
+            set(&["1", "2", "3"])
+        );
+        assert_eq!(members(diff(&["missing", "a"]).await), set(&[]));
+        assert_eq!(members(diff(&["c"]).await), set(&["3", "5", "6"]));
+    }
+
+    #[tokio::test]
+    async fn any_non_set_key_is_a_wrong_type_error() {
+        let db = db();
+        db.set("string".into(), ValueType::String(Bytes::from("v")), None);
+
+        for result in [
+            SInter::new(keys(&["a", "string"])).apply(&db).await,
+            SUnion::new(keys(&["string", "a"])).apply(&db).await,
+            SDiff::new(keys(&["a", "string"])).apply(&db).await,
+        ] {
+            let err = result.unwrap_err();
+            assert!(matches!(
+                err.downcast_ref::<CommandError>(),
+                Some(CommandError::WrongType)
+            ));
+        }
+    }
+}
@@ -0,0 +1,7 @@
+pub mod algebra;
+pub mod sadd;
+pub mod smembers;
+
+pub use algebra::{SDiff, SInter, SUnion};
+pub use sadd::{SAdd, SRem};
+pub use smembers::{SCard, SIsMember, SMembers};
@@ -0,0 +1,204 @@
+use bytes::Bytes;
+
+use crate::{config::ServerConfig, resp::RESP, Db, RespReader, RespReaderError};
+
+#[derive(Debug, Default)]
+pub struct SAdd {
+    /// cache lookup key
+    key: String,
+
+    /// members to add
+    members: Vec<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct SRem {
+    /// cache lookup key
+    key: String,
+
+    /// members to remove
+    members: Vec<String>,
+}
+
+impl SAdd {
+    /// contruct new SAdd command
+    pub fn new(key: String, members: Vec<String>) -> Self {
+        SAdd { key, members }
+    }
+
+    /// Construct new SAdd command by consuming the RespReader
+    pub fn from_parts(reader: &mut RespReader) -> Result<Self, RespReaderError> {
+        let (key, members) = parse_members(reader)?;
+        Ok(SAdd { key, members })
+    }
+
+    /// Apply the sadd command and return the number of new members
+    pub async fn apply(self, db: &Db, config: ServerConfig) -> crate::Result<Option<RESP>> {
+        let added = db.set_op(&self.key, "sadd", |set| {
+            let added = self
+                .members
+                .into_iter()
+                .filter(|member| set.insert(member.clone(), &config.encoding))
+                .count();
+            (added, added > 0)
+        })?;
+
+        Ok(Some(RESP::Integer(added as i64)))
+    }
+}
+
+impl SRem {
+    /// contruct new SRem command
+    pub fn new(key: String, members: Vec<String>) -> Self {
+        SRem { key, members }
+    }
+
+    /// Construct new SRem command by consuming the RespReader
+    pub fn from_parts(reader: &mut RespReader) -> Result<Self, RespReaderError> {
+        let (key, members) = parse_members(reader)?;
+        Ok(SRem { key, members })
+    }
+
+    /// Apply the srem command and return the number of removed members
+    ///
+    /// The key is deleted once the last member is removed
+    pub async fn apply(self, db: &Db) -> crate::Result<Option<RESP>> {
+        let removed = db.set_op(&self.key, "srem", |set| {
+            let removed = self
+                .members
+                .iter()
+                .filter(|member| set.remove(member))
+                .count();
+            (removed, removed > 0)
+        })?;
+
+        Ok(Some(RESP::Integer(removed as i64)))
+    }
+}
+
+/// Parse `key member [member ...]`
+fn parse_members(reader: &mut RespReader) -> Result<(String, Vec<String>), RespReaderError> {
+    let key = reader.next_string()?;
+
+    let mut members = vec![reader.next_string()?];
+    while let Ok(member) = reader.next_string() {
+        members.push(member);
+    }
+
+    Ok((key, members))
+}
+
+/// Convert SAdd command back into an equivalent `RESP`
+impl From<SAdd> for RESP {
+    fn from(value: SAdd) -> Self {
+        members_resp("sadd", value.key, value.members)
+    }
+}
+
+/// Convert SRem command back into an equivalent `RESP`
+impl From<SRem> for RESP {
+    fn from(value: SRem) -> Self {
+        members_resp("srem", value.key, value.members)
+    }
+}
+
+fn members_resp(name: &'static str, key: String, members: Vec<String>) -> RESP {
+    let mut resp = RESP::array();
+    resp.push_bulk(Bytes::from(name));
+    resp.push_bulk(Bytes::from(key));
+    for member in members {
+        resp.push_bulk(Bytes::from(member));
+    }
+    resp
+}
+
+#[cfg(test)]
+mod test {
+    use bytes::Bytes;
+
+    use super::{SAdd, SRem};
+    use crate::{config::ServerConfig, resp::RESP, CommandError, Db, ValueType};
+
+    fn encoding(db: &Db, key: &str) -> &'static str {
+        match db.get(key) {
+            Some(value) => value.encoding(),
+            None => panic!("{key} is missing"),
+        }
+    }
+
+    #[tokio::test]
+    async fn sadd_creates_the_set_and_srem_deletes_it_once_empty() {
+        let db = Db::new();
+
+        let resp = SAdd::new("set".into(), vec!["a".into(), "b".into(), "a".into()])
+            .apply(&db, ServerConfig::for_test())
+            .await
+            .unwrap();
+        assert!(matches!(resp, Some(RESP::Integer(2))));
+
+        let resp = SAdd::new("set".into(), vec!["b".into(), "c".into()])
+            .apply(&db, ServerConfig::for_test())
+            .await
+            .unwrap();
+        assert!(matches!(resp, Some(RESP::Integer(1))));
+
+        let resp = SRem::new("set".into(), vec!["a".into(), "missing".into()])
+            .apply(&db)
+            .await
+            .unwrap();
+        assert!(matches!(resp, Some(RESP::Integer(1))));
+        assert!(matches!(db.get("set"), Some(ValueType::Set(set)) if set.len() == 2));
+
+        let resp = SRem::new("set".into(), vec!["b".into(), "c".into()])
+            .apply(&db)
+            .await
+            .unwrap();
+        assert!(matches!(resp, Some(RESP::Integer(2))));
+        assert!(db.get("set").is_none());
+    }
+
+    #[tokio::test]
+    async fn sadd_grows_an_intset_into_a_hashtable() {
+        let db = Db::new();
+        let mut config = ServerConfig::for_test();
+        config.encoding.set_max_intset_entries = 3;
+
+        let members = vec!["1".into(), "2".into(), "3".into()];
+        SAdd::new("ints".into(), members)
+            .apply(&db, config.clone())
+            .await
+            .unwrap();
+        assert_eq!(encoding(&db, "ints"), "intset");
+
+        SAdd::new("ints".into(), vec!["4".into()])
+            .apply(&db, config.clone())
+            .await
+            .unwrap();
+        assert_eq!(encoding(&db, "ints"), "hashtable");
+
+        SAdd::new("small".into(), vec!["1".into(), "a".into()])
+            .apply(&db, config)
+            .await
+            .unwrap();
+        assert_eq!(encoding(&db, "small"), "listpack");
+    }
+
+    #[tokio::test]
+    async fn sadd_and_srem_on_a_string_are_wrong_type_errors() {
+        let db = Db::new();
+        db.set("key".into(), ValueType::String(Bytes::from("v")), None);
+
+        for result in [
+            SAdd::new("key".into(), vec!["a".into()])
+                .apply(&db, ServerConfig::for_test())
+                .await,
+            SRem::new("key".into(), vec!["a".into()]).apply(&db).await,
+        ] {
+            let err = result.unwrap_err();
+            assert!(matches!(
+                err.downcast_ref::<CommandError>(),
+                Some(CommandError::WrongType)
+            ));
+        }
+    }
+}
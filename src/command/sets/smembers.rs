@@ -0,0 +1,194 @@
+use bytes::Bytes;
+
+use crate::{resp::RESP, CommandError, Db, RespReader, RespReaderError, ValueType};
+
+#[derive(Debug, Default)]
+pub struct SMembers {
+    /// cache lookup key
+    key: String,
+}
+
+#[derive(Debug, Default)]
+pub struct SIsMember {
+    /// cache lookup key
+    key: String,
+
+    /// member to look for
+    member: String,
+}
+
+#[derive(Debug, Default)]
+pub struct SCard {
+    /// cache lookup key
+    key: String,
+}
+
+impl SMembers {
+    /// contruct new SMembers command
+    pub fn new(key: String) -> Self {
+        SMembers { key }
+    }
+
+    /// Construct new SMembers command by consuming the RespReader
+    pub fn from_parts(reader: &mut RespReader) -> Result<Self, RespReaderError> {
+        let key = reader.next_string()?;
+        Ok(SMembers { key })
+    }
+
+    /// Apply the smembers command and return every member of the set
+    pub async fn apply(self, db: &Db) -> crate::Result<Option<RESP>> {
+        let members = match db.get(&self.key) {
+            Some(ValueType::Set(set)) => set
+                .iter()
+                .map(|member| RESP::Bulk(Bytes::from(member)))
+                .collect(),
+            Some(_) => return Err(CommandError::WrongType.into()),
+            None => vec![],
+        };
+
+        Ok(Some(RESP::Array(members)))
+    }
+}
+
+impl SIsMember {
+    /// contruct new SIsMember command
+    pub fn new(key: String, member: String) -> Self {
+        SIsMember { key, member }
+    }
+
+    /// Construct new SIsMember command by consuming the RespReader
+    pub fn from_parts(reader: &mut RespReader) -> Result<Self, RespReaderError> {
+        let key = reader.next_string()?;
+        let member = reader.next_string()?;
+        Ok(SIsMember { key, member })
+    }
+
+    /// Apply the sismember command, replying 1 if the member is in the set
+    pub async fn apply(self, db: &Db) -> crate::Result<Option<RESP>> {
+        let is_member = match db.get(&self.key) {
+            Some(ValueType::Set(set)) => set.contains(&self.member),
+            Some(_) => return Err(CommandError::WrongType.into()),
+            None => false,
+        };
+
+        Ok(Some(RESP::Integer(is_member as i64)))
+    }
+}
+
+impl SCard {
+    /// contruct new SCard command
+    pub fn new(key: String) -> Self {
+        SCard { key }
+    }
+
+    /// Construct new SCard command by consuming the RespReader
+    pub fn from_parts(reader: &mut RespReader) -> Result<Self, RespReaderError> {
+        let key = reader.next_string()?;
+        Ok(SCard { key })
+    }
+
+    /// Apply the scard command and return the number of members
+    pub async fn apply(self, db: &Db) -> crate::Result<Option<RESP>> {
+        let len = match db.get(&self.key) {
+            Some(ValueType::Set(set)) => set.len(),
+            Some(_) => return Err(CommandError::WrongType.into()),
+            None => 0,
+        };
+
+        Ok(Some(RESP::Integer(len as i64)))
+    }
+}
+
+/// Convert SMembers command back into an equivalent `RESP`
+impl From<SMembers> for RESP {
+    fn from(value: SMembers) -> Self {
+        let mut resp = RESP::array();
+        resp.push_bulk(Bytes::from("smembers"));
+        resp.push_bulk(Bytes::from(value.key));
+        resp
+    }
+}
+
+/// Convert SIsMember command back into an equivalent `RESP`
+impl From<SIsMember> for RESP {
+    fn from(value: SIsMember) -> Self {
+        let mut resp = RESP::array();
+        resp.push_bulk(Bytes::from("sismember"));
+        resp.push_bulk(Bytes::from(value.key));
+        resp.push_bulk(Bytes::from(value.member));
+        resp
+    }
+}
+
+/// Convert SCard command back into an equivalent `RESP`
+impl From<SCard> for RESP {
+    fn from(value: SCard) -> Self {
+        let mut resp = RESP::array();
+        resp.push_bulk(Bytes::from("scard"));
+        resp.push_bulk(Bytes::from(value.key));
+        resp
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashSet;
+
+    use bytes::Bytes;
+
+    use super::{SCard, SIsMember, SMembers};
+    use crate::{resp::RESP, CommandError, Db, Set, ValueType};
+
+    #[tokio::test]
+    async fn reads_report_members_and_cardinality() {
+        let db = Db::new();
+        let set = Set::from(HashSet::from(["a".to_string(), "b".to_string()]));
+        db.set("set".into(), ValueType::Set(set), None);
+
+        // member order is unspecified
+        let members = match SMembers::new("set".into()).apply(&db).await.unwrap() {
+            Some(RESP::Array(members)) => members
+                .into_iter()
+                .map(|member| match member {
+                    RESP::Bulk(member) => String::from_utf8(member.to_vec()).unwrap(),
+                    member => panic!("unexpected reply {member:?}"),
+                })
+                .collect::<HashSet<_>>(),
+            resp => panic!("expected an array, got {resp:?}"),
+        };
+        assert_eq!(members, HashSet::from(["a".into(), "b".into()]));
+
+        let sismember = |member: &str| SIsMember::new("set".into(), member.into()).apply(&db);
+        assert!(matches!(
+            sismember("a").await.unwrap(),
+            Some(RESP::Integer(1))
+        ));
+        assert!(matches!(
+            sismember("c").await.unwrap(),
+            Some(RESP::Integer(0))
+        ));
+
+        let resp = SCard::new("set".into()).apply(&db).await.unwrap();
+        assert!(matches!(resp, Some(RESP::Integer(2))));
+        let resp = SCard::new("missing".into()).apply(&db).await.unwrap();
+        assert!(matches!(resp, Some(RESP::Integer(0))));
+    }
+
+    #[tokio::test]
+    async fn reading_a_string_is_a_wrong_type_error() {
+        let db = Db::new();
+        db.set("key".into(), ValueType::String(Bytes::from("v")), None);
+
+        for result in [
+            SMembers::new("key".into()).apply(&db).await,
+            SIsMember::new("key".into(), "a".into()).apply(&db).await,
+            SCard::new("key".into()).apply(&db).await,
+        ] {
+            let err = result.unwrap_err();
+            assert!(matches!(
+                err.downcast_ref::<CommandError>(),
+                Some(CommandError::WrongType)
+            ));
+        }
+    }
+}
@@ -1,7 +1,9 @@
 pub mod xadd;
+pub mod xdel;
 pub mod xrange;
 pub mod xread;
 
 pub use xadd::XAdd;
+pub use xdel::XDel;
 pub use xrange::XRange;
 pub use xread::XRead;
@@ -4,16 +4,19 @@ use std::{
 };
 
 use bytes::Bytes;
-use tokio::time::Instant;
 
-use crate::{resp::RESP, Db, RespReader, RespReaderError, StreamData, ValueType};
+use crate::{is_keyword, resp::RESP, CommandError, Db, RespReader, RespReaderError};
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone)]
 pub struct XAdd {
     pub key: String,
     pub id: Option<(u64, u64)>,
     pub stream_id: Option<String>,
     pub fields: HashMap<String, String>,
+    /// don't create the stream if it doesn't exist
+    pub nomkstream: bool,
+    /// number of entries the stream is trimmed to after the insert
+    pub maxlen: Option<usize>,
 }
 
 impl XAdd {
@@ -31,7 +34,25 @@ impl XAdd {
     ///
     pub fn from_parts(reader: &mut RespReader) -> Result<Self, RespReaderError> {
         let key = reader.next_string()?;
-        let stream_id = reader.next_string()?;
+
+        let mut nomkstream = false;
+        let mut maxlen = None;
+        let stream_id = loop {
+            let arg = reader.next_string()?;
+            if is_keyword(&arg, "nomkstream") {
+                nomkstream = true;
+            } else if is_keyword(&arg, "maxlen") {
+                // trimming is always exact, `~` is accepted and ignored
+                let mut count = reader.next_string()?;
+                if count == "~" || count == "=" {
+                    count = reader.next_string()?;
+                }
+                let count = count.parse().map_err(|_| CommandError::NotInteger)?;
+                maxlen = Some(count);
+            } else {
+                break arg;
+            }
+        };
 
         let mut pairs = HashMap::new();
 
@@ -49,173 +70,283 @@ impl XAdd {
             fields: pairs,
             id: None,
             stream_id: Some(stream_id),
+            nomkstream,
+            maxlen,
         })
     }
 
-    fn is_stream_id_valid(&self) -> bool {
-        let ids = self
-            .stream_id
-            .clone()
-            .unwrap_or("".to_string())
-            .split('-')
-            .map(|char| char.to_string())
-            .collect::<Vec<String>>();
+    /// The same XADD adding the entry at `id`, replicas are sent the id
+    /// the master picked rather than picking one of their own
+    pub fn with_id(self, id: String) -> XAdd {
+        XAdd {
+            stream_id: Some(id),
+            ..self
+        }
+    }
 
-        if ids.len() > 2 {
-            return false;
+    /// Apply the stream command and reply with the id of the new entry
+    pub async fn apply(self, db: &Db) -> crate::Result<Option<RESP>> {
+        let requested = parse_stream_id(self.stream_id.as_deref().unwrap_or_default())?;
+
+        let added = db.xadd(
+            &self.key,
+            self.fields,
+            self.nomkstream,
+            self.maxlen,
+            |top| requested.resolve(top, now_millis()),
+        )?;
+
+        match added {
+            Some((ms, seq)) => Ok(Some(RESP::Bulk(Bytes::from(format!("{ms}-{seq}"))))),
+            None => Ok(Some(RESP::Null)),
         }
+    }
+}
 
-        if ids.len() == 2 {
-            if ids[1] == "0" {
-                return ids[0] != "0";
+/// Entry id requested by XADD
+#[derive(Debug, PartialEq)]
+enum StreamId {
+    /// `*`, the current time with the next sequence
+    Auto,
+    /// `ms-*`, the next sequence within the given milliseconds
+    AutoSequence(u64),
+    /// `ms-seq` or `ms`, used as is
+    Explicit(u64, u64),
+}
+
+impl StreamId {
+    /// Turn the requested id into the id of the new entry given the id
+    /// of the stream's top entry
+    ///
+    /// The id has to be greater than the top entry's, and than 0-0 for
+    /// an empty stream
+    fn resolve(self, top: Option<(u64, u64)>, now: u64) -> Result<(u64, u64), CommandError> {
+        // `None` once the sequences of `ms` are used up
+        let next_sequence = |ms: u64| match top {
+            Some((top_ms, top_seq)) if top_ms == ms => top_seq.checked_add(1),
+            _ if ms == 0 => Some(1),
+            _ => Some(0),
+        };
+
+        let id = match self {
+            StreamId::Auto => {
+                // never go back in time if the clock moved backwards
+                let ms = top.map_or(now, |(top_ms, _)| top_ms.max(now));
+                match next_sequence(ms) {
+                    Some(seq) => Some((ms, seq)),
+                    // carry over into the next millisecond
+                    None => ms.checked_add(1).map(|ms| (ms, 0)),
+                }
             }
-        }
+            StreamId::AutoSequence(ms) => next_sequence(ms).map(|seq| (ms, seq)),
+            StreamId::Explicit(ms, seq) => Some((ms, seq)),
+        };
 
-        if ids.len() == 1 && ids[0] != "*" {
-            return false;
+        match id {
+            Some((0, 0)) => Err(CommandError::StreamIdZero),
+            Some(id) if top.is_none_or(|top| top < id) => Ok(id),
+            _ => Err(CommandError::StreamIdTooSmall),
         }
+    }
+}
+
+fn parse_stream_id(id: &str) -> Result<StreamId, CommandError> {
+    let int = |part: &str| part.parse::<u64>().map_err(|_| CommandError::StreamId);
 
-        return true;
+    match id.split_once('-') {
+        _ if id == "*" => Ok(StreamId::Auto),
+        Some((ms, "*")) => Ok(StreamId::AutoSequence(int(ms)?)),
+        Some((ms, seq)) => Ok(StreamId::Explicit(int(ms)?, int(seq)?)),
+        None => Ok(StreamId::Explicit(int(id)?, 0)),
     }
+}
 
-    fn get_stream_id(&self, next_sequence_id: u64) -> (u64, u64) {
-        let ids = self
-            .stream_id
-            .clone()
-            .unwrap()
-            .split('-')
-            .map(|char| char.to_string())
-            .collect::<Vec<String>>();
+fn now_millis() -> u64 {
+    let since_the_epoch = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("Time went backwards");
+    since_the_epoch.as_millis() as u64
+}
 
-        let now = {
-            let start = SystemTime::now();
-            let since_the_epoch = start
-                .duration_since(UNIX_EPOCH)
-                .expect("Time went backwards");
-            since_the_epoch.as_secs() * 1000 + since_the_epoch.subsec_nanos() as u64 / 1_000_000
-        };
+impl From<XAdd> for RESP {
+    fn from(this: XAdd) -> Self {
+        let mut resp = RESP::array();
+        resp.push_bulk(Bytes::from("XADD"));
+        resp.push_bulk(Bytes::from(this.key));
+        if this.nomkstream {
+            resp.push_bulk(Bytes::from("NOMKSTREAM"));
+        }
+        if let Some(maxlen) = this.maxlen {
+            resp.push_bulk(Bytes::from("MAXLEN"));
+            resp.push_bulk(Bytes::from(maxlen.to_string()));
+        }
+        resp.push_bulk(Bytes::from(this.stream_id.unwrap()));
+        for (key, value) in this.fields.into_iter() {
+            resp.push_bulk(Bytes::from(key));
+            resp.push_bulk(Bytes::from(value));
+        }
+        resp
+    }
+}
 
-        let millisec = ids
-            .get(0)
-            .map(|t| if t == "*" { now } else { t.parse().unwrap() })
-            .or(Some(now))
-            .unwrap();
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
 
-        let sequence_id = ids
-            .get(1)
-            .map(|t| {
-                if t == "*" {
-                    next_sequence_id
-                } else {
-                    t.parse().unwrap()
-                }
-            })
-            .or(Some(next_sequence_id))
-            .unwrap();
+    use bytes::Bytes;
 
-        (millisec, sequence_id)
+    use super::XAdd;
+    use crate::{resp::RESP, CommandError, Db, RespReader, ValueType};
+
+    fn parse(args: &[&str]) -> XAdd {
+        let args = args
+            .iter()
+            .map(|arg| RESP::Bulk(Bytes::from(arg.to_string())))
+            .collect();
+        XAdd::from_parts(&mut RespReader::new(RESP::Array(args)).unwrap()).unwrap()
     }
 
-    /// Apply the stream command and write to the Tcp connection stream
-    pub async fn apply(self, db: &Db) -> crate::Result<Option<RESP>> {
-        if !self.is_stream_id_valid() {
-            return Ok(Some(RESP::Error(
-                "ERR The ID specified in XADD must be greater than 0-0".to_string(),
-            )));
+    /// Add an entry to "stream", errors are turned into the reply the
+    /// client would get
+    async fn xadd(db: &Db, id: &str) -> RESP {
+        let xadd = XAdd {
+            key: "stream".into(),
+            id: None,
+            stream_id: Some(id.into()),
+            fields: HashMap::from([("field".to_string(), "value".to_string())]),
+            ..XAdd::default()
+        };
+        match xadd.apply(db).await {
+            Ok(resp) => resp.unwrap(),
+            Err(err) => RESP::Error(err.to_string()),
         }
+    }
 
-        let prev_stream = db.get(&self.key);
+    #[tokio::test]
+    async fn auto_sequence_starts_at_one_for_zero_ms() {
+        let db = Db::new();
 
-        let mut streams = if let Some(prev_stream) = prev_stream {
-            match prev_stream {
-                ValueType::Stream(stream) => stream,
-                _ => vec![],
-            }
-        } else {
-            vec![]
-        };
+        assert!(matches!(xadd(&db, "0-*").await, RESP::Bulk(id) if id == "0-1"));
+        assert!(matches!(xadd(&db, "0-*").await, RESP::Bulk(id) if id == "0-2"));
+        assert!(matches!(xadd(&db, "1-*").await, RESP::Bulk(id) if id == "1-0"));
+    }
 
-        let next_sequence_id = {
-            let ids = self
-                .stream_id
-                .clone()
-                .unwrap_or("".to_string())
-                .split('-')
-                .map(|char| char.to_string())
-                .collect::<Vec<String>>();
-
-            if ids[0] == "*" {
-                if ids.len() == 1 {
-                    0
-                } else {
-                    1
-                }
-            } else {
-                let time_part: u64 = ids[0].parse().unwrap();
-                let time_match = streams
-                    .iter()
-                    .rev()
-                    .find(|stream| stream.id.0 == time_part)
-                    .map(|stream| stream.id.1);
-
-                if time_match.is_some() {
-                    println!("Match found: {:?}", time_match);
-                    time_match.unwrap() + 1
-                } else {
-                    if ids[0] == "0" {
-                        1
-                    } else {
-                        0
-                    }
-                }
-            }
-        };
+    #[tokio::test]
+    async fn auto_sequence_continues_from_the_top_entry() {
+        let db = Db::new();
 
-        println!("Next Sequence: {next_sequence_id}");
+        assert!(matches!(xadd(&db, "5-3").await, RESP::Bulk(id) if id == "5-3"));
+        assert!(matches!(xadd(&db, "5-*").await, RESP::Bulk(id) if id == "5-4"));
+        assert!(matches!(xadd(&db, "7-*").await, RESP::Bulk(id) if id == "7-0"));
 
-        let stream_id = self.get_stream_id(next_sequence_id);
+        // an older ms can't be appended to
+        assert!(matches!(xadd(&db, "5-*").await, RESP::Error(_)));
+    }
 
-        let next_stream_id = format!("{}-{}", stream_id.0, stream_id.1);
+    #[tokio::test]
+    async fn explicit_ids_must_grow() {
+        let db = Db::new();
+
+        assert!(
+            matches!(xadd(&db, "0-0").await, RESP::Error(err) if err.contains("greater than 0-0"))
+        );
+        assert!(matches!(xadd(&db, "5-5").await, RESP::Bulk(id) if id == "5-5"));
+
+        let smaller =
+            "ERR The ID specified in XADD is equal or smaller than the target stream top item";
+        assert!(matches!(xadd(&db, "5-5").await, RESP::Error(err) if err == smaller));
+        assert!(matches!(xadd(&db, "5-4").await, RESP::Error(err) if err == smaller));
+        assert!(matches!(xadd(&db, "4-9").await, RESP::Error(err) if err == smaller));
+        assert!(matches!(xadd(&db, "6").await, RESP::Bulk(id) if id == "6-0"));
+        assert!(matches!(xadd(&db, "6-x").await, RESP::Error(_)));
+
+        // the current time is past any id used above
+        assert!(matches!(xadd(&db, "*").await, RESP::Bulk(id) if !id.starts_with(b"6-")));
+    }
 
-        let new_stream = StreamData {
-            id: stream_id,
-            pairs: self.fields,
-            _created_at: Instant::now(),
-        };
+    #[tokio::test]
+    async fn nomkstream_does_not_create_the_stream() {
+        let db = Db::new();
 
-        // The ID should be greater than the ID of the last entry in the stream.
-        // The millisecondsTime part of the ID should be greater than or equal to the millisecondsTime of the last entry.
-        // If the millisecondsTime part of the ID is equal to the millisecondsTime of the last entry, the sequenceNumber part of the ID should be greater than the sequenceNumber of the last entry.
-        // If the stream is empty, the ID should be greater than 0-0
-        for stream in streams.iter() {
-            if stream.id >= new_stream.id {
-                println!("Prev Stream: {:?}, Stream: {:?}", stream.id, new_stream.id);
-                return Ok(Some(RESP::Error(
-                        "ERR The ID specified in XADD is equal or smaller than the target stream top item".to_string(),
-                    )));
-            }
+        let resp = parse(&["stream", "NOMKSTREAM", "1-1", "field", "value"])
+            .apply(&db)
+            .await
+            .unwrap();
+        assert!(matches!(resp, Some(RESP::Null)));
+        assert!(db.get("stream").is_none());
+
+        // an existing stream is appended to as usual
+        xadd(&db, "1-1").await;
+        let resp = parse(&["stream", "NOMKSTREAM", "1-2", "field", "value"])
+            .apply(&db)
+            .await
+            .unwrap();
+        assert!(matches!(resp, Some(RESP::Bulk(id)) if id == "1-2"));
+    }
+
+    #[tokio::test]
+    async fn maxlen_keeps_the_newest_entries() {
+        let db = Db::new();
+        for id in ["1-1", "1-2", "1-3"] {
+            xadd(&db, id).await;
         }
 
-        streams.push(new_stream);
+        parse(&["stream", "MAXLEN", "~", "2", "1-4", "field", "value"])
+            .apply(&db)
+            .await
+            .unwrap();
+        parse(&["stream", "MAXLEN", "2", "1-5", "field", "value"])
+            .apply(&db)
+            .await
+            .unwrap();
 
-        let value = ValueType::Stream(streams);
+        match db.get("stream") {
+            Some(ValueType::Stream(stream)) => {
+                let ids = stream
+                    .entries
+                    .iter()
+                    .map(|entry| entry.id)
+                    .collect::<Vec<_>>();
+                assert_eq!(ids, [(1, 4), (1, 5)]);
+            }
+            value => panic!("expected a stream, got {value:?}"),
+        }
+    }
 
-        db.set(self.key, value, None);
+    #[tokio::test]
+    async fn exhausted_sequences_are_rejected_or_carried_over() {
+        let db = Db::new();
+        let max = u64::MAX;
+
+        let top = format!("5-{max}");
+        assert!(matches!(xadd(&db, &top).await, RESP::Bulk(id) if id == top));
+        let smaller =
+            "ERR The ID specified in XADD is equal or smaller than the target stream top item";
+        assert!(matches!(xadd(&db, "5-*").await, RESP::Error(err) if err == smaller));
+
+        // the last possible id can't be followed by anything
+        let last = format!("{max}-{max}");
+        assert!(matches!(xadd(&db, &last).await, RESP::Bulk(id) if id == last));
+        for id in [format!("{max}-*"), "*".to_string()] {
+            assert!(matches!(xadd(&db, &id).await, RESP::Error(err) if err == smaller));
+        }
 
-        Ok(Some(RESP::Bulk(Bytes::from(next_stream_id))))
+        // `*` moves on to the next millisecond once one is used up
+        let db = Db::new();
+        let top = format!("{}-{max}", max - 1);
+        xadd(&db, &top).await;
+        assert!(matches!(xadd(&db, "*").await, RESP::Bulk(id) if id == format!("{max}-0")));
     }
-}
 
-impl From<XAdd> for RESP {
-    fn from(this: XAdd) -> Self {
-        let mut resp = RESP::array();
-        resp.push_bulk(Bytes::from("XADD"));
-        resp.push_bulk(Bytes::from(this.key));
-        resp.push_bulk(Bytes::from(this.stream_id.unwrap()));
-        for (key, value) in this.fields.into_iter() {
-            resp.push_bulk(Bytes::from(key));
-            resp.push_bulk(Bytes::from(value));
-        }
-        resp
+    #[tokio::test]
+    async fn non_stream_keys_are_not_overwritten() {
+        let db = Db::new();
+        db.set("stream".into(), ValueType::String(Bytes::from("v")), None);
+
+        assert!(matches!(
+            xadd(&db, "1-1").await,
+            RESP::Error(err) if err == CommandError::WrongType.to_string()
+        ));
+        assert!(matches!(db.get("stream"), Some(ValueType::String(v)) if v == "v"));
     }
 }
@@ -0,0 +1,165 @@
+use bytes::Bytes;
+
+use super::xrange::get_range_value;
+use crate::{resp::RESP, Db, RespReader, RespReaderError};
+
+#[derive(Debug, Default)]
+pub struct XDel {
+    /// stream key
+    key: String,
+
+    /// ids of the entries to delete
+    ids: Vec<(u64, u64)>,
+}
+
+impl XDel {
+    /// contruct new XDel command
+    pub fn new(key: String, ids: Vec<(u64, u64)>) -> Self {
+        XDel { key, ids }
+    }
+
+    /// Construct new XDel command by consuming the RespReader
+    ///
+    /// Ids are `ms-seq` with the sequence defaulting to 0 when omitted
+    pub fn from_parts(reader: &mut RespReader) -> Result<Self, RespReaderError> {
+        let key = reader.next_string()?;
+
+        let mut ids = vec![get_range_value(reader.next_string()?)?];
+        while let Ok(id) = reader.next_string() {
+            ids.push(get_range_value(id)?);
+        }
+
+        Ok(XDel { key, ids })
+    }
+
+    /// Apply the xdel command and return the number of deleted entries,
+    /// ids that aren't in the stream aren't counted
+    pub async fn apply(self, db: &Db) -> crate::Result<Option<RESP>> {
+        let deleted = db.xdel(&self.key, &self.ids)?;
+
+        Ok(Some(RESP::Integer(deleted as i64)))
+    }
+}
+
+/// Convert XDel command back into an equivalent `RESP`
+impl From<XDel> for RESP {
+    fn from(value: XDel) -> Self {
+        let mut resp = RESP::array();
+        resp.push_bulk(Bytes::from("XDEL"));
+        resp.push_bulk(Bytes::from(value.key));
+        for (ms, seq) in value.ids {
+            resp.push_bulk(Bytes::from(format!("{ms}-{seq}")));
+        }
+        resp
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use bytes::Bytes;
+
+    use super::XDel;
+    use crate::{
+        command::stream::{XAdd, XRange},
+        resp::RESP,
+        CommandError, Db, RespReader, RespReaderError,
+    };
+
+    async fn xadd(db: &Db, id: &str) {
+        let xadd = XAdd {
+            key: "stream".into(),
+            id: None,
+            stream_id: Some(id.into()),
+            fields: HashMap::from([("field".to_string(), id.to_string())]),
+            ..XAdd::default()
+        };
+        xadd.apply(db).await.unwrap();
+    }
+
+    async fn ids(db: &Db) -> Vec<String> {
+        let xrange = XRange {
+            key: "stream".into(),
+            start: (0, 0),
+            end: (u64::MAX, u64::MAX),
+            mode: None,
+        };
+        match xrange.apply(db).await.unwrap() {
+            Some(RESP::Array(entries)) => entries
+                .into_iter()
+                .map(|entry| match entry {
+                    RESP::Array(mut entry) => match entry.remove(0) {
+                        RESP::Bulk(id) => String::from_utf8(id.to_vec()).unwrap(),
+                        id => panic!("unexpected id {id:?}"),
+                    },
+                    entry => panic!("unexpected entry {entry:?}"),
+                })
+                .collect(),
+            resp => panic!("expected an array, got {resp:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn deletes_entries_by_id() {
+        let db = Db::new();
+        for id in ["1-1", "1-2", "2-0"] {
+            xadd(&db, id).await;
+        }
+
+        let resp = XDel::new("stream".into(), vec![(1, 2), (5, 0)])
+            .apply(&db)
+            .await
+            .unwrap();
+        // missing ids aren't counted
+        assert!(matches!(resp, Some(RESP::Integer(1))));
+        assert_eq!(ids(&db).await, ["1-1", "2-0"]);
+
+        let resp = XDel::new("missing".into(), vec![(1, 1)])
+            .apply(&db)
+            .await
+            .unwrap();
+        assert!(matches!(resp, Some(RESP::Integer(0))));
+    }
+
+    #[tokio::test]
+    async fn deleted_ids_are_not_handed_out_again() {
+        let db = Db::new();
+        for id in ["1-1", "2-0"] {
+            xadd(&db, id).await;
+        }
+        XDel::new("stream".into(), vec![(2, 0)])
+            .apply(&db)
+            .await
+            .unwrap();
+
+        let readd = XAdd {
+            key: "stream".into(),
+            stream_id: Some("2-0".into()),
+            fields: HashMap::from([("field".to_string(), "again".to_string())]),
+            ..XAdd::default()
+        };
+        let err = readd.apply(&db).await.unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<CommandError>(),
+            Some(&CommandError::StreamIdTooSmall)
+        );
+
+        // the sequence carries on from the deleted top id
+        xadd(&db, "2-*").await;
+        assert_eq!(ids(&db).await, ["1-1", "2-1"]);
+    }
+
+    #[test]
+    fn malformed_ids_are_command_errors() {
+        let args = ["stream", "1-1", "notanid"]
+            .iter()
+            .map(|arg| RESP::Bulk(Bytes::from(arg.to_string())))
+            .collect();
+        let mut reader = RespReader::new(RESP::Array(args)).unwrap();
+        assert!(matches!(
+            XDel::from_parts(&mut reader),
+            Err(RespReaderError::Command(CommandError::StreamId))
+        ));
+    }
+}
@@ -1,4 +1,4 @@
-use crate::{resp::RESP, Db, RespReader, RespReaderError, ValueType};
+use crate::{resp::RESP, CommandError, Db, RespReader, RespReaderError, ValueType};
 use bytes::Bytes;
 
 // const MAX_TIMESTAMP: u64 = 32536799999000; // '2038-01-19 03:14:07' UTC.
@@ -11,17 +11,13 @@ pub struct XRange {
     pub mode: Option<String>,
 }
 
-fn get_range_value(string: String) -> (u64, u64) {
-    let ids = string
-        .split('-')
-        .map(|char| char.to_string())
-        .collect::<Vec<String>>();
-
-    let millisec = ids.get(0).map(|t| t.parse().unwrap()).unwrap();
-
-    let sequence_id = ids.get(1).map(|t| t.parse().unwrap()).or(Some(0)).unwrap();
-
-    (millisec, sequence_id)
+/// Parse an entry id, `ms-seq` with the sequence defaulting to 0 when omitted
+pub fn get_range_value(string: String) -> Result<(u64, u64), RespReaderError> {
+    let (ms, seq) = string.split_once('-').unwrap_or((&string, "0"));
+    match (ms.parse(), seq.parse()) {
+        (Ok(ms), Ok(seq)) => Ok((ms, seq)),
+        _ => Err(CommandError::StreamId.into()),
+    }
 }
 
 impl XRange {
@@ -42,18 +38,21 @@ impl XRange {
 
         let (start, end, mode) = match reader.next_string() {
             Ok(range_or_mode) if range_or_mode == "-" => {
-                let end = get_range_value(reader.next_string()?);
+                let end = match reader.next_string()? {
+                    end if end == "+" => (u64::MAX, u64::MAX),
+                    end => get_range_value(end)?,
+                };
                 ((0, 0), end, Some(range_or_mode))
             }
             Ok(start) => {
-                let start = get_range_value(start);
+                let start = get_range_value(start)?;
                 let value = reader.next_string()?;
                 let mut end: (u64, u64) = (0, 0);
                 let mut mode = None;
                 if value == "+" {
                     mode = Some(value);
                 } else {
-                    end = get_range_value(value);
+                    end = get_range_value(value)?;
                 }
                 (start, end, mode)
             }
@@ -75,7 +74,7 @@ impl XRange {
 
         let streams = if let Some(prev_stream) = streams {
             match prev_stream {
-                ValueType::Stream(stream) => stream,
+                ValueType::Stream(stream) => stream.entries,
                 _ => vec![],
             }
         } else {
@@ -164,3 +163,55 @@ impl From<XRange> for RESP {
         resp
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use bytes::Bytes;
+
+    use super::XRange;
+    use crate::{command::stream::XAdd, resp::RESP, CommandError, Db, RespReader, RespReaderError};
+
+    fn parse(args: &[&str]) -> Result<XRange, RespReaderError> {
+        let parts = args
+            .iter()
+            .map(|arg| RESP::Bulk(Bytes::from(arg.to_string())));
+        XRange::from_parts(&mut RespReader::new(RESP::Array(parts.collect())).unwrap())
+    }
+
+    #[tokio::test]
+    async fn minus_to_plus_is_the_whole_stream() {
+        let db = Db::new();
+        for id in ["1-1", "2-0"] {
+            let xadd = XAdd {
+                key: "stream".into(),
+                stream_id: Some(id.into()),
+                fields: HashMap::from([("f".to_string(), "v".to_string())]),
+                ..XAdd::default()
+            };
+            xadd.apply(&db).await.unwrap();
+        }
+
+        let resp = parse(&["stream", "-", "+"])
+            .unwrap()
+            .apply(&db)
+            .await
+            .unwrap();
+        assert!(matches!(resp, Some(RESP::Array(entries)) if entries.len() == 2));
+    }
+
+    #[test]
+    fn malformed_ids_are_command_errors() {
+        for args in [
+            ["stream", "1-x", "+"],
+            ["stream", "-", "nope"],
+            ["stream", "1", "2-3-4"],
+        ] {
+            assert!(matches!(
+                parse(&args),
+                Err(RespReaderError::Command(CommandError::StreamId))
+            ));
+        }
+    }
+}
@@ -1,33 +1,45 @@
-use std::time::Duration;
+use std::{fmt, future, time::Duration};
 
-use crate::{resp::RESP, Db, RespReader, RespReaderError, ValueType};
+use crate::{is_keyword, resp::RESP, CommandError, Db, RespReader, RespReaderError, ValueType};
 use bytes::Bytes;
+use futures::future::select_all;
+
+use super::xrange::get_range_value;
 use tokio::time::Instant;
 
 #[derive(Debug, Default)]
 pub struct XRead {
     pub streams: Vec<StreamFilter>,
     pub block: Option<u64>, // pub stream_ids: Vec<(u64, u64)>,
+    /// max number of entries returned per stream
+    pub count: Option<u64>,
 }
 
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Clone)]
 pub struct StreamFilter {
     key: String,
-    id: (u64, u64),
-    created_at_filter: Option<Instant>,
+    start: StartId,
 }
 
-fn get_range_value(string: String) -> (u64, u64) {
-    let ids = string
-        .split('-')
-        .map(|char| char.to_string())
-        .collect::<Vec<String>>();
-
-    let millisec = ids.get(0).map(|t| t.parse().unwrap()).unwrap();
-
-    let sequence_id = ids.get(1).map(|t| t.parse().unwrap()).or(Some(0)).unwrap();
+/// Where reading a stream starts from
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum StartId {
+    /// Entries with a greater id
+    After((u64, u64)),
+    /// `$`, entries added after XREAD was issued
+    New,
+    /// `+`, the last entry of the stream
+    Last,
+}
 
-    (millisec, sequence_id)
+impl fmt::Display for StartId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StartId::After((ms, seq)) => write!(f, "{ms}-{seq}"),
+            StartId::New => write!(f, "$"),
+            StartId::Last => write!(f, "+"),
+        }
+    }
 }
 
 impl XRead {
@@ -43,63 +55,88 @@ impl XRead {
     /// Parse next_string()? to get the pair key
     /// Parse next_string()? to get the pair value
     pub fn from_parts(reader: &mut RespReader) -> Result<Self, RespReaderError> {
-        let mut streams = vec![];
-        let mut keys = vec![];
-        let mut ids = vec![];
-
         let mut block = None;
+        let mut count = None;
 
-        while let Ok(next) = reader.next_string() {
-            match next.to_lowercase().as_str() {
-                "block" => {
+        loop {
+            let next = reader.next_string()?;
+            match next.as_str() {
+                next if is_keyword(next, "block") => {
                     block = Some(reader.next_int()?);
                 }
-                "streams" => continue,
-                "count" => unimplemented!("COUNT option not implement for XREAD ❌"),
-                "$" => ids.push("$".to_string()),
-                next => {
-                    let parts = next
-                        .split('-')
-                        .map(|char| char.to_string())
-                        .collect::<Vec<String>>();
-                    if parts.len() == 2 {
-                        ids.push(next.to_owned());
-                    } else {
-                        keys.push(next.to_owned());
-                    }
+                next if is_keyword(next, "count") => {
+                    count = Some(reader.next_int()?);
                 }
+                next if is_keyword(next, "streams") => break,
+                _ => return Err(CommandError::SyntaxError.into()),
             }
         }
 
-        for (idx, key) in keys.iter().enumerate() {
-            let id = ids
-                .get(idx)
-                .map(|p| p.to_owned())
-                .unwrap_or("0-0".to_string());
-
-            if id == "$" {
-                streams.push(StreamFilter {
-                    key: key.to_owned(),
-                    id: (0, 0),
-                    created_at_filter: Some(Instant::now()),
-                })
-            } else {
-                streams.push(StreamFilter {
-                    key: key.to_owned(),
-                    id: get_range_value(id),
-                    created_at_filter: None,
-                })
-            }
+        // every key is followed by the id it's read from, in the same order
+        let mut args = vec![];
+        while let Ok(next) = reader.next_string() {
+            args.push(next);
+        }
+        if args.is_empty() || !args.len().is_multiple_of(2) {
+            return Err(CommandError::UnbalancedStreams.into());
         }
+        let ids = args.split_off(args.len() / 2);
+
+        let streams = args
+            .into_iter()
+            .zip(ids)
+            .map(|(key, id)| {
+                let start = match id.as_str() {
+                    "$" => StartId::New,
+                    "+" => StartId::Last,
+                    _ => StartId::After(get_range_value(id)?),
+                };
+                Ok(StreamFilter { key, start })
+            })
+            .collect::<Result<_, RespReaderError>>()?;
 
-        Ok(XRead { streams, block })
+        Ok(XRead {
+            streams,
+            block,
+            count,
+        })
     }
 
-    async fn run_command(&self, db: &Db) -> Vec<RESP> {
+    /// Resolve every stream to the id entries are read after, `$` and
+    /// `+` refer to the last id of the stream when XREAD is issued
+    fn resolve(&self, db: &Db) -> Vec<(String, (u64, u64))> {
         self.streams
             .iter()
-            .filter_map(|stream| {
-                let streams = db.get(&stream.key);
+            .map(|stream| {
+                // `$` is the last id ever added, `+` the last entry left
+                let (last_id, last_entry) = match db.get(&stream.key) {
+                    Some(ValueType::Stream(stream)) => (
+                        Some(stream.last_id),
+                        stream.entries.last().map(|entry| entry.id),
+                    ),
+                    _ => (None, None),
+                };
+
+                let id = match (stream.start, last_id, last_entry) {
+                    (StartId::After(id), _, _) => id,
+                    (StartId::New, Some(last_id), _) => last_id,
+                    // read after the id preceding the last one so it's included
+                    (StartId::Last, _, Some((ms, 0))) => (ms - 1, u64::MAX),
+                    (StartId::Last, _, Some((ms, seq))) => (ms, seq - 1),
+                    // every id is greater than 0-0
+                    (StartId::New, None, _) | (StartId::Last, _, None) => (0, 0),
+                };
+
+                (stream.key.clone(), id)
+            })
+            .collect()
+    }
+
+    async fn run_command(&self, db: &Db, streams: &[(String, (u64, u64))]) -> Vec<RESP> {
+        streams
+            .iter()
+            .filter_map(|(key, id)| {
+                let streams = db.get(key);
                 let streams = if let Some(prev_stream) = streams {
                     match prev_stream {
                         ValueType::Stream(stream) => Some(stream),
@@ -117,20 +154,15 @@ impl XRead {
                     let mut stream_resp = RESP::array();
 
                     let results: Vec<RESP> = streams
+                        .entries
                         .iter()
-                        .filter_map(|entry| {
-                            if stream.created_at_filter.is_some() {
-                                if stream.created_at_filter.unwrap() < entry._created_at {
-                                    Some(entry.into())
-                                } else {
-                                    None
-                                }
-                            } else if entry.id > stream.id {
-                                Some(entry.into())
-                            } else {
-                                None
-                            }
+                        .filter(|entry| entry.id > *id)
+                        // a COUNT of 0 is the same as no COUNT
+                        .take(match self.count {
+                            Some(count) if count > 0 => count as usize,
+                            _ => usize::MAX,
                         })
+                        .map(|entry| entry.into())
                         .collect();
 
                     if results.len() == 0 {
@@ -143,7 +175,7 @@ impl XRead {
                         field_resp.push(result);
                     }
 
-                    stream_resp.push_bulk(Bytes::from(stream.key.to_owned()));
+                    stream_resp.push_bulk(Bytes::from(key.to_owned()));
                     stream_resp.push(field_resp);
 
                     Some(stream_resp)
@@ -152,24 +184,61 @@ impl XRead {
             .collect()
     }
 
+    /// Read the streams, waiting for an XADD to one of them while there's
+    /// nothing to return until `block` milliseconds have passed, 0 waits
+    /// without a timeout
+    async fn blocking_read(
+        &self,
+        db: &Db,
+        streams: &[(String, (u64, u64))],
+        block: u64,
+    ) -> Vec<RESP> {
+        let deadline = (block > 0).then(|| Instant::now() + Duration::from_millis(block));
+
+        let xreads = loop {
+            let notifiers = streams
+                .iter()
+                .map(|(key, _)| db.key_notifier(key))
+                .collect::<Vec<_>>();
+            // registered before reading so an XADD in between isn't missed
+            let written = notifiers
+                .iter()
+                .map(|notify| Box::pin(notify.notified()))
+                .collect::<Vec<_>>();
+
+            let xreads = self.run_command(db, streams).await;
+            if !xreads.is_empty() {
+                break xreads;
+            }
+
+            let timed_out = async {
+                match deadline {
+                    Some(deadline) => tokio::time::sleep_until(deadline).await,
+                    None => future::pending().await,
+                }
+            };
+
+            tokio::select! {
+                _ = select_all(written) => continue,
+                _ = timed_out => break xreads,
+            }
+        };
+
+        for (key, _) in streams {
+            db.release_key_notifier(key);
+        }
+
+        xreads
+    }
+
     /// Apply the stream command and write to the Tcp connection stream
     pub async fn apply(self, db: &Db) -> crate::Result<Option<RESP>> {
         let mut resp = RESP::Null;
 
+        let streams = self.resolve(db);
         let xreads = match self.block {
-            Some(0) => {
-                let mut streams = self.run_command(db).await;
-                while streams.len() == 0 {
-                    tokio::time::sleep(Duration::from_millis(100)).await;
-                    streams = self.run_command(db).await;
-                }
-                streams
-            }
-            Some(timeout) => {
-                tokio::time::sleep(Duration::from_millis(timeout)).await;
-                self.run_command(db).await
-            }
-            None => self.run_command(db).await,
+            Some(block) => self.blocking_read(db, &streams, block).await,
+            None => self.run_command(db, &streams).await,
         };
 
         if xreads.len() > 0 {
@@ -188,15 +257,192 @@ impl From<XRead> for RESP {
     fn from(this: XRead) -> Self {
         let mut resp = RESP::array();
         resp.push_bulk(Bytes::from("XREAD"));
+        if let Some(count) = this.count {
+            resp.push_bulk(Bytes::from("COUNT"));
+            resp.push_bulk(Bytes::from(count.to_string()));
+        }
 
         resp.push_bulk(Bytes::from("streams"));
         for stream in this.streams.iter() {
             resp.push_bulk(Bytes::from(stream.key.to_owned()));
         }
         for stream in this.streams.iter() {
-            resp.push_bulk(Bytes::from(format!("{}-{}", stream.id.0, stream.id.1)));
+            resp.push_bulk(Bytes::from(stream.start.to_string()));
         }
 
         resp
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use bytes::Bytes;
+    use tokio::time::{Duration, Instant};
+
+    use super::XRead;
+    use crate::{
+        command::stream::XAdd, resp::RESP, CommandError, Db, RespReader, RespReaderError,
+        StreamData, ValueType,
+    };
+
+    fn parse(args: &[&str]) -> Result<XRead, RespReaderError> {
+        let parts = args
+            .iter()
+            .map(|arg| RESP::Bulk(Bytes::from(arg.to_string())));
+        XRead::from_parts(&mut RespReader::new(RESP::Array(parts.collect())).unwrap())
+    }
+
+    fn xread(args: &[&str]) -> XRead {
+        parse(args).unwrap()
+    }
+
+    fn entry(id: (u64, u64), created_at: Instant) -> StreamData {
+        StreamData {
+            id,
+            pairs: HashMap::from([("f".to_string(), "v".to_string())]),
+            _created_at: created_at,
+        }
+    }
+
+    /// Ids of the entries read from the single stream in a reply
+    fn ids(resp: Option<RESP>) -> Vec<String> {
+        let streams = match resp {
+            Some(RESP::Array(streams)) => streams,
+            resp => panic!("expected streams, got {resp:?}"),
+        };
+        match &streams[..] {
+            [RESP::Array(stream)] => match &stream[1] {
+                RESP::Array(entries) => entries
+                    .iter()
+                    .map(|entry| match entry {
+                        RESP::Array(entry) => match &entry[0] {
+                            RESP::Bulk(id) => String::from_utf8(id.to_vec()).unwrap(),
+                            id => panic!("expected an id, got {id:?}"),
+                        },
+                        entry => panic!("expected an entry, got {entry:?}"),
+                    })
+                    .collect(),
+                entries => panic!("expected entries, got {entries:?}"),
+            },
+            streams => panic!("expected one stream, got {streams:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn dollar_reads_entries_added_after_xread_is_issued() {
+        let db = Db::new();
+        let created_at = Instant::now();
+        db.set(
+            "s".into(),
+            ValueType::Stream(vec![entry((1, 1), created_at)].into()),
+            None,
+        );
+
+        let read = tokio::spawn({
+            let db = db.clone();
+            async move { xread(&["BLOCK", "0", "STREAMS", "s", "$"]).apply(&db).await }
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+
+        // the new entry claims to be as old as the first one, only its id
+        // tells it was added after XREAD
+        db.set(
+            "s".into(),
+            ValueType::Stream(vec![entry((1, 1), created_at), entry((2, 0), created_at)].into()),
+            None,
+        );
+
+        let resp = tokio::time::timeout(Duration::from_secs(2), read)
+            .await
+            .expect("XREAD didn't wake up")
+            .unwrap()
+            .unwrap();
+        assert_eq!(ids(resp), ["2-0"]);
+    }
+
+    #[tokio::test]
+    async fn plus_reads_the_last_entry() {
+        let db = Db::new();
+        db.set(
+            "s".into(),
+            ValueType::Stream(
+                vec![entry((1, 1), Instant::now()), entry((2, 0), Instant::now())].into(),
+            ),
+            None,
+        );
+
+        let resp = xread(&["STREAMS", "s", "+"]).apply(&db).await.unwrap();
+        assert_eq!(ids(resp), ["2-0"]);
+
+        let resp = xread(&["BLOCK", "10", "STREAMS", "missing", "+"])
+            .apply(&db)
+            .await
+            .unwrap();
+        assert!(matches!(resp, Some(RESP::Null)));
+    }
+
+    #[tokio::test]
+    async fn count_caps_the_entries_of_each_stream() {
+        let db = Db::new();
+        let entries: Vec<_> = (1..=5).map(|seq| entry((1, seq), Instant::now())).collect();
+        db.set("k".into(), ValueType::Stream(entries.into()), None);
+
+        let resp = xread(&["COUNT", "2", "STREAMS", "k", "0"])
+            .apply(&db)
+            .await
+            .unwrap();
+        assert_eq!(ids(resp), ["1-1", "1-2"]);
+
+        let resp = xread(&["COUNT", "2", "STREAMS", "k", "1-4"])
+            .apply(&db)
+            .await
+            .unwrap();
+        assert_eq!(ids(resp), ["1-5"]);
+    }
+
+    #[tokio::test]
+    async fn xadd_wakes_a_blocked_xread_right_away() {
+        let db = Db::new();
+
+        let read = tokio::spawn({
+            let db = db.clone();
+            async move { xread(&["BLOCK", "0", "STREAMS", "s", "$"]).apply(&db).await }
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+
+        let added = Instant::now();
+        let xadd = XAdd {
+            key: "s".into(),
+            stream_id: Some("1-1".into()),
+            fields: HashMap::from([("f".to_string(), "v".to_string())]),
+            ..XAdd::default()
+        };
+        xadd.apply(&db).await.unwrap();
+
+        let resp = tokio::time::timeout(Duration::from_secs(2), read)
+            .await
+            .expect("XREAD didn't wake up")
+            .unwrap()
+            .unwrap();
+        assert_eq!(ids(resp), ["1-1"]);
+        assert!(added.elapsed() < Duration::from_millis(50));
+    }
+
+    #[test]
+    fn malformed_arguments_are_command_errors() {
+        assert!(matches!(
+            parse(&["FOO", "1", "STREAMS", "s", "0"]),
+            Err(RespReaderError::Command(CommandError::SyntaxError))
+        ));
+        assert!(matches!(
+            parse(&["STREAMS", "s1", "s2", "0"]),
+            Err(RespReaderError::Command(CommandError::UnbalancedStreams))
+        ));
+        assert!(matches!(
+            parse(&["STREAMS", "s", "1-x"]),
+            Err(RespReaderError::Command(CommandError::StreamId))
+        ));
+    }
+}
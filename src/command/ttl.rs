@@ -0,0 +1,143 @@
+use bytes::Bytes;
+use tokio::time::Instant;
+
+use crate::{resp::RESP, Db, RespReader, RespReaderError};
+
+#[derive(Debug, Default)]
+pub struct Ttl {
+    /// cache lookup key
+    key: String,
+}
+
+#[derive(Debug, Default)]
+pub struct Pttl {
+    /// cache lookup key
+    key: String,
+}
+
+/// Remaining time to live of a key in milliseconds, -2 if the key
+/// doesn't exist and -1 if it has no expiry
+fn remaining_millis(db: &Db, key: &str) -> i64 {
+    match db.lookup(key) {
+        Some(value) => match value.expires_at {
+            Some(expires_at) => {
+                let remaining = expires_at.saturating_duration_since(Instant::now());
+                remaining.as_millis() as i64
+            }
+            None => -1,
+        },
+        None => -2,
+    }
+}
+
+/// Round milliseconds left to the nearest second the way redis 7 does,
+/// so a key with 1500ms left has a TTL of 2 and one with 1499ms a TTL of 1
+pub fn round_to_seconds(millis: i64) -> i64 {
+    (millis + 500) / 1000
+}
+
+impl Ttl {
+    /// contruct new Ttl command
+    pub fn new(key: String) -> Self {
+        Ttl { key }
+    }
+
+    /// Construct new Ttl command by consuming the RespReader
+    pub fn from_parts(reader: &mut RespReader) -> Result<Self, RespReaderError> {
+        let key = reader.next_string()?;
+
+        Ok(Ttl { key })
+    }
+
+    /// Apply the ttl command, returns the seconds left rounded
+    /// to the nearest second
+    pub async fn apply(self, db: &Db) -> crate::Result<Option<RESP>> {
+        let ttl = match remaining_millis(db, &self.key) {
+            millis if millis < 0 => millis,
+            millis => round_to_seconds(millis),
+        };
+
+        Ok(Some(RESP::Integer(ttl)))
+    }
+}
+
+impl Pttl {
+    /// contruct new Pttl command
+    pub fn new(key: String) -> Self {
+        Pttl { key }
+    }
+
+    /// Construct new Pttl command by consuming the RespReader
+    pub fn from_parts(reader: &mut RespReader) -> Result<Self, RespReaderError> {
+        let key = reader.next_string()?;
+
+        Ok(Pttl { key })
+    }
+
+    /// Apply the pttl command, returns the exact milliseconds left
+    pub async fn apply(self, db: &Db) -> crate::Result<Option<RESP>> {
+        Ok(Some(RESP::Integer(remaining_millis(db, &self.key))))
+    }
+}
+
+/// Convert Ttl command back into an equivalent `RESP`
+impl From<Ttl> for RESP {
+    fn from(value: Ttl) -> Self {
+        let mut resp = RESP::array();
+        resp.push_bulk(Bytes::from("ttl"));
+        resp.push_bulk(Bytes::from(value.key));
+        resp
+    }
+}
+
+/// Convert Pttl command back into an equivalent `RESP`
+impl From<Pttl> for RESP {
+    fn from(value: Pttl) -> Self {
+        let mut resp = RESP::array();
+        resp.push_bulk(Bytes::from("pttl"));
+        resp.push_bulk(Bytes::from(value.key));
+        resp
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bytes::Bytes;
+    use tokio::time::Duration;
+
+    use super::{round_to_seconds, Pttl, Ttl};
+    use crate::{resp::RESP, Db, ValueType};
+
+    #[test]
+    fn ttl_rounds_to_the_nearest_second() {
+        assert_eq!(round_to_seconds(1500), 2);
+        assert_eq!(round_to_seconds(1499), 1);
+        assert_eq!(round_to_seconds(500), 1);
+        assert_eq!(round_to_seconds(499), 0);
+        assert_eq!(round_to_seconds(0), 0);
+    }
+
+    #[tokio::test]
+    async fn ttl_and_pttl_of_a_key() {
+        let db = Db::new();
+        db.set(
+            "key".into(),
+            ValueType::String(Bytes::from("1")),
+            Some(Duration::from_millis(1500)),
+        );
+        db.set("forever".into(), ValueType::String(Bytes::from("1")), None);
+
+        let resp = Pttl::new("key".into()).apply(&db).await.unwrap();
+        assert!(matches!(resp, Some(RESP::Integer(millis)) if (1400..=1500).contains(&millis)));
+
+        // anything past 1499ms is rounded down
+        let resp = Ttl::new("key".into()).apply(&db).await.unwrap();
+        assert!(matches!(resp, Some(RESP::Integer(1 | 2))));
+
+        let resp = Ttl::new("forever".into()).apply(&db).await.unwrap();
+        assert!(matches!(resp, Some(RESP::Integer(-1))));
+
+        let resp = Pttl::new("missing".into()).apply(&db).await.unwrap();
+        assert!(matches!(resp, Some(RESP::Integer(-2))));
+    }
+}
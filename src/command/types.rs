@@ -1,6 +1,6 @@
 use bytes::Bytes;
 
-use crate::{connection::Connection, resp::RESP, Db, RespReader, RespReaderError, ValueType};
+use crate::{connection::Connection, resp::RESP, Db, RespReader, RespReaderError};
 
 #[derive(Debug, Default)]
 pub struct Type {
@@ -26,20 +26,14 @@ impl Type {
         Ok(Type { key })
     }
 
-    /// Apply the echo command and write to the Tcp connection stream
+    /// Apply the type command and return the name of the value's type
     pub async fn apply(self, db: &Db, _dst: &mut Connection) -> crate::Result<Option<RESP>> {
-        // set the value in the shared cache.
-        let value = db.get(&self.key);
-
-        if let Some(value_type) = value {
-            println!("Value type: {:?}", value_type);
-            match value_type {
-                ValueType::String(_) => Ok(Some(RESP::Simple("string".to_string()))),
-                ValueType::Stream(_) => Ok(Some(RESP::Simple("stream".to_string()))),
-            }
-        } else {
-            Ok(Some(RESP::Simple("none".to_string())))
-        }
+        let name = match db.get(&self.key) {
+            Some(value) => value.type_name(),
+            None => "none",
+        };
+
+        Ok(Some(RESP::Simple(name.to_string())))
     }
 }
 
@@ -1,14 +1,33 @@
-use crate::{connection::Connection, resp::RESP};
+use crate::{connection::Connection, resp::RESP, RespReader, RespReaderError};
 
 #[derive(Debug, Default)]
 pub struct Unknown {
     command_name: String,
+
+    /// arguments the command was sent with, echoed back in the error
+    args: Vec<String>,
 }
 
 impl Unknown {
     /// contruct new Unknown command
     pub fn new(command_name: String) -> Self {
-        Unknown { command_name }
+        Unknown {
+            command_name,
+            args: vec![],
+        }
+    }
+
+    /// Construct new Unknown command by consuming the RespReader
+    pub fn from_parts(
+        command_name: String,
+        reader: &mut RespReader,
+    ) -> Result<Self, RespReaderError> {
+        let mut args = vec![];
+        while let Ok(arg) = reader.next_string() {
+            args.push(arg);
+        }
+
+        Ok(Unknown { command_name, args })
     }
 
     /// Returns command name
@@ -16,10 +35,18 @@ impl Unknown {
         &self.command_name
     }
 
-    /// Apply the echo command and write to the Tcp connection stream
+    /// Apply the unknown command, replies with an error naming the command
     pub async fn apply(self, _dst: &mut Connection) -> crate::Result<Option<RESP>> {
-        #[allow(unused_assignments)]
-        let resp = RESP::Error(format!("Err unknown command: {}", self.command_name));
+        let args = self
+            .args
+            .iter()
+            .map(|arg| format!("'{arg}' "))
+            .collect::<String>();
+
+        let resp = RESP::Error(format!(
+            "ERR unknown command '{}', with args beginning with: {}",
+            self.command_name, args
+        ));
 
         Ok(Some(resp))
     }
@@ -0,0 +1,75 @@
+use std::ops::Bound;
+
+use crate::CommandError;
+
+pub mod zadd;
+pub mod zmpop;
+pub mod zpop;
+pub mod zrange;
+pub mod zrangebylex;
+pub mod zrank;
+pub mod zscore;
+
+pub use zadd::ZAdd;
+pub use zmpop::ZMPop;
+pub use zpop::{BZMPop, BZPopMax, BZPopMin, ZPopMax, ZPopMin};
+pub use zrange::{ZRange, ZRangeByScore};
+pub use zrangebylex::ZRangeByLex;
+pub use zrank::ZRank;
+pub use zscore::ZScore;
+
+/// Format a score the way redis replies with it, `1`, `1.5`, `inf`
+pub fn format_score(score: f64) -> String {
+    score.to_string()
+}
+
+/// Parse a score range bound, `5` is inclusive, `(5` is exclusive and
+/// `-inf` and `+inf` are the ends of the range
+pub fn parse_score_bound(arg: &str) -> Result<Bound<f64>, CommandError> {
+    let (score, exclusive) = match arg.strip_prefix('(') {
+        Some(score) => (score, true),
+        None => (arg, false),
+    };
+
+    match score.parse::<f64>() {
+        Ok(score) if score.is_nan() => Err(CommandError::ScoreRange),
+        Ok(score) if exclusive => Ok(Bound::Excluded(score)),
+        Ok(score) => Ok(Bound::Included(score)),
+        Err(_) => Err(CommandError::ScoreRange),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::ops::Bound;
+
+    use super::parse_score_bound;
+    use crate::CommandError;
+
+    #[test]
+    fn score_bounds() {
+        assert_eq!(parse_score_bound("5"), Ok(Bound::Included(5.0)));
+        assert_eq!(parse_score_bound("(5"), Ok(Bound::Excluded(5.0)));
+        assert_eq!(parse_score_bound("-1.5"), Ok(Bound::Included(-1.5)));
+        assert_eq!(
+            parse_score_bound("-inf"),
+            Ok(Bound::Included(f64::NEG_INFINITY))
+        );
+        assert_eq!(
+            parse_score_bound("+inf"),
+            Ok(Bound::Included(f64::INFINITY))
+        );
+        assert_eq!(
+            parse_score_bound("(+inf"),
+            Ok(Bound::Excluded(f64::INFINITY))
+        );
+
+        for arg in ["", "(", "five", "[5", "nan", "((5"] {
+            assert_eq!(
+                parse_score_bound(arg),
+                Err(CommandError::ScoreRange),
+                "{arg}"
+            );
+        }
+    }
+}
@@ -0,0 +1,159 @@
+use bytes::Bytes;
+
+use super::format_score;
+use crate::{resp::RESP, CommandError, Db, RespReader, RespReaderError};
+
+#[derive(Debug, Default)]
+pub struct ZAdd {
+    /// cache lookup key
+    key: String,
+
+    /// members to add along with their scores
+    members: Vec<(f64, String)>,
+}
+
+impl ZAdd {
+    /// contruct new ZAdd command
+    pub fn new(key: String, members: Vec<(f64, String)>) -> Self {
+        ZAdd { key, members }
+    }
+
+    /// Construct new ZAdd command by consuming the RespReader
+    ///
+    /// Arguments are `key score member [score member ...]`
+    pub fn from_parts(reader: &mut RespReader) -> Result<Self, RespReaderError> {
+        let key = reader.next_string()?;
+
+        let mut members = vec![];
+        loop {
+            let score = match reader.next_string() {
+                Ok(score) => score,
+                Err(RespReaderError::EndOfStream) if !members.is_empty() => break,
+                Err(err) => return Err(err),
+            };
+            let member = reader.next_string()?;
+            members.push((parse_score(&score)?, member));
+        }
+
+        Ok(ZAdd { key, members })
+    }
+
+    /// Apply the zadd command and return the number of new members,
+    /// the scores of existing members are updated
+    pub async fn apply(self, db: &Db) -> crate::Result<Option<RESP>> {
+        let added = db.zadd(self.key, self.members)?;
+
+        Ok(Some(RESP::Integer(added as i64)))
+    }
+}
+
+/// Parse a score, `inf`, `+inf` and `-inf` are valid but `nan` isn't
+fn parse_score(score: &str) -> Result<f64, RespReaderError> {
+    match score.parse::<f64>() {
+        Ok(score) if !score.is_nan() => Ok(score),
+        _ => Err(CommandError::NotFloat.into()),
+    }
+}
+
+/// Convert ZAdd command back into an equivalent `RESP`
+impl From<ZAdd> for RESP {
+    fn from(value: ZAdd) -> Self {
+        let mut resp = RESP::array();
+        resp.push_bulk(Bytes::from("zadd"));
+        resp.push_bulk(Bytes::from(value.key));
+        for (score, member) in value.members {
+            resp.push_bulk(Bytes::from(format_score(score)));
+            resp.push_bulk(Bytes::from(member));
+        }
+        resp
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bytes::Bytes;
+
+    use super::ZAdd;
+    use crate::{
+        resp::RESP, zset::ZRank, CommandError, Db, RespReader, RespReaderError, ValueType,
+    };
+
+    fn reader(args: &[&str]) -> RespReader {
+        let args = args
+            .iter()
+            .map(|arg| RESP::Bulk(Bytes::from(arg.to_string())))
+            .collect();
+        RespReader::new(RESP::Array(args)).unwrap()
+    }
+
+    async fn rank(db: &Db, member: &str) -> Option<i64> {
+        match ZRank::new("zset".into(), member.into()).apply(db).await {
+            Ok(Some(RESP::Integer(rank))) => Some(rank),
+            Ok(Some(RESP::Null)) => None,
+            resp => panic!("unexpected reply {resp:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn updating_a_score_repositions_the_member() {
+        let db = Db::new();
+
+        let members = vec![(1.0, "a".into()), (2.0, "b".into()), (3.0, "c".into())];
+        let resp = ZAdd::new("zset".into(), members).apply(&db).await.unwrap();
+        assert!(matches!(resp, Some(RESP::Integer(3))));
+        assert_eq!(rank(&db, "a").await, Some(0));
+        assert_eq!(rank(&db, "c").await, Some(2));
+
+        // an update isn't counted as added
+        let members = vec![(10.0, "a".into()), (0.5, "d".into())];
+        let resp = ZAdd::new("zset".into(), members).apply(&db).await.unwrap();
+        assert!(matches!(resp, Some(RESP::Integer(1))));
+
+        assert_eq!(rank(&db, "d").await, Some(0));
+        assert_eq!(rank(&db, "b").await, Some(1));
+        assert_eq!(rank(&db, "c").await, Some(2));
+        assert_eq!(rank(&db, "a").await, Some(3));
+        assert!(
+            matches!(db.get("zset"), Some(ValueType::ZSet(zset)) if zset.score("a") == Some(10.0))
+        );
+    }
+
+    #[tokio::test]
+    async fn zadd_on_a_string_is_a_wrong_type_error() {
+        let db = Db::new();
+        db.set("key".into(), ValueType::String(Bytes::from("v")), None);
+
+        let err = ZAdd::new("key".into(), vec![(1.0, "a".into())])
+            .apply(&db)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<CommandError>(),
+            Some(CommandError::WrongType)
+        ));
+    }
+
+    #[test]
+    fn parses_score_member_pairs() {
+        let zadd = ZAdd::from_parts(&mut reader(&["zset", "1.5", "a", "-inf", "b"])).unwrap();
+        assert_eq!(
+            zadd.members,
+            vec![(1.5, "a".into()), (f64::NEG_INFINITY, "b".into())]
+        );
+
+        assert!(matches!(
+            ZAdd::from_parts(&mut reader(&["zset", "1"])),
+            Err(RespReaderError::EndOfStream)
+        ));
+        assert!(matches!(
+            ZAdd::from_parts(&mut reader(&["zset", "1", "a", "2"])),
+            Err(RespReaderError::EndOfStream)
+        ));
+        for score in ["one", "nan"] {
+            assert!(matches!(
+                ZAdd::from_parts(&mut reader(&["zset", score, "a"])),
+                Err(RespReaderError::Command(CommandError::NotFloat))
+            ));
+        }
+    }
+}
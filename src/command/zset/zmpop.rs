@@ -0,0 +1,113 @@
+use bytes::Bytes;
+
+use crate::{parse_mpop_args, resp::RESP, Db, RespReader, RespReaderError};
+
+#[derive(Debug, Default)]
+pub struct ZMPop {
+    /// sorted sets to pop from, the first non-empty one is used
+    keys: Vec<String>,
+
+    /// pop the highest scores instead of the lowest
+    max: bool,
+
+    /// max number of members to pop
+    count: usize,
+}
+
+impl ZMPop {
+    /// contruct new ZMPop command
+    pub fn new(keys: Vec<String>, max: bool, count: usize) -> Self {
+        ZMPop { keys, max, count }
+    }
+
+    /// Construct new ZMPop command by consuming the RespReader
+    ///
+    /// Arguments are `numkeys key [key ...] MIN|MAX [COUNT count]`
+    pub fn from_parts(reader: &mut RespReader) -> Result<Self, RespReaderError> {
+        let (keys, max, count) = parse_mpop_args(reader, ["min", "max"])?;
+        Ok(ZMPop { keys, max, count })
+    }
+
+    /// Apply the zmpop command, replying with the key popped from and
+    /// the `[member, score]` pairs, or a null when every sorted set is
+    /// empty
+    pub async fn apply(self, db: &Db) -> crate::Result<Option<RESP>> {
+        let resp = match db.zmpop(&self.keys, self.count, self.max)? {
+            Some((key, members)) => RESP::Array(vec![
+                RESP::Bulk(Bytes::from(key)),
+                RESP::Array(
+                    members
+                        .into_iter()
+                        .map(|(member, score)| {
+                            RESP::Array(vec![RESP::Bulk(Bytes::from(member)), RESP::Double(score)])
+                        })
+                        .collect(),
+                ),
+            ]),
+            None => RESP::Null,
+        };
+
+        Ok(Some(resp))
+    }
+}
+
+/// Convert ZMPop command back into an equivalent `RESP`
+impl From<ZMPop> for RESP {
+    fn from(value: ZMPop) -> Self {
+        let mut resp = RESP::array();
+        resp.push_bulk(Bytes::from("zmpop"));
+        resp.push_bulk(Bytes::from(value.keys.len().to_string()));
+        for key in value.keys {
+            resp.push_bulk(Bytes::from(key));
+        }
+        resp.push_bulk(Bytes::from(if value.max { "max" } else { "min" }));
+        resp.push_bulk(Bytes::from("count"));
+        resp.push_bulk(Bytes::from(value.count.to_string()));
+        resp
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::ZMPop;
+    use crate::{resp::RESP, Db};
+
+    #[tokio::test]
+    async fn pops_from_the_first_non_empty_sorted_set() {
+        let db = Db::new();
+        let members = vec![(1.0, "a".into()), (2.0, "b".into()), (3.0, "c".into())];
+        db.zadd("second".into(), members).unwrap();
+
+        let keys = vec!["first".into(), "second".into()];
+        let resp = ZMPop::new(keys.clone(), true, 2).apply(&db).await.unwrap();
+        match resp {
+            Some(RESP::Array(reply)) => match reply.as_slice() {
+                [RESP::Bulk(key), RESP::Array(members)] => {
+                    assert_eq!(key, "second");
+                    let members: Vec<(String, f64)> = members
+                        .iter()
+                        .map(|pair| match pair {
+                            RESP::Array(pair) => match pair.as_slice() {
+                                [RESP::Bulk(member), RESP::Double(score)] => {
+                                    (String::from_utf8(member.to_vec()).unwrap(), *score)
+                                }
+                                pair => panic!("unexpected pair {pair:?}"),
+                            },
+                            pair => panic!("unexpected pair {pair:?}"),
+                        })
+                        .collect();
+                    assert_eq!(members, vec![("c".into(), 3.0), ("b".into(), 2.0)]);
+                }
+                reply => panic!("unexpected reply {reply:?}"),
+            },
+            resp => panic!("unexpected reply {resp:?}"),
+        }
+
+        let resp = ZMPop::new(keys.clone(), false, 1).apply(&db).await.unwrap();
+        assert!(matches!(resp, Some(RESP::Array(_))));
+        assert!(db.get("second").is_none());
+
+        let resp = ZMPop::new(keys, false, 1).apply(&db).await.unwrap();
+        assert!(matches!(resp, Some(RESP::Null)));
+    }
+}
@@ -0,0 +1,462 @@
+use std::{future, time::Duration};
+
+use bytes::Bytes;
+use futures::future::select_all;
+use tokio::time;
+
+use super::format_score;
+use crate::{
+    parse_blocking_args, parse_mpop_args, parse_timeout, resp::RESP, Db, MultiPop, RespReader,
+    RespReaderError,
+};
+
+#[derive(Debug, Default)]
+pub struct ZPopMin {
+    /// cache lookup key
+    key: String,
+
+    /// number of members to pop, a single member is replied without
+    /// wrapping it when the count is omitted
+    count: Option<u64>,
+}
+
+#[derive(Debug, Default)]
+pub struct ZPopMax {
+    /// cache lookup key
+    key: String,
+
+    /// number of members to pop
+    count: Option<u64>,
+}
+
+#[derive(Debug, Default)]
+pub struct BZPopMin {
+    /// keys checked in order, the first non-empty one is popped from
+    keys: Vec<String>,
+
+    /// seconds to block for, 0 blocks until a member is available
+    timeout: f64,
+}
+
+#[derive(Debug, Default)]
+pub struct BZPopMax {
+    /// keys checked in order, the first non-empty one is popped from
+    keys: Vec<String>,
+
+    /// seconds to block for, 0 blocks until a member is available
+    timeout: f64,
+}
+
+#[derive(Debug, Default)]
+pub struct BZMPop {
+    /// seconds to block for, 0 blocks until a member is available
+    timeout: f64,
+
+    /// keys checked in order, the first non-empty one is popped from
+    keys: Vec<String>,
+
+    /// pop the highest scores instead of the lowest
+    max: bool,
+
+    /// max number of members to pop
+    count: usize,
+}
+
+impl ZPopMin {
+    /// contruct new ZPopMin command
+    pub fn new(key: String, count: Option<u64>) -> Self {
+        ZPopMin { key, count }
+    }
+
+    /// Construct new ZPopMin command by consuming the RespReader
+    pub fn from_parts(reader: &mut RespReader) -> Result<Self, RespReaderError> {
+        let (key, count) = parse_pop(reader)?;
+        Ok(ZPopMin { key, count })
+    }
+
+    /// Apply the zpopmin command, popping the lowest scored members
+    pub async fn apply(self, db: &Db) -> crate::Result<Option<RESP>> {
+        pop(db, &self.key, self.count, false)
+    }
+}
+
+impl ZPopMax {
+    /// contruct new ZPopMax command
+    pub fn new(key: String, count: Option<u64>) -> Self {
+        ZPopMax { key, count }
+    }
+
+    /// Construct new ZPopMax command by consuming the RespReader
+    pub fn from_parts(reader: &mut RespReader) -> Result<Self, RespReaderError> {
+        let (key, count) = parse_pop(reader)?;
+        Ok(ZPopMax { key, count })
+    }
+
+    /// Apply the zpopmax command, popping the highest scored members
+    pub async fn apply(self, db: &Db) -> crate::Result<Option<RESP>> {
+        pop(db, &self.key, self.count, true)
+    }
+}
+
+impl BZPopMin {
+    /// contruct new BZPopMin command
+    pub fn new(keys: Vec<String>, timeout: f64) -> Self {
+        BZPopMin { keys, timeout }
+    }
+
+    /// Construct new BZPopMin command by consuming the RespReader
+    pub fn from_parts(reader: &mut RespReader) -> Result<Self, RespReaderError> {
+        let (keys, timeout) = parse_blocking_args(reader)?;
+        Ok(BZPopMin { keys, timeout })
+    }
+
+    /// Apply the bzpopmin command, blocking until a member is available
+    pub async fn apply(self, db: &Db) -> crate::Result<Option<RESP>> {
+        let popped = blocking_pop(db, &self.keys, self.timeout, 1, false).await?;
+        Ok(Some(single_pop_resp(popped)))
+    }
+}
+
+impl BZPopMax {
+    /// contruct new BZPopMax command
+    pub fn new(keys: Vec<String>, timeout: f64) -> Self {
+        BZPopMax { keys, timeout }
+    }
+
+    /// Construct new BZPopMax command by consuming the RespReader
+    pub fn from_parts(reader: &mut RespReader) -> Result<Self, RespReaderError> {
+        let (keys, timeout) = parse_blocking_args(reader)?;
+        Ok(BZPopMax { keys, timeout })
+    }
+
+    /// Apply the bzpopmax command, blocking until a member is available
+    pub async fn apply(self, db: &Db) -> crate::Result<Option<RESP>> {
+        let popped = blocking_pop(db, &self.keys, self.timeout, 1, true).await?;
+        Ok(Some(single_pop_resp(popped)))
+    }
+}
+
+impl BZMPop {
+    /// contruct new BZMPop command
+    pub fn new(timeout: f64, keys: Vec<String>, max: bool, count: usize) -> Self {
+        BZMPop {
+            timeout,
+            keys,
+            max,
+            count,
+        }
+    }
+
+    /// Construct new BZMPop command by consuming the RespReader
+    ///
+    /// Arguments are `timeout numkeys key [key ...] MIN|MAX [COUNT count]`
+    pub fn from_parts(reader: &mut RespReader) -> Result<Self, RespReaderError> {
+        let timeout = parse_timeout(&reader.next_string()?)?;
+        let (keys, max, count) = parse_mpop_args(reader, ["min", "max"])?;
+
+        Ok(BZMPop {
+            timeout,
+            keys,
+            max,
+            count,
+        })
+    }
+
+    /// Whether the highest scored members are popped
+    pub fn max(&self) -> bool {
+        self.max
+    }
+
+    /// Apply the bzmpop command, blocking until a member is available,
+    /// replies like ZMPOP
+    pub async fn apply(self, db: &Db) -> crate::Result<Option<RESP>> {
+        let resp = match blocking_pop(db, &self.keys, self.timeout, self.count, self.max).await? {
+            Some((key, members)) => RESP::Array(vec![
+                RESP::Bulk(Bytes::from(key)),
+                RESP::Array(
+                    members
+                        .into_iter()
+                        .map(|(member, score)| {
+                            RESP::Array(vec![RESP::Bulk(Bytes::from(member)), RESP::Double(score)])
+                        })
+                        .collect(),
+                ),
+            ]),
+            None => RESP::Null,
+        };
+
+        Ok(Some(resp))
+    }
+}
+
+/// Parse `key [count]`
+fn parse_pop(reader: &mut RespReader) -> Result<(String, Option<u64>), RespReaderError> {
+    let key = reader.next_string()?;
+
+    let count = match reader.next_int() {
+        Ok(count) => Some(count),
+        Err(RespReaderError::EndOfStream) => None,
+        Err(err) => return Err(err),
+    };
+
+    Ok((key, count))
+}
+
+/// Pop from a single sorted set and reply with a flat array of
+/// member and score pairs
+fn pop(db: &Db, key: &str, count: Option<u64>, max: bool) -> crate::Result<Option<RESP>> {
+    let count = count.map_or(1, |count| count as usize);
+
+    let mut resp = RESP::array();
+    for (member, score) in db.zpop(key, count, max)? {
+        resp.push_bulk(Bytes::from(member));
+        resp.push_bulk(Bytes::from(format_score(score)));
+    }
+
+    Ok(Some(resp))
+}
+
+/// Pop up to `count` members from the first non-empty sorted set in
+/// `keys`, waiting for one to be written to until the timeout runs out
+///
+/// Returns the key and the members with their scores or `None` on timeout
+async fn blocking_pop(
+    db: &Db,
+    keys: &[String],
+    timeout: f64,
+    count: usize,
+    max: bool,
+) -> crate::Result<MultiPop<(String, f64)>> {
+    let deadline = (timeout > 0.0).then(|| time::Instant::now() + Duration::from_secs_f64(timeout));
+
+    let popped = loop {
+        let notifiers = keys
+            .iter()
+            .map(|key| db.key_notifier(key))
+            .collect::<Vec<_>>();
+        // registered before checking the keys so a ZADD in between isn't missed
+        let written = notifiers
+            .iter()
+            .map(|notify| Box::pin(notify.notified()))
+            .collect::<Vec<_>>();
+
+        if let Some(popped) = db.zmpop(keys, count, max)? {
+            break Some(popped);
+        }
+
+        let timed_out = async {
+            match deadline {
+                Some(deadline) => time::sleep_until(deadline).await,
+                None => future::pending().await,
+            }
+        };
+
+        tokio::select! {
+            _ = select_all(written) => continue,
+            _ = timed_out => break None,
+        }
+    };
+
+    for key in keys {
+        db.release_key_notifier(key);
+    }
+
+    Ok(popped)
+}
+
+/// Reply with the key and the member popped from it with its score,
+/// or a null
+fn single_pop_resp(popped: MultiPop<(String, f64)>) -> RESP {
+    match popped {
+        Some((key, mut members)) => {
+            let (member, score) = members.remove(0);
+            RESP::Array(vec![
+                RESP::Bulk(Bytes::from(key)),
+                RESP::Bulk(Bytes::from(member)),
+                RESP::Bulk(Bytes::from(format_score(score))),
+            ])
+        }
+        None => RESP::Null,
+    }
+}
+
+/// Convert ZPopMin command back into an equivalent `RESP`
+impl From<ZPopMin> for RESP {
+    fn from(value: ZPopMin) -> Self {
+        pop_resp("zpopmin", value.key, value.count)
+    }
+}
+
+/// Convert ZPopMax command back into an equivalent `RESP`
+impl From<ZPopMax> for RESP {
+    fn from(value: ZPopMax) -> Self {
+        pop_resp("zpopmax", value.key, value.count)
+    }
+}
+
+/// Convert BZPopMin command back into an equivalent `RESP`
+impl From<BZPopMin> for RESP {
+    fn from(value: BZPopMin) -> Self {
+        blocking_pop_resp("bzpopmin", value.keys, value.timeout)
+    }
+}
+
+/// Convert BZPopMax command back into an equivalent `RESP`
+impl From<BZPopMax> for RESP {
+    fn from(value: BZPopMax) -> Self {
+        blocking_pop_resp("bzpopmax", value.keys, value.timeout)
+    }
+}
+
+/// Convert BZMPop command back into an equivalent `RESP`
+impl From<BZMPop> for RESP {
+    fn from(value: BZMPop) -> Self {
+        let mut resp = RESP::array();
+        resp.push_bulk(Bytes::from("bzmpop"));
+        resp.push_bulk(Bytes::from(value.timeout.to_string()));
+        resp.push_bulk(Bytes::from(value.keys.len().to_string()));
+        for key in value.keys {
+            resp.push_bulk(Bytes::from(key));
+        }
+        resp.push_bulk(Bytes::from(if value.max { "max" } else { "min" }));
+        resp.push_bulk(Bytes::from("count"));
+        resp.push_bulk(Bytes::from(value.count.to_string()));
+        resp
+    }
+}
+
+fn pop_resp(name: &'static str, key: String, count: Option<u64>) -> RESP {
+    let mut resp = RESP::array();
+    resp.push_bulk(Bytes::from(name));
+    resp.push_bulk(Bytes::from(key));
+    if let Some(count) = count {
+        resp.push_bulk(Bytes::from(count.to_string()));
+    }
+    resp
+}
+
+fn blocking_pop_resp(name: &'static str, keys: Vec<String>, timeout: f64) -> RESP {
+    let mut resp = RESP::array();
+    resp.push_bulk(Bytes::from(name));
+    for key in keys {
+        resp.push_bulk(Bytes::from(key));
+    }
+    resp.push_bulk(Bytes::from(timeout.to_string()));
+    resp
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::{BZMPop, BZPopMin, ZPopMin};
+    use crate::{resp::RESP, Db};
+
+    fn strings(resp: Option<RESP>) -> Vec<String> {
+        match resp {
+            Some(RESP::Array(values)) => values
+                .into_iter()
+                .map(|value| match value {
+                    RESP::Bulk(value) => String::from_utf8(value.to_vec()).unwrap(),
+                    value => panic!("unexpected reply {value:?}"),
+                })
+                .collect(),
+            resp => panic!("expected an array, got {resp:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn zpopmin_returns_the_lowest_score() {
+        let db = Db::new();
+        db.zadd(
+            "zset".into(),
+            vec![(3.0, "c".into()), (1.5, "a".into()), (2.0, "b".into())],
+        )
+        .unwrap();
+
+        let resp = ZPopMin::new("zset".into(), None).apply(&db).await.unwrap();
+        assert_eq!(strings(resp), ["a", "1.5"]);
+
+        let resp = ZPopMin::new("zset".into(), Some(5))
+            .apply(&db)
+            .await
+            .unwrap();
+        assert_eq!(strings(resp), ["b", "2", "c", "3"]);
+
+        // popping the last member deletes the key
+        assert!(db.get("zset").is_none());
+    }
+
+    #[tokio::test]
+    async fn bzpopmin_wakes_on_zadd() {
+        let db = Db::new();
+
+        let pop = tokio::spawn({
+            let db = db.clone();
+            async move {
+                BZPopMin::new(vec!["other".into(), "zset".into()], 0.0)
+                    .apply(&db)
+                    .await
+                    .unwrap()
+            }
+        });
+
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(!pop.is_finished());
+        db.zadd("zset".into(), vec![(1.0, "a".into())]).unwrap();
+
+        let resp = tokio::time::timeout(Duration::from_secs(2), pop)
+            .await
+            .expect("BZPOPMIN was not woken by ZADD")
+            .unwrap();
+        assert_eq!(strings(resp), ["zset", "a", "1"]);
+
+        // nothing left to pop, the timeout runs out
+        let resp = BZPopMin::new(vec!["zset".into()], 0.05)
+            .apply(&db)
+            .await
+            .unwrap();
+        assert!(matches!(resp, Some(RESP::Null)));
+    }
+
+    #[tokio::test]
+    async fn bzmpop_returns_once_any_watched_key_is_written_to() {
+        let db = Db::new();
+
+        let pop = tokio::spawn({
+            let db = db.clone();
+            async move {
+                BZMPop::new(0.0, vec!["first".into(), "second".into()], true, 1)
+                    .apply(&db)
+                    .await
+                    .unwrap()
+            }
+        });
+
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(!pop.is_finished());
+        let members = vec![(1.0, "a".into()), (2.0, "b".into())];
+        db.zadd("second".into(), members).unwrap();
+
+        let resp = tokio::time::timeout(Duration::from_millis(500), pop)
+            .await
+            .expect("BZMPOP was not woken by ZADD")
+            .unwrap();
+        match resp {
+            Some(RESP::Array(reply)) => match reply.as_slice() {
+                [RESP::Bulk(key), RESP::Array(members)] => {
+                    assert_eq!(key, "second");
+                    assert!(matches!(
+                        members.as_slice(),
+                        [RESP::Array(pair)] if matches!(
+                            pair.as_slice(),
+                            [RESP::Bulk(member), RESP::Double(score)] if member == "b" && *score == 2.0
+                        )
+                    ));
+                }
+                reply => panic!("unexpected reply {reply:?}"),
+            },
+            resp => panic!("unexpected reply {resp:?}"),
+        }
+    }
+}
@@ -0,0 +1,271 @@
+use bytes::Bytes;
+
+use super::{format_score, parse_score_bound};
+use crate::{
+    is_keyword, range::index_range, resp::RESP, CommandError, Db, RespReader, RespReaderError,
+    ValueType,
+};
+
+#[derive(Debug, Default)]
+pub struct ZRange {
+    /// cache lookup key
+    key: String,
+
+    /// first index, negative counts from the highest score
+    start: String,
+
+    /// last index, inclusive
+    stop: String,
+
+    /// trailing options, only `WITHSCORES` is supported
+    options: Vec<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct ZRangeByScore {
+    /// cache lookup key
+    key: String,
+
+    /// lower bound, `score`, `(score` or `-inf`
+    min: String,
+
+    /// upper bound, `score`, `(score` or `+inf`
+    max: String,
+
+    /// trailing options, only `WITHSCORES` is supported
+    options: Vec<String>,
+}
+
+impl ZRange {
+    /// contruct new ZRange command
+    pub fn new(key: String, start: i64, stop: i64, with_scores: bool) -> Self {
+        ZRange {
+            key,
+            start: start.to_string(),
+            stop: stop.to_string(),
+            options: with_scores_option(with_scores),
+        }
+    }
+
+    /// Construct new ZRange command by consuming the RespReader
+    pub fn from_parts(reader: &mut RespReader) -> Result<Self, RespReaderError> {
+        let key = reader.next_string()?;
+        let start = reader.next_string()?;
+        let stop = reader.next_string()?;
+
+        Ok(ZRange {
+            key,
+            start,
+            stop,
+            options: parse_options(reader),
+        })
+    }
+
+    /// Apply the zrange command and return the members between the two
+    /// indexes, from the lowest score up
+    pub async fn apply(self, db: &Db) -> crate::Result<Option<RESP>> {
+        let start = self.start.parse().map_err(|_| CommandError::NotInteger)?;
+        let stop = self.stop.parse().map_err(|_| CommandError::NotInteger)?;
+        let with_scores = parse_with_scores(&self.options)?;
+
+        let zset = match db.get(&self.key) {
+            Some(ValueType::ZSet(zset)) => zset,
+            Some(_) => return Err(CommandError::WrongType.into()),
+            None => return Ok(Some(RESP::array())),
+        };
+
+        let range = index_range(zset.len(), start, stop);
+        let members = zset.iter().skip(range.start).take(range.len());
+
+        Ok(Some(members_resp(members, with_scores)))
+    }
+}
+
+impl ZRangeByScore {
+    /// contruct new ZRangeByScore command
+    pub fn new(key: String, min: String, max: String, with_scores: bool) -> Self {
+        ZRangeByScore {
+            key,
+            min,
+            max,
+            options: with_scores_option(with_scores),
+        }
+    }
+
+    /// Construct new ZRangeByScore command by consuming the RespReader
+    pub fn from_parts(reader: &mut RespReader) -> Result<Self, RespReaderError> {
+        let key = reader.next_string()?;
+        let min = reader.next_string()?;
+        let max = reader.next_string()?;
+
+        Ok(ZRangeByScore {
+            key,
+            min,
+            max,
+            options: parse_options(reader),
+        })
+    }
+
+    /// Apply the zrangebyscore command and return the members with a
+    /// score between the bounds, from the lowest score up
+    ///
+    /// Members with the same score are ordered by member
+    pub async fn apply(self, db: &Db) -> crate::Result<Option<RESP>> {
+        let min = parse_score_bound(&self.min)?;
+        let max = parse_score_bound(&self.max)?;
+        let with_scores = parse_with_scores(&self.options)?;
+
+        let zset = match db.get(&self.key) {
+            Some(ValueType::ZSet(zset)) => zset,
+            Some(_) => return Err(CommandError::WrongType.into()),
+            None => return Ok(Some(RESP::array())),
+        };
+
+        let members = zset.range_by_score(min, max);
+
+        Ok(Some(members_resp(members, with_scores)))
+    }
+}
+
+fn parse_options(reader: &mut RespReader) -> Vec<String> {
+    let mut options = vec![];
+    while let Ok(option) = reader.next_string() {
+        options.push(option);
+    }
+    options
+}
+
+fn parse_with_scores(options: &[String]) -> Result<bool, CommandError> {
+    match options {
+        [] => Ok(false),
+        [option] if is_keyword(option, "withscores") => Ok(true),
+        _ => Err(CommandError::SyntaxError),
+    }
+}
+
+fn with_scores_option(with_scores: bool) -> Vec<String> {
+    match with_scores {
+        true => vec!["WITHSCORES".into()],
+        false => vec![],
+    }
+}
+
+/// Reply with the members, each followed by its score if `with_scores`
+/// is set
+fn members_resp<'a>(members: impl Iterator<Item = (&'a String, f64)>, with_scores: bool) -> RESP {
+    let mut resp = RESP::array();
+    for (member, score) in members {
+        resp.push_bulk(Bytes::from(member.clone()));
+        if with_scores {
+            resp.push_bulk(Bytes::from(format_score(score)));
+        }
+    }
+    resp
+}
+
+/// Convert ZRange command back into an equivalent `RESP`
+impl From<ZRange> for RESP {
+    fn from(value: ZRange) -> Self {
+        let mut resp = RESP::array();
+        resp.push_bulk(Bytes::from("zrange"));
+        resp.push_bulk(Bytes::from(value.key));
+        resp.push_bulk(Bytes::from(value.start));
+        resp.push_bulk(Bytes::from(value.stop));
+        for option in value.options {
+            resp.push_bulk(Bytes::from(option));
+        }
+        resp
+    }
+}
+
+/// Convert ZRangeByScore command back into an equivalent `RESP`
+impl From<ZRangeByScore> for RESP {
+    fn from(value: ZRangeByScore) -> Self {
+        let mut resp = RESP::array();
+        resp.push_bulk(Bytes::from("zrangebyscore"));
+        resp.push_bulk(Bytes::from(value.key));
+        resp.push_bulk(Bytes::from(value.min));
+        resp.push_bulk(Bytes::from(value.max));
+        for option in value.options {
+            resp.push_bulk(Bytes::from(option));
+        }
+        resp
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{ZRange, ZRangeByScore};
+    use crate::{resp::RESP, Db};
+
+    fn db_with_members() -> Db {
+        let db = Db::new();
+        let members = vec![
+            (1.0, "a".into()),
+            (2.0, "c".into()),
+            (2.0, "b".into()),
+            (3.0, "d".into()),
+            (f64::INFINITY, "e".into()),
+        ];
+        db.zadd("zset".into(), members).unwrap();
+        db
+    }
+
+    fn strings(resp: crate::Result<Option<RESP>>) -> Vec<String> {
+        match resp.unwrap() {
+            Some(RESP::Array(members)) => members
+                .into_iter()
+                .map(|member| match member {
+                    RESP::Bulk(member) => String::from_utf8(member.to_vec()).unwrap(),
+                    member => panic!("unexpected member {member:?}"),
+                })
+                .collect(),
+            resp => panic!("expected an array, got {resp:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn zrange_by_index() {
+        let db = db_with_members();
+
+        let range = |start, stop| ZRange::new("zset".into(), start, stop, false).apply(&db);
+        // ties are ordered by member
+        assert_eq!(strings(range(0, -1).await), ["a", "b", "c", "d", "e"]);
+        assert_eq!(strings(range(1, 2).await), ["b", "c"]);
+        assert_eq!(strings(range(-2, -1).await), ["d", "e"]);
+        assert_eq!(strings(range(3, 100).await), ["d", "e"]);
+        assert!(strings(range(3, 1).await).is_empty());
+
+        let resp = ZRange::new("zset".into(), 0, 1, true).apply(&db).await;
+        assert_eq!(strings(resp), ["a", "1", "b", "2"]);
+
+        let resp = ZRange::new("missing".into(), 0, -1, false).apply(&db).await;
+        assert!(strings(resp).is_empty());
+    }
+
+    #[tokio::test]
+    async fn zrangebyscore_bounds() {
+        let db = db_with_members();
+
+        let range = |min: &str, max: &str| {
+            ZRangeByScore::new("zset".into(), min.into(), max.into(), false).apply(&db)
+        };
+        assert_eq!(
+            strings(range("-inf", "+inf").await),
+            ["a", "b", "c", "d", "e"]
+        );
+        assert_eq!(strings(range("2", "3").await), ["b", "c", "d"]);
+        assert_eq!(strings(range("(2", "3").await), ["d"]);
+        assert_eq!(strings(range("1", "(2").await), ["a"]);
+        assert_eq!(strings(range("(3", "+inf").await), ["e"]);
+        assert!(strings(range("3", "2").await).is_empty());
+
+        let resp = ZRangeByScore::new("zset".into(), "3".into(), "inf".into(), true)
+            .apply(&db)
+            .await;
+        assert_eq!(strings(resp), ["d", "3", "e", "inf"]);
+
+        let err = range("a", "1").await.unwrap_err();
+        assert_eq!(err.to_string(), "ERR min or max is not a float");
+    }
+}
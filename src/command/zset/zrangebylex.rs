@@ -0,0 +1,204 @@
+use std::ops::Bound;
+
+use bytes::Bytes;
+
+use crate::{is_keyword, resp::RESP, CommandError, Db, RespReader, RespReaderError, ValueType};
+
+#[derive(Debug, Default)]
+pub struct ZRangeByLex {
+    /// cache lookup key
+    key: String,
+
+    /// lower bound, `[member`, `(member` or `-`
+    min: String,
+
+    /// upper bound, `[member`, `(member` or `+`
+    max: String,
+
+    /// `LIMIT offset count` arguments, validated when applied
+    limit: Vec<String>,
+}
+
+/// A lexicographic range bound
+#[derive(Debug, PartialEq, Eq)]
+enum LexBound<'a> {
+    NegInf,
+    PosInf,
+    Included(&'a str),
+    Excluded(&'a str),
+}
+
+impl ZRangeByLex {
+    /// contruct new ZRangeByLex command
+    pub fn new(key: String, min: String, max: String, limit: Option<(i64, i64)>) -> Self {
+        let limit = match limit {
+            Some((offset, count)) => vec!["LIMIT".into(), offset.to_string(), count.to_string()],
+            None => vec![],
+        };
+
+        ZRangeByLex {
+            key,
+            min,
+            max,
+            limit,
+        }
+    }
+
+    /// Construct new ZRangeByLex command by consuming the RespReader
+    pub fn from_parts(reader: &mut RespReader) -> Result<Self, RespReaderError> {
+        let key = reader.next_string()?;
+        let min = reader.next_string()?;
+        let max = reader.next_string()?;
+
+        let mut limit = vec![];
+        while let Ok(arg) = reader.next_string() {
+            limit.push(arg);
+        }
+
+        Ok(ZRangeByLex {
+            key,
+            min,
+            max,
+            limit,
+        })
+    }
+
+    /// Apply the zrangebylex command and return the members in range
+    pub async fn apply(self, db: &Db) -> crate::Result<Option<RESP>> {
+        let min = parse_bound(&self.min)?;
+        let max = parse_bound(&self.max)?;
+        let (offset, count) = parse_limit(&self.limit)?;
+
+        let zset = match db.get(&self.key) {
+            Some(ValueType::ZSet(zset)) => zset,
+            Some(_) => return Err(CommandError::WrongType.into()),
+            None => return Ok(Some(RESP::array())),
+        };
+
+        let min = match min {
+            LexBound::NegInf => Bound::Unbounded,
+            LexBound::PosInf => return Ok(Some(RESP::array())),
+            LexBound::Included(member) => Bound::Included(member),
+            LexBound::Excluded(member) => Bound::Excluded(member),
+        };
+        let max = match max {
+            LexBound::PosInf => Bound::Unbounded,
+            LexBound::NegInf => return Ok(Some(RESP::array())),
+            LexBound::Included(member) => Bound::Included(member),
+            LexBound::Excluded(member) => Bound::Excluded(member),
+        };
+
+        // a negative offset is an empty range and a negative count
+        // returns everything past the offset, same as redis
+        let members = match usize::try_from(offset) {
+            Ok(offset) => zset
+                .range_by_lex(min, max)
+                .skip(offset)
+                .take(usize::try_from(count).unwrap_or(usize::MAX))
+                .map(|member| RESP::Bulk(Bytes::from(member.clone())))
+                .collect(),
+            Err(_) => vec![],
+        };
+
+        Ok(Some(RESP::Array(members)))
+    }
+}
+
+/// Parse `-`, `+`, `[member` or `(member`
+fn parse_bound(arg: &str) -> Result<LexBound<'_>, CommandError> {
+    match arg {
+        "-" => Ok(LexBound::NegInf),
+        "+" => Ok(LexBound::PosInf),
+        _ => match (arg.strip_prefix('['), arg.strip_prefix('(')) {
+            (Some(member), _) => Ok(LexBound::Included(member)),
+            (_, Some(member)) => Ok(LexBound::Excluded(member)),
+            _ => Err(CommandError::LexRange),
+        },
+    }
+}
+
+/// Parse the optional `LIMIT offset count`, no limit is `(0, -1)`
+fn parse_limit(args: &[String]) -> Result<(i64, i64), CommandError> {
+    match args {
+        [] => Ok((0, -1)),
+        [limit, offset, count] if is_keyword(limit, "limit") => {
+            let offset = offset.parse().map_err(|_| CommandError::NotInteger)?;
+            let count = count.parse().map_err(|_| CommandError::NotInteger)?;
+            Ok((offset, count))
+        }
+        _ => Err(CommandError::SyntaxError),
+    }
+}
+
+/// Convert ZRangeByLex command back into an equivalent `RESP`
+impl From<ZRangeByLex> for RESP {
+    fn from(value: ZRangeByLex) -> Self {
+        let mut resp = RESP::array();
+        resp.push_bulk(Bytes::from("zrangebylex"));
+        resp.push_bulk(Bytes::from(value.key));
+        resp.push_bulk(Bytes::from(value.min));
+        resp.push_bulk(Bytes::from(value.max));
+        for arg in value.limit {
+            resp.push_bulk(Bytes::from(arg));
+        }
+        resp
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::ZRangeByLex;
+    use crate::{resp::RESP, Db, ValueType, ZSet};
+
+    fn db_with_members() -> Db {
+        let db = Db::new();
+        let mut zset = ZSet::new();
+        for member in ["a", "b", "c", "d", "e"] {
+            zset.insert(member.into(), 0.0);
+        }
+        db.set("zset".into(), ValueType::ZSet(zset), None);
+        db
+    }
+
+    async fn range(db: &Db, min: &str, max: &str, limit: Option<(i64, i64)>) -> Vec<String> {
+        let cmd = ZRangeByLex::new("zset".into(), min.into(), max.into(), limit);
+        match cmd.apply(db).await.unwrap() {
+            Some(RESP::Array(members)) => members
+                .into_iter()
+                .map(|member| match member {
+                    RESP::Bulk(member) => String::from_utf8(member.to_vec()).unwrap(),
+                    member => panic!("unexpected member {member:?}"),
+                })
+                .collect(),
+            resp => panic!("expected an array, got {resp:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn inclusive_and_exclusive_bounds() {
+        let db = db_with_members();
+
+        assert_eq!(range(&db, "[b", "[d", None).await, ["b", "c", "d"]);
+        assert_eq!(range(&db, "(b", "(d", None).await, ["c"]);
+        assert_eq!(range(&db, "(b", "[d", None).await, ["c", "d"]);
+        assert_eq!(range(&db, "[bb", "+", Some((1, 2))).await, ["d", "e"]);
+        assert!(range(&db, "+", "-", None).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn full_range() {
+        let db = db_with_members();
+
+        assert_eq!(range(&db, "-", "+", None).await, ["a", "b", "c", "d", "e"]);
+        assert_eq!(range(&db, "-", "+", Some((3, -1))).await, ["d", "e"]);
+
+        let err = ZRangeByLex::new("zset".into(), "a".into(), "+".into(), None)
+            .apply(&db)
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "ERR min or max not valid string range item"
+        );
+    }
+}
@@ -0,0 +1,91 @@
+use bytes::Bytes;
+
+use crate::{resp::RESP, CommandError, Db, RespReader, RespReaderError, ValueType};
+
+#[derive(Debug, Default)]
+pub struct ZRank {
+    /// cache lookup key
+    key: String,
+
+    /// member whose rank is looked up
+    member: String,
+}
+
+impl ZRank {
+    /// contruct new ZRank command
+    pub fn new(key: String, member: String) -> Self {
+        ZRank { key, member }
+    }
+
+    /// Construct new ZRank command by consuming the RespReader
+    pub fn from_parts(reader: &mut RespReader) -> Result<Self, RespReaderError> {
+        let key = reader.next_string()?;
+        let member = reader.next_string()?;
+
+        Ok(ZRank { key, member })
+    }
+
+    /// Apply the zrank command and return the member's position counting
+    /// from the lowest score, `Null` if the member or key is missing
+    pub async fn apply(self, db: &Db) -> crate::Result<Option<RESP>> {
+        let rank = match db.get(&self.key) {
+            Some(ValueType::ZSet(zset)) => zset.rank(&self.member),
+            Some(_) => return Err(CommandError::WrongType.into()),
+            None => None,
+        };
+
+        Ok(Some(
+            rank.map_or(RESP::Null, |rank| RESP::Integer(rank as i64)),
+        ))
+    }
+}
+
+/// Convert ZRank command back into an equivalent `RESP`
+impl From<ZRank> for RESP {
+    fn from(value: ZRank) -> Self {
+        let mut resp = RESP::array();
+        resp.push_bulk(Bytes::from("zrank"));
+        resp.push_bulk(Bytes::from(value.key));
+        resp.push_bulk(Bytes::from(value.member));
+        resp
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bytes::Bytes;
+
+    use super::ZRank;
+    use crate::{resp::RESP, CommandError, Db, ValueType};
+
+    #[tokio::test]
+    async fn equal_scores_rank_by_member() {
+        let db = Db::new();
+        let members = vec![(1.0, "b".into()), (1.0, "a".into()), (0.0, "c".into())];
+        db.zadd("zset".into(), members).unwrap();
+
+        let rank = |member: &str| ZRank::new("zset".into(), member.into()).apply(&db);
+        assert!(matches!(rank("c").await.unwrap(), Some(RESP::Integer(0))));
+        assert!(matches!(rank("a").await.unwrap(), Some(RESP::Integer(1))));
+        assert!(matches!(rank("b").await.unwrap(), Some(RESP::Integer(2))));
+        assert!(matches!(rank("missing").await.unwrap(), Some(RESP::Null)));
+
+        let resp = ZRank::new("missing".into(), "a".into()).apply(&db).await;
+        assert!(matches!(resp.unwrap(), Some(RESP::Null)));
+    }
+
+    #[tokio::test]
+    async fn zrank_on_a_string_is_a_wrong_type_error() {
+        let db = Db::new();
+        db.set("key".into(), ValueType::String(Bytes::from("v")), None);
+
+        let err = ZRank::new("key".into(), "a".into())
+            .apply(&db)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<CommandError>(),
+            Some(CommandError::WrongType)
+        ));
+    }
+}
@@ -0,0 +1,90 @@
+use bytes::Bytes;
+
+use crate::{resp::RESP, CommandError, Db, RespReader, RespReaderError, ValueType};
+
+#[derive(Debug, Default)]
+pub struct ZScore {
+    /// cache lookup key
+    key: String,
+
+    /// member whose score is looked up
+    member: String,
+}
+
+impl ZScore {
+    /// contruct new ZScore command
+    pub fn new(key: String, member: String) -> Self {
+        ZScore { key, member }
+    }
+
+    /// Construct new ZScore command by consuming the RespReader
+    pub fn from_parts(reader: &mut RespReader) -> Result<Self, RespReaderError> {
+        let key = reader.next_string()?;
+        let member = reader.next_string()?;
+
+        Ok(ZScore { key, member })
+    }
+
+    /// Apply the zscore command and return the member's score
+    ///
+    /// The score is a double, the connection writes it as a bulk
+    /// string to RESP2 clients
+    pub async fn apply(self, db: &Db) -> crate::Result<Option<RESP>> {
+        let score = match db.get(&self.key) {
+            Some(ValueType::ZSet(zset)) => zset.score(&self.member),
+            Some(_) => return Err(CommandError::WrongType.into()),
+            None => None,
+        };
+
+        Ok(Some(score.map_or(RESP::Null, RESP::Double)))
+    }
+}
+
+/// Convert ZScore command back into an equivalent `RESP`
+impl From<ZScore> for RESP {
+    fn from(value: ZScore) -> Self {
+        let mut resp = RESP::array();
+        resp.push_bulk(Bytes::from("zscore"));
+        resp.push_bulk(Bytes::from(value.key));
+        resp.push_bulk(Bytes::from(value.member));
+        resp
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use tokio::io::{duplex, AsyncReadExt};
+
+    use super::ZScore;
+    use crate::{connection::Connection, resp::RESP, Db};
+
+    #[tokio::test]
+    async fn score_is_a_double_for_resp3_and_a_bulk_for_resp2() {
+        let db = Db::new();
+        db.zadd("zset".into(), vec![(1.5, "a".into())]).unwrap();
+
+        let (mut client, server) = duplex(1024);
+        let mut connection = Connection::from_stream(server, false);
+        let mut buf = vec![0; 1024];
+
+        for (protocol_version, expected) in [(2, "$3\r\n1.5\r\n"), (3, ",1.5\r\n")] {
+            connection.protocol_version = protocol_version;
+
+            let resp = ZScore::new("zset".into(), "a".into())
+                .apply(&db)
+                .await
+                .unwrap()
+                .unwrap();
+            connection.write_frame(&resp).await.unwrap();
+
+            let len = client.read(&mut buf).await.unwrap();
+            assert_eq!(&buf[..len], expected.as_bytes());
+        }
+
+        let resp = ZScore::new("zset".into(), "missing".into())
+            .apply(&db)
+            .await
+            .unwrap();
+        assert!(matches!(resp, Some(RESP::Null)));
+    }
+}
@@ -1,9 +1,16 @@
 use std::{
     env::Args,
-    sync::{atomic::AtomicU64, Arc},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, AtomicUsize},
+        Arc,
+    },
+    time::Duration,
 };
 
-use crate::{ReplicaInfo, Role};
+use crate::{
+    durability::DurabilityBarrier, latency::LatencyStats, pause::ClientPause,
+    shutdown::ShutdownRequest, ReplicaInfo, Role,
+};
 
 #[derive(Debug, Default)]
 pub struct CliConfig {
@@ -12,12 +19,102 @@ pub struct CliConfig {
     pub is_replication: bool,
     pub dir: Option<String>,
     pub dbfilename: Option<String>,
+    pub encoding: EncodingConfig,
+    pub max_clients: usize,
+    /// `DEFAULT_PROTO_MAX_BULK_LEN` unless set
+    pub proto_max_bulk_len: Option<usize>,
+    pub lazyfree: LazyFreeConfig,
+    /// Seconds a client may stay idle before it's disconnected, 0
+    /// never disconnects it
+    pub timeout: u64,
+    /// `DEFAULT_SAVE_POINTS` unless set, `--save ""` clears them
+    pub save: Option<Vec<(u64, u64)>>,
+}
+
+/// Max number of connected clients unless `--maxclients` is passed
+pub const DEFAULT_MAX_CLIENTS: usize = 10000;
+
+/// Max length of a string value unless `--proto-max-bulk-len` is passed
+pub const DEFAULT_PROTO_MAX_BULK_LEN: usize = 512 * 1024 * 1024;
+
+/// Save points unless `--save` is passed, same as redis
+pub const DEFAULT_SAVE_POINTS: &[(u64, u64)] = &[(3600, 1), (300, 100), (60, 10000)];
+
+/// Size limits that decide when values switch to a bigger
+/// internal encoding
+#[derive(Debug, Clone, Copy)]
+pub struct EncodingConfig {
+    /// Max number of fields a hash may hold as a listpack
+    pub hash_max_listpack_entries: usize,
+    /// Max length of a field or value a hash may hold as a listpack
+    pub hash_max_listpack_value: usize,
+    /// Max number of members a set of integers may hold as an intset
+    pub set_max_intset_entries: usize,
+    /// Max number of members a set may hold as a listpack
+    pub set_max_listpack_entries: usize,
+}
+
+impl Default for EncodingConfig {
+    fn default() -> Self {
+        EncodingConfig {
+            hash_max_listpack_entries: 128,
+            hash_max_listpack_value: 64,
+            set_max_intset_entries: 512,
+            set_max_listpack_entries: 128,
+        }
+    }
+}
+
+/// Which deletions hand large values to the background thread
+/// instead of freeing them inline, all off by default like redis
+///
+/// There's no maxmemory eviction, `lazy_eviction` is only reported
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LazyFreeConfig {
+    /// Keys evicted on expiry
+    pub lazy_expire: bool,
+    /// Keys evicted to stay under maxmemory
+    pub lazy_eviction: bool,
+    /// Keys removed with DEL
+    pub lazy_user_del: bool,
+}
+
+/// Parse a `yes` or `no` argument
+fn parse_yes_no(value: Option<String>, name: &str) -> bool {
+    match value.map(|value| value.to_lowercase()).as_deref() {
+        Some("yes") => true,
+        Some("no") => false,
+        _ => panic!("Could not parse {name} parameter"),
+    }
+}
+
+/// Parse save points given as `<seconds> <changes>` pairs, an empty
+/// string has none
+fn parse_save_points(value: Option<String>) -> Vec<(u64, u64)> {
+    let value = value.unwrap_or_else(|| panic!("Could not parse save parameter"));
+    let numbers: Vec<u64> = value
+        .split_whitespace()
+        .map(|number| {
+            number
+                .parse()
+                .unwrap_or_else(|_| panic!("Could not parse save parameter"))
+        })
+        .collect();
+    if !numbers.len().is_multiple_of(2) {
+        panic!("Could not parse save parameter");
+    }
+
+    numbers
+        .chunks(2)
+        .map(|point| (point[0], point[1]))
+        .collect()
 }
 
 pub fn parse_config(args: &mut Args) -> CliConfig {
     const MSG: &str = "Pass --port <port> argument to start command";
     let mut config = CliConfig {
         port: 6379,
+        max_clients: DEFAULT_MAX_CLIENTS,
         ..Default::default()
     };
 
@@ -52,6 +149,60 @@ pub fn parse_config(args: &mut Args) -> CliConfig {
                 }
                 None => panic!("Could not parse dbfilename parameter"),
             },
+            Some(s) if s == "--hash-max-listpack-entries" => match args.next().map(|v| v.parse()) {
+                Some(Ok(value)) => {
+                    config.encoding.hash_max_listpack_entries = value;
+                }
+                _ => panic!("Could not parse hash-max-listpack-entries parameter"),
+            },
+            Some(s) if s == "--hash-max-listpack-value" => match args.next().map(|v| v.parse()) {
+                Some(Ok(value)) => {
+                    config.encoding.hash_max_listpack_value = value;
+                }
+                _ => panic!("Could not parse hash-max-listpack-value parameter"),
+            },
+            Some(s) if s == "--set-max-intset-entries" => match args.next().map(|v| v.parse()) {
+                Some(Ok(value)) => {
+                    config.encoding.set_max_intset_entries = value;
+                }
+                _ => panic!("Could not parse set-max-intset-entries parameter"),
+            },
+            Some(s) if s == "--set-max-listpack-entries" => match args.next().map(|v| v.parse()) {
+                Some(Ok(value)) => {
+                    config.encoding.set_max_listpack_entries = value;
+                }
+                _ => panic!("Could not parse set-max-listpack-entries parameter"),
+            },
+            Some(s) if s == "--maxclients" => match args.next().map(|v| v.parse()) {
+                Some(Ok(value)) => {
+                    config.max_clients = value;
+                }
+                _ => panic!("Could not parse maxclients parameter"),
+            },
+            Some(s) if s == "--proto-max-bulk-len" => match args.next().map(|v| v.parse()) {
+                Some(Ok(value)) => {
+                    config.proto_max_bulk_len = Some(value);
+                }
+                _ => panic!("Could not parse proto-max-bulk-len parameter"),
+            },
+            Some(s) if s == "--timeout" => match args.next().map(|v| v.parse()) {
+                Some(Ok(value)) => {
+                    config.timeout = value;
+                }
+                _ => panic!("Could not parse timeout parameter"),
+            },
+            Some(s) if s == "--save" => {
+                config.save = Some(parse_save_points(args.next()));
+            }
+            Some(s) if s == "--lazyfree-lazy-expire" => {
+                config.lazyfree.lazy_expire = parse_yes_no(args.next(), "lazyfree-lazy-expire");
+            }
+            Some(s) if s == "--lazyfree-lazy-eviction" => {
+                config.lazyfree.lazy_eviction = parse_yes_no(args.next(), "lazyfree-lazy-eviction");
+            }
+            Some(s) if s == "--lazyfree-lazy-user-del" => {
+                config.lazyfree.lazy_user_del = parse_yes_no(args.next(), "lazyfree-lazy-user-del");
+            }
             Some(s) => {
                 println!("arg {}", s);
                 panic!("Invalid arg: {} passed to server, {}", s, MSG)
@@ -84,6 +235,33 @@ pub struct ServerConfig {
     pub network_config: Option<(String, u64)>,
     pub dir: Option<String>,
     pub dbfilename: Option<String>,
+    /// Round-trip latency of PING replies
+    pub ping_latency: Arc<LatencyStats>,
+    pub encoding: EncodingConfig,
+    /// Connections past this limit are rejected
+    pub max_clients: usize,
+    /// Strings can't grow past this many bytes
+    pub proto_max_bulk_len: usize,
+    pub lazyfree: LazyFreeConfig,
+    /// Idle clients are disconnected after this long, `None` never
+    /// disconnects them
+    pub timeout: Option<Duration>,
+    /// `(seconds, changes)` save points, there's no periodic saving
+    /// so they only decide whether the dataset is saved on shutdown
+    pub save: Vec<(u64, u64)>,
+    /// Set by SHUTDOWN to stop the server
+    pub shutdown_request: Arc<ShutdownRequest>,
+    /// Number of client connections being handled
+    pub connected_clients: Arc<AtomicUsize>,
+    /// Database the replication stream last selected
+    pub repl_db: Arc<AtomicUsize>,
+    /// Pause set with CLIENT PAUSE
+    pub client_pause: Arc<ClientPause>,
+    /// Whether a replica is connected to its master, reported as
+    /// `master_link_status` by INFO
+    pub master_link_up: Arc<AtomicBool>,
+    /// What WAIT blocks on for writes to be acknowledged
+    pub durability: Arc<DurabilityBarrier>,
 }
 
 impl ServerConfig {
@@ -102,6 +280,34 @@ impl ServerConfig {
             dir,
             dbfilename,
             network_config: network,
+            ping_latency: Arc::new(LatencyStats::new()),
+            encoding: EncodingConfig::default(),
+            max_clients: DEFAULT_MAX_CLIENTS,
+            proto_max_bulk_len: DEFAULT_PROTO_MAX_BULK_LEN,
+            lazyfree: LazyFreeConfig::default(),
+            timeout: None,
+            save: DEFAULT_SAVE_POINTS.to_vec(),
+            shutdown_request: Arc::new(ShutdownRequest::new()),
+            connected_clients: Arc::new(AtomicUsize::new(0)),
+            repl_db: Arc::new(AtomicUsize::new(0)),
+            client_pause: Arc::new(ClientPause::new()),
+            master_link_up: Arc::new(AtomicBool::new(false)),
+            durability: Arc::new(DurabilityBarrier::new()),
         }
     }
 }
+
+#[cfg(test)]
+impl ServerConfig {
+    /// Config of a master with the defaults and nowhere to save to
+    pub(crate) fn for_test() -> Self {
+        ServerConfig::new(
+            Some(("".into(), 0)),
+            Role::Master,
+            None,
+            Arc::new(AtomicU64::new(0)),
+            None,
+            None,
+        )
+    }
+}
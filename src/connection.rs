@@ -1,26 +1,94 @@
 use std::{
     io::{self, Cursor},
-    sync::atomic::AtomicU64,
+    net::SocketAddr,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
     time::{Duration, Instant},
 };
 
 #[allow(unused_imports)]
-use bytes::{Buf, BytesMut};
+use bytes::{Buf, Bytes, BytesMut};
 use futures::{future::BoxFuture, FutureExt};
 use tokio::{
-    io::{AsyncReadExt, AsyncWriteExt},
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, ReadBuf, ReadHalf, WriteHalf},
     net::TcpStream,
+    sync::oneshot,
     // time::timeout,
 };
 
-use crate::resp::RESP;
+use crate::resp::{format_double, get_line, get_u8, RESP};
+
+/// Byte stream a `Connection` can be built on, a `TcpStream`
+/// or an in-memory duplex stream in tests
+pub trait AsyncStream: AsyncRead + AsyncWrite + Unpin + Send + Sync + std::fmt::Debug {}
+
+impl<T: AsyncRead + AsyncWrite + Unpin + Send + Sync + std::fmt::Debug> AsyncStream for T {}
+
+/// One side of a stream split with `tokio::io::split`, the other
+/// direction behaves like a closed stream
+#[derive(Debug)]
+enum Half {
+    Read(ReadHalf<Box<dyn AsyncStream>>),
+    Write(WriteHalf<Box<dyn AsyncStream>>),
+}
+
+impl AsyncRead for Half {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Half::Read(reader) => Pin::new(reader).poll_read(cx, buf),
+            // nothing is ever read, as if the stream was at EOF
+            Half::Write(_) => Poll::Ready(Ok(())),
+        }
+    }
+}
+
+impl AsyncWrite for Half {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        match self.get_mut() {
+            Half::Write(writer) => Pin::new(writer).poll_write(cx, buf),
+            Half::Read(_) => Poll::Ready(Err(io::ErrorKind::BrokenPipe.into())),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Half::Write(writer) => Pin::new(writer).poll_flush(cx),
+            Half::Read(_) => Poll::Ready(Ok(())),
+        }
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Half::Write(writer) => Pin::new(writer).poll_shutdown(cx),
+            Half::Read(_) => Poll::Ready(Ok(())),
+        }
+    }
+}
 
 /// Read and write RESP data from the socket
 /// to read
 #[derive(Debug)]
 pub struct Connection {
     /// A self reference to the tcp connection
-    stream: TcpStream,
+    stream: Box<dyn AsyncStream>,
+
+    /// local address of the socket, `None` for in-memory streams
+    addr: Option<SocketAddr>,
+
+    /// remote address of the socket, `None` for in-memory streams
+    peer_addr: Option<SocketAddr>,
 
     /// Wrap incoming `TcpStream` with `BufWriter` to provide
     /// buffered writing to the socket
@@ -29,8 +97,13 @@ pub struct Connection {
     /// an in-memory buffer for holding RESP raw bytes for passing
     buffer: BytesMut,
 
-    /// Idle window allowed before closing the connection
-    pub idle_close: Duration,
+    /// Idle window allowed before closing the connection, `None`
+    /// keeps it open however long it's idle
+    pub idle_close: Option<Duration>,
+
+    /// Longest bulk string accepted from the peer, a longer header is
+    /// a protocol error
+    pub max_bulk_len: usize,
 
     /// last time the connection was active
     /// i.e received a resp from the client
@@ -41,32 +114,181 @@ pub struct Connection {
 
     pub is_master: bool,
 
+    /// Set once a replica completed PSYNC on this connection, only
+    /// those count towards WAIT and are asked for ACKs
+    pub is_replica: bool,
+
+    /// Port a replica announced with `REPLCONF listening-port`, the
+    /// one it serves clients on rather than the one it connected from
+    pub listening_port: Option<u16>,
+
+    /// Capabilities a replica announced with `REPLCONF capa`
+    pub capabilities: Vec<String>,
+
     // keep track of total bytes of replica commands
     // sent to this connection
     pub repl_offset: AtomicU64,
+
+    // offset in the last ACK received from the replica, shared
+    // with the reader half once the connection is split
+    acked_offset: Arc<AtomicU64>,
+
+    // master offset the replica was fully synced at, the offsets it
+    // acknowledges count from there
+    ack_base: u64,
+
+    /// Set on the writer half of a split connection, dropping it
+    /// tells the task holding the reader half to stop
+    _closed_tx: Option<oneshot::Sender<()>>,
+
+    /// RESP version negotiated with HELLO, replies are
+    /// encoded as RESP2 unless it's 3
+    pub protocol_version: u8,
+
+    /// Library name and version the client announced with
+    /// `CLIENT SETINFO`, empty until it does
+    pub lib_name: String,
+    pub lib_ver: String,
 }
 
 /// Read bytes from tcpStream and convert to RESP for processing
 /// Write RESP to tcp stream
 impl Connection {
     pub fn new(stream: TcpStream, is_master: bool) -> Connection {
+        let addr = stream.local_addr().ok();
+        let peer_addr = stream.peer_addr().ok();
+
+        let mut connection = Connection::from_stream(stream, is_master);
+        connection.addr = addr;
+        connection.peer_addr = peer_addr;
+        connection
+    }
+
+    /// Wrap any byte stream, mostly useful for testing over
+    /// `tokio::io::duplex`
+    pub fn from_stream(stream: impl AsyncStream + 'static, is_master: bool) -> Connection {
         Connection {
-            stream,
+            stream: Box::new(stream),
+            addr: None,
+            peer_addr: None,
             buffer: BytesMut::with_capacity(4 * 1024),
-            idle_close: Duration::from_secs(60 * 60 * 24), // connection ttl = 24 hours
+            idle_close: None,
+            max_bulk_len: usize::MAX,
             closed: false,
             last_active_time: None,
             is_master,
+            is_replica: false,
+            listening_port: None,
+            capabilities: vec![],
             repl_offset: AtomicU64::new(0),
+            acked_offset: Arc::new(AtomicU64::new(0)),
+            ack_base: 0,
+            _closed_tx: None,
+            protocol_version: 2,
+            lib_name: String::new(),
+            lib_ver: String::new(),
         }
     }
 
+    /// When the connection is due to be closed for being idle,
+    /// `None` if it never is
+    pub fn idle_deadline(&self) -> Option<Instant> {
+        Some(self.last_active_time? + self.idle_close?)
+    }
+
+    /// Split the connection into a reader and a writer half that can
+    /// be used from different tasks
+    ///
+    /// Both halves record ACKs into the same offset. The receiver
+    /// resolves once the writer is dropped, so the task holding the
+    /// reader knows to let go of the socket
+    pub fn split(self) -> (Connection, Connection, oneshot::Receiver<()>) {
+        let (reader, writer) = tokio::io::split(self.stream);
+        let (closed_tx, closed_rx) = oneshot::channel();
+
+        let mut read_half = Connection::from_stream(Half::Read(reader), self.is_master);
+        // bytes already read off the socket belong to the reader
+        read_half.buffer = self.buffer;
+        read_half.addr = self.addr;
+        read_half.peer_addr = self.peer_addr;
+        read_half.is_replica = self.is_replica;
+        read_half.acked_offset = self.acked_offset.clone();
+        read_half.ack_base = self.ack_base;
+        read_half.max_bulk_len = self.max_bulk_len;
+
+        let write_half = Connection {
+            stream: Box::new(Half::Write(writer)),
+            addr: self.addr,
+            peer_addr: self.peer_addr,
+            buffer: BytesMut::with_capacity(4 * 1024),
+            idle_close: self.idle_close,
+            max_bulk_len: self.max_bulk_len,
+            last_active_time: self.last_active_time,
+            closed: self.closed,
+            is_master: self.is_master,
+            is_replica: self.is_replica,
+            listening_port: self.listening_port,
+            capabilities: self.capabilities,
+            repl_offset: self.repl_offset,
+            acked_offset: self.acked_offset,
+            ack_base: self.ack_base,
+            _closed_tx: Some(closed_tx),
+            protocol_version: self.protocol_version,
+            lib_name: self.lib_name,
+            lib_ver: self.lib_ver,
+        };
+
+        (read_half, write_half, closed_rx)
+    }
+
     pub fn get_addr(&mut self) -> String {
-        self.stream
-            .local_addr()
+        self.addr
             .map_or("UknownSocketAddr".to_string(), |socket| socket.to_string())
     }
 
+    /// Remote address of the socket, `None` for in-memory streams
+    pub fn peer_addr(&self) -> Option<SocketAddr> {
+        self.peer_addr
+    }
+
+    /// Local address of the socket, `None` for in-memory streams
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        self.addr
+    }
+
+    /// Address a replica serves clients on, the ip of the socket with
+    /// the port the replica announced, if it did
+    pub fn replica_addr(&self) -> Option<SocketAddr> {
+        let mut addr = self.peer_addr?;
+        if let Some(port) = self.listening_port {
+            addr.set_port(port);
+        }
+        Some(addr)
+    }
+
+    /// Replication offset last acknowledged by the replica
+    /// on the other end of the connection
+    pub fn acked_offset(&self) -> u64 {
+        self.acked_offset.load(Ordering::SeqCst)
+    }
+
+    /// Record the offset of an ACK received from the replica,
+    /// acks only ever move forward
+    ///
+    /// A replica counts the bytes it processed since its last full
+    /// resync, so the offset is moved past the point it synced at
+    pub fn record_ack(&self, offset: u64) {
+        self.acked_offset
+            .fetch_max(self.ack_base + offset, Ordering::SeqCst);
+    }
+
+    /// Mark the replica as fully synced at the master's `offset`, it has
+    /// every write up to there from the snapshot it was sent
+    pub fn mark_synced(&mut self, offset: u64) {
+        self.ack_base = offset;
+        self.acked_offset.store(offset, Ordering::SeqCst);
+    }
+
     pub async fn flush_stream(&mut self) -> io::Result<()> {
         self.stream.flush().await
     }
@@ -81,6 +303,8 @@ impl Connection {
 
             if 0 == self.stream.read_buf(&mut self.buffer).await? {
                 if self.buffer.is_empty() {
+                    // the peer closed the connection cleanly
+                    self.closed = true;
                     return Ok(None);
                 } else {
                     return Err("Connection reset by peer".into());
@@ -89,6 +313,40 @@ impl Connection {
         }
     }
 
+    /// Read the RDB file a master sends after FULLRESYNC, framed as
+    /// `$<len>\r\n` followed by exactly `len` bytes without a trailing
+    /// CRLF. Anything buffered past it is left for `read_resp`
+    pub async fn read_rdb_file(&mut self) -> crate::Result<Bytes> {
+        loop {
+            let mut cursor = Cursor::new(&self.buffer[..]);
+            let header = match get_u8(&mut cursor) {
+                Ok(b'$') => match get_line(&mut cursor) {
+                    Ok(line) => {
+                        let line = line.to_vec();
+                        Some((cursor.position() as usize, line))
+                    }
+                    Err(_) => None,
+                },
+                Ok(byte) => {
+                    return Err(format!("Expected an RDB file, got {:?}", byte as char).into())
+                }
+                Err(_) => None,
+            };
+
+            if let Some((start, len)) = header {
+                let len: usize = std::str::from_utf8(&len)?.parse()?;
+                if self.buffer.len() >= start + len {
+                    self.buffer.advance(start);
+                    return Ok(self.buffer.split_to(len).freeze());
+                }
+            }
+
+            if 0 == self.stream.read_buf(&mut self.buffer).await? {
+                return Err("Connection reset by peer".into());
+            }
+        }
+    }
+
     /// Attempts to parse bytes from the buffered connection
     /// stream to a `RESP` data structure for processing
     pub fn parse_resp(&mut self) -> crate::Result<Option<(RESP, usize)>> {
@@ -105,7 +363,7 @@ impl Connection {
         // to extract a valid RESP data structure
         //
         // If the buffer is invalid we return the Err Arm
-        match RESP::check(&mut cursor) {
+        match RESP::check_with_limit(&mut cursor, self.max_bulk_len) {
             Ok(_) => {
                 // we store the length of the valid RESP to be parsed
                 let len = cursor.position() as usize;
@@ -132,8 +390,18 @@ impl Connection {
     }
 
     /// Write a single `RESP` value to the underlying connection stream
+    ///
+    /// Errors without writing anything if the value holds a `RESP::File`,
+    /// use `write_rdb_file` for those
     pub fn write_frame<'a>(&'a mut self, resp: &'a RESP) -> BoxFuture<'a, io::Result<()>> {
         async move {
+            if contains_file(resp) {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "RDB files can't be written as a frame",
+                ));
+            }
+
             // println!("Write resp {:?}", &resp);
             match resp {
                 RESP::Array(list) => {
@@ -155,21 +423,54 @@ impl Connection {
         .boxed()
     }
 
+    /// Send an RDB file during a full resync, it's framed like a bulk
+    /// string without the trailing CRLF
+    pub async fn write_rdb_file(&mut self, data: &[u8]) -> io::Result<()> {
+        self.stream.write_all(b"$").await?;
+        self.write_decimal(data.len() as u64).await?;
+        self.stream.write_all(data).await?;
+        self.stream.flush().await
+    }
+
+    /// Write the text of a simple string or an error and end the frame
+    ///
+    /// They're framed by the CRLF that ends them, errors may quote what
+    /// the client sent so line breaks are replaced by spaces like redis
+    /// does, the length stays the same
+    async fn write_line(&mut self, line: &str) -> io::Result<()> {
+        if line.contains(['\r', '\n']) {
+            let line = line.replace(['\r', '\n'], " ");
+            self.stream.write_all(line.as_bytes()).await?;
+        } else {
+            self.stream.write_all(line.as_bytes()).await?;
+        }
+        self.stream.write_all(b"\r\n").await
+    }
+
     /// Write a single `RESP` value to the underlying connection stream
+    ///
+    /// RESP3 only types fall back to their RESP2 equivalent unless the
+    /// client switched to RESP3 with HELLO
     async fn write_value(&mut self, resp: &RESP) -> io::Result<()> {
+        let resp3 = self.protocol_version == 3;
+
         match resp {
+            RESP::Null | RESP::NullArray if resp3 => {
+                self.stream.write_all(b"_\r\n").await?;
+            }
             RESP::Null => {
                 self.stream.write_all(b"$-1\r\n").await?;
             }
+            RESP::NullArray => {
+                self.stream.write_all(b"*-1\r\n").await?;
+            }
             RESP::Error(error) => {
                 self.stream.write_all(b"-").await?;
-                self.stream.write_all(error.as_bytes()).await?;
-                self.stream.write_all(b"\r\n").await?;
+                self.write_line(error).await?;
             }
             RESP::Simple(string) => {
                 self.stream.write_all(b"+").await?;
-                self.stream.write_all(string.as_bytes()).await?;
-                self.stream.write_all(b"\r\n").await?;
+                self.write_line(string).await?;
             }
             RESP::Integer(int) => {
                 self.stream.write_all(b":").await?;
@@ -182,13 +483,7 @@ impl Connection {
                 self.stream.write_all(data).await?;
                 self.stream.write_all(b"\r\n").await?;
             }
-            RESP::File(data) => {
-                self.stream.write_all(b"$").await?;
-                let len = data.len() as u64;
-                self.write_decimal(len).await?;
-                println!("Write File: {}", len);
-                self.stream.write_all(data).await?;
-            }
+            RESP::File(_) => unreachable!("write_frame refuses RDB files"),
             RESP::Array(frames) => {
                 // Encode the RESP data type prefix for an array `*`
                 self.stream.write_all(b"*").await?;
@@ -198,16 +493,59 @@ impl Connection {
                     self.write_frame(&frame).await?;
                 }
             }
+            RESP::Map(pairs) => {
+                if resp3 {
+                    self.stream.write_all(b"%").await?;
+                    self.write_decimal(pairs.len() as u64).await?;
+                } else {
+                    self.stream.write_all(b"*").await?;
+                    self.write_decimal(pairs.len() as u64 * 2).await?;
+                }
+
+                for (key, value) in pairs {
+                    self.write_frame(key).await?;
+                    self.write_frame(value).await?;
+                }
+            }
+            RESP::Double(double) if resp3 => {
+                self.stream.write_all(b",").await?;
+                self.stream
+                    .write_all(format_double(*double).as_bytes())
+                    .await?;
+                self.stream.write_all(b"\r\n").await?;
+            }
+            RESP::Double(double) => {
+                let bulk = RESP::Bulk(format_double(*double).into());
+                self.write_frame(&bulk).await?;
+            }
+            RESP::Boolean(boolean) if resp3 => {
+                let boolean: &[u8] = if *boolean { b"#t\r\n" } else { b"#f\r\n" };
+                self.stream.write_all(boolean).await?;
+            }
+            RESP::Boolean(boolean) => {
+                self.stream.write_all(b":").await?;
+                self.write_decimal(*boolean as i64).await?;
+            }
+            RESP::BigNumber(number) if resp3 => {
+                self.stream.write_all(b"(").await?;
+                self.stream.write_all(number.as_bytes()).await?;
+                self.stream.write_all(b"\r\n").await?;
+            }
+            RESP::BigNumber(number) => {
+                let bulk = RESP::Bulk(number.clone().into());
+                self.write_frame(&bulk).await?;
+            }
         }
 
         Ok(())
     }
 
     /// Write a decimal to the stream
-    async fn write_decimal(&mut self, val: u64) -> io::Result<()> {
+    async fn write_decimal(&mut self, val: impl std::fmt::Display) -> io::Result<()> {
         use std::io::Write;
 
-        let mut buf = [0u8, 20];
+        // wide enough for i64::MIN
+        let mut buf = [0u8; 20];
         let mut buf = Cursor::new(&mut buf[..]);
 
         write!(&mut buf, "{}", val).unwrap();
@@ -219,3 +557,141 @@ impl Connection {
         Ok(())
     }
 }
+
+/// Whether a `RESP::File` is nested anywhere in the value
+fn contains_file(resp: &RESP) -> bool {
+    match resp {
+        RESP::File(_) => true,
+        RESP::Array(items) => items.iter().any(contains_file),
+        RESP::Map(pairs) => pairs
+            .iter()
+            .any(|(key, value)| contains_file(key) || contains_file(value)),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use tokio::io::{duplex, AsyncReadExt, AsyncWriteExt};
+
+    use super::Connection;
+    use crate::{resp::RESP, Replconf};
+
+    #[tokio::test]
+    async fn wide_decimals_are_written_in_full() {
+        let (mut client, server) = duplex(1024);
+        let mut connection = Connection::from_stream(server, false);
+
+        let resp = RESP::Array(vec![
+            RESP::Integer(i64::MIN),
+            RESP::Integer(1_234_567_890),
+            RESP::Bulk("x".repeat(123).into()),
+        ]);
+        connection.write_frame(&resp).await.unwrap();
+
+        let expected = format!(
+            "*3\r\n:-9223372036854775808\r\n:1234567890\r\n$123\r\n{}\r\n",
+            "x".repeat(123)
+        );
+        let mut written = vec![0; expected.len()];
+        client.read_exact(&mut written).await.unwrap();
+        assert_eq!(String::from_utf8(written).unwrap(), expected);
+        assert_eq!(resp.encoded_len(), expected.len());
+    }
+
+    #[tokio::test]
+    async fn line_frames_are_the_same_in_both_protocols() {
+        for protocol_version in [2, 3] {
+            let (mut client, server) = duplex(1024);
+            let mut connection = Connection::from_stream(server, false);
+            connection.protocol_version = protocol_version;
+
+            let resp = RESP::Array(vec![
+                RESP::Simple("string".into()),
+                RESP::Error("ERR bad 'a\r\nb'".into()),
+            ]);
+            connection.write_frame(&resp).await.unwrap();
+
+            let expected = "*2\r\n+string\r\n-ERR bad 'a  b'\r\n";
+            let mut written = vec![0; expected.len()];
+            client.read_exact(&mut written).await.unwrap();
+            assert_eq!(String::from_utf8(written).unwrap(), expected);
+            assert_eq!(resp.encoded_len(), expected.len());
+        }
+    }
+
+    #[tokio::test]
+    async fn null_arrays_are_plain_nulls_in_resp3() {
+        for (protocol_version, expected) in [(2, "*-1\r\n"), (3, "_\r\n")] {
+            let (mut client, server) = duplex(1024);
+            let mut connection = Connection::from_stream(server, false);
+            connection.protocol_version = protocol_version;
+
+            connection.write_frame(&RESP::NullArray).await.unwrap();
+
+            let mut written = vec![0; expected.len()];
+            client.read_exact(&mut written).await.unwrap();
+            assert_eq!(String::from_utf8(written).unwrap(), expected);
+        }
+    }
+
+    #[tokio::test]
+    async fn getack_offset_matches_the_written_frame() {
+        let (mut client, server) = duplex(1024);
+        let mut connection = Connection::from_stream(server, false);
+
+        let getack = Replconf::getack();
+        connection.write_frame(&getack).await.unwrap();
+
+        let expected = b"*3\r\n$8\r\nREPLCONF\r\n$6\r\nGETACK\r\n$1\r\n*\r\n";
+        let mut written = vec![0; expected.len()];
+        client.read_exact(&mut written).await.unwrap();
+        assert_eq!(written, expected);
+        assert_eq!(getack.encoded_len(), written.len());
+    }
+
+    #[tokio::test]
+    async fn rdb_file_is_read_up_to_its_length() {
+        let (mut master, replica) = duplex(1024);
+        let mut connection = Connection::from_stream(replica, false);
+
+        // the file ends without a CRLF and a command is pipelined
+        // right behind it, the file is split over two writes
+        master.write_all(b"$7\r\nRED").await.unwrap();
+        let written = tokio::spawn(async move {
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+            master
+                .write_all(b"IS\r\n*1\r\n$4\r\nPING\r\n")
+                .await
+                .unwrap();
+            master
+        });
+
+        let rdb = connection.read_rdb_file().await.unwrap();
+        assert_eq!(&rdb[..], b"REDIS\r\n");
+
+        let _master = written.await.unwrap();
+        let (resp, _) = connection.read_resp().await.unwrap().unwrap();
+        assert!(matches!(resp, RESP::Array(parts) if parts.len() == 1));
+    }
+
+    #[tokio::test]
+    async fn file_frames_are_refused_as_replies() {
+        let (mut client, server) = duplex(1024);
+        let mut connection = Connection::from_stream(server, false);
+
+        for resp in [
+            RESP::File("rdb".into()),
+            RESP::Array(vec![RESP::Integer(1), RESP::File("rdb".into())]),
+            RESP::Map(vec![(RESP::Simple("k".into()), RESP::File("rdb".into()))]),
+        ] {
+            assert!(connection.write_frame(&resp).await.is_err());
+        }
+
+        // only the file sent for a resync reached the stream
+        connection.write_rdb_file(b"rdb").await.unwrap();
+        let mut written = vec![0; 7];
+        client.read_exact(&mut written).await.unwrap();
+        assert_eq!(written, b"$3\r\nrdb");
+    }
+}
@@ -1,10 +1,17 @@
+use bytes::{Bytes, BytesMut};
 use std::{
-    collections::{BTreeSet, HashMap},
-    sync::{Arc, Mutex},
+    collections::{hash_map::Entry, BTreeSet, HashMap, VecDeque},
+    sync::{atomic::AtomicBool, Arc, Mutex, Weak},
+};
+use tokio::{
+    sync::{broadcast, mpsc, Notify},
+    time::{Duration, Instant},
 };
-use tokio::time::{Duration, Instant};
 
-use crate::{rdb::DerivedDatabase, Value, ValueType};
+use crate::{
+    config::LazyFreeConfig, lazyfree::LazyFree, rdb::DerivedDatabase, CommandError, Hash, Set,
+    Shutdown, Stream, StreamData, Value, ValueType, ZSet,
+};
 
 /// Instantiates a single db and exposes multiple references
 /// of it to the server
@@ -13,47 +20,135 @@ pub struct DbGuard {
     db: Db,
 }
 
+/// Number of logical databases, same as redis' default
+pub const DEFAULT_DATABASES: usize = 16;
+
+/// Key a multi-key pop took from along with what it popped, `None`
+/// when every key was empty
+pub type MultiPop<T> = Option<(String, Vec<T>)>;
+
+/// Number of entries a keyspace walk visits per lock acquisition,
+/// give or take the size of a shard
+pub const SCAN_BATCH: usize = 1000;
+
+/// Number of maps the entries of a keyspace are spread over
+const ENTRY_SHARDS: usize = 256;
+
+/// Streams the keys of a database in bounded batches, releasing the
+/// lock in between so writers aren't stalled behind a large KEYS
+#[derive(Debug)]
+pub struct KeyBatches {
+    db: Db,
+    /// shard the next batch starts at
+    cursor: Option<usize>,
+    done: bool,
+}
+
+impl Iterator for KeyBatches {
+    type Item = Vec<String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let (next, keys) = self.db.scan(self.cursor.unwrap_or(0), SCAN_BATCH);
+        self.done = next.is_none();
+        self.cursor = next;
+
+        Some(keys)
+    }
+}
+
 /// A database wrapper structure that encapsulates the
 /// shared database state
+///
+/// Every operation is scoped to the logical database at `index`
 #[derive(Debug, Clone)]
 pub struct Db {
     pub inner: Arc<SharedDb>,
+    index: usize,
 }
 
 #[derive(Debug)]
 pub struct SharedDb {
     pub state: Mutex<State>,
+
+    /// Set while a BGSAVE task is writing a snapshot
+    pub save_in_progress: AtomicBool,
+
+    /// Frees the values of deletions configured to be lazy
+    pub lazyfree: LazyFree,
 }
 
 /// State management for protocol
 ///
 /// # keys
-/// entries: the key-value store for cached contents,
-/// expirations: Stored entries expiration in BTreeSet for it's sorting benefits
+/// dbs: the keyspace of every logical database
 #[derive(Debug)]
 pub struct State {
+    // keyspaces indexed by their database number
+    dbs: Vec<Keyspace>,
+
+    // Replication state identifiers
+    replid: Option<String>,
+    repl_offset: u64,
+
+    // Replicas only hide expired keys from reads and leave
+    // deleting them to the master's DEL
+    active_expiry: bool,
+
+    // Keys evicted on expiry are sent here for propagation
+    // along with the index of their database
+    expired_tx: Option<mpsc::UnboundedSender<(usize, String)>>,
+
+    // Number of writes since startup
+    dirty: u64,
+
+    // Keyspace notifications of every write, the index of the
+    // database followed by the key and the event name
+    keyspace_tx: Option<mpsc::UnboundedSender<(usize, String, &'static str)>>,
+
+    // Id handed to the next client blocking on a list
+    next_waiter: u64,
+
+    // Deletions that free their value in the background
+    lazyfree: LazyFreeConfig,
+}
+
+/// A single logical database
+///
+/// # keys
+/// entries: the key-value store for cached contents
+/// expirations: Stored entries expiration in BTreeSet for it's sorting benefits
+/// field_expirations: hashes with fields given a TTL, by when the field expires
+/// notifiers: wake clients blocked on a key when it's written to
+/// waiters: clients blocked popping from a list, served in FIFO order
+#[derive(Debug, Default)]
+pub struct Keyspace {
     // key value map for storing cached entries
-    entries: HashMap<String, Value>,
+    entries: Entries,
 
     // Unique entries of expiration time sorted by time
-    #[allow(unused)]
     expirations: BTreeSet<(Instant, String)>,
 
-    // Replication state identifiers
-    replid: Option<String>,
-    repl_offset: u64,
+    // Keys of hashes with a field expiring at the given time
+    field_expirations: BTreeSet<(Instant, String)>,
+
+    // Notifiers of keys blocking commands wait on
+    notifiers: HashMap<String, Arc<Notify>>,
+
+    // Ids of the clients blocked on a list in the order they blocked
+    waiters: HashMap<String, VecDeque<u64>>,
 }
 
 impl DbGuard {
-    /// create a new DbGuard instance
-    pub fn new() -> DbGuard {
-        DbGuard { db: Db::new() }
-    }
-
-    /// create a new DbGuard instance from derived rdb database
-    pub fn from_derived(database: DerivedDatabase) -> DbGuard {
-        let db = Db::from_derived(database);
-        DbGuard { db }
+    /// create a new DbGuard instance, its background tasks stop
+    /// once `shutdown` fires
+    pub fn new(shutdown: broadcast::Receiver<()>) -> DbGuard {
+        DbGuard {
+            db: Db::with_shutdown(shutdown),
+        }
     }
 
     pub fn db(&self) -> Db {
@@ -63,61 +158,217 @@ impl DbGuard {
 
 impl Db {
     /// Create a new Instance of the Db
+    ///
+    /// The purge task runs until every handle to the db is dropped
     pub fn new() -> Db {
-        let shared = Arc::new(SharedDb::new());
+        Db::start(SharedDb::new(), None)
+    }
+
+    /// Create a new Instance of the Db whose purge task also stops
+    /// once `shutdown` fires
+    pub fn with_shutdown(shutdown: broadcast::Receiver<()>) -> Db {
+        Db::start(SharedDb::new(), Some(Shutdown::new(shutdown)))
+    }
+
+    /// Wrap `shared` and start its background tasks
+    fn start(shared: SharedDb, shutdown: Option<Shutdown>) -> Db {
+        let shared = Arc::new(shared);
 
         // start background tasks
-        tokio::spawn(purge_expired_keys(shared.clone()));
+        tokio::spawn(purge_expired_keys(Arc::downgrade(&shared), shutdown));
 
-        Db { inner: shared }
+        Db {
+            inner: shared,
+            index: 0,
+        }
     }
 
-    /// Create a new Instance of the Db using derived rdb database data
-    pub fn from_derived(database: DerivedDatabase) -> Db {
-        let shared = Arc::new(SharedDb::from_derived(database));
+    /// Replace the keyspace of the database at `index` with `database`,
+    /// clients blocked on its keys stay blocked
+    pub fn load(&self, index: usize, database: DerivedDatabase) {
+        let mut state = self.inner.state.lock().unwrap();
+        let db = &mut state.dbs[index];
+
+        db.entries = database.entries.into_iter().collect();
+        db.expirations = database.expirations;
+        // every field TTL gets a tracker, same as HEXPIRE sets them
+        db.field_expirations = db
+            .entries
+            .iter()
+            .filter_map(|(key, value)| match &value.data {
+                ValueType::Hash(hash) => Some((key, hash)),
+                _ => None,
+            })
+            .flat_map(|(key, hash)| {
+                hash.iter_with_ttl()
+                    .filter_map(move |(_, _, at)| Some((at?, key.clone())))
+            })
+            .collect();
+    }
 
-        // start background tasks
-        tokio::spawn(purge_expired_keys(shared.clone()));
+    /// Returns a handle to the logical database at `index`
+    /// sharing the same underlying state
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of range, see `Db::databases`
+    pub fn with_index(&self, index: usize) -> Db {
+        assert!(index < self.databases(), "DB index is out of range");
+
+        Db {
+            inner: self.inner.clone(),
+            index,
+        }
+    }
+
+    /// Index of the logical database this handle is scoped to
+    pub fn index(&self) -> usize {
+        self.index
+    }
 
-        Db { inner: shared }
+    /// Number of logical databases
+    pub fn databases(&self) -> usize {
+        self.inner.state.lock().unwrap().dbs.len()
     }
 
-    /// Get the byte associated with a key
+    /// Look up the entry stored at a key
     ///
-    /// Returns `None` if there's no value associated with the key
-    pub fn get(&self, key: &str) -> Option<ValueType> {
-        let state = self.inner.state.lock().unwrap();
+    /// Every read goes through here so a logically expired entry is
+    /// absent no matter when the purge task last ran. It's evicted
+    /// on the spot unless active expiry is off
+    pub fn lookup(&self, key: &str) -> Option<Value> {
+        self.lookup_with(key, Value::clone)
+    }
+
+    /// Look up the entry stored at a key along with the address it
+    /// lives at, as `DEBUG OBJECT` reports it
+    pub fn lookup_object(&self, key: &str) -> Option<(usize, Value)> {
+        self.lookup_with(key, |value| (value as *const Value as usize, value.clone()))
+    }
 
-        let entry = state.entries.get(key);
+    /// Run `f` on the live entry stored at a key, see `lookup`
+    fn lookup_with<T>(&self, key: &str, f: impl FnOnce(&Value) -> T) -> Option<T> {
+        let mut state = self.inner.state.lock().unwrap();
 
-        if entry.is_none() {
+        if state.dbs[self.index]
+            .entries
+            .get(key)
+            .is_some_and(Value::is_expired)
+        {
+            if state.active_expiry {
+                let evicted = state.evict(self.index, key);
+                let lazy = state.lazyfree.lazy_expire;
+                drop(state);
+                self.inner.free(evicted, lazy);
+            }
             return None;
         }
 
-        let bytes = entry.unwrap().data.clone();
+        let value = state.dbs[self.index].lookup(key).map(f);
 
         // don't forget to release lock on state mutex
         drop(state);
 
-        Some(bytes)
+        value
     }
 
-    /// Get the all Keys
+    /// Get the data associated with a key
     ///
     /// Returns `None` if there's no value associated with the key
+    /// or the value has expired but hasn't been purged yet
+    pub fn get(&self, key: &str) -> Option<ValueType> {
+        self.lookup(key).map(|value| value.data)
+    }
+
+    /// Get the values of every key in `keys` under a single lock
+    ///
+    /// Missing and expired keys are `None`
+    pub fn get_all(&self, keys: &[String]) -> Vec<Option<ValueType>> {
+        let state = self.inner.state.lock().unwrap();
+        let db = &state.dbs[self.index];
+
+        let values = keys
+            .iter()
+            .map(|key| db.lookup(key).map(|value| value.data.clone()))
+            .collect();
+
+        drop(state);
+
+        values
+    }
+
+    /// Get the all Keys
+    ///
+    /// Keys that have expired but haven't been purged yet are skipped.
+    /// The keyspace is walked in batches so the lock is never held for
+    /// the whole copy, see `scan`
     pub fn keys(&self) -> Vec<String> {
+        self.key_batches().flatten().collect()
+    }
+
+    /// Visit whole shards starting at `cursor` until at least `count`
+    /// keys were visited, and return the live ones along with the shard
+    /// to resume at, `None` once the whole keyspace has been visited
+    ///
+    /// The lock is only held for a single batch. Keys that exist for the
+    /// whole walk are returned exactly once, keys written or deleted in
+    /// between may or may not be, the same guarantees redis gives for SCAN
+    pub fn scan(&self, cursor: usize, count: usize) -> (Option<usize>, Vec<String>) {
         let state = self.inner.state.lock().unwrap();
+        let shards = &state.dbs[self.index].entries.shards;
 
-        let keys = state
-            .entries
-            .keys()
-            .map(|key| key.to_owned())
-            .collect::<Vec<String>>();
+        let mut keys = vec![];
+        let mut visited = 0;
+        let mut next = cursor;
+        while next < shards.len() && visited < count {
+            visited += shards[next].len();
+            keys.extend(
+                shards[next]
+                    .iter()
+                    .filter(|(_, value)| !value.is_expired())
+                    .map(|(key, _)| key.to_owned()),
+            );
+            next += 1;
+        }
+
+        // don't forget to release lock on state mutex
+        drop(state);
+
+        let next = (next < ENTRY_SHARDS).then_some(next);
+        (next, keys)
+    }
+
+    /// Iterate over the live keys in batches of `SCAN_BATCH`, taking
+    /// the lock once per batch
+    pub fn key_batches(&self) -> KeyBatches {
+        KeyBatches {
+            db: self.clone(),
+            cursor: None,
+            done: false,
+        }
+    }
+
+    /// Live entries of every logical database, indexed by database
+    /// number, cloned under a single lock
+    pub fn snapshot(&self) -> Vec<Vec<(String, Value)>> {
+        let state = self.inner.state.lock().unwrap();
+
+        let snapshot = state
+            .dbs
+            .iter()
+            .map(|db| {
+                db.entries
+                    .iter()
+                    .filter(|(_, value)| !value.is_expired())
+                    .map(|(key, value)| (key.clone(), value.clone()))
+                    .collect()
+            })
+            .collect();
 
         // don't forget to release lock on state mutex
         drop(state);
 
-        keys
+        snapshot
     }
 
     /// Set a value associated to a key with an optional expiration
@@ -127,105 +378,1643 @@ impl Db {
         let value = Value::new(value, expires_at);
         let mut state = self.inner.state.lock().unwrap();
 
-        // insert expires_at into expiration tracker
-        // when key expires it'll automatically be removed later
-        if let Some(expiry) = value.expires_at {
-            state.expirations.insert((expiry, key.clone()));
+        state.dbs[self.index].insert(key.clone(), value);
+
+        drop(state);
+
+        self.notify_write(&key, "set");
+    }
+
+    /// Set every key value pair under a single lock, dropping
+    /// any pending expiration on the keys
+    pub fn set_all(&self, pairs: Vec<(String, ValueType)>) {
+        let mut state = self.inner.state.lock().unwrap();
+        let db = &mut state.dbs[self.index];
+
+        let keys: Vec<String> = pairs.iter().map(|(key, _)| key.clone()).collect();
+        for (key, value) in pairs {
+            db.insert(key, Value::new(value, None));
         }
 
-        // Insert key value entry into store
-        state.entries.insert(key.clone(), value);
+        drop(state);
+
+        for key in keys {
+            self.notify_write(&key, "set");
+        }
+    }
+
+    /// Set a string value and return the string it replaced, the
+    /// key loses any pending expiration
+    ///
+    /// Errors without setting the value if the key holds another type
+    pub fn get_set(&self, key: String, value: ValueType) -> crate::Result<Option<ValueType>> {
+        let mut state = self.inner.state.lock().unwrap();
+        let db = &mut state.dbs[self.index];
+
+        let old = match db.entries.get(&key) {
+            Some(old) if old.is_expired() => None,
+            Some(Value {
+                data: ValueType::String(data),
+                ..
+            }) => Some(ValueType::String(data.clone())),
+            Some(_) => return Err(CommandError::WrongType.into()),
+            None => None,
+        };
+
+        db.insert(key.clone(), Value::new(value, None));
 
         drop(state);
+
+        self.notify_write(&key, "set");
+
+        Ok(old)
     }
 
-    pub fn set_repl_id(&self, replid: String) {
+    /// Set a value only if the key doesn't exist yet
+    ///
+    /// Returns true if the value was set
+    pub fn set_nx(&self, key: String, value: ValueType) -> bool {
         let mut state = self.inner.state.lock().unwrap();
-        let state = &mut *state;
-        state.replid = Some(replid);
+        let db = &mut state.dbs[self.index];
+
+        if db.lookup(&key).is_some() {
+            return false;
+        }
+
+        db.insert(key.clone(), Value::new(value, None));
+
+        drop(state);
+
+        self.notify_write(&key, "set");
+
+        true
     }
 
-    pub fn get_repl_info(&self) -> (Option<String>, u64) {
-        let state = self.inner.state.lock().unwrap();
+    /// Append bytes to the string stored at a key, creating it if
+    /// it doesn't exist, and keep any pending expiration
+    ///
+    /// Returns the length of the string after the append, the string
+    /// is left alone if it would grow past `max_len`
+    pub fn append(&self, key: String, bytes: &[u8], max_len: usize) -> crate::Result<usize> {
+        self.update_string(key, "append", |data| {
+            if data.len() + bytes.len() > max_len {
+                return Err(CommandError::StringTooLong);
+            }
+            data.extend_from_slice(bytes);
+            Ok(data.len())
+        })
+    }
 
-        let replid = state.replid.clone();
-        let repl_offset = state.repl_offset.clone();
+    /// Overwrite the string stored at a key starting at `offset`, the
+    /// string is created or zero-padded up to `offset` as needed
+    ///
+    /// Returns the length of the string after the write
+    pub fn set_range(&self, key: String, offset: usize, bytes: &[u8]) -> crate::Result<usize> {
+        self.update_string(key, "setrange", |data| {
+            let end = offset + bytes.len();
+            if data.len() < end {
+                data.resize(end, 0);
+            }
+            data[offset..end].copy_from_slice(bytes);
+            Ok(data.len())
+        })
+    }
+
+    /// Add `delta` to the integer stored at a key under a single lock,
+    /// a missing key counts as 0 and the key keeps any pending expiration
+    ///
+    /// Returns the new value or an error if the value isn't an integer
+    /// or the result doesn't fit in an i64
+    pub fn incr_by(&self, key: String, delta: i64) -> crate::Result<i64> {
+        let mut state = self.inner.state.lock().unwrap();
+        let db = &mut state.dbs[self.index];
+
+        // an expired key that wasn't purged yet counts as missing
+        if db.lookup(&key).is_none() {
+            db.remove(&key);
+        }
+
+        let current = match db.entries.get(&key).map(|value| &value.data) {
+            Some(ValueType::String(data)) => {
+                // values are binary safe, only parse them when they
+                // hold a valid utf-8 number
+                let int = std::str::from_utf8(data)
+                    .ok()
+                    .and_then(|s| s.parse::<i64>().ok());
+                int.ok_or(CommandError::NotInteger)?
+            }
+            Some(_) => return Err(CommandError::WrongType.into()),
+            None => 0,
+        };
+
+        let int = current.checked_add(delta).ok_or(CommandError::Overflow)?;
+
+        let data = ValueType::String(Bytes::from(int.to_string()));
+        match db.entries.get_mut(&key) {
+            Some(value) => value.data = data,
+            None => db.insert(key.clone(), Value::new(data, None)),
+        }
 
         drop(state);
 
-        (replid, repl_offset)
+        self.notify_write(&key, "incrby");
+
+        Ok(int)
     }
-}
 
-impl SharedDb {
-    pub fn new() -> SharedDb {
-        SharedDb {
-            state: Mutex::new(State {
-                entries: HashMap::new(),
-                expirations: BTreeSet::new(),
-                replid: None,
-                repl_offset: 0,
-            }),
+    /// Modify the string stored at a key in place under a single lock,
+    /// a missing key starts out as an empty string and the key keeps
+    /// any pending expiration
+    ///
+    /// Returns the result of `update`, the write is announced as `event`.
+    /// When `update` fails the string is left as it was
+    pub fn update_string<T>(
+        &self,
+        key: String,
+        event: &'static str,
+        update: impl FnOnce(&mut BytesMut) -> Result<T, CommandError>,
+    ) -> crate::Result<T> {
+        let mut state = self.inner.state.lock().unwrap();
+        let db = &mut state.dbs[self.index];
+
+        // an expired key that wasn't purged yet counts as missing
+        if db.lookup(&key).is_none() {
+            db.remove(&key);
         }
+
+        let created = !db.entries.contains_key(&key);
+        let value = db
+            .entries
+            .entry(key.clone())
+            .or_insert_with(|| Value::new(ValueType::String(Bytes::new()), None));
+
+        let result = match &mut value.data {
+            ValueType::String(data) => {
+                let mut updated = BytesMut::from(&data[..]);
+                let result = update(&mut updated);
+                if result.is_ok() {
+                    *data = updated.freeze();
+                }
+                result
+            }
+            _ => return Err(CommandError::WrongType.into()),
+        };
+
+        let result = match result {
+            Ok(result) => result,
+            Err(err) => {
+                if created {
+                    db.remove(&key);
+                }
+                return Err(err.into());
+            }
+        };
+
+        drop(state);
+
+        self.notify_write(&key, event);
+
+        Ok(result)
     }
 
-    pub fn from_derived(datbase: DerivedDatabase) -> SharedDb {
-        SharedDb {
-            state: Mutex::new(State {
-                entries: datbase.entries,
-                expirations: datbase.expirations,
-                replid: None,
-                repl_offset: 0,
-            }),
+    /// Modify the hash stored at a key in place under a single lock,
+    /// a missing key starts out as an empty hash and the key is deleted
+    /// once the hash is empty
+    ///
+    /// `update` returns its result along with whether it wrote to the
+    /// hash, only writes are announced as `event`
+    pub fn hash_op<T>(
+        &self,
+        key: &str,
+        event: &'static str,
+        update: impl FnOnce(&mut Hash) -> (T, bool),
+    ) -> crate::Result<T> {
+        let mut state = self.inner.state.lock().unwrap();
+        let db = &mut state.dbs[self.index];
+
+        // an expired key that wasn't purged yet counts as missing
+        if db.lookup(key).is_none() {
+            db.remove(key);
+        }
+
+        let value = db
+            .entries
+            .entry(key.to_string())
+            .or_insert_with(|| Value::new(ValueType::Hash(Hash::new()), None));
+
+        let (result, written, is_empty) = match &mut value.data {
+            ValueType::Hash(hash) => {
+                hash.purge_expired();
+                let (result, written) = update(hash);
+                (result, written, hash.is_empty())
+            }
+            _ => return Err(CommandError::WrongType.into()),
+        };
+
+        if is_empty {
+            db.remove(key);
         }
+
+        drop(state);
+
+        if written {
+            self.notify_write(key, event);
+        }
+
+        Ok(result)
     }
 
-    /// Purge expired keys and return Instant of the next
-    /// expiration
-    pub fn clear_expired_keys(&self) -> Option<Instant> {
-        let mut state = self.state.lock().unwrap();
+    /// Expire `fields` of the hash stored at `key` at `at` and return
+    /// the status of each field: -2 when there's no such field, 1 when
+    /// the TTL was set and 2 when the field was deleted right away
+    /// because `at` is already past
+    pub fn hexpire(&self, key: &str, fields: &[String], at: Instant) -> crate::Result<Vec<i64>> {
+        let mut state = self.inner.state.lock().unwrap();
+        let db = &mut state.dbs[self.index];
 
-        let state = &mut *state;
+        let hash = match db.entries.get_mut(key) {
+            Some(value) if value.is_expired() => return Ok(vec![-2; fields.len()]),
+            Some(Value {
+                data: ValueType::Hash(hash),
+                ..
+            }) => hash,
+            Some(_) => return Err(CommandError::WrongType.into()),
+            None => return Ok(vec![-2; fields.len()]),
+        };
 
         let now = Instant::now();
+        let statuses = fields
+            .iter()
+            .map(|field| match at <= now {
+                true if hash.remove(field) => 2,
+                false if hash.expire(field, at) => 1,
+                _ => -2,
+            })
+            .collect::<Vec<i64>>();
+
+        let is_empty = hash.is_empty();
+        if statuses.contains(&1) {
+            db.field_expirations.insert((at, key.to_string()));
+        }
+        if is_empty {
+            db.remove(key);
+        }
+
+        drop(state);
+
+        if statuses.iter().any(|status| *status > 0) {
+            self.notify_write(key, "hexpire");
+        }
+
+        Ok(statuses)
+    }
+
+    /// Modify the set stored at a key in place under a single lock,
+    /// a missing key starts out as an empty set and the key is deleted
+    /// once the set is empty
+    ///
+    /// `update` returns its result along with whether it wrote to the
+    /// set, only writes are announced as `event`
+    pub fn set_op<T>(
+        &self,
+        key: &str,
+        event: &'static str,
+        update: impl FnOnce(&mut Set) -> (T, bool),
+    ) -> crate::Result<T> {
+        let mut state = self.inner.state.lock().unwrap();
+        let db = &mut state.dbs[self.index];
+
+        // an expired key that wasn't purged yet counts as missing
+        if db.lookup(key).is_none() {
+            db.remove(key);
+        }
 
-        while let Some((expires_at, key)) = state.expirations.iter().next() {
-            let expires_at = expires_at.to_owned();
-            if expires_at > now {
-                return Some(expires_at);
+        let value = db
+            .entries
+            .entry(key.to_string())
+            .or_insert_with(|| Value::new(ValueType::Set(Set::new()), None));
+
+        let (result, written, is_empty) = match &mut value.data {
+            ValueType::Set(set) => {
+                let (result, written) = update(set);
+                (result, written, set.is_empty())
             }
+            _ => return Err(CommandError::WrongType.into()),
+        };
+
+        if is_empty {
+            db.remove(key);
+        }
 
-            state.entries.remove(key.as_str());
-            state
-                .expirations
-                .remove(&(expires_at, key.clone().to_owned()));
+        drop(state);
+
+        if written {
+            self.notify_write(key, event);
         }
 
-        None
+        Ok(result)
     }
-}
 
-impl State {
-    pub fn next_expiration(&self) -> Option<Instant> {
-        self.expirations.iter().next().map(|entry| entry.0)
+    /// Append an entry with `fields` to the stream stored at `key`,
+    /// `next_id` picks the id of the entry given the id of the stream's
+    /// top entry or rejects it. The stream is created unless `nomkstream`
+    /// is set, and trimmed to its newest `maxlen` entries
+    ///
+    /// Returns the id of the new entry, `None` if there's no stream and
+    /// `nomkstream` is set
+    pub fn xadd(
+        &self,
+        key: &str,
+        fields: HashMap<String, String>,
+        nomkstream: bool,
+        maxlen: Option<usize>,
+        next_id: impl FnOnce(Option<(u64, u64)>) -> Result<(u64, u64), CommandError>,
+    ) -> crate::Result<Option<(u64, u64)>> {
+        let mut state = self.inner.state.lock().unwrap();
+        let db = &mut state.dbs[self.index];
+
+        // an expired key that wasn't purged yet counts as missing
+        if db.lookup(key).is_none() {
+            if nomkstream {
+                return Ok(None);
+            }
+            db.remove(key);
+        }
+
+        let top = match db.entries.get(key).map(|value| &value.data) {
+            // ids removed by XDEL or trimming still count
+            Some(ValueType::Stream(stream)) => Some(stream.last_id),
+            Some(_) => return Err(CommandError::WrongType.into()),
+            None => None,
+        };
+        let id = next_id(top)?;
+
+        let value = db
+            .entries
+            .entry(key.to_string())
+            .or_insert_with(|| Value::new(ValueType::Stream(Stream::default()), None));
+        if let ValueType::Stream(stream) = &mut value.data {
+            stream.entries.push(StreamData {
+                id,
+                pairs: fields,
+                _created_at: Instant::now(),
+            });
+            stream.last_id = id;
+
+            // drop the oldest entries past the limit
+            if let Some(maxlen) = maxlen {
+                let excess = stream.entries.len().saturating_sub(maxlen);
+                stream.entries.drain(..excess);
+            }
+        }
+
+        drop(state);
+
+        self.notify_write(key, "xadd");
+
+        Ok(Some(id))
     }
-}
 
-// TODO: Implement background task notifier and shutdown listner
-// the cache
-pub async fn purge_expired_keys(shared_db: Arc<SharedDb>) {
-    // run a loop
-    // wait for the next instant in the expiry and remove expired keys
-    // from the cache
-    loop {
-        if let Some(when) = shared_db.clear_expired_keys() {
-            // expired entries have been purged and the next entry is returned
-            // wait until when to purge state again
-            // println!("Wait until {:?} to purge state", &when);
-            tokio::time::sleep_until(when).await;
-        } else {
-            // println!("Sleep for 1 sec");
-            tokio::time::sleep_until(Instant::now() + Duration::from_millis(10)).await;
+    /// Remove the entries with the given ids from the stream stored at
+    /// `key` and return how many of them were found, the key is kept
+    /// even when the stream ends up empty
+    pub fn xdel(&self, key: &str, ids: &[(u64, u64)]) -> crate::Result<usize> {
+        let mut state = self.inner.state.lock().unwrap();
+
+        let stream = match state.dbs[self.index].entries.get_mut(key) {
+            Some(value) if value.is_expired() => return Ok(0),
+            Some(Value {
+                data: ValueType::Stream(stream),
+                ..
+            }) => stream,
+            Some(_) => return Err(CommandError::WrongType.into()),
+            None => return Ok(0),
+        };
+
+        let len = stream.entries.len();
+        stream.entries.retain(|entry| !ids.contains(&entry.id));
+        let deleted = len - stream.entries.len();
+
+        drop(state);
+
+        if deleted > 0 {
+            self.notify_write(key, "xdel");
         }
+
+        Ok(deleted)
+    }
+
+    /// Move the value stored at `src` to `dst` along with its expiry,
+    /// overwriting `dst` unless `nx` is set
+    ///
+    /// Returns false if `nx` is set and `dst` exists, or an error if
+    /// `src` doesn't exist
+    pub fn rename(&self, src: &str, dst: &str, nx: bool) -> crate::Result<bool> {
+        let mut state = self.inner.state.lock().unwrap();
+        let db = &mut state.dbs[self.index];
+
+        if db.lookup(src).is_none() {
+            return Err(CommandError::NoSuchKey.into());
+        }
+
+        if nx && db.lookup(dst).is_some() {
+            return Ok(false);
+        }
+
+        // insert keeps the expiration tracker in sync, so the
+        // tracker of `src` moves over to `dst` along with the value
+        if src != dst {
+            let value = db.remove(src).expect("looked up above");
+            db.insert(dst.to_string(), value);
+        }
+
+        drop(state);
+
+        // a rename is a single write, announced on the key it lands on
+        self.notify_write(dst, "rename_to");
+
+        Ok(true)
+    }
+
+    /// Add members to the sorted set at a key, creating it if needed
+    ///
+    /// Returns the number of new members
+    pub fn zadd(&self, key: String, members: Vec<(f64, String)>) -> crate::Result<usize> {
+        let mut state = self.inner.state.lock().unwrap();
+        let db = &mut state.dbs[self.index];
+
+        // an expired key that wasn't purged yet counts as missing
+        if db.lookup(&key).is_none() {
+            db.remove(&key);
+        }
+
+        let value = db
+            .entries
+            .entry(key.clone())
+            .or_insert_with(|| Value::new(ValueType::ZSet(ZSet::new()), None));
+
+        let added = match &mut value.data {
+            ValueType::ZSet(zset) => members
+                .into_iter()
+                .filter(|(score, member)| zset.insert(member.clone(), *score))
+                .count(),
+            _ => return Err(CommandError::WrongType.into()),
+        };
+
+        drop(state);
+
+        self.notify_write(&key, "zadd");
+
+        Ok(added)
+    }
+
+    /// Pop up to `count` of the lowest, or highest if `max` is set,
+    /// scored members of the sorted set at a key
+    ///
+    /// The key is deleted once the sorted set is empty
+    pub fn zpop(&self, key: &str, count: usize, max: bool) -> crate::Result<Vec<(String, f64)>> {
+        let mut state = self.inner.state.lock().unwrap();
+        let popped = state.dbs[self.index].zpop(key, count, max)?;
+        drop(state);
+
+        if !popped.is_empty() {
+            self.notify_write(key, if max { "zpopmax" } else { "zpopmin" });
+        }
+
+        Ok(popped)
+    }
+
+    /// Push values onto the head, or the tail if `tail` is set, of the
+    /// list at a key, creating it if needed
+    ///
+    /// Returns the length of the list after the push
+    pub fn push(&self, key: String, values: Vec<Bytes>, tail: bool) -> crate::Result<usize> {
+        let mut state = self.inner.state.lock().unwrap();
+        let db = &mut state.dbs[self.index];
+
+        // an expired key that wasn't purged yet counts as missing
+        if db.lookup(&key).is_none() {
+            db.remove(&key);
+        }
+
+        let value = db
+            .entries
+            .entry(key.clone())
+            .or_insert_with(|| Value::new(ValueType::List(VecDeque::new()), None));
+
+        let len = match &mut value.data {
+            ValueType::List(list) => {
+                for value in values {
+                    if tail {
+                        list.push_back(value);
+                    } else {
+                        list.push_front(value);
+                    }
+                }
+                list.len()
+            }
+            _ => return Err(CommandError::WrongType.into()),
+        };
+
+        drop(state);
+
+        self.notify_write(&key, if tail { "rpush" } else { "lpush" });
+
+        Ok(len)
+    }
+
+    /// Pop up to `count` values from the head, or the tail if `tail`
+    /// is set, of the list at a key
+    ///
+    /// The key is deleted once the list is empty
+    pub fn pop(&self, key: &str, count: usize, tail: bool) -> crate::Result<Vec<Bytes>> {
+        let mut state = self.inner.state.lock().unwrap();
+        let popped = state.dbs[self.index].pop(key, count, tail)?;
+        drop(state);
+
+        if !popped.is_empty() {
+            self.notify_write(key, if tail { "rpop" } else { "lpop" });
+        }
+
+        Ok(popped)
+    }
+
+    /// Pop up to `count` values from the head, or the tail if `tail` is
+    /// set, of the first non-empty list among `keys`
+    ///
+    /// Returns the key popped from along with the values, `None` if
+    /// every list is empty
+    pub fn mpop(
+        &self,
+        keys: &[String],
+        count: usize,
+        tail: bool,
+    ) -> crate::Result<MultiPop<Bytes>> {
+        let mut state = self.inner.state.lock().unwrap();
+        let db = &mut state.dbs[self.index];
+
+        let mut popped = None;
+        for key in keys {
+            let values = db.pop(key, count, tail)?;
+            if !values.is_empty() {
+                popped = Some((key.clone(), values));
+                break;
+            }
+        }
+
+        drop(state);
+
+        if let Some((key, _)) = &popped {
+            self.notify_write(key, if tail { "rpop" } else { "lpop" });
+        }
+
+        Ok(popped)
+    }
+
+    /// Pop up to `count` of the lowest, or highest if `max` is set,
+    /// scored members of the first non-empty sorted set among `keys`
+    ///
+    /// Returns the key popped from along with the members, `None` if
+    /// every sorted set is empty
+    pub fn zmpop(
+        &self,
+        keys: &[String],
+        count: usize,
+        max: bool,
+    ) -> crate::Result<MultiPop<(String, f64)>> {
+        let mut state = self.inner.state.lock().unwrap();
+        let db = &mut state.dbs[self.index];
+
+        let mut popped = None;
+        for key in keys {
+            let members = db.zpop(key, count, max)?;
+            if !members.is_empty() {
+                popped = Some((key.clone(), members));
+                break;
+            }
+        }
+
+        drop(state);
+
+        if let Some((key, _)) = &popped {
+            self.notify_write(key, if max { "zpopmax" } else { "zpopmin" });
+        }
+
+        Ok(popped)
+    }
+
+    /// Queue a client blocking on the lists at `keys`, it's served
+    /// before any client blocking on them later
+    ///
+    /// Returns the id `pop_waiting` and `unblock` take
+    pub fn block(&self, keys: &[String]) -> u64 {
+        let mut state = self.inner.state.lock().unwrap();
+
+        let waiter = state.next_waiter;
+        state.next_waiter += 1;
+
+        for key in keys {
+            state.dbs[self.index]
+                .waiters
+                .entry(key.clone())
+                .or_default()
+                .push_back(waiter);
+        }
+
+        waiter
+    }
+
+    /// Pop up to `count` values from the head, or the tail if `tail` is
+    /// set, of the list at a key for a blocked client
+    ///
+    /// Only the client that has waited on the key the longest may pop,
+    /// the others get nothing until it's unblocked
+    pub fn pop_waiting(
+        &self,
+        key: &str,
+        waiter: u64,
+        count: usize,
+        tail: bool,
+    ) -> crate::Result<Vec<Bytes>> {
+        let mut state = self.inner.state.lock().unwrap();
+        let db = &mut state.dbs[self.index];
+
+        if db.waiters.get(key).and_then(|queue| queue.front()) != Some(&waiter) {
+            return Ok(vec![]);
+        }
+
+        let popped = db.pop(key, count, tail)?;
+        drop(state);
+
+        if !popped.is_empty() {
+            self.notify_write(key, if tail { "rpop" } else { "lpop" });
+        }
+
+        Ok(popped)
+    }
+
+    /// Take a client out of the queues of `keys` once it's served or
+    /// gave up, waking the client next in line
+    pub fn unblock(&self, keys: &[String], waiter: u64) {
+        let mut state = self.inner.state.lock().unwrap();
+        let db = &mut state.dbs[self.index];
+
+        for key in keys {
+            let Some(queue) = db.waiters.get_mut(key) else {
+                continue;
+            };
+
+            let was_first = queue.front() == Some(&waiter);
+            queue.retain(|id| *id != waiter);
+            if queue.is_empty() {
+                db.waiters.remove(key);
+            }
+
+            if was_first {
+                if let Some(notify) = db.notifiers.remove(key) {
+                    notify.notify_waiters();
+                }
+            }
+        }
+    }
+
+    /// Record a write to a key, every mutating method ends up here
+    /// once it has released the state lock
+    ///
+    /// Bumps the dirty counter, wakes the clients blocked on the key
+    /// and sends a keyspace notification if anyone listens for them
+    pub fn notify_write(&self, key: &str, event: &'static str) {
+        let mut state = self.inner.state.lock().unwrap();
+        let state = &mut *state;
+
+        state.dirty += 1;
+
+        // waiters fetch a fresh notifier every time they check the key
+        if let Some(notify) = state.dbs[self.index].notifiers.remove(key) {
+            notify.notify_waiters();
+        }
+
+        if let Some(tx) = &state.keyspace_tx {
+            let _ = tx.send((self.index, key.to_string(), event));
+        }
+    }
+
+    /// Number of writes since the server started
+    pub fn dirty(&self) -> u64 {
+        self.inner.state.lock().unwrap().dirty
+    }
+
+    /// Returns a receiver yielding a notification for every write,
+    /// the index of the database followed by the key and the event
+    ///
+    /// Replaces any previously returned receiver
+    pub fn keyspace_events(&self) -> mpsc::UnboundedReceiver<(usize, String, &'static str)> {
+        let (tx, rx) = mpsc::unbounded_channel();
+
+        let mut state = self.inner.state.lock().unwrap();
+        state.keyspace_tx = Some(tx);
+
+        rx
+    }
+
+    /// Notifier woken when the key is written to by a command
+    /// clients can block on
+    ///
+    /// Call `Notify::notified` before checking the key so a write in
+    /// between isn't missed
+    pub fn key_notifier(&self, key: &str) -> Arc<Notify> {
+        let mut state = self.inner.state.lock().unwrap();
+
+        state.dbs[self.index]
+            .notifiers
+            .entry(key.to_string())
+            .or_default()
+            .clone()
+    }
+
+    /// Drop the notifier of a key once nothing waits on it anymore
+    pub fn release_key_notifier(&self, key: &str) {
+        let mut state = self.inner.state.lock().unwrap();
+        let notifiers = &mut state.dbs[self.index].notifiers;
+
+        if notifiers
+            .get(key)
+            .is_some_and(|notify| Arc::strong_count(notify) == 1)
+        {
+            notifiers.remove(key);
+        }
+    }
+
+    /// Remove a key and any pending expiration
+    ///
+    /// Returns true if the key existed
+    pub fn remove(&self, key: &str) -> bool {
+        let mut state = self.inner.state.lock().unwrap();
+
+        let value = state.dbs[self.index].remove(key);
+        let removed = value.as_ref().is_some_and(|value| !value.is_expired());
+        let lazy = state.lazyfree.lazy_user_del;
+
+        drop(state);
+
+        self.inner.free(value, lazy);
+
+        if removed {
+            self.notify_write(key, "del");
+        }
+
+        removed
+    }
+
+    /// Remove every key in `keys` under a single lock so
+    /// concurrent writers never observe a partial delete
+    ///
+    /// Returns how many keys existed, a key listed twice counts once
+    pub fn remove_all(&self, keys: &[String]) -> usize {
+        let mut state = self.inner.state.lock().unwrap();
+        let db = &mut state.dbs[self.index];
+
+        let mut values = vec![];
+        let removed: Vec<&String> = keys
+            .iter()
+            .filter(|key| match db.remove(key) {
+                Some(value) => {
+                    let live = !value.is_expired();
+                    values.push(value);
+                    live
+                }
+                None => false,
+            })
+            .collect();
+        let lazy = state.lazyfree.lazy_user_del;
+
+        drop(state);
+
+        for value in values {
+            self.inner.free(Some(value), lazy);
+        }
+
+        for key in &removed {
+            self.notify_write(key, "del");
+        }
+
+        removed.len()
+    }
+
+    /// Count the live keys in `keys` under a single lock
+    ///
+    /// A key listed more than once is counted every time
+    pub fn count_existing(&self, keys: &[String]) -> usize {
+        let state = self.inner.state.lock().unwrap();
+        let db = &state.dbs[self.index];
+
+        let count = keys.iter().filter(|key| db.lookup(key).is_some()).count();
+
+        drop(state);
+
+        count
+    }
+
+    /// Set the instant a key expires at
+    ///
+    /// Returns false if the key doesn't exist
+    pub fn set_expiry(&self, key: &str, at: Instant) -> bool {
+        let mut state = self.inner.state.lock().unwrap();
+
+        let updated = state.dbs[self.index].set_expiry(key, at);
+
+        drop(state);
+
+        if updated {
+            self.notify_write(key, "expire");
+        }
+
+        updated
+    }
+
+    /// Remove the expiry of a key making it persistent
+    ///
+    /// Returns true if an expiry was removed
+    pub fn persist(&self, key: &str) -> bool {
+        let mut state = self.inner.state.lock().unwrap();
+
+        let persisted = state.dbs[self.index].persist(key);
+
+        drop(state);
+
+        if persisted {
+            self.notify_write(key, "persist");
+        }
+
+        persisted
+    }
+
+    /// Expire a key immediately and purge it from the store
+    ///
+    /// Returns false if the key doesn't exist
+    pub fn expire_now(&self, key: &str) -> bool {
+        if !self.set_expiry(key, Instant::now()) {
+            return false;
+        }
+
+        self.inner.clear_expired_keys();
+
+        true
+    }
+
+    /// Stop evicting expired keys, as replicas wait on the
+    /// master to propagate a DEL instead
+    ///
+    /// Expired keys stay hidden from reads in the meantime
+    pub fn disable_active_expiry(&self) {
+        let mut state = self.inner.state.lock().unwrap();
+        state.active_expiry = false;
+    }
+
+    /// Choose which deletions free their value in the background
+    pub fn set_lazyfree(&self, config: LazyFreeConfig) {
+        let mut state = self.inner.state.lock().unwrap();
+        state.lazyfree = config;
+    }
+
+    /// Returns a receiver yielding every key evicted on expiry
+    /// along with the index of its database
+    ///
+    /// Replaces any previously returned receiver
+    pub fn expired_keys(&self) -> mpsc::UnboundedReceiver<(usize, String)> {
+        let (tx, rx) = mpsc::unbounded_channel();
+
+        let mut state = self.inner.state.lock().unwrap();
+        state.expired_tx = Some(tx);
+
+        rx
+    }
+
+    pub fn set_repl_id(&self, replid: String) {
+        let mut state = self.inner.state.lock().unwrap();
+        let state = &mut *state;
+        state.replid = Some(replid);
+    }
+
+    pub fn get_repl_info(&self) -> (Option<String>, u64) {
+        let state = self.inner.state.lock().unwrap();
+
+        let replid = state.replid.clone();
+        let repl_offset = state.repl_offset.clone();
+
+        drop(state);
+
+        (replid, repl_offset)
+    }
+}
+
+/// Wrap the shared state scoped to db 0, unlike `Db::new`
+/// no purge task is spawned
+impl From<Arc<SharedDb>> for Db {
+    fn from(inner: Arc<SharedDb>) -> Self {
+        Db { inner, index: 0 }
+    }
+}
+
+impl SharedDb {
+    pub fn new() -> SharedDb {
+        SharedDb {
+            state: Mutex::new(State {
+                dbs: (0..DEFAULT_DATABASES)
+                    .map(|_| Keyspace::default())
+                    .collect(),
+                replid: None,
+                repl_offset: 0,
+                active_expiry: true,
+                expired_tx: None,
+                dirty: 0,
+                keyspace_tx: None,
+                next_waiter: 0,
+                lazyfree: LazyFreeConfig::default(),
+            }),
+            save_in_progress: AtomicBool::new(false),
+            lazyfree: LazyFree::new(),
+        }
+    }
+
+    /// Purge expired keys and return Instant of the next
+    /// expiration
+    pub fn clear_expired_keys(&self) -> Option<Instant> {
+        let mut guard = self.state.lock().unwrap();
+
+        let state = &mut *guard;
+
+        if !state.active_expiry {
+            return None;
+        }
+
+        let now = Instant::now();
+        let mut evicted = vec![];
+
+        for index in 0..state.dbs.len() {
+            while let Some((expires_at, key)) = state.dbs[index].expirations.iter().next() {
+                if *expires_at > now {
+                    break;
+                }
+
+                let entry = (*expires_at, key.clone());
+                state.dbs[index].expirations.remove(&entry);
+
+                // skip trackers left behind by keys that were overwritten since
+                let (expires_at, key) = entry;
+                if state.dbs[index]
+                    .entries
+                    .get(&key)
+                    .is_some_and(|value| value.expires_at == Some(expires_at))
+                {
+                    evicted.push(state.evict(index, &key));
+                }
+            }
+
+            while let Some((expires_at, key)) = state.dbs[index].field_expirations.first() {
+                if *expires_at > now {
+                    break;
+                }
+
+                let entry = (*expires_at, key.clone());
+                state.dbs[index].field_expirations.remove(&entry);
+
+                // the hash goes away along with its last field
+                if state.dbs[index].purge_fields(&entry.1) {
+                    evicted.push(state.evict(index, &entry.1));
+                }
+            }
+        }
+
+        let next = state.next_expiration();
+        let lazy = state.lazyfree.lazy_expire;
+        drop(guard);
+
+        for value in evicted {
+            self.free(value, lazy);
+        }
+
+        next
+    }
+
+    /// Free a deleted value, in the background if `lazy` is set
+    fn free(&self, value: Option<Value>, lazy: bool) {
+        match value {
+            Some(value) if lazy => self.lazyfree.free(value),
+            value => drop(value),
+        }
+    }
+}
+
+impl State {
+    /// Earliest expiration across every database
+    pub fn next_expiration(&self) -> Option<Instant> {
+        self.dbs.iter().filter_map(Keyspace::next_expiration).min()
+    }
+
+    /// Remove an expired entry and hand its key over for propagation
+    ///
+    /// Returns the value for the caller to free once the lock is released
+    #[must_use]
+    fn evict(&mut self, index: usize, key: &str) -> Option<Value> {
+        let value = self.dbs[index].remove(key);
+
+        if let Some(tx) = &self.expired_tx {
+            let _ = tx.send((index, key.to_string()));
+        }
+
+        value
+    }
+}
+
+/// Key value map split into shards by the hash of the key
+///
+/// A key always lives in the same shard, so a walk over the keyspace
+/// can copy one shard at a time and release the lock in between
+/// without losing its place when the other shards are written to
+#[derive(Debug)]
+struct Entries {
+    shards: Vec<HashMap<String, Value>>,
+}
+
+impl Default for Entries {
+    fn default() -> Self {
+        Entries {
+            shards: (0..ENTRY_SHARDS).map(|_| HashMap::new()).collect(),
+        }
+    }
+}
+
+impl FromIterator<(String, Value)> for Entries {
+    fn from_iter<I: IntoIterator<Item = (String, Value)>>(iter: I) -> Self {
+        let mut entries = Entries::default();
+        for (key, value) in iter {
+            entries.insert(key, value);
+        }
+        entries
+    }
+}
+
+impl Entries {
+    /// Shard a key lives in, picked with FNV-1a since it's hashed
+    /// again by the shard. Keys crafted to share a shard only make
+    /// the walk copy more of them per batch
+    fn shard(&self, key: &str) -> usize {
+        let hash = key.bytes().fold(0xcbf29ce484222325u64, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+        });
+        hash as usize % self.shards.len()
+    }
+
+    fn get(&self, key: &str) -> Option<&Value> {
+        self.shards[self.shard(key)].get(key)
+    }
+
+    fn get_mut(&mut self, key: &str) -> Option<&mut Value> {
+        let shard = self.shard(key);
+        self.shards[shard].get_mut(key)
+    }
+
+    fn contains_key(&self, key: &str) -> bool {
+        self.shards[self.shard(key)].contains_key(key)
+    }
+
+    fn insert(&mut self, key: String, value: Value) -> Option<Value> {
+        let shard = self.shard(&key);
+        self.shards[shard].insert(key, value)
+    }
+
+    fn remove(&mut self, key: &str) -> Option<Value> {
+        let shard = self.shard(key);
+        self.shards[shard].remove(key)
+    }
+
+    fn entry(&mut self, key: String) -> Entry<'_, String, Value> {
+        let shard = self.shard(&key);
+        self.shards[shard].entry(key)
+    }
+
+    fn iter(&self) -> impl Iterator<Item = (&String, &Value)> {
+        self.shards.iter().flatten()
+    }
+}
+
+impl Keyspace {
+    pub fn next_expiration(&self) -> Option<Instant> {
+        let key = self.expirations.iter().next().map(|entry| entry.0);
+        let field = self.field_expirations.first().map(|entry| entry.0);
+
+        key.into_iter().chain(field).min()
+    }
+
+    /// Drop the expired fields of the hash at a key, returns true
+    /// if no field is left
+    fn purge_fields(&mut self, key: &str) -> bool {
+        match self.entries.get_mut(key) {
+            Some(Value {
+                data: ValueType::Hash(hash),
+                ..
+            }) => {
+                hash.purge_expired();
+                hash.is_empty()
+            }
+            _ => false,
+        }
+    }
+
+    /// Live entry stored at a key, expired entries that weren't
+    /// purged yet are skipped
+    fn lookup(&self, key: &str) -> Option<&Value> {
+        self.entries.get(key).filter(|value| !value.is_expired())
+    }
+
+    /// Replace the expiration of a live entry, keeping the
+    /// expiration tracker in sync
+    fn set_expiry(&mut self, key: &str, at: Instant) -> bool {
+        let value = match self.entries.get_mut(key) {
+            Some(value) if !value.is_expired() => value,
+            _ => return false,
+        };
+
+        if let Some(expires_at) = value.expires_at.replace(at) {
+            self.expirations.remove(&(expires_at, key.to_string()));
+        }
+        self.expirations.insert((at, key.to_string()));
+
+        true
+    }
+
+    /// Clear the expiration of a live entry, the tracker is keyed
+    /// on the current expiry so it's taken off the value first
+    fn persist(&mut self, key: &str) -> bool {
+        let expires_at = match self.entries.get_mut(key) {
+            Some(value) if !value.is_expired() => value.expires_at.take(),
+            _ => return false,
+        };
+
+        match expires_at {
+            Some(expires_at) => {
+                self.expirations.remove(&(expires_at, key.to_string()));
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Insert an entry, replacing any previous value along with
+    /// its expiration tracker
+    fn insert(&mut self, key: String, value: Value) {
+        self.remove(&key);
+
+        // insert expires_at into expiration tracker
+        // when key expires it'll automatically be removed later
+        if let Some(expiry) = value.expires_at {
+            self.expirations.insert((expiry, key.clone()));
+        }
+
+        // Insert key value entry into store
+        self.entries.insert(key, value);
+    }
+
+    /// Pop up to `count` values from the head, or the tail if `tail`
+    /// is set, of a live list, deleting the key once it's empty
+    fn pop(&mut self, key: &str, count: usize, tail: bool) -> crate::Result<Vec<Bytes>> {
+        let list = match self.entries.get_mut(key) {
+            Some(value) if value.is_expired() => return Ok(vec![]),
+            Some(Value {
+                data: ValueType::List(list),
+                ..
+            }) => list,
+            Some(_) => return Err(CommandError::WrongType.into()),
+            None => return Ok(vec![]),
+        };
+
+        let popped: Vec<Bytes> = (0..count)
+            .map_while(|_| {
+                if tail {
+                    list.pop_back()
+                } else {
+                    list.pop_front()
+                }
+            })
+            .collect();
+
+        if list.is_empty() {
+            self.remove(key);
+        }
+
+        Ok(popped)
+    }
+
+    /// Pop up to `count` of the lowest, or highest if `max` is set,
+    /// scored members of the sorted set at a key, deleting the key
+    /// once the sorted set is empty
+    fn zpop(&mut self, key: &str, count: usize, max: bool) -> crate::Result<Vec<(String, f64)>> {
+        let zset = match self.entries.get_mut(key) {
+            Some(value) if value.is_expired() => return Ok(vec![]),
+            Some(Value {
+                data: ValueType::ZSet(zset),
+                ..
+            }) => zset,
+            Some(_) => return Err(CommandError::WrongType.into()),
+            None => return Ok(vec![]),
+        };
+
+        let popped: Vec<(String, f64)> = (0..count)
+            .map_while(|_| if max { zset.pop_max() } else { zset.pop_min() })
+            .collect();
+
+        if zset.is_empty() {
+            self.remove(key);
+        }
+
+        Ok(popped)
+    }
+
+    /// Remove an entry along with its expiration tracker
+    fn remove(&mut self, key: &str) -> Option<Value> {
+        let value = self.entries.remove(key)?;
+
+        if let Some(expires_at) = value.expires_at {
+            self.expirations.remove(&(expires_at, key.to_string()));
+        }
+
+        Some(value)
+    }
+}
+
+/// Purge expired keys from the cache until `shutdown` fires or the
+/// db is dropped
+///
+/// Only a weak reference is held so the task doesn't keep the db alive
+pub(crate) async fn purge_expired_keys(shared_db: Weak<SharedDb>, mut shutdown: Option<Shutdown>) {
+    // run a loop
+    // wait for the next instant in the expiry and remove expired keys
+    // from the cache
+    loop {
+        let Some(shared) = shared_db.upgrade() else {
+            return;
+        };
+        // expired entries are purged and the next expiry is returned,
+        // wait until then to purge state again
+        let when = shared
+            .clear_expired_keys()
+            .unwrap_or_else(|| Instant::now() + Duration::from_millis(10));
+        drop(shared);
+
+        match shutdown.as_mut() {
+            Some(shutdown) => tokio::select! {
+                _ = tokio::time::sleep_until(when) => {}
+                _ = shutdown.recv() => return,
+            },
+            None => tokio::time::sleep_until(when).await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use bytes::Bytes;
+    use tokio::time::Duration;
+
+    use super::{Db, SharedDb};
+    use crate::{
+        command::{
+            append::Append, del::Del, expire::Expire, get::Get, incr::Incr, rename::Rename,
+            set::Set, setnx::SetNx,
+        },
+        config::{LazyFreeConfig, ServerConfig},
+        resp::RESP,
+        ValueType,
+    };
+
+    /// A list large enough to be freed in the background
+    fn large_list() -> ValueType {
+        ValueType::List((0..1000).map(|i| Bytes::from(i.to_string())).collect())
+    }
+
+    /// Wait for the lazyfree thread to have freed `count` values
+    async fn wait_freed(db: &Db, count: u64) {
+        let freed = async {
+            while db.inner.lazyfree.freed() < count {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+        };
+        tokio::time::timeout(Duration::from_secs(1), freed)
+            .await
+            .expect("the value was never freed in the background");
+        assert_eq!(db.inner.lazyfree.pending(), 0);
+    }
+
+    #[tokio::test]
+    async fn lazy_user_del_frees_large_values_in_the_background() {
+        let db = Db::new();
+
+        // off by default, the value is freed inline
+        db.set("inline".into(), large_list(), None);
+        assert_eq!(db.remove_all(&["inline".into()]), 1);
+
+        db.set_lazyfree(LazyFreeConfig {
+            lazy_user_del: true,
+            ..Default::default()
+        });
+
+        // small values aren't worth the trip
+        db.set("small".into(), ValueType::String(Bytes::from("1")), None);
+        assert_eq!(db.remove_all(&["small".into()]), 1);
+        assert_eq!(db.inner.lazyfree.freed(), 0);
+
+        db.set("large".into(), large_list(), None);
+        assert_eq!(db.remove_all(&["large".into()]), 1);
+        assert!(db.get("large").is_none());
+        wait_freed(&db, 1).await;
+    }
+
+    #[tokio::test]
+    async fn lazy_expire_frees_expired_values_in_the_background() {
+        let db = Db::new();
+        db.set_lazyfree(LazyFreeConfig {
+            lazy_expire: true,
+            ..Default::default()
+        });
+
+        db.set(
+            "large".into(),
+            large_list(),
+            Some(Duration::from_millis(10)),
+        );
+        wait_freed(&db, 1).await;
+        assert!(db.keys().is_empty());
+    }
+
+    #[tokio::test]
+    async fn replica_keeps_expired_keys_for_the_master_del() {
+        let db = Db::new();
+        db.disable_active_expiry();
+        let mut expired = db.expired_keys();
+
+        db.set(
+            "key".into(),
+            ValueType::String(Bytes::from("1")),
+            Some(Duration::from_millis(1)),
+        );
+        tokio::time::sleep(Duration::from_millis(20)).await;
+
+        // hidden from reads but only dropped once the master says so
+        assert!(db.get("key").is_none());
+        assert!(db.keys().is_empty());
+        assert!(db.inner.state.lock().unwrap().dbs[0]
+            .entries
+            .contains_key("key"));
+        assert!(expired.try_recv().is_err());
+
+        db.remove("key");
+        assert!(!db.inner.state.lock().unwrap().dbs[0]
+            .entries
+            .contains_key("key"));
+    }
+
+    #[tokio::test]
+    async fn lazy_expiry_evicts_and_notifies() {
+        let db = Db::new();
+        let mut expired = db.expired_keys();
+
+        db.set(
+            "key".into(),
+            ValueType::String(Bytes::from("1")),
+            Some(Duration::from_millis(1)),
+        );
+        tokio::time::sleep(Duration::from_millis(5)).await;
+
+        assert!(db.get("key").is_none());
+        assert_eq!(expired.recv().await.unwrap(), (0, "key".to_string()));
+        assert!(db.keys().is_empty());
+    }
+
+    #[tokio::test]
+    async fn databases_are_isolated() {
+        let db = Db::new();
+        let other = db.with_index(3);
+        let mut expired = db.expired_keys();
+
+        db.set("key".into(), ValueType::String(Bytes::from("0")), None);
+        other.set(
+            "key".into(),
+            ValueType::String(Bytes::from("3")),
+            Some(Duration::from_millis(1)),
+        );
+        other.set("only".into(), ValueType::String(Bytes::from("3")), None);
+
+        assert!(matches!(db.get("key"), Some(ValueType::String(v)) if v == "0"));
+        assert!(db.get("only").is_none());
+        assert_eq!(db.keys(), vec!["key".to_string()]);
+
+        // expiring a key in one database leaves the others alone
+        assert_eq!(expired.recv().await.unwrap(), (3, "key".to_string()));
+        assert!(other.get("key").is_none());
+        assert!(db.get("key").is_some());
+        assert_eq!(other.keys(), vec!["only".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn every_write_bumps_the_dirty_counter_once() {
+        let db = Db::new();
+        let mut events = db.keyspace_events();
+
+        Set::new("a".into(), Bytes::from("1"), None)
+            .apply(&db)
+            .await
+            .unwrap();
+        Incr::new("a".into()).apply(&db).await.unwrap();
+        let config = ServerConfig::for_test();
+        Append::new("b".into(), Bytes::from("x"))
+            .apply(&db, config)
+            .await
+            .unwrap();
+        Expire::new("a".into(), 100).apply(&db).await.unwrap();
+        Rename::new("b".into(), "c".into())
+            .apply(&db)
+            .await
+            .unwrap();
+        db.zadd("z".into(), vec![(1.0, "m".into())]).unwrap();
+        Del::new(vec!["a".into(), "missing".into()])
+            .apply(&db)
+            .await
+            .unwrap();
+        assert_eq!(db.dirty(), 7);
+
+        // commands that end up not writing anything don't count
+        SetNx::new("c".into(), Bytes::from("1"))
+            .apply(&db)
+            .await
+            .unwrap();
+        Del::new(vec!["missing".into()]).apply(&db).await.unwrap();
+        assert_eq!(db.dirty(), 7);
+
+        let mut received = vec![];
+        while let Ok((index, key, event)) = events.try_recv() {
+            assert_eq!(index, 0);
+            received.push((key, event));
+        }
+        let expected = [
+            ("a", "set"),
+            ("a", "incrby"),
+            ("b", "append"),
+            ("a", "expire"),
+            ("c", "rename_to"),
+            ("z", "zadd"),
+            ("a", "del"),
+        ];
+        assert_eq!(received.len(), expected.len());
+        for ((key, event), (expected_key, expected_event)) in received.iter().zip(expected) {
+            assert_eq!((key.as_str(), *event), (expected_key, expected_event));
+        }
+    }
+
+    #[tokio::test]
+    async fn key_walk_tolerates_writes_between_batches() {
+        let db = Db::new();
+        let total = super::SCAN_BATCH * 3;
+        for i in 0..total {
+            db.set(
+                format!("key:{i}"),
+                ValueType::String(Bytes::from("1")),
+                None,
+            );
+        }
+
+        let mut seen = vec![];
+        for (i, batch) in db.key_batches().enumerate() {
+            seen.extend(batch);
+            // would deadlock if the walk still held the lock
+            db.set(
+                format!("new:{i}"),
+                ValueType::String(Bytes::from("1")),
+                None,
+            );
+            db.remove("key:0");
+        }
+
+        // keys present for the whole walk are returned exactly once
+        assert!((1..total).all(|i| {
+            let key = format!("key:{i}");
+            seen.iter().filter(|seen| **seen == key).count() == 1
+        }));
+    }
+
+    #[tokio::test]
+    async fn key_walk_copies_bounded_batches() {
+        let db = Db::new();
+        let total = super::SCAN_BATCH * 20;
+        for i in 0..total {
+            db.set(
+                format!("key:{i}"),
+                ValueType::String(Bytes::from("1")),
+                None,
+            );
+        }
+        let largest_shard = db.inner.state.lock().unwrap().dbs[0]
+            .entries
+            .shards
+            .iter()
+            .map(|shard| shard.len())
+            .max()
+            .unwrap();
+
+        let batches = db.key_batches().collect::<Vec<_>>();
+
+        // the lock is never held to copy more than a batch and a shard
+        assert!(batches.len() > 1);
+        assert!(batches
+            .iter()
+            .all(|batch| batch.len() < super::SCAN_BATCH + largest_shard));
+        assert_eq!(batches.concat().len(), total);
+    }
+
+    /// Wait for the purge task to let go of its reference to the db
+    async fn wait_purge_exit(db: &Db) {
+        let exited = async {
+            while Arc::weak_count(&db.inner) > 0 {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+        };
+        tokio::time::timeout(Duration::from_secs(1), exited)
+            .await
+            .expect("the purge task is still running");
+    }
+
+    #[tokio::test]
+    async fn purge_task_exits_on_shutdown() {
+        let (notify_shutdown, _) = tokio::sync::broadcast::channel::<()>(1);
+        let db = Db::with_shutdown(notify_shutdown.subscribe());
+        // the purge task holds the only weak reference
+        assert_eq!(Arc::weak_count(&db.inner), 1);
+
+        drop(notify_shutdown);
+        wait_purge_exit(&db).await;
+
+        // the db outlives the task
+        db.set("key".into(), ValueType::String(Bytes::from("v")), None);
+        assert!(db.get("key").is_some());
+    }
+
+    #[tokio::test]
+    async fn get_hides_keys_past_their_ttl_before_any_purge() {
+        // no purge task runs for this db, only the read can expire the key
+        let db = Db::from(Arc::new(SharedDb::new()));
+
+        Set::new(
+            "key".into(),
+            Bytes::from("v"),
+            Some(Duration::from_millis(10)),
+        )
+        .apply(&db)
+        .await
+        .unwrap();
+        tokio::time::sleep(Duration::from_millis(20)).await;
+
+        let resp = Get::new("key".into()).apply(&db).await.unwrap();
+        assert!(matches!(resp, Some(RESP::Null)));
+
+        // the read evicted the entry along with its expiration
+        let state = db.inner.state.lock().unwrap();
+        assert!(!state.dbs[0].entries.contains_key("key"));
+        assert!(state.dbs[0].expirations.is_empty());
+    }
+
+    #[tokio::test]
+    async fn load_tracks_the_field_ttls_of_hashes() {
+        let mut hash = crate::Hash::from(std::collections::HashMap::from([
+            ("a".to_string(), "1".to_string()),
+            ("b".to_string(), "2".to_string()),
+        ]));
+        let at = tokio::time::Instant::now() + Duration::from_secs(100);
+        hash.expire("a", at);
+        hash.expire("b", at + Duration::from_secs(1));
+
+        let mut database = crate::rdb::DerivedDatabase::default();
+        database.entries.insert(
+            "h".into(),
+            crate::Value {
+                data: ValueType::Hash(hash),
+                _created_at: tokio::time::Instant::now(),
+                expires_at: None,
+            },
+        );
+
+        let db = Db::new();
+        db.load(1, database);
+
+        let state = db.inner.state.lock().unwrap();
+        let trackers: Vec<_> = state.dbs[1].field_expirations.iter().cloned().collect();
+        assert_eq!(
+            trackers,
+            [
+                (at, "h".to_string()),
+                (at + Duration::from_secs(1), "h".to_string())
+            ]
+        );
     }
 }
@@ -0,0 +1,181 @@
+use std::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+use tokio::{
+    sync::{Notify, RwLock},
+    time::{self, Instant},
+};
+
+use crate::{connection::Connection, replication::count_synced};
+
+/// Where a write has made it to, as reported once a barrier releases
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Durability {
+    /// the write was fsynced to the local append only file
+    pub local: bool,
+    /// number of replicas that acknowledged the write
+    pub replicas: u64,
+}
+
+/// Blocks WAIT-style commands until a replication offset is fsynced
+/// locally and/or acknowledged by enough replicas
+///
+/// Replica ACKs and local fsyncs both wake the waiters through the
+/// same notifier, waiters check both conditions again on every wake.
+/// There's no append only file yet, nothing records an fsync outside
+/// of tests so waiting on a local fsync runs out the timeout
+#[derive(Debug, Default)]
+pub struct DurabilityBarrier {
+    /// offset the local append only file was last fsynced at
+    fsynced: AtomicU64,
+    /// woken on every replica ACK and local fsync
+    progress: Notify,
+}
+
+impl DurabilityBarrier {
+    pub fn new() -> DurabilityBarrier {
+        DurabilityBarrier::default()
+    }
+
+    /// Record that the local append only file is fsynced up to `offset`
+    pub fn record_fsync(&self, offset: u64) {
+        self.fsynced.fetch_max(offset, Ordering::SeqCst);
+        self.progress.notify_waiters();
+    }
+
+    /// Wake the waiters after a replica acknowledged an offset, see
+    /// `Connection::record_ack`
+    pub fn record_ack(&self) {
+        self.progress.notify_waiters();
+    }
+
+    /// Offset the local append only file was last fsynced at
+    pub fn fsynced_offset(&self) -> u64 {
+        self.fsynced.load(Ordering::SeqCst)
+    }
+
+    /// Wait until `target_offset` is fsynced locally if `local` is set
+    /// and acknowledged by `needed` replicas, or the timeout runs out,
+    /// `None` waits without a timeout
+    pub async fn wait(
+        &self,
+        replicas: &RwLock<Vec<Connection>>,
+        target_offset: u64,
+        local: bool,
+        needed: u64,
+        timeout: Option<Duration>,
+    ) -> Durability {
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+
+        loop {
+            // registered before checking so progress made in between
+            // isn't missed
+            let progress = self.progress.notified();
+            tokio::pin!(progress);
+            progress.as_mut().enable();
+
+            let durability = Durability {
+                local: self.fsynced_offset() >= target_offset,
+                replicas: count_synced(replicas, target_offset).await,
+            };
+            if (durability.local || !local) && durability.replicas >= needed {
+                return durability;
+            }
+
+            match deadline {
+                Some(deadline) if deadline <= Instant::now() => return durability,
+                Some(deadline) => {
+                    tokio::select! {
+                        _ = progress => {}
+                        _ = time::sleep_until(deadline) => {}
+                    }
+                }
+                None => progress.await,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{sync::Arc, time::Duration};
+
+    use tokio::{
+        io::{duplex, AsyncWriteExt},
+        sync::RwLock,
+    };
+
+    use super::{Durability, DurabilityBarrier};
+    use crate::{connection::Connection, spawn_ack_reader};
+
+    #[tokio::test]
+    async fn releases_once_fsynced_and_acked() {
+        let barrier = Arc::new(DurabilityBarrier::new());
+        let (stream, mut replica_end) = duplex(1024);
+        let mut connection = Connection::from_stream(stream, false);
+        connection.is_replica = true;
+        let replicas = Arc::new(RwLock::new(vec![spawn_ack_reader(
+            connection,
+            barrier.clone(),
+        )]));
+
+        let waiter = tokio::spawn({
+            let (barrier, replicas) = (barrier.clone(), replicas.clone());
+            async move { barrier.wait(&replicas, 42, true, 1, None).await }
+        });
+
+        // the replica acks but the write isn't fsynced locally yet
+        replica_end
+            .write_all(b"*3\r\n$8\r\nREPLCONF\r\n$3\r\nACK\r\n$2\r\n42\r\n")
+            .await
+            .unwrap();
+        let acked = async {
+            while replicas.read().await[0].acked_offset() != 42 {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+        };
+        tokio::time::timeout(Duration::from_secs(1), acked)
+            .await
+            .expect("the ACK was never recorded");
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(!waiter.is_finished());
+
+        // an fsync short of the target doesn't release it either
+        barrier.record_fsync(41);
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(!waiter.is_finished());
+
+        barrier.record_fsync(42);
+        let durability = tokio::time::timeout(Duration::from_secs(1), waiter)
+            .await
+            .expect("the barrier didn't release")
+            .unwrap();
+        assert_eq!(
+            durability,
+            Durability {
+                local: true,
+                replicas: 1
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn reports_partial_progress_at_the_timeout() {
+        let barrier = DurabilityBarrier::new();
+        let replicas = RwLock::new(vec![]);
+        barrier.record_fsync(10);
+
+        let durability = barrier
+            .wait(&replicas, 10, true, 1, Some(Duration::from_millis(50)))
+            .await;
+        assert_eq!(
+            durability,
+            Durability {
+                local: true,
+                replicas: 0
+            }
+        );
+    }
+}
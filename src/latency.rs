@@ -0,0 +1,78 @@
+use std::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+/// Latency samples recorded for a single class of events
+///
+/// All counters are atomics so recording a sample never takes
+/// a lock and can be shared freely between connection handlers
+#[derive(Debug, Default)]
+pub struct LatencyStats {
+    calls: AtomicU64,
+    total_usec: AtomicU64,
+    max_usec: AtomicU64,
+    last_usec: AtomicU64,
+}
+
+impl LatencyStats {
+    pub fn new() -> LatencyStats {
+        LatencyStats::default()
+    }
+
+    /// Record a single latency sample
+    pub fn record(&self, elapsed: Duration) {
+        let usec = elapsed.as_micros() as u64;
+
+        self.calls.fetch_add(1, Ordering::Relaxed);
+        self.total_usec.fetch_add(usec, Ordering::Relaxed);
+        self.max_usec.fetch_max(usec, Ordering::Relaxed);
+        self.last_usec.store(usec, Ordering::Relaxed);
+    }
+
+    /// Number of samples recorded so far
+    pub fn calls(&self) -> u64 {
+        self.calls.load(Ordering::Relaxed)
+    }
+
+    /// Latency of the most recent sample
+    pub fn last(&self) -> Duration {
+        Duration::from_micros(self.last_usec.load(Ordering::Relaxed))
+    }
+
+    /// Highest latency observed
+    pub fn max(&self) -> Duration {
+        Duration::from_micros(self.max_usec.load(Ordering::Relaxed))
+    }
+
+    /// Average latency over all recorded samples
+    pub fn average(&self) -> Duration {
+        let calls = self.calls();
+        if calls == 0 {
+            return Duration::ZERO;
+        }
+
+        Duration::from_micros(self.total_usec.load(Ordering::Relaxed) / calls)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::LatencyStats;
+
+    #[test]
+    fn record_samples() {
+        let stats = LatencyStats::new();
+        assert_eq!(stats.average(), Duration::ZERO);
+
+        stats.record(Duration::from_micros(100));
+        stats.record(Duration::from_micros(300));
+
+        assert_eq!(stats.calls(), 2);
+        assert_eq!(stats.last(), Duration::from_micros(300));
+        assert_eq!(stats.max(), Duration::from_micros(300));
+        assert_eq!(stats.average(), Duration::from_micros(200));
+    }
+}
@@ -0,0 +1,91 @@
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        mpsc, Arc,
+    },
+    thread,
+};
+
+use crate::{Value, ValueType};
+
+/// Values made of more elements than this are worth handing to the
+/// background thread, smaller ones are freed inline, same as redis
+pub const LAZYFREE_THRESHOLD: usize = 64;
+
+/// Frees deleted values on a thread of its own so dropping a large
+/// value doesn't stall the command that deleted it
+///
+/// The thread exits once the queue is dropped
+#[derive(Debug)]
+pub struct LazyFree {
+    tx: mpsc::Sender<Value>,
+    /// values queued but not freed yet
+    pending: Arc<AtomicU64>,
+    /// values the thread freed since startup
+    freed: Arc<AtomicU64>,
+}
+
+impl LazyFree {
+    pub fn new() -> LazyFree {
+        let (tx, rx) = mpsc::channel::<Value>();
+        let pending = Arc::new(AtomicU64::new(0));
+        let freed = Arc::new(AtomicU64::new(0));
+
+        let (thread_pending, thread_freed) = (pending.clone(), freed.clone());
+        thread::Builder::new()
+            .name("lazyfree".into())
+            .spawn(move || {
+                for value in rx {
+                    drop(value);
+                    thread_pending.fetch_sub(1, Ordering::SeqCst);
+                    thread_freed.fetch_add(1, Ordering::SeqCst);
+                }
+            })
+            .expect("failed to spawn the lazyfree thread");
+
+        LazyFree { tx, pending, freed }
+    }
+
+    /// Free `value` in the background if it's large enough to be worth
+    /// it, otherwise right away
+    pub fn free(&self, value: Value) {
+        if free_effort(&value.data) <= LAZYFREE_THRESHOLD {
+            return;
+        }
+
+        self.pending.fetch_add(1, Ordering::SeqCst);
+        if let Err(mpsc::SendError(value)) = self.tx.send(value) {
+            // the thread is gone, there's nothing left to do but free it here
+            self.pending.fetch_sub(1, Ordering::SeqCst);
+            drop(value);
+        }
+    }
+
+    /// Number of values waiting to be freed
+    pub fn pending(&self) -> u64 {
+        self.pending.load(Ordering::SeqCst)
+    }
+
+    /// Number of values freed in the background
+    pub fn freed(&self) -> u64 {
+        self.freed.load(Ordering::SeqCst)
+    }
+}
+
+impl Default for LazyFree {
+    fn default() -> Self {
+        LazyFree::new()
+    }
+}
+
+/// Rough cost of freeing a value, the number of allocations it holds
+fn free_effort(value: &ValueType) -> usize {
+    match value {
+        ValueType::String(_) => 1,
+        ValueType::List(list) => list.len(),
+        ValueType::Set(set) => set.len(),
+        ValueType::Hash(hash) => hash.len(),
+        ValueType::ZSet(zset) => zset.len(),
+        ValueType::Stream(stream) => stream.entries.len(),
+    }
+}
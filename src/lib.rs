@@ -2,6 +2,10 @@ pub mod command;
 pub mod config;
 pub mod connection;
 pub mod db;
+pub mod durability;
+pub mod latency;
+pub mod lazyfree;
+pub mod pause;
 pub mod rdb;
 pub mod replication;
 pub mod resp;
@@ -0,0 +1,97 @@
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use tokio::{sync::Notify, time};
+
+/// Commands held back by a `CLIENT PAUSE`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum PauseMode {
+    /// Only commands that write to the keyspace
+    Write,
+    /// Every client command
+    All,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Pause {
+    until: Instant,
+    mode: PauseMode,
+}
+
+/// Pause shared between connection handlers
+///
+/// Handlers wait on it before applying a command, waiters are woken
+/// when the pause changes and otherwise sleep until it runs out
+#[derive(Debug, Default)]
+pub struct ClientPause {
+    pause: Mutex<Option<Pause>>,
+    changed: Notify,
+}
+
+impl ClientPause {
+    pub fn new() -> ClientPause {
+        ClientPause::default()
+    }
+
+    /// Pause commands affected by `mode` for `duration`
+    ///
+    /// Like redis an active pause is never shortened or weakened,
+    /// the latest deadline and the strictest mode win
+    pub fn pause(&self, duration: Duration, mode: PauseMode) {
+        let mut pause = self.pause.lock().unwrap();
+        let until = Instant::now() + duration;
+
+        *pause = match *pause {
+            Some(active) if active.until > Instant::now() => Some(Pause {
+                until: until.max(active.until),
+                mode: mode.max(active.mode),
+            }),
+            _ => Some(Pause { until, mode }),
+        };
+        drop(pause);
+
+        self.changed.notify_waiters();
+    }
+
+    /// Lift the pause right away
+    pub fn unpause(&self) {
+        self.pause.lock().unwrap().take();
+
+        self.changed.notify_waiters();
+    }
+
+    /// Wait until a command is allowed to run, `is_write` tells
+    /// whether the command writes to the keyspace
+    pub async fn wait(&self, is_write: bool) {
+        loop {
+            // registered before the check so an unpause in between isn't missed
+            let changed = self.changed.notified();
+
+            let until = match self.paused_until(is_write) {
+                Some(until) => until,
+                None => return,
+            };
+
+            tokio::select! {
+                _ = changed => {}
+                _ = time::sleep_until(until.into()) => {}
+            }
+        }
+    }
+
+    /// Deadline of the pause holding back the command if any
+    fn paused_until(&self, is_write: bool) -> Option<Instant> {
+        let mut pause = self.pause.lock().unwrap();
+
+        match *pause {
+            Some(active) if active.until <= Instant::now() => {
+                pause.take();
+                None
+            }
+            Some(active) if active.mode == PauseMode::All || is_write => Some(active.until),
+            _ => None,
+        }
+    }
+}
@@ -1,14 +1,12 @@
 #![allow(unused_variables)]
 
 use std::{
-    cell::RefCell,
-    collections::{BTreeSet, HashMap},
-    ops::{Add, Sub},
+    cell::{Cell, RefCell},
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
     time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
-use crate::Value;
-use bytes::Bytes;
+use crate::{Hash, Set, Value, ValueType};
 use tokio::time::Instant;
 
 // database
@@ -30,40 +28,37 @@ pub struct DerivedDatabase {
     pub expirations: BTreeSet<(Instant, String)>,
 }
 
+/// Instant of a unix timestamp in milliseconds, one too far in the past
+/// to represent is clamped to now as whatever it times out is expired
+/// either way
+pub(crate) fn instant_from_unix_millis(millis: u64) -> Instant {
+    let elasped = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("Time since epoch not")
+        .as_millis();
+
+    let now = tokio::time::Instant::now();
+
+    // offset now by how far the unix time is from the current time
+    if elasped > millis as u128 {
+        let diff = Duration::from_millis((elasped - millis as u128) as u64);
+
+        now.checked_sub(diff).unwrap_or(now)
+    } else {
+        let diff = Duration::from_millis(millis - elasped as u64);
+
+        now + diff
+    }
+}
+
 impl Database {
-    fn set(&self, key: String, value: Vec<u8>, expiry: Option<u64>) {
-        let expire_at = {
-            if expiry.is_none() {
-                None
-            } else {
-                let now = SystemTime::now();
-                let elasped = now
-                    .duration_since(UNIX_EPOCH)
-                    .expect("Time since epoch not")
-                    .as_millis();
-
-                let now = tokio::time::Instant::now();
-
-                let expires_at = if elasped > expiry.unwrap() as u128 {
-                    let diff = Duration::from_millis((elasped - expiry.unwrap() as u128) as u64);
-                    let dur = Duration::from_millis(elasped as u64).sub(diff);
-
-                    now - dur
-                } else {
-                    let diff = Duration::from_millis(expiry.unwrap() - elasped as u64);
-                    let dur = Duration::from_millis(elasped as u64).add(diff);
-
-                    now + dur
-                };
-
-                Some(expires_at)
-            }
-        };
+    fn set(&self, key: String, value: ValueType, expiry: Option<u64>) {
+        let expire_at = expiry.map(instant_from_unix_millis);
 
         self.hash.borrow_mut().insert(
             key.clone(),
             Value {
-                data: crate::ValueType::String(Bytes::from(value)),
+                data: value,
                 _created_at: Instant::now(),
                 expires_at: expire_at,
             },
@@ -86,9 +81,11 @@ impl Database {
 
 #[derive(Debug, Default)]
 pub struct RdbBuilder {
-    pub databases: RefCell<Vec<Database>>,
+    /// Databases finished so far by their index
+    pub databases: RefCell<BTreeMap<u32, Database>>,
     pub aux: RefCell<HashMap<String, String>>,
     pub current_db: RefCell<Option<Database>>,
+    pub current_index: Cell<u32>,
     pub db_size: u32,
     pub expire_size: u32,
 }
@@ -97,16 +94,40 @@ pub trait Builder {
     fn start_rdb(&self) {}
     fn end_rdb(&self) {}
 
-    fn start_database(&self) {}
+    fn start_database(&self, index: u32) {}
     fn end_database(&self) {}
 
-    fn resizedb(&self, db_size: u32, expiry_size: u32) {}
-    fn set(&self, key: String, value: Vec<u8>, expire_time: Option<u64>) {}
+    fn resizedb(&self, db_size: u64, expiry_size: u64) {}
+    fn set(&self, key: String, value: ValueType, expire_time: Option<u64>) {}
+    fn set_set(&self, key: String, members: HashSet<String>, expire_time: Option<u64>) {
+        self.set(key, ValueType::Set(Set::from(members)), expire_time)
+    }
+    fn set_hash(&self, key: String, hash: HashMap<String, String>, expire_time: Option<u64>) {
+        self.set(key, ValueType::Hash(Hash::from(hash)), expire_time)
+    }
+    /// Set a hash whose fields in `field_expirations` expire at the
+    /// given unix time in milliseconds
+    fn set_hash_with_ttls(
+        &self,
+        key: String,
+        hash: HashMap<String, String>,
+        field_expirations: HashMap<String, u64>,
+        expire_time: Option<u64>,
+    ) {
+        let mut hash = Hash::from(hash);
+        for (field, at) in field_expirations {
+            hash.expire(&field, instant_from_unix_millis(at));
+        }
+        self.set(key, ValueType::Hash(hash), expire_time)
+    }
     fn set_aux_field(&self, key: String, value: String) {}
 
     fn checksum(&self) {}
 
     fn get_database(&self) -> Option<DerivedDatabase>;
+
+    /// Every database read along with its index
+    fn get_databases(&self) -> Vec<(u32, DerivedDatabase)>;
 }
 
 impl Builder for RdbBuilder {
@@ -118,9 +139,16 @@ impl Builder for RdbBuilder {
         unimplemented!()
     }
 
-    fn start_database(&self) {
+    fn start_database(&self, index: u32) {
         // println!("Start database");
-        *self.current_db.borrow_mut() = Some(Database::default());
+        // the database read so far is kept, a file only selects each
+        // database once
+        if let Some(database) = self.current_db.borrow_mut().replace(Database::default()) {
+            self.databases
+                .borrow_mut()
+                .insert(self.current_index.get(), database);
+        }
+        self.current_index.set(index);
     }
 
     fn end_database(&self) {
@@ -130,12 +158,12 @@ impl Builder for RdbBuilder {
         //     .push(self.current_db.borrow_mut().clone());
     }
 
-    fn resizedb(&self, db_size: u32, expiry_size: u32) {
+    fn resizedb(&self, db_size: u64, expiry_size: u64) {
         // todo!()
         println!("Resize DB--- Entries: {db_size}, Expirations: {expiry_size}")
     }
 
-    fn set(&self, key: String, value: Vec<u8>, expire_time: Option<u64>) {
+    fn set(&self, key: String, value: ValueType, expire_time: Option<u64>) {
         self.current_db
             .borrow_mut()
             .as_mut()
@@ -158,4 +186,18 @@ impl Builder for RdbBuilder {
 
         Some(self.current_db.borrow_mut().as_mut().unwrap().get_db())
     }
+
+    fn get_databases(&self) -> Vec<(u32, DerivedDatabase)> {
+        let mut databases: Vec<(u32, DerivedDatabase)> = self
+            .databases
+            .borrow()
+            .iter()
+            .map(|(index, database)| (*index, database.get_db()))
+            .collect();
+        if let Some(database) = self.get_database() {
+            databases.push((self.current_index.get(), database));
+        }
+
+        databases
+    }
 }
@@ -26,6 +26,14 @@ impl DefaultFilter {
     pub fn new() -> DefaultFilter {
         DefaultFilter::default()
     }
+
+    /// Only match the keys of the given databases
+    pub fn with_databases(databases: Vec<u32>) -> DefaultFilter {
+        DefaultFilter {
+            databases,
+            ..Default::default()
+        }
+    }
 }
 
 impl Filter for DefaultFilter {
@@ -54,3 +62,25 @@ impl Filter for DefaultFilter {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::{DefaultFilter, Filter};
+    use crate::rdb::{encoding_type, Type};
+
+    #[test]
+    fn matches_type_covers_every_list_encoding() {
+        let filter = DefaultFilter {
+            types: vec![Type::List],
+            ..Default::default()
+        };
+
+        assert!(filter.matches_type(encoding_type::LIST));
+        assert!(filter.matches_type(encoding_type::LIST_ZIPLIST));
+        assert!(filter.matches_type(encoding_type::LIST_QUICKLIST));
+        assert!(!filter.matches_type(encoding_type::STRING));
+
+        // no types given matches everything
+        assert!(DefaultFilter::new().matches_type(encoding_type::LIST_QUICKLIST));
+    }
+}
@@ -1,10 +1,12 @@
 pub mod dbfile;
 pub mod filter;
 pub mod parser;
+pub mod writer;
 
 pub use dbfile::*;
 pub use filter::*;
 pub use parser::*;
+pub use writer::*;
 
 #[derive(Debug, PartialEq)]
 pub enum Type {
@@ -12,15 +14,26 @@ pub enum Type {
     List,
     Set,
     Hash,
+    ZSet,
+    Stream,
 }
 
 impl Type {
     pub fn from_encoding(enc_type: u8) -> Type {
         match enc_type {
             encoding_type::STRING => Type::String,
-            encoding_type::SET => Type::Set,
-            encoding_type::LIST => Type::List,
-            encoding_type::HASH => Type::Hash,
+            encoding_type::SET | encoding_type::SET_INTSET => Type::Set,
+            encoding_type::LIST | encoding_type::LIST_ZIPLIST | encoding_type::LIST_QUICKLIST => {
+                Type::List
+            }
+            encoding_type::HASH
+            | encoding_type::HASH_ZIPMAP
+            | encoding_type::HASH_ZIPLIST
+            | encoding_type::HASH_METADATA => Type::Hash,
+            encoding_type::ZSET_2 => Type::ZSet,
+            encoding_type::STREAM_LISTPACKS
+            | encoding_type::STREAM_LISTPACKS_2
+            | encoding_type::STREAM_LISTPACKS_3 => Type::Stream,
             _ => {
                 panic!("Unimplemented or unsuported encoding type -> Type transform");
             }
@@ -1,10 +1,15 @@
-use std::{io::Cursor, ops::Mul, path::Path};
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    io::Cursor,
+    ops::Mul,
+    path::Path,
+};
 
 use byteorder::ByteOrder;
-use bytes::{Buf, BytesMut};
+use bytes::{Buf, Bytes, BytesMut};
 use redis_derive::gen_cursor_util;
 
-use crate::{rdb::Filter, Result};
+use crate::{rdb::Filter, Result, Stream, StreamData, ValueType, ZSet};
 
 use super::{Builder, DerivedDatabase, Type};
 
@@ -12,6 +17,9 @@ pub mod constants {
     pub const RDB_6BITLEN: u8 = 0;
     pub const RDB_14BITLEN: u8 = 1;
     pub const RDB_ENCVAL: u8 = 3;
+    // whole first byte of the lengths that follow in 4 or 8 bytes
+    pub const RDB_32BITLEN: u8 = 0x80;
+    pub const RDB_64BITLEN: u8 = 0x81;
     // pub const RDB_MAGIC: &'static str = "REDIS";
 }
 
@@ -30,19 +38,30 @@ pub mod encoding_type {
     pub const SET: u8 = 2;
     pub const ZSET: u8 = 3;
     pub const HASH: u8 = 4;
+    pub const ZSET_2: u8 = 5;
     pub const HASH_ZIPMAP: u8 = 9;
     pub const LIST_ZIPLIST: u8 = 10;
     pub const SET_INTSET: u8 = 11;
     pub const ZSET_ZIPLIST: u8 = 12;
     pub const HASH_ZIPLIST: u8 = 13;
     pub const LIST_QUICKLIST: u8 = 14;
+    pub const STREAM_LISTPACKS: u8 = 15;
+    pub const STREAM_LISTPACKS_2: u8 = 19;
+    pub const STREAM_LISTPACKS_3: u8 = 21;
+    pub const HASH_METADATA: u8 = 24;
+}
+
+/// Flags of an entry in a stream listpack node
+pub mod stream_flags {
+    pub const DELETED: i64 = 1;
+    pub const SAMEFIELDS: i64 = 2;
 }
 
 pub mod encoding {
-    pub const INT8: u32 = 0;
-    pub const INT16: u32 = 1;
-    pub const INT32: u32 = 2;
-    pub const LZF: u32 = 3;
+    pub const INT8: u64 = 0;
+    pub const INT16: u64 = 1;
+    pub const INT32: u64 = 2;
+    pub const LZF: u64 = 3;
 }
 
 pub mod helpers {
@@ -79,7 +98,22 @@ impl<F: Filter, B: Builder> RdbParser<F, B> {
         }
     }
 
+    /// Parse the file and return the last database read
     pub fn parse(&mut self) -> crate::Result<Option<DerivedDatabase>> {
+        self.read_file()?;
+
+        Ok(self.builder.get_database())
+    }
+
+    /// Parse the file and return every database read along with its
+    /// index, in order
+    pub fn parse_databases(&mut self) -> crate::Result<Vec<(u32, DerivedDatabase)>> {
+        self.read_file()?;
+
+        Ok(self.builder.get_databases())
+    }
+
+    fn read_file(&mut self) -> crate::Result<()> {
         let mut buffer = BytesMut::new();
         buffer.extend_from_slice(&self.rdb);
 
@@ -97,10 +131,14 @@ impl<F: Filter, B: Builder> RdbParser<F, B> {
                 opcodes::EOF => {
                     // end database
                     // end rdb
+                    // the checksum covers every byte up to and including EOF,
+                    // a zero checksum means it was disabled when saving
                     let pos = cursor.position() as usize;
                     if cursor.has_remaining() {
-                        let checksum: Vec<u8> = cursor.get_ref()[pos..].to_vec();
-                        println!("Checksum: {:?}", checksum);
+                        let checksum = take(&mut cursor, 8)?.as_slice().get_u64_le();
+                        if checksum != 0 && checksum != crc64(&buffer[..pos]) {
+                            return Err("RDB checksum mismatch".into());
+                        }
                     }
                     break;
                 }
@@ -114,9 +152,14 @@ impl<F: Filter, B: Builder> RdbParser<F, B> {
                 }
                 opcodes::SELECTDB => {
                     // start database and read database till the end
-                    (db_count, _) = get_length_with_encoding(&mut cursor)?;
+                    db_count = u32::try_from(get_length(&mut cursor)?)
+                        .map_err(|_| "RDB database number doesn't fit in 32 bits")?;
                     println!("Start database: {db_count}");
-                    self.builder.start_database();
+                    // starting a database drops the one built so far,
+                    // only start the ones the filter keeps
+                    if self.filter.matches_db(db_count) {
+                        self.builder.start_database(db_count);
+                    }
                 }
                 opcodes::RESIZEDB => {
                     let db_size = get_length(&mut cursor)?;
@@ -161,7 +204,7 @@ impl<F: Filter, B: Builder> RdbParser<F, B> {
             }
         }
 
-        Ok(self.builder.get_database())
+        Ok(())
     }
 
     fn read_data(&self, src: &mut Cursor<&[u8]>) -> crate::Result<Vec<u8>> {
@@ -170,8 +213,13 @@ impl<F: Filter, B: Builder> RdbParser<F, B> {
         if is_encoded {
             match len {
                 encoding::INT8 => helpers::convert_int_to_vec(get_i8(src)? as i32),
-                encoding::INT16 => helpers::convert_int_to_vec(get_i16(src)? as i32),
-                encoding::INT32 => helpers::convert_int_to_vec(get_i32(src)? as i32),
+                // integers are stored little endian
+                encoding::INT16 => {
+                    helpers::convert_int_to_vec(take(src, 2)?.as_slice().get_i16_le() as i32)
+                }
+                encoding::INT32 => {
+                    helpers::convert_int_to_vec(take(src, 4)?.as_slice().get_i32_le())
+                }
                 encoding::LZF => {
                     todo!()
                 }
@@ -180,10 +228,7 @@ impl<F: Filter, B: Builder> RdbParser<F, B> {
                 }
             }
         } else {
-            let pos = src.position() as usize;
-            let data = &src.get_ref()[pos..pos + len as usize].to_vec();
-            src.advance(len as usize);
-            Ok(data.to_owned())
+            take(src, blob_len(len)?)
         }
     }
 
@@ -193,7 +238,141 @@ impl<F: Filter, B: Builder> RdbParser<F, B> {
                 let val = self.read_data(src)?;
                 self.builder.set(
                     String::from_utf8(key.to_owned())?,
-                    val,
+                    ValueType::String(Bytes::from(val)),
+                    self.last_expiry_time,
+                );
+            }
+            encoding_type::LIST => {
+                let len = get_length(src)?;
+                let mut list = VecDeque::with_capacity(len as usize);
+                for _ in 0..len {
+                    list.push_back(Bytes::from(self.read_data(src)?));
+                }
+                self.builder.set(
+                    String::from_utf8(key.to_owned())?,
+                    ValueType::List(list),
+                    self.last_expiry_time,
+                );
+            }
+            encoding_type::LIST_ZIPLIST | encoding_type::LIST_QUICKLIST => {
+                // a quicklist is a list of ziplists, a plain ziplist is one node
+                let nodes = match enc_type {
+                    encoding_type::LIST_QUICKLIST => get_length(src)?,
+                    _ => 1,
+                };
+
+                let mut list = VecDeque::new();
+                for _ in 0..nodes {
+                    let ziplist = self.read_data(src)?;
+                    list.extend(read_ziplist(&ziplist)?.into_iter().map(Bytes::from));
+                }
+                self.builder.set(
+                    String::from_utf8(key.to_owned())?,
+                    ValueType::List(list),
+                    self.last_expiry_time,
+                );
+            }
+            encoding_type::SET => {
+                let len = get_length(src)?;
+                let mut set = HashSet::with_capacity(len as usize);
+                for _ in 0..len {
+                    set.insert(self.read_string(src)?);
+                }
+                self.builder.set_set(
+                    String::from_utf8(key.to_owned())?,
+                    set,
+                    self.last_expiry_time,
+                );
+            }
+            encoding_type::SET_INTSET => {
+                let intset = self.read_data(src)?;
+                let set = read_intset(&intset)?
+                    .into_iter()
+                    .map(|int| int.to_string())
+                    .collect();
+                self.builder.set_set(
+                    String::from_utf8(key.to_owned())?,
+                    set,
+                    self.last_expiry_time,
+                );
+            }
+            encoding_type::HASH => {
+                let len = get_length(src)?;
+                let mut hash = HashMap::with_capacity(len as usize);
+                for _ in 0..len {
+                    let field = self.read_string(src)?;
+                    hash.insert(field, self.read_string(src)?);
+                }
+                self.builder.set_hash(
+                    String::from_utf8(key.to_owned())?,
+                    hash,
+                    self.last_expiry_time,
+                );
+            }
+            encoding_type::HASH_METADATA => {
+                // field TTLs are stored relative to the earliest one,
+                // off by one so 0 is a field without a TTL
+                let min_expire = take(src, 8)?.as_slice().get_u64_le();
+                let len = get_length(src)?;
+                let mut hash = HashMap::with_capacity(len as usize);
+                let mut field_expirations = HashMap::new();
+                for _ in 0..len {
+                    let ttl = get_length(src)?;
+                    let field = self.read_string(src)?;
+                    if ttl != 0 {
+                        field_expirations.insert(field.clone(), min_expire.saturating_add(ttl - 1));
+                    }
+                    hash.insert(field, self.read_string(src)?);
+                }
+                self.builder.set_hash_with_ttls(
+                    String::from_utf8(key.to_owned())?,
+                    hash,
+                    field_expirations,
+                    self.last_expiry_time,
+                );
+            }
+            encoding_type::HASH_ZIPMAP | encoding_type::HASH_ZIPLIST => {
+                let blob = self.read_data(src)?;
+                let entries = match enc_type {
+                    encoding_type::HASH_ZIPMAP => read_zipmap(&blob)?,
+                    _ => read_ziplist(&blob)?,
+                };
+                if entries.len() % 2 != 0 {
+                    return Err("Hash ziplist has a field without a value".into());
+                }
+
+                let mut hash = HashMap::with_capacity(entries.len() / 2);
+                let mut entries = entries.into_iter();
+                while let (Some(field), Some(value)) = (entries.next(), entries.next()) {
+                    hash.insert(String::from_utf8(field)?, String::from_utf8(value)?);
+                }
+                self.builder.set_hash(
+                    String::from_utf8(key.to_owned())?,
+                    hash,
+                    self.last_expiry_time,
+                );
+            }
+            encoding_type::ZSET_2 => {
+                let len = get_length(src)?;
+                let mut zset = ZSet::new();
+                for _ in 0..len {
+                    let member = self.read_string(src)?;
+                    let score = take(src, 8)?.as_slice().get_f64_le();
+                    zset.insert(member, score);
+                }
+                self.builder.set(
+                    String::from_utf8(key.to_owned())?,
+                    ValueType::ZSet(zset),
+                    self.last_expiry_time,
+                );
+            }
+            encoding_type::STREAM_LISTPACKS
+            | encoding_type::STREAM_LISTPACKS_2
+            | encoding_type::STREAM_LISTPACKS_3 => {
+                let stream = self.read_stream(src, enc_type)?;
+                self.builder.set(
+                    String::from_utf8(key.to_owned())?,
+                    ValueType::Stream(stream),
                     self.last_expiry_time,
                 );
             }
@@ -211,8 +390,45 @@ impl<F: Filter, B: Builder> RdbParser<F, B> {
         Ok(string)
     }
 
-    fn skip(&self, src: &mut Cursor<&[u8]>, len: usize) {
-        src.advance(len)
+    /// Read a stream of any of the `STREAM_LISTPACKS` types, its
+    /// metadata is read past but for the last id
+    fn read_stream(&self, src: &mut Cursor<&[u8]>, enc_type: u8) -> crate::Result<Stream> {
+        let mut entries = vec![];
+        for _ in 0..get_length(src)? {
+            let master_id = self.read_data(src)?;
+            if master_id.len() != 16 {
+                return Err("Invalid stream node id".into());
+            }
+            let master_id = (
+                master_id[..8].as_ref().get_u64(),
+                master_id[8..].as_ref().get_u64(),
+            );
+            let listpack = read_listpack(&self.read_data(src)?)?;
+            entries.extend(read_stream_node(master_id, listpack)?);
+        }
+
+        // length and last id
+        get_length(src)?;
+        let last_id = (get_length(src)? as u64, get_length(src)? as u64);
+        if enc_type != encoding_type::STREAM_LISTPACKS {
+            // first id, max deleted id and entries added
+            for _ in 0..5 {
+                get_length(src)?;
+            }
+        }
+        if get_length(src)? != 0 {
+            return Err("Stream consumer groups aren't supported".into());
+        }
+
+        Ok(Stream { entries, last_id })
+    }
+
+    fn skip(&self, src: &mut Cursor<&[u8]>, len: usize) -> crate::Result<()> {
+        if src.remaining() < len {
+            return Err("Unexpected end of RDB data".into());
+        }
+        src.advance(len);
+        Ok(())
     }
 
     fn skip_blob(&self, src: &mut Cursor<&[u8]>) -> crate::Result<()> {
@@ -237,9 +453,7 @@ impl<F: Filter, B: Builder> RdbParser<F, B> {
             skip_bytes = len;
         }
 
-        self.skip(src, skip_bytes as usize);
-
-        Ok(())
+        self.skip(src, blob_len(skip_bytes)?)
     }
 
     fn skip_object(&self, src: &mut Cursor<&[u8]>, enc_type: u8) -> crate::Result<()> {
@@ -254,6 +468,29 @@ impl<F: Filter, B: Builder> RdbParser<F, B> {
                 get_length(src)?
             }
             encoding_type::ZSET | encoding_type::HASH => get_length(src)? * 2,
+            encoding_type::ZSET_2 => {
+                // members are followed by their binary double score
+                for _ in 0..get_length(src)? {
+                    self.skip_blob(src)?;
+                    take(src, 8)?;
+                }
+                0
+            }
+            encoding_type::STREAM_LISTPACKS
+            | encoding_type::STREAM_LISTPACKS_2
+            | encoding_type::STREAM_LISTPACKS_3 => {
+                self.read_stream(src, enc_type)?;
+                0
+            }
+            encoding_type::HASH_METADATA => {
+                take(src, 8)?;
+                for _ in 0..get_length(src)? {
+                    get_length(src)?;
+                    self.skip_blob(src)?;
+                    self.skip_blob(src)?;
+                }
+                0
+            }
             _ => {
                 panic!("Unknown encoding type: {}", enc_type)
             }
@@ -274,6 +511,291 @@ impl<F: Filter, B: Builder> RdbParser<F, B> {
     }
 }
 
+/// Decode the entries of a ziplist, integers are returned as
+/// their decimal string like every other integer encoded value
+///
+/// Layout: `<zlbytes u32><zltail u32><zllen u16><entry>...<0xFF>` where
+/// each entry is `<prevlen><encoding><data>`
+fn read_ziplist(ziplist: &[u8]) -> crate::Result<Vec<Vec<u8>>> {
+    let mut src = Cursor::new(ziplist);
+    if src.remaining() < 10 {
+        return Err("Invalid ziplist".into());
+    }
+    src.advance(10);
+
+    let mut entries = vec![];
+    loop {
+        // previous entry length, 4 more bytes follow 0xFE
+        match get_u8(&mut src)? {
+            0xFF => break,
+            0xFE => {
+                get_u32(&mut src)?;
+            }
+            _ => {}
+        }
+
+        let enc = get_u8(&mut src)?;
+        let entry = match enc >> 6 {
+            // strings, the length is big endian
+            0b00 => take(&mut src, (enc & 0x3F) as usize)?,
+            0b01 => {
+                let len = (((enc & 0x3F) as usize) << 8) | get_u8(&mut src)? as usize;
+                take(&mut src, len)?
+            }
+            0b10 => {
+                let len = get_u32(&mut src)? as usize;
+                take(&mut src, len)?
+            }
+            // integers, little endian
+            _ => {
+                let int = match enc {
+                    0xC0 => take(&mut src, 2)?.as_slice().get_i16_le() as i64,
+                    0xD0 => take(&mut src, 4)?.as_slice().get_i32_le() as i64,
+                    0xE0 => take(&mut src, 8)?.as_slice().get_i64_le(),
+                    0xF0 => {
+                        let bytes = take(&mut src, 3)?;
+                        // sign extend the 24 bit integer
+                        (i32::from_le_bytes([0, bytes[0], bytes[1], bytes[2]]) >> 8) as i64
+                    }
+                    0xFE => get_i8(&mut src)? as i64,
+                    // 4 bit immediate between 1 and 13 holding 0 to 12
+                    0xF1..=0xFD => (enc & 0x0F) as i64 - 1,
+                    _ => return Err(format!("Invalid ziplist entry encoding {enc}").into()),
+                };
+                int.to_string().into_bytes()
+            }
+        };
+        entries.push(entry);
+    }
+
+    Ok(entries)
+}
+
+/// Decode the alternating fields and values of a zipmap
+///
+/// Layout: `<zmlen u8><len><field><len><free u8><value>...<0xFF>` where
+/// a length below 254 is a single byte and 254 is followed by the
+/// length as 4 bytes little endian, `free` unused bytes trail a value
+/// Read every element of a listpack, integers are returned as their
+/// string form like the ziplist ones
+fn read_listpack(listpack: &[u8]) -> crate::Result<Vec<Vec<u8>>> {
+    let mut src = Cursor::new(listpack);
+    // total bytes and number of elements
+    take(&mut src, 6)?;
+
+    let mut entries = vec![];
+    loop {
+        let start = src.position() as usize;
+        let enc = get_u8(&mut src)?;
+        let entry = match enc {
+            0xFF => break,
+            // strings
+            0x80..=0xBF => take(&mut src, (enc & 0x3F) as usize)?,
+            0xE0..=0xEF => {
+                let len = (((enc & 0x0F) as usize) << 8) | get_u8(&mut src)? as usize;
+                take(&mut src, len)?
+            }
+            0xF0 => {
+                let len = take(&mut src, 4)?.as_slice().get_u32_le() as usize;
+                take(&mut src, len)?
+            }
+            // integers, little endian
+            _ => {
+                let int = match enc {
+                    0x00..=0x7F => enc as i64,
+                    0xC0..=0xDF => {
+                        let int = (((enc & 0x1F) as i64) << 8) | get_u8(&mut src)? as i64;
+                        // sign extend the 13 bit integer
+                        (int << 51) >> 51
+                    }
+                    0xF1 => take(&mut src, 2)?.as_slice().get_i16_le() as i64,
+                    0xF2 => {
+                        let bytes = take(&mut src, 3)?;
+                        // sign extend the 24 bit integer
+                        (i32::from_le_bytes([0, bytes[0], bytes[1], bytes[2]]) >> 8) as i64
+                    }
+                    0xF3 => take(&mut src, 4)?.as_slice().get_i32_le() as i64,
+                    0xF4 => take(&mut src, 8)?.as_slice().get_i64_le(),
+                    _ => return Err(format!("Invalid listpack entry encoding {enc}").into()),
+                };
+                int.to_string().into_bytes()
+            }
+        };
+        let len = src.position() as usize - start;
+        take(&mut src, backlen_size(len))?;
+        entries.push(entry);
+    }
+
+    Ok(entries)
+}
+
+/// Number of bytes the length of a listpack element is stored back in
+pub(crate) fn backlen_size(len: usize) -> usize {
+    match len {
+        0..=127 => 1,
+        128..=16382 => 2,
+        16383..=2097150 => 3,
+        2097151..=268435454 => 4,
+        _ => 5,
+    }
+}
+
+/// Entries of a stream listpack node, their ids are stored relative
+/// to the node's `master_id`. Deleted entries are left out
+fn read_stream_node(
+    master_id: (u64, u64),
+    elements: Vec<Vec<u8>>,
+) -> crate::Result<Vec<StreamData>> {
+    let mut elements = elements.into_iter();
+
+    let count = next_int_element(&mut elements)?;
+    let deleted = next_int_element(&mut elements)?;
+    let master_fields = (0..next_int_element(&mut elements)?)
+        .map(|_| next_string_element(&mut elements))
+        .collect::<crate::Result<Vec<_>>>()?;
+    // the master entry ends with a 0
+    next_int_element(&mut elements)?;
+
+    let mut entries = vec![];
+    for _ in 0..count + deleted {
+        let flags = next_int_element(&mut elements)?;
+        let ms = master_id
+            .0
+            .wrapping_add(next_int_element(&mut elements)? as u64);
+        let seq = master_id
+            .1
+            .wrapping_add(next_int_element(&mut elements)? as u64);
+
+        let mut pairs = HashMap::new();
+        if flags & stream_flags::SAMEFIELDS != 0 {
+            for field in &master_fields {
+                pairs.insert(field.clone(), next_string_element(&mut elements)?);
+            }
+        } else {
+            for _ in 0..next_int_element(&mut elements)? {
+                let field = next_string_element(&mut elements)?;
+                pairs.insert(field, next_string_element(&mut elements)?);
+            }
+        }
+        // lp-count, only needed to walk the node backwards
+        next_int_element(&mut elements)?;
+
+        if flags & stream_flags::DELETED == 0 {
+            entries.push(StreamData {
+                id: (ms, seq),
+                pairs,
+                _created_at: tokio::time::Instant::now(),
+            });
+        }
+    }
+
+    Ok(entries)
+}
+
+fn next_string_element(elements: &mut impl Iterator<Item = Vec<u8>>) -> crate::Result<String> {
+    let element = elements.next().ok_or("Stream node ended early")?;
+    Ok(String::from_utf8(element)?)
+}
+
+fn next_int_element(elements: &mut impl Iterator<Item = Vec<u8>>) -> crate::Result<i64> {
+    Ok(next_string_element(elements)?
+        .parse()
+        .map_err(|_| "Invalid integer in stream node")?)
+}
+
+fn read_zipmap(zipmap: &[u8]) -> crate::Result<Vec<Vec<u8>>> {
+    let mut src = Cursor::new(zipmap);
+    take(&mut src, 1)?;
+
+    let read_len = |src: &mut Cursor<&[u8]>| -> crate::Result<Option<usize>> {
+        match get_u8(src)? {
+            0xFF => Ok(None),
+            0xFE => Ok(Some(take(src, 4)?.as_slice().get_u32_le() as usize)),
+            len => Ok(Some(len as usize)),
+        }
+    };
+
+    let mut entries = vec![];
+    while let Some(len) = read_len(&mut src)? {
+        entries.push(take(&mut src, len)?);
+
+        let len = read_len(&mut src)?.ok_or("Zipmap field without a value")?;
+        let free = get_u8(&mut src)? as usize;
+        entries.push(take(&mut src, len)?);
+        take(&mut src, free)?;
+    }
+
+    Ok(entries)
+}
+
+/// Decode the integers of an intset
+///
+/// Layout: `<encoding u32><length u32><int>...` where the encoding is
+/// the 2, 4 or 8 byte width of every int, all little endian
+fn read_intset(intset: &[u8]) -> crate::Result<Vec<i64>> {
+    let mut src = Cursor::new(intset);
+    let width = take(&mut src, 4)?.as_slice().get_u32_le();
+    let len = take(&mut src, 4)?.as_slice().get_u32_le();
+
+    let mut ints = Vec::with_capacity(len as usize);
+    for _ in 0..len {
+        let int = take(&mut src, width as usize)?;
+        let int = match width {
+            2 => int.as_slice().get_i16_le() as i64,
+            4 => int.as_slice().get_i32_le() as i64,
+            8 => int.as_slice().get_i64_le(),
+            _ => return Err(format!("Invalid intset encoding {width}").into()),
+        };
+        ints.push(int);
+    }
+
+    Ok(ints)
+}
+
+/// Read `len` bytes off the cursor
+fn take(src: &mut Cursor<&[u8]>, len: usize) -> crate::Result<Vec<u8>> {
+    if src.remaining() < len {
+        return Err("Unexpected end of RDB data".into());
+    }
+
+    let data = src.chunk()[..len].to_vec();
+    src.advance(len);
+    Ok(data)
+}
+
+/// CRC-64 with the Jones polynomial, reflected, as used by redis
+/// for the RDB checksum
+pub fn crc64(data: &[u8]) -> u64 {
+    data.iter().fold(0, |crc, byte| {
+        CRC64_TABLE[((crc ^ *byte as u64) & 0xFF) as usize] ^ (crc >> 8)
+    })
+}
+
+const CRC64_TABLE: [u64; 256] = crc64_table();
+
+const fn crc64_table() -> [u64; 256] {
+    // reflected form of 0xad93d23594c935a9
+    const POLY: u64 = 0x95ac9329ac4bc9b5;
+
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u64;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ POLY
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+}
+
 fn verify_magic(src: &mut Cursor<&[u8]>) -> crate::Result<()> {
     if !src.has_remaining() {
         return Err("Invalid RDB magic string".into());
@@ -366,21 +888,409 @@ gen_cursor_util!(get_u64);
 //     Ok(src.get_u64())
 // }
 
-fn get_length_with_encoding(src: &mut Cursor<&[u8]>) -> crate::Result<(u32, bool)> {
+/// Read a length, or the kind of a specially encoded value along with
+/// `true`
+///
+/// The two most significant bits of the first byte pick the form, `00`
+/// is a 6 bit length and `01` a 14 bit one spilling into the next byte.
+/// `10` is a big endian length in the next 4 bytes, or 8 when the byte
+/// is `RDB_64BITLEN`. `11` is an encoded value, the low 6 bits tell its
+/// kind
+fn get_length_with_encoding(src: &mut Cursor<&[u8]>) -> crate::Result<(u64, bool)> {
     let enc_byte = get_u8(src)?;
 
-    match (enc_byte & 0xCF) >> 6 {
-        constants::RDB_ENCVAL => Ok(((enc_byte & 0x3F) as u32, true)),
-        constants::RDB_6BITLEN => Ok(((enc_byte & 0x3F) as u32, false)),
+    match (enc_byte & 0xC0) >> 6 {
+        constants::RDB_ENCVAL => Ok(((enc_byte & 0x3F) as u64, true)),
+        constants::RDB_6BITLEN => Ok(((enc_byte & 0x3F) as u64, false)),
         constants::RDB_14BITLEN => {
             let next_byte = get_u8(src)?;
-            Ok(((((enc_byte & 0x3F) as u32) << 8) | next_byte as u32, false))
+            Ok(((((enc_byte & 0x3F) as u64) << 8) | next_byte as u64, false))
         }
-        _ => Ok((get_u32(src)?, false)),
+        _ => match enc_byte {
+            constants::RDB_32BITLEN => Ok((take(src, 4)?.as_slice().get_u32() as u64, false)),
+            constants::RDB_64BITLEN => Ok((take(src, 8)?.as_slice().get_u64(), false)),
+            _ => Err(format!("Invalid RDB length encoding {enc_byte:#04x}").into()),
+        },
     }
 }
 
-fn get_length(src: &mut Cursor<&[u8]>) -> crate::Result<u32> {
+fn get_length(src: &mut Cursor<&[u8]>) -> crate::Result<u64> {
     let (len, _) = get_length_with_encoding(src)?;
     Ok(len)
 }
+
+/// A length read from the file as a byte count to take from memory
+fn blob_len(len: u64) -> crate::Result<usize> {
+    usize::try_from(len).map_err(|_| format!("RDB blob of {len} bytes is too large").into())
+}
+
+#[cfg(test)]
+mod test {
+    use bytes::Bytes;
+
+    use std::io::Cursor;
+
+    use super::{crc64, get_length_with_encoding, RdbParser};
+    use crate::{
+        object::Object,
+        rdb::{DefaultFilter, RdbBuilder},
+        resp::RESP,
+        Db, ValueType,
+    };
+
+    /// RDB with a LIST encoded key and a quicklist of one ziplist
+    fn list_rdb() -> Vec<u8> {
+        let mut ziplist = vec![0; 10];
+        ziplist.extend_from_slice(&[0x00, 0x01, b'x']); // string "x"
+        ziplist.extend_from_slice(&[0x03, 0xF8]); // 4 bit immediate 7
+        ziplist.extend_from_slice(&[0x02, 0xC0, 0xD4, 0xFE]); // int16 -300
+        ziplist.extend_from_slice(&[0x04, 0xF0, 0xFF, 0xFF, 0xFF]); // int24 -1
+        ziplist.push(0xFF);
+        let zlbytes = ziplist.len() as u32;
+        ziplist[..4].copy_from_slice(&zlbytes.to_le_bytes());
+
+        let mut rdb = b"REDIS0006".to_vec();
+        rdb.extend_from_slice(&[0xFE, 0x00]);
+        rdb.extend_from_slice(&[
+            0x01, 0x04, b'l', b'i', b's', b't', 0x02, 0x01, b'a', 0x01, b'b',
+        ]);
+        rdb.extend_from_slice(&[0x0E, 0x02, b'q', b'l', 0x01, ziplist.len() as u8]);
+        rdb.extend_from_slice(&ziplist);
+        rdb.push(0xFF);
+        rdb.extend_from_slice(&[0; 8]);
+        rdb
+    }
+
+    /// RDB with plain and intset encoded sets, empty ones included
+    fn set_rdb() -> Vec<u8> {
+        let intset = |width: u32, ints: &[i64]| {
+            let mut intset = width.to_le_bytes().to_vec();
+            intset.extend_from_slice(&(ints.len() as u32).to_le_bytes());
+            for int in ints {
+                intset.extend_from_slice(&int.to_le_bytes()[..width as usize]);
+            }
+            intset
+        };
+
+        let mut rdb = b"REDIS0006".to_vec();
+        rdb.extend_from_slice(&[0xFE, 0x00]);
+        rdb.extend_from_slice(&[0x02, 0x01, b's', 0x02, 0x01, b'a', 0x01, b'b']);
+        rdb.extend_from_slice(&[0x02, 0x05, b'e', b'm', b'p', b't', b'y', 0x00]);
+        for (key, intset) in [
+            (&b"i16"[..], intset(2, &[1, -2])),
+            (&b"i64"[..], intset(8, &[1 << 40])),
+            (&b"none"[..], intset(4, &[])),
+        ] {
+            rdb.extend_from_slice(&[0x0B, key.len() as u8]);
+            rdb.extend_from_slice(key);
+            rdb.push(intset.len() as u8);
+            rdb.extend_from_slice(&intset);
+        }
+        rdb.push(0xFF);
+        rdb.extend_from_slice(&[0; 8]);
+        rdb
+    }
+
+    /// RDB with plain, zipmap and ziplist encoded hashes
+    fn hash_rdb() -> Vec<u8> {
+        // zipmap with "a" => "1" and "bb" => "22" plus 2 free bytes
+        let zipmap = [
+            0x02, 0x01, b'a', 0x01, 0x00, b'1', 0x02, b'b', b'b', 0x02, 0x02, b'2', b'2', 0x00,
+            0x00, 0xFF,
+        ];
+
+        let mut ziplist = vec![0; 10];
+        ziplist.extend_from_slice(&[0x00, 0x01, b'f']); // string "f"
+        ziplist.extend_from_slice(&[0x03, 0xC0, 0xE8, 0x03]); // int16 1000
+        ziplist.push(0xFF);
+        let zlbytes = ziplist.len() as u32;
+        ziplist[..4].copy_from_slice(&zlbytes.to_le_bytes());
+
+        let mut rdb = b"REDIS0006".to_vec();
+        rdb.extend_from_slice(&[0xFE, 0x00]);
+        rdb.extend_from_slice(&[0x04, 0x01, b'h', 0x01, 0x01, b'k', 0x01, b'v']);
+        rdb.extend_from_slice(&[0x09, 0x02, b'z', b'm', zipmap.len() as u8]);
+        rdb.extend_from_slice(&zipmap);
+        rdb.extend_from_slice(&[0x0D, 0x02, b'z', b'l', ziplist.len() as u8]);
+        rdb.extend_from_slice(&ziplist);
+        rdb.push(0xFF);
+        rdb.extend_from_slice(&[0; 8]);
+        rdb
+    }
+
+    fn hash(value: Option<&crate::Value>) -> Vec<(String, String)> {
+        match value.map(|value| &value.data) {
+            Some(ValueType::Hash(hash)) => {
+                let mut pairs: Vec<(String, String)> =
+                    hash.iter().map(|(f, v)| (f.clone(), v.clone())).collect();
+                pairs.sort();
+                pairs
+            }
+            value => panic!("expected a hash, got {value:?}"),
+        }
+    }
+
+    fn set(value: Option<&crate::Value>) -> Vec<String> {
+        match value.map(|value| &value.data) {
+            Some(ValueType::Set(set)) => {
+                let mut members: Vec<String> = set.iter().collect();
+                members.sort();
+                members
+            }
+            value => panic!("expected a set, got {value:?}"),
+        }
+    }
+
+    fn list(value: Option<&crate::Value>) -> Vec<Bytes> {
+        match value.map(|value| &value.data) {
+            Some(ValueType::List(list)) => list.iter().cloned().collect(),
+            value => panic!("expected a list, got {value:?}"),
+        }
+    }
+
+    #[test]
+    fn parses_list_and_quicklist_keys() {
+        let mut parser = RdbParser::new(DefaultFilter::new(), RdbBuilder::default(), list_rdb());
+        let database = parser.parse().unwrap().unwrap();
+
+        assert_eq!(list(database.entries.get("list")), ["a", "b"]);
+        assert_eq!(list(database.entries.get("ql")), ["x", "7", "-300", "-1"]);
+    }
+
+    #[test]
+    fn parses_set_and_intset_keys() {
+        let mut parser = RdbParser::new(DefaultFilter::new(), RdbBuilder::default(), set_rdb());
+        let database = parser.parse().unwrap().unwrap();
+
+        assert_eq!(set(database.entries.get("s")), ["a", "b"]);
+        assert!(set(database.entries.get("empty")).is_empty());
+        assert_eq!(set(database.entries.get("i16")), ["-2", "1"]);
+        assert_eq!(set(database.entries.get("i64")), ["1099511627776"]);
+        assert!(set(database.entries.get("none")).is_empty());
+    }
+
+    #[test]
+    fn parses_hash_zipmap_and_ziplist_keys() {
+        let mut parser = RdbParser::new(DefaultFilter::new(), RdbBuilder::default(), hash_rdb());
+        let database = parser.parse().unwrap().unwrap();
+
+        let pair = |f: &str, v: &str| (f.to_string(), v.to_string());
+        assert_eq!(hash(database.entries.get("h")), [pair("k", "v")]);
+        assert_eq!(
+            hash(database.entries.get("zm")),
+            [pair("a", "1"), pair("bb", "22")]
+        );
+        assert_eq!(hash(database.entries.get("zl")), [pair("f", "1000")]);
+    }
+
+    #[test]
+    fn crc64_matches_redis() {
+        assert_eq!(crc64(b"123456789"), 0xe9c6d914c4b8d9ca);
+    }
+
+    #[test]
+    fn verifies_the_checksum_unless_it_is_zero() {
+        let mut rdb = list_rdb();
+        let body = rdb.len() - 8;
+        let checksum = crc64(&rdb[..body]);
+        rdb[body..].copy_from_slice(&checksum.to_le_bytes());
+
+        let mut parser = RdbParser::new(DefaultFilter::new(), RdbBuilder::default(), rdb.clone());
+        let database = parser.parse().unwrap().unwrap();
+        assert_eq!(list(database.entries.get("list")), ["a", "b"]);
+
+        // corrupt a value byte, "b" becomes "c"
+        let value = rdb.iter().position(|byte| *byte == b'b').unwrap();
+        rdb[value] = b'c';
+        let mut parser = RdbParser::new(DefaultFilter::new(), RdbBuilder::default(), rdb);
+        let err = parser.parse().unwrap_err();
+        assert_eq!(err.to_string(), "RDB checksum mismatch");
+    }
+
+    #[tokio::test]
+    async fn int_encoded_strings_load_with_the_int_encoding() {
+        let mut rdb = b"REDIS0006".to_vec();
+        rdb.extend_from_slice(&[0xFE, 0x00]);
+        rdb.extend_from_slice(&[0x00, 0x02, b'i', b'8', 0xC0, 0x85]); // int8 -123
+        rdb.extend_from_slice(&[0x00, 0x03, b'i', b'1', b'6', 0xC1, 0xE8, 0x03]); // int16 1000
+        rdb.extend_from_slice(&[0x00, 0x03, b'i', b'3', b'2', 0xC2]);
+        rdb.extend_from_slice(&100_000i32.to_le_bytes());
+        rdb.extend_from_slice(&[0x00, 0x03, b's', b't', b'r', 0x02, b'1', b'x']);
+        rdb.push(0xFF);
+        rdb.extend_from_slice(&[0; 8]);
+
+        let mut parser = RdbParser::new(DefaultFilter::new(), RdbBuilder::default(), rdb);
+        let db = Db::new();
+        db.load(0, parser.parse().unwrap().unwrap());
+
+        for (key, value, encoding) in [
+            ("i8", "-123", "int"),
+            ("i16", "1000", "int"),
+            ("i32", "100000", "int"),
+            ("str", "1x", "embstr"),
+        ] {
+            assert!(matches!(db.get(key), Some(ValueType::String(v)) if v == value));
+            let resp = Object::new("encoding".into(), Some(key.into()))
+                .apply(&db)
+                .await
+                .unwrap();
+            assert!(
+                matches!(&resp, Some(RESP::Bulk(e)) if e == encoding),
+                "{key}: {resp:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn every_length_encoding_form() {
+        for (bytes, expected) in [
+            // 6 bit
+            (&[0x00][..], (0, false)),
+            (&[0x2A][..], (42, false)),
+            (&[0x3F][..], (63, false)),
+            // 14 bit
+            (&[0x41, 0x2C][..], (300, false)),
+            (&[0x7F, 0xFF][..], (16383, false)),
+            // 32 bit, big endian
+            (&[0x80, 0x00, 0x01, 0x00, 0x00][..], (65536, false)),
+            // 64 bit, big endian, past what 32 bits hold
+            (&[0x81, 0, 0, 0, 0, 0, 0, 0x01, 0x00][..], (256, false)),
+            (&[0x81, 0, 0, 0, 0x01, 0, 0, 0, 0][..], (1 << 32, false)),
+            // encoded values, int8 to lzf
+            (&[0xC0][..], (0, true)),
+            (&[0xC1][..], (1, true)),
+            (&[0xC2][..], (2, true)),
+            (&[0xC3][..], (3, true)),
+        ] {
+            let mut cursor = Cursor::new(bytes);
+            assert_eq!(
+                get_length_with_encoding(&mut cursor).unwrap(),
+                expected,
+                "{bytes:x?}"
+            );
+            // exactly the bytes of the length were consumed
+            assert_eq!(cursor.position() as usize, bytes.len(), "{bytes:x?}");
+        }
+
+        for bytes in [
+            // 10 with anything but a 32 or 64 bit length
+            &[0x82][..],
+            &[0xBF][..],
+            // cut short
+            &[0x41][..],
+            &[0x80, 0x00, 0x00][..],
+        ] {
+            assert!(
+                get_length_with_encoding(&mut Cursor::new(bytes)).is_err(),
+                "{bytes:x?}"
+            );
+        }
+    }
+
+    #[test]
+    fn strings_with_32_and_64_bit_lengths_load_or_skip() {
+        let mut rdb = b"REDIS0006".to_vec();
+        for db in [0x00, 0x01] {
+            rdb.extend_from_slice(&[0xFE, db]);
+            rdb.extend_from_slice(&[0x00, 0x80, 0, 0, 0, 0x03, b'k', b'3', b'2']);
+            rdb.extend_from_slice(&[0x80, 0, 0, 0, 0x02, b'v', b'1']);
+            rdb.extend_from_slice(&[0x00, 0x81, 0, 0, 0, 0, 0, 0, 0, 0x03, b'k', b'6', b'4']);
+            rdb.extend_from_slice(&[0x81, 0, 0, 0, 0, 0, 0, 0, 0x02, b'v', b'2']);
+        }
+        rdb.push(0xFF);
+        rdb.extend_from_slice(&[0; 8]);
+
+        // db 1 is skipped blob by blob with the same lengths
+        let filter = DefaultFilter::with_databases(vec![0]);
+        let mut parser = RdbParser::new(filter, RdbBuilder::default(), rdb);
+        let database = parser.parse().unwrap().unwrap();
+
+        assert_eq!(database.entries.len(), 2);
+        for (key, value) in [("k32", "v1"), ("k64", "v2")] {
+            assert!(matches!(
+                database.entries.get(key).map(|value| &value.data),
+                Some(ValueType::String(v)) if v == value
+            ));
+        }
+    }
+
+    #[test]
+    fn lengths_past_the_end_of_the_file_are_errors() {
+        for len in [
+            &[0x80, 0, 0, 0x01, 0][..],
+            &[0x81, 0x01, 0, 0, 0, 0, 0, 0, 0],
+        ] {
+            for db in [0x00, 0x01] {
+                let mut rdb = b"REDIS0006".to_vec();
+                rdb.extend_from_slice(&[0xFE, db, 0x00, 0x01, b'k']);
+                rdb.extend_from_slice(len);
+                rdb.extend_from_slice(b"short");
+
+                let filter = DefaultFilter::with_databases(vec![0]);
+                let mut parser = RdbParser::new(filter, RdbBuilder::default(), rdb);
+                assert!(parser.parse().is_err(), "{len:x?} in db {db}");
+            }
+        }
+    }
+
+    #[test]
+    fn parses_stream_nodes_as_redis_writes_them() {
+        // every element here is short enough for a single backlen byte
+        let listpack = |elements: &[&[u8]]| {
+            let mut listpack = vec![0; 4];
+            listpack.extend_from_slice(&(elements.len() as u16).to_le_bytes());
+            for element in elements {
+                listpack.extend_from_slice(element);
+                listpack.push(element.len() as u8);
+            }
+            listpack.push(0xFF);
+            let total = listpack.len() as u32;
+            listpack[..4].copy_from_slice(&total.to_le_bytes());
+            listpack
+        };
+        let node = listpack(&[
+            // 1 entry, 1 deleted, master field "f"
+            &[0x01],
+            &[0x01],
+            &[0x01],
+            &[0x81, b'f'],
+            &[0x00],
+            // deleted entry with the master fields at the master id
+            &[0x03],
+            &[0x00],
+            &[0x00],
+            &[0x84, b'g', b'o', b'n', b'e'],
+            &[0x04],
+            // entry with the master fields 300 ms and -1 seq later
+            &[0x02],
+            &[0xC1, 0x2C],
+            &[0xF1, 0xFF, 0xFF],
+            &[0x81, b'v'],
+            &[0x04],
+        ]);
+
+        let mut rdb = b"REDIS0011".to_vec();
+        rdb.extend_from_slice(&[0xFE, 0x00, 0x15, 0x01, b's', 0x01, 0x10]);
+        rdb.extend_from_slice(&1000u64.to_be_bytes());
+        rdb.extend_from_slice(&5u64.to_be_bytes());
+        rdb.extend_from_slice(&[0x40, node.len() as u8]);
+        rdb.extend_from_slice(&node);
+        // length, last id, first id, max deleted id, entries added and
+        // no consumer groups
+        rdb.extend_from_slice(&[0x01, 0x45, 0x14, 0x04, 0x45, 0x14, 0x04]);
+        rdb.extend_from_slice(&[0x43, 0xE8, 0x05, 0x02, 0x00]);
+        rdb.push(0xFF);
+        rdb.extend_from_slice(&[0; 8]);
+
+        let mut parser = RdbParser::new(DefaultFilter::new(), RdbBuilder::default(), rdb);
+        let database = parser.parse().unwrap().unwrap();
+        match &database.entries["s"].data {
+            ValueType::Stream(stream) => {
+                assert_eq!(stream.entries.len(), 1);
+                assert_eq!(stream.entries[0].id, (1300, 4));
+                assert_eq!(stream.entries[0].pairs["f"], "v");
+            }
+            value => panic!("expected a stream, got {value:?}"),
+        }
+    }
+}
@@ -0,0 +1,498 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use bytes::{BufMut, BytesMut};
+use tokio::time::Instant;
+
+use crate::{Hash, Stream, StreamData, Value, ValueType};
+
+use super::{backlen_size, constants, crc64, encoding_type, opcodes};
+
+/// Version written after the `REDIS` magic
+const RDB_VERSION: &str = "0012";
+
+/// Entries per stream listpack node, redis' default
+/// `stream-node-max-entries`
+const STREAM_NODE_MAX_ENTRIES: usize = 100;
+
+/// Encode the entries of every logical database, indexed by
+/// database number, into an RDB file
+///
+/// Expiries are written as unix timestamps in milliseconds
+pub fn encode_rdb(databases: &[Vec<(String, Value)>]) -> Vec<u8> {
+    let mut dst = BytesMut::new();
+    dst.put_slice(b"REDIS");
+    dst.put_slice(RDB_VERSION.as_bytes());
+
+    let unix_now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("Time since epoch not");
+
+    for (key, value) in [
+        ("redis-ver", env!("CARGO_PKG_VERSION").to_string()),
+        ("redis-bits", "64".to_string()),
+        ("ctime", unix_now.as_secs().to_string()),
+    ] {
+        dst.put_u8(opcodes::AUX);
+        put_string(&mut dst, key.as_bytes());
+        put_string(&mut dst, value.as_bytes());
+    }
+
+    let now = Instant::now();
+    for (index, entries) in databases.iter().enumerate() {
+        if entries.is_empty() {
+            continue;
+        }
+
+        dst.put_u8(opcodes::SELECTDB);
+        put_length(&mut dst, index);
+        dst.put_u8(opcodes::RESIZEDB);
+        put_length(&mut dst, entries.len());
+        put_length(
+            &mut dst,
+            entries
+                .iter()
+                .filter(|(_, value)| value.expires_at.is_some())
+                .count(),
+        );
+
+        for (key, value) in entries {
+            if let Some(expires_at) = value.expires_at {
+                let expires_at = unix_now + expires_at.saturating_duration_since(now);
+                dst.put_u8(opcodes::EXPIRETIME_MS);
+                dst.put_u64_le(expires_at.as_millis() as u64);
+            }
+            put_value(&mut dst, key, &value.data);
+        }
+    }
+
+    dst.put_u8(opcodes::EOF);
+    let checksum = crc64(&dst);
+    dst.put_u64_le(checksum);
+
+    dst.to_vec()
+}
+
+/// Number of bytes the writer encodes `value` in, not counting the
+/// type byte and key
+pub fn serialized_length(value: &ValueType) -> usize {
+    let mut dst = BytesMut::new();
+    put_payload(&mut dst, value);
+    dst.len()
+}
+
+/// Write the type byte, key and value of an entry
+fn put_value(dst: &mut BytesMut, key: &str, value: &ValueType) {
+    // the payload picks the type, a hash is only written with its
+    // field TTLs when it still has some
+    let mut payload = BytesMut::new();
+    let type_byte = put_payload(&mut payload, value);
+
+    dst.put_u8(type_byte);
+    put_string(dst, key.as_bytes());
+    dst.put_slice(&payload);
+}
+
+/// Write the value of an entry, returns the type it's written as
+fn put_payload(dst: &mut BytesMut, value: &ValueType) -> u8 {
+    match value {
+        ValueType::String(bytes) => {
+            put_string(dst, bytes);
+            encoding_type::STRING
+        }
+        ValueType::List(list) => {
+            put_length(dst, list.len());
+            for item in list {
+                put_string(dst, item);
+            }
+            encoding_type::LIST
+        }
+        ValueType::Set(set) => {
+            put_length(dst, set.len());
+            for member in set.iter() {
+                put_string(dst, member.as_bytes());
+            }
+            encoding_type::SET
+        }
+        ValueType::Hash(hash) => put_hash(dst, hash),
+        ValueType::ZSet(zset) => {
+            put_length(dst, zset.len());
+            for (member, score) in zset.iter() {
+                put_string(dst, member.as_bytes());
+                dst.put_f64_le(score);
+            }
+            encoding_type::ZSET_2
+        }
+        ValueType::Stream(stream) => {
+            put_stream(dst, stream);
+            encoding_type::STREAM_LISTPACKS
+        }
+    }
+}
+
+/// Write a hash as `HASH`, or as `HASH_METADATA` when fields have a
+/// TTL. Field TTLs are unix times in milliseconds stored relative to
+/// the earliest one plus 1, 0 is a field without a TTL
+fn put_hash(dst: &mut BytesMut, hash: &Hash) -> u8 {
+    // the fields are collected first so the length matches what's
+    // written even if a field expires meanwhile
+    let fields: Vec<(&String, &String, Option<Instant>)> = hash.iter_with_ttl().collect();
+
+    let unix_now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("Time since epoch not");
+    let now = Instant::now();
+    let unix_millis =
+        |at: Instant| (unix_now + at.saturating_duration_since(now)).as_millis() as u64;
+
+    let min_expire = match fields.iter().filter_map(|(_, _, at)| *at).min() {
+        Some(at) => unix_millis(at),
+        None => {
+            put_length(dst, fields.len());
+            for (field, value, _) in fields {
+                put_string(dst, field.as_bytes());
+                put_string(dst, value.as_bytes());
+            }
+            return encoding_type::HASH;
+        }
+    };
+
+    dst.put_u64_le(min_expire);
+    put_length(dst, fields.len());
+    for (field, value, at) in fields {
+        let ttl = at.map_or(0, |at| unix_millis(at) - min_expire + 1);
+        put_length(dst, ttl as usize);
+        put_string(dst, field.as_bytes());
+        put_string(dst, value.as_bytes());
+    }
+
+    encoding_type::HASH_METADATA
+}
+
+/// Write a stream as `STREAM_LISTPACKS`, listpack nodes of up to
+/// `STREAM_NODE_MAX_ENTRIES` entries keyed by the id of their first
+/// entry, followed by the length and last id. Streams have no consumer
+/// groups so the group count is always 0
+fn put_stream(dst: &mut BytesMut, stream: &Stream) {
+    let nodes = stream.entries.chunks(STREAM_NODE_MAX_ENTRIES);
+    put_length(dst, nodes.len());
+    for node in nodes {
+        let (ms, seq) = node[0].id;
+        let mut master_id = ms.to_be_bytes().to_vec();
+        master_id.extend_from_slice(&seq.to_be_bytes());
+        put_string(dst, &master_id);
+        put_string(dst, &stream_listpack(node));
+    }
+
+    let (ms, seq) = stream.last_id;
+    put_length(dst, stream.entries.len());
+    put_length(dst, ms as usize);
+    put_length(dst, seq as usize);
+    put_length(dst, 0);
+}
+
+/// Listpack of a stream node, the master entry followed by every entry
+/// with its fields. Ids are stored relative to the first entry's
+fn stream_listpack(node: &[StreamData]) -> Vec<u8> {
+    let (master_ms, master_seq) = node[0].id;
+    let master_fields: Vec<&String> = node[0].pairs.keys().collect();
+
+    // count, deleted count, the master fields and a closing 0
+    let mut elements = vec![
+        ListpackEntry::Int(node.len() as i64),
+        ListpackEntry::Int(0),
+        ListpackEntry::Int(master_fields.len() as i64),
+    ];
+    elements.extend(
+        master_fields
+            .iter()
+            .map(|field| ListpackEntry::Str(field.as_bytes())),
+    );
+    elements.push(ListpackEntry::Int(0));
+
+    for entry in node {
+        let (ms, seq) = entry.id;
+        // no flags, the entry lists its own fields
+        elements.push(ListpackEntry::Int(0));
+        elements.push(ListpackEntry::Int(ms.wrapping_sub(master_ms) as i64));
+        elements.push(ListpackEntry::Int(seq.wrapping_sub(master_seq) as i64));
+        elements.push(ListpackEntry::Int(entry.pairs.len() as i64));
+        for (field, value) in &entry.pairs {
+            elements.push(ListpackEntry::Str(field.as_bytes()));
+            elements.push(ListpackEntry::Str(value.as_bytes()));
+        }
+        // lp-count, the number of elements of the entry before it
+        elements.push(ListpackEntry::Int(entry.pairs.len() as i64 * 2 + 4));
+    }
+
+    encode_listpack(&elements)
+}
+
+/// Element of a listpack
+enum ListpackEntry<'a> {
+    Int(i64),
+    Str(&'a [u8]),
+}
+
+/// Encode `elements` as a listpack, small integers take a single byte
+/// and every other one 8, strings take a 1, 2 or 5 byte header
+fn encode_listpack(elements: &[ListpackEntry]) -> Vec<u8> {
+    let mut dst = BytesMut::new();
+    // total bytes, filled in once known
+    dst.put_u32_le(0);
+    dst.put_u16_le(elements.len().min(u16::MAX as usize) as u16);
+
+    for element in elements {
+        let start = dst.len();
+        match *element {
+            ListpackEntry::Int(int @ 0..=127) => dst.put_u8(int as u8),
+            ListpackEntry::Int(int) => {
+                dst.put_u8(0xF4);
+                dst.put_i64_le(int);
+            }
+            ListpackEntry::Str(bytes) => {
+                let len = bytes.len();
+                if len < 1 << 6 {
+                    dst.put_u8(0x80 | len as u8);
+                } else if len < 1 << 12 {
+                    dst.put_u8(0xE0 | (len >> 8) as u8);
+                    dst.put_u8(len as u8);
+                } else {
+                    dst.put_u8(0xF0);
+                    dst.put_u32_le(len as u32);
+                }
+                dst.put_slice(bytes);
+            }
+        }
+        let len = dst.len() - start;
+        put_backlen(&mut dst, len);
+    }
+
+    dst.put_u8(0xFF);
+    let total = dst.len() as u32;
+    dst[..4].copy_from_slice(&total.to_le_bytes());
+
+    dst.to_vec()
+}
+
+/// Write the length of a listpack element so it reads back to front,
+/// 7 bits per byte with the high bit set on all but the first byte
+fn put_backlen(dst: &mut BytesMut, len: usize) {
+    let bytes = backlen_size(len);
+    for i in (0..bytes).rev() {
+        let byte = ((len >> (7 * i)) & 127) as u8;
+        dst.put_u8(if i == bytes - 1 { byte } else { byte | 128 });
+    }
+}
+
+/// Length prefixed string, always written raw without int or LZF encoding
+fn put_string(dst: &mut BytesMut, bytes: &[u8]) {
+    put_length(dst, bytes.len());
+    dst.put_slice(bytes);
+}
+
+/// Write a length with the bit layout `get_length_with_encoding` reads,
+/// 6 bits in one byte, 14 bits over two bytes or a 32 or 64 bit big
+/// endian int
+fn put_length(dst: &mut BytesMut, len: usize) {
+    if len < 1 << 6 {
+        dst.put_u8(len as u8);
+    } else if len < 1 << 14 {
+        dst.put_u8(0x40 | (len >> 8) as u8);
+        dst.put_u8(len as u8);
+    } else if let Ok(len) = u32::try_from(len) {
+        dst.put_u8(constants::RDB_32BITLEN);
+        dst.put_u32(len);
+    } else {
+        dst.put_u8(constants::RDB_64BITLEN);
+        dst.put_u64(len as u64);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::{HashMap, HashSet, VecDeque};
+
+    use bytes::Bytes;
+    use tokio::time::{Duration, Instant};
+
+    use super::encode_rdb;
+    use crate::{
+        rdb::{crc64, DefaultFilter, RdbBuilder, RdbParser},
+        Db, Hash, Set, Stream, StreamData, ValueType,
+    };
+
+    #[tokio::test]
+    async fn saved_entries_and_expiries_parse_back() {
+        let db = Db::new();
+        db.set(
+            "string".into(),
+            ValueType::String(Bytes::from("value")),
+            Some(Duration::from_secs(100)),
+        );
+        db.set(
+            "list".into(),
+            ValueType::List(VecDeque::from([Bytes::from("a"), Bytes::from("b")])),
+            None,
+        );
+        db.set(
+            "set".into(),
+            ValueType::Set(Set::from(HashSet::from(["m".to_string()]))),
+            None,
+        );
+        db.zadd(
+            "zset".into(),
+            vec![(1.5, "one".into()), (-2.0, "two".into())],
+        )
+        .unwrap();
+        // long enough for a 14 bit length
+        db.set(
+            "long".into(),
+            ValueType::String(Bytes::from("x".repeat(100))),
+            None,
+        );
+
+        let rdb = encode_rdb(&db.snapshot());
+        assert!(rdb.starts_with(b"REDIS0012"));
+        let (body, checksum) = rdb.split_at(rdb.len() - 8);
+        assert_eq!(crc64(body).to_le_bytes(), checksum);
+
+        let mut parser = RdbParser::new(DefaultFilter::new(), RdbBuilder::default(), rdb);
+        let database = parser.parse().unwrap().unwrap();
+        assert_eq!(database.entries.len(), 5);
+
+        let string = &database.entries["string"];
+        assert!(matches!(&string.data, ValueType::String(v) if v == "value"));
+        let ttl = string.expires_at.unwrap() - Instant::now();
+        assert!(ttl > Duration::from_secs(98) && ttl <= Duration::from_secs(100));
+        assert_eq!(database.expirations.len(), 1);
+
+        assert!(matches!(&database.entries["list"].data, ValueType::List(l) if l == &["a", "b"]));
+        assert!(matches!(&database.entries["set"].data, ValueType::Set(s) if s.contains("m")));
+        assert!(matches!(&database.entries["long"].data, ValueType::String(v) if v.len() == 100));
+        match &database.entries["zset"].data {
+            ValueType::ZSet(zset) => {
+                let members: Vec<(&String, f64)> = zset.iter().collect();
+                assert_eq!(
+                    members,
+                    [(&"two".to_string(), -2.0), (&"one".to_string(), 1.5)]
+                );
+            }
+            value => panic!("expected a zset, got {value:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn streams_parse_back_entry_for_entry() {
+        let entry = |id: (u64, u64), pairs: &[(&str, String)]| StreamData {
+            id,
+            pairs: pairs
+                .iter()
+                .map(|(field, value)| (field.to_string(), value.clone()))
+                .collect::<HashMap<_, _>>(),
+            _created_at: Instant::now(),
+        };
+
+        // more entries than fit a node, ids past 32 bits, a sequence
+        // below the node's first one and a value long enough for a 32
+        // bit listpack string
+        let mut stream = vec![entry((1, 5), &[("a", "1".into())])];
+        for ms in 2..250 {
+            stream.push(entry(
+                (1_700_000_000_000 + ms, 0),
+                &[("a", ms.to_string()), ("b", "-7".into())],
+            ));
+        }
+        stream.push(entry((u64::MAX, u64::MAX), &[("long", "x".repeat(5000))]));
+
+        let db = Db::new();
+        db.set(
+            "stream".into(),
+            ValueType::Stream(stream.clone().into()),
+            None,
+        );
+        db.set("empty".into(), ValueType::Stream(Stream::default()), None);
+
+        let rdb = encode_rdb(&db.snapshot());
+        let mut parser = RdbParser::new(DefaultFilter::new(), RdbBuilder::default(), rdb);
+        let database = parser.parse().unwrap().unwrap();
+
+        let ids_and_pairs = |value: &ValueType| match value {
+            ValueType::Stream(stream) => stream
+                .entries
+                .iter()
+                .map(|entry| (entry.id, entry.pairs.clone()))
+                .collect::<Vec<_>>(),
+            value => panic!("expected a stream, got {value:?}"),
+        };
+        assert_eq!(
+            ids_and_pairs(&database.entries["stream"].data),
+            ids_and_pairs(&ValueType::Stream(stream.into()))
+        );
+        assert!(ids_and_pairs(&database.entries["empty"].data).is_empty());
+    }
+
+    #[tokio::test]
+    async fn stream_last_id_outlives_its_entries() {
+        let db = Db::new();
+        let stream = Stream {
+            entries: vec![],
+            last_id: (5, 3),
+        };
+        db.set("stream".into(), ValueType::Stream(stream), None);
+
+        let rdb = encode_rdb(&db.snapshot());
+        let mut parser = RdbParser::new(DefaultFilter::new(), RdbBuilder::default(), rdb);
+        let database = parser.parse().unwrap().unwrap();
+        match &database.entries["stream"].data {
+            ValueType::Stream(stream) => assert_eq!(stream.last_id, (5, 3)),
+            value => panic!("expected a stream, got {value:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn hash_field_ttls_parse_back() {
+        let db = Db::new();
+        let hash = HashMap::from([
+            ("a".to_string(), "1".to_string()),
+            ("b".to_string(), "2".to_string()),
+            ("c".to_string(), "3".to_string()),
+        ]);
+        db.set(
+            "ttls".into(),
+            ValueType::Hash(Hash::from(hash.clone())),
+            None,
+        );
+        db.set("plain".into(), ValueType::Hash(Hash::from(hash)), None);
+        let now = Instant::now();
+        db.hexpire("ttls", &["a".into()], now + Duration::from_secs(100))
+            .unwrap();
+        db.hexpire("ttls", &["b".into()], now + Duration::from_secs(200))
+            .unwrap();
+
+        let rdb = encode_rdb(&db.snapshot());
+        let mut parser = RdbParser::new(DefaultFilter::new(), RdbBuilder::default(), rdb);
+        let database = parser.parse().unwrap().unwrap();
+
+        let hash = |key: &str| match &database.entries[key].data {
+            ValueType::Hash(hash) => hash.clone(),
+            value => panic!("expected a hash, got {value:?}"),
+        };
+        let ttl = |hash: &Hash, field: &str| {
+            hash.expires_at(field)
+                .unwrap()
+                .map(|at| at.saturating_duration_since(Instant::now()))
+        };
+
+        let ttls = hash("ttls");
+        assert_eq!(ttls.len(), 3);
+        let a = ttl(&ttls, "a").unwrap();
+        assert!(a > Duration::from_secs(98) && a <= Duration::from_secs(100));
+        let b = ttl(&ttls, "b").unwrap();
+        assert!(b > Duration::from_secs(198) && b <= Duration::from_secs(200));
+        assert_eq!(ttl(&ttls, "c"), None);
+
+        let plain = hash("plain");
+        assert_eq!(plain.len(), 3);
+        assert!(["a", "b", "c"].iter().all(|f| ttl(&plain, f).is_none()));
+    }
+}
@@ -1,4 +1,12 @@
-use std::fmt;
+use std::{
+    fmt,
+    sync::{atomic::Ordering, Arc},
+    time::Duration,
+};
+
+use tokio::{sync::RwLock, time};
+
+use crate::{connection::Connection, durability::DurabilityBarrier, is_keyword, Command, Replconf};
 
 #[derive(Debug, Clone)]
 pub struct ReplicaInfo {
@@ -28,8 +36,273 @@ impl fmt::Display for Role {
     }
 }
 
+/// Ask the replicas for their offset with `REPLCONF GETACK *` and wait
+/// until `needed` replicas have acknowledged `target_offset` or the
+/// timeout runs out, `None` waits without a timeout
+///
+/// Returns how many replicas acknowledged `target_offset`. The ACKs are
+/// read off the links by the tasks from `spawn_ack_reader`, this waits
+/// on `barrier` for them to record one, see `Connection::acked_offset`
+pub async fn poll_replica_acks(
+    barrier: &DurabilityBarrier,
+    replicas: &RwLock<Vec<Connection>>,
+    target_offset: u64,
+    needed: u64,
+    timeout: Option<Duration>,
+) -> u64 {
+    // nothing was replicated yet, every replica is in sync
+    if target_offset == 0 {
+        return count_synced(replicas, 0).await;
+    }
+
+    let synced = count_synced(replicas, target_offset).await;
+    if synced >= needed {
+        return synced;
+    }
+
+    let getack = Replconf::getack();
+
+    for (idx, connection) in replicas.write().await.iter_mut().enumerate() {
+        if !connection.is_replica || connection.acked_offset() >= target_offset {
+            continue;
+        }
+
+        let sent = time::timeout(Duration::from_millis(5), connection.write_frame(&getack)).await;
+        if !matches!(sent, Ok(Ok(()))) {
+            println!("Failed to send GETACK to Replica: {idx}");
+            continue;
+        }
+        connection
+            .repl_offset
+            .fetch_add(getack.encoded_len() as u64, Ordering::SeqCst);
+    }
+
+    barrier
+        .wait(replicas, target_offset, false, needed, timeout)
+        .await
+        .replicas
+}
+
+/// Number of replicas that acknowledged `target_offset`
+pub(crate) async fn count_synced(replicas: &RwLock<Vec<Connection>>, target_offset: u64) -> u64 {
+    replicas
+        .read()
+        .await
+        .iter()
+        .filter(|connection| connection.is_replica && connection.acked_offset() >= target_offset)
+        .count() as u64
+}
+
+/// Hand the read side of a replica link to a task of its own that
+/// records every ACK the replica sends, whether or not a WAIT asked
+/// for it, so writes and WAIT never compete for the socket
+///
+/// Every ACK wakes the waiters on `barrier`. Returns the writer half to
+/// replicate to, the task stops and the socket closes once it is dropped
+pub fn spawn_ack_reader(connection: Connection, barrier: Arc<DurabilityBarrier>) -> Connection {
+    let (mut reader, writer, mut closed) = connection.split();
+
+    tokio::spawn(async move {
+        loop {
+            let resp = tokio::select! {
+                _ = &mut closed => return,
+                resp = reader.read_resp() => match resp {
+                    Ok(Some((resp, _))) => resp,
+                    _ => return,
+                },
+            };
+
+            let ack = match Command::from_resp(resp) {
+                Ok(Command::Replconf(cmd)) => match cmd.values.as_slice() {
+                    [key, offset, ..] if is_keyword(key, "ack") => offset.parse().ok(),
+                    _ => None,
+                },
+                _ => None,
+            };
+
+            if let Some(ack) = ack {
+                reader.record_ack(ack);
+                barrier.record_ack();
+            }
+        }
+    });
+
+    writer
+}
+
 // impl PartialEq for Role {
 //     fn eq(&self, other: &Self) -> bool {
 //         self.to_string() == other.to_string()
 //     }
 // }
+
+#[cfg(test)]
+mod test {
+    use std::{sync::Arc, time::Duration};
+
+    use tokio::{
+        io::{duplex, AsyncReadExt, AsyncWriteExt, DuplexStream},
+        sync::RwLock,
+    };
+
+    use super::{poll_replica_acks, spawn_ack_reader};
+    use crate::{connection::Connection, durability::DurabilityBarrier};
+
+    const GETACK: &[u8] = b"*3\r\n$8\r\nREPLCONF\r\n$6\r\nGETACK\r\n$1\r\n*\r\n";
+
+    /// Answer a single GETACK with an ACK of `offset`
+    async fn ack(mut replica: DuplexStream, offset: u64) -> DuplexStream {
+        let mut getack = vec![0u8; GETACK.len()];
+        replica.read_exact(&mut getack).await.unwrap();
+        assert_eq!(getack, GETACK);
+
+        let offset = offset.to_string();
+        let ack = format!(
+            "*3\r\n$8\r\nREPLCONF\r\n$3\r\nACK\r\n${}\r\n{}\r\n",
+            offset.len(),
+            offset
+        );
+        replica.write_all(ack.as_bytes()).await.unwrap();
+        replica
+    }
+
+    /// The master's side of the link to a replica that completed PSYNC
+    fn replica(stream: DuplexStream, barrier: &Arc<DurabilityBarrier>) -> Connection {
+        let mut connection = Connection::from_stream(stream, false);
+        connection.is_replica = true;
+        spawn_ack_reader(connection, barrier.clone())
+    }
+
+    #[tokio::test]
+    async fn poll_replica_acks_counts_replicas_at_the_target() {
+        let barrier = Arc::new(DurabilityBarrier::new());
+        let (ahead, ahead_replica) = duplex(1024);
+        let (behind, behind_replica) = duplex(1024);
+        let replicas = RwLock::new(vec![replica(ahead, &barrier), replica(behind, &barrier)]);
+
+        let ahead_replica = tokio::spawn(ack(ahead_replica, 100));
+        let behind_replica = tokio::spawn(ack(behind_replica, 10));
+
+        // the lagging replica never catches up so this runs out the timeout
+        let synced =
+            poll_replica_acks(&barrier, &replicas, 50, 2, Some(Duration::from_millis(100))).await;
+        assert_eq!(synced, 1);
+
+        let _ahead_replica = ahead_replica.await.unwrap();
+        let _behind_replica = behind_replica.await.unwrap();
+        let acked = replicas
+            .read()
+            .await
+            .iter()
+            .map(Connection::acked_offset)
+            .collect::<Vec<_>>();
+        assert_eq!(acked, vec![100, 10]);
+
+        // a replica already known to be in sync isn't asked again
+        let synced = poll_replica_acks(&barrier, &replicas, 50, 1, None).await;
+        assert_eq!(synced, 1);
+    }
+
+    #[tokio::test]
+    async fn silent_replica_runs_out_the_timeout_with_a_partial_count() {
+        let barrier = Arc::new(DurabilityBarrier::new());
+        let (acking, acking_replica) = duplex(1024);
+        let (silent, _silent_replica) = duplex(1024);
+        let replicas = RwLock::new(vec![replica(acking, &barrier), replica(silent, &barrier)]);
+
+        let acking_replica = tokio::spawn(ack(acking_replica, 100));
+
+        // the test runtime has a single thread, the ticker only gets to
+        // run if the wait yields
+        let ticker = tokio::spawn(async {
+            let mut ticks = 0;
+            loop {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+                ticks += 1;
+                if ticks == 5 {
+                    return ticks;
+                }
+            }
+        });
+
+        let started = tokio::time::Instant::now();
+        let timeout = Duration::from_millis(150);
+        let synced = tokio::time::timeout(
+            Duration::from_secs(2),
+            poll_replica_acks(&barrier, &replicas, 50, 2, Some(timeout)),
+        )
+        .await
+        .expect("WAIT hung on a silent replica");
+
+        assert_eq!(synced, 1);
+        assert!(started.elapsed() >= timeout);
+        assert!(ticker.is_finished());
+        let _acking_replica = acking_replica.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn only_replica_connections_are_counted_and_asked_for_acks() {
+        let barrier = Arc::new(DurabilityBarrier::new());
+        let (replica_stream, replica_end) = duplex(1024);
+        let (client_stream, mut client_end) = duplex(1024);
+
+        // a connection that never completed PSYNC, even with an ACK
+        // recorded it doesn't count
+        let client = Connection::from_stream(client_stream, false);
+        client.record_ack(100);
+        let replicas = RwLock::new(vec![client, replica(replica_stream, &barrier)]);
+
+        assert_eq!(poll_replica_acks(&barrier, &replicas, 0, 2, None).await, 1);
+
+        let replica_end = tokio::spawn(ack(replica_end, 100));
+        let synced =
+            poll_replica_acks(&barrier, &replicas, 50, 2, Some(Duration::from_millis(100))).await;
+        assert_eq!(synced, 1);
+        let _replica_end = replica_end.await.unwrap();
+
+        // no GETACK was sent to the other connection
+        let mut buf = [0u8; 1];
+        let read = tokio::time::timeout(Duration::from_millis(20), client_end.read(&mut buf)).await;
+        assert!(read.is_err());
+    }
+
+    #[tokio::test]
+    async fn acks_sent_outside_of_wait_are_recorded() {
+        let barrier = Arc::new(DurabilityBarrier::new());
+        let (stream, mut replica_end) = duplex(1024);
+        let replicas = RwLock::new(vec![replica(stream, &barrier)]);
+
+        // a replica reports its offset on its own, no GETACK was sent
+        replica_end
+            .write_all(b"*3\r\n$8\r\nREPLCONF\r\n$3\r\nACK\r\n$2\r\n42\r\n")
+            .await
+            .unwrap();
+
+        let recorded = async {
+            while replicas.read().await[0].acked_offset() != 42 {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+        };
+        tokio::time::timeout(Duration::from_secs(1), recorded)
+            .await
+            .expect("the ACK was never recorded");
+
+        // WAIT is satisfied without asking the replica again
+        assert_eq!(poll_replica_acks(&barrier, &replicas, 42, 1, None).await, 1);
+        let mut buf = [0u8; 1];
+        let read =
+            tokio::time::timeout(Duration::from_millis(20), replica_end.read(&mut buf)).await;
+        assert!(read.is_err());
+    }
+
+    #[tokio::test]
+    async fn dropping_the_writer_closes_the_link() {
+        let barrier = Arc::new(DurabilityBarrier::new());
+        let (stream, mut replica_end) = duplex(1024);
+        drop(replica(stream, &barrier));
+
+        let mut buf = [0u8; 1];
+        let read = tokio::time::timeout(Duration::from_secs(1), replica_end.read(&mut buf)).await;
+        assert!(matches!(read, Ok(Ok(0))));
+    }
+}
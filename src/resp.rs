@@ -17,16 +17,32 @@ pub const TERMINATOR: &str = "\r\n";
 pub enum RESP {
     Simple(String),
     Error(String),
-    Integer(u64),
+    Integer(i64),
     Bulk(Bytes),
+    /// RDB payload of a full resync, a bulk string without the trailing
+    /// CRLF. It only comes out of parsing the replication stream and is
+    /// sent with `Connection::write_rdb_file`, `write_frame` refuses it
+    /// so it can't be returned as a command reply
     File(Bytes),
     Null,
+    /// Null array `*-1`, written as a plain null to RESP3 clients
+    NullArray,
     Array(Vec<RESP>),
+    /// RESP3 map, written as a flat array of keys and values to RESP2 clients
+    Map(Vec<(RESP, RESP)>),
+    /// RESP3 double, written as a bulk string to RESP2 clients
+    Double(f64),
+    /// RESP3 boolean, written as an integer to RESP2 clients
+    Boolean(bool),
+    /// RESP3 big number, written as a bulk string to RESP2 clients
+    BigNumber(String),
 }
 
 #[derive(Debug)]
 pub enum RESPError {
     Incomplete,
+    /// A bulk string header announced more than the allowed length
+    BulkTooLong,
     Other(crate::Error),
 }
 
@@ -60,13 +76,46 @@ impl RESP {
     /// # Panics
     ///
     /// Panics if `self` is not an array
-    pub fn push_int(&mut self, value: u64) {
+    pub fn push_int(&mut self, value: i64) {
         match self {
             RESP::Array(vec) => vec.push(RESP::Integer(value)),
             _ => panic!("Not `RESP::Array`"),
         }
     }
 
+    /// Number of bytes `self` occupies once written to a RESP2
+    /// connection, the protocol replicas are always spoken to in
+    pub fn encoded_len(&self) -> usize {
+        // prefix byte, the decimal digits and the trailing CRLF
+        fn header(decimal: usize) -> usize {
+            1 + decimal.to_string().len() + 2
+        }
+
+        match self {
+            RESP::Simple(string) | RESP::Error(string) => 1 + string.len() + 2,
+            RESP::Integer(int) => 1 + int.to_string().len() + 2,
+            RESP::Bulk(data) => header(data.len()) + data.len() + 2,
+            RESP::File(data) => header(data.len()) + data.len(),
+            RESP::Null | RESP::NullArray => 5,
+            RESP::Array(list) => {
+                header(list.len()) + list.iter().map(RESP::encoded_len).sum::<usize>()
+            }
+            RESP::Map(pairs) => {
+                header(pairs.len() * 2)
+                    + pairs
+                        .iter()
+                        .map(|(key, value)| key.encoded_len() + value.encoded_len())
+                        .sum::<usize>()
+            }
+            RESP::Double(double) => {
+                let len = format_double(*double).len();
+                header(len) + len + 2
+            }
+            RESP::Boolean(_) => 4,
+            RESP::BigNumber(number) => header(number.len()) + number.len() + 2,
+        }
+    }
+
     /// Parse the message from the client
     pub fn parse_resp(cursor: &mut Cursor<&[u8]>) -> Result<RESP, RESPError> {
         match get_u8(cursor)? {
@@ -76,6 +125,12 @@ impl RESP {
                 Ok(RESP::Simple(string))
             }
             b'*' => {
+                if b'-' == peak_u8(cursor)? {
+                    // null array '*-1\r\n'
+                    get_line(cursor)?;
+                    return Ok(RESP::Null);
+                }
+
                 let len = get_decimal(cursor)?.try_into()?;
                 let mut out = Vec::with_capacity(len as usize);
                 for _ in 0..len {
@@ -101,17 +156,7 @@ impl RESP {
                     let data = Bytes::copy_from_slice(&cursor.chunk()[..len]);
                     skip(cursor, len)?;
 
-                    let pos = cursor.position() as usize;
-
-                    let clrf = if cursor.has_remaining() {
-                        &cursor.get_ref()[pos..pos + 2] == b"\r\n"
-                    } else {
-                        false
-                    };
-
-                    if clrf {
-                        // skip that number of bytes + 2
-                        skip(cursor, 2)?;
+                    if bulk_terminated(cursor)? {
                         Ok(RESP::Bulk(data))
                     } else {
                         Ok(RESP::File(data))
@@ -119,8 +164,8 @@ impl RESP {
                 }
             }
             b':' => {
-                // integer data type (u64)
-                let int = get_decimal(cursor)?;
+                // integer data type (i64)
+                let int = get_signed_decimal(cursor)?;
                 Ok(RESP::Integer(int))
             }
             b'-' => {
@@ -131,6 +176,7 @@ impl RESP {
             }
             b'_' => {
                 // null data type
+                get_line(cursor)?;
                 Ok(RESP::Null)
             }
             raw => Err(format!("Invalid RESP data type: `{}`", raw).into()),
@@ -140,6 +186,13 @@ impl RESP {
     #[allow(unused)]
     /// Validate if a message can be decoded from the `src`
     pub fn check(src: &mut Cursor<&[u8]>) -> Result<(), RESPError> {
+        Self::check_with_limit(src, usize::MAX)
+    }
+
+    /// Validate if a message can be decoded from the `src`, rejecting
+    /// bulk strings longer than `max_bulk_len` as soon as their header
+    /// is read so their data is never buffered
+    pub fn check_with_limit(src: &mut Cursor<&[u8]>, max_bulk_len: usize) -> Result<(), RESPError> {
         match get_u8(src)? {
             b'+' => {
                 // strings resp
@@ -148,9 +201,15 @@ impl RESP {
             }
             b'*' => {
                 // arrays resp
+                if b'-' == peak_u8(src)? {
+                    // null array '*-1\r\n'
+                    get_line(src)?;
+                    return Ok(());
+                }
+
                 let len = get_decimal(src)?;
-                for i in 0..len {
-                    Self::check(src)?;
+                for _ in 0..len {
+                    Self::check_with_limit(src, max_bulk_len)?;
                 }
                 Ok(())
             }
@@ -158,31 +217,23 @@ impl RESP {
                 // bulk strings resp
                 if b'-' == peak_u8(src)? {
                     // '-1\r\n'
-                    skip(src, 4)?;
+                    get_line(src)?;
                     Ok(())
                 } else {
-                    let len = get_decimal(src)?.try_into()?;
+                    let len: usize = get_decimal(src)?.try_into()?;
+                    if len > max_bulk_len {
+                        return Err(RESPError::BulkTooLong);
+                    }
 
                     skip(src, len)?;
+                    bulk_terminated(src)?;
 
-                    let pos = src.position() as usize;
-
-                    let clrf = if src.has_remaining() {
-                        &src.get_ref()[pos..pos + 2] == b"\r\n"
-                    } else {
-                        false
-                    };
-
-                    if clrf {
-                        // skip that number of bytes + 2
-                        skip(src, 2)?;
-                    }
                     Ok(())
                 }
             }
             b':' => {
                 // integers resp
-                get_decimal(src)?;
+                get_signed_decimal(src)?;
                 Ok(())
             }
             b'-' => {
@@ -193,6 +244,7 @@ impl RESP {
             }
             b'_' => {
                 // null resp
+                get_line(src)?;
                 Ok(())
             }
             err => Err(format!("Error reading request {}", err).into()),
@@ -200,9 +252,33 @@ impl RESP {
     }
 }
 
+/// Format a double the way redis writes it, `1.5`, `3`, `inf` or `nan`
+pub fn format_double(double: f64) -> String {
+    if double.is_nan() {
+        "nan".to_string()
+    } else {
+        double.to_string()
+    }
+}
+
+/// Consume the CRLF following a bulk string's data
+///
+/// A bulk without one is an RDB file sent during a full resync,
+/// a lone `\r` is a CRLF that hasn't fully arrived yet
+fn bulk_terminated(src: &mut Cursor<&[u8]>) -> Result<bool, RESPError> {
+    match src.chunk() {
+        [b'\r', b'\n', ..] => {
+            skip(src, 2)?;
+            Ok(true)
+        }
+        [b'\r'] => Err(RESPError::Incomplete),
+        _ => Ok(false),
+    }
+}
+
 pub fn get_line<'a>(src: &'a mut Cursor<&[u8]>) -> Result<&'a [u8], RESPError> {
     let start = src.position() as usize;
-    let end = src.get_ref().len() - 1;
+    let end = src.get_ref().len().saturating_sub(1);
 
     for i in start..end {
         if src.get_ref()[i] == b'\r' && src.get_ref()[i + 1] == b'\n' {
@@ -233,13 +309,24 @@ pub fn get_u8(src: &mut Cursor<&[u8]>) -> Result<u8, RESPError> {
 pub fn get_decimal(src: &mut Cursor<&[u8]>) -> Result<u64, RESPError> {
     let line = get_line(src)?.to_vec();
     let string = String::from_utf8(line)?;
-    let int: u64 = string.parse().unwrap();
+    let int = string
+        .parse()
+        .map_err(|_| format!("Invalid length: `{}`", string))?;
+    Ok(int)
+}
+
+pub fn get_signed_decimal(src: &mut Cursor<&[u8]>) -> Result<i64, RESPError> {
+    let line = get_line(src)?.to_vec();
+    let string = String::from_utf8(line)?;
+    let int = string
+        .parse()
+        .map_err(|_| format!("Invalid integer: `{}`", string))?;
     Ok(int)
 }
 
 pub fn write_decimal(dst: &mut BufWriter<&mut TcpStream>, val: u64) -> io::Result<()> {
     // use std::io::Write;
-    let mut buf = [0u8, 20];
+    let mut buf = [0u8; 20];
     let mut buf = Cursor::new(&mut buf[..]);
 
     write!(&mut buf, "{}", val).unwrap();
@@ -252,6 +339,10 @@ pub fn write_decimal(dst: &mut BufWriter<&mut TcpStream>, val: u64) -> io::Resul
 }
 
 pub fn skip(src: &mut Cursor<&[u8]>, n: usize) -> Result<(), RESPError> {
+    if src.remaining() < n {
+        return Err(RESPError::Incomplete);
+    }
+
     src.advance(n);
     Ok(())
 }
@@ -284,6 +375,7 @@ impl fmt::Display for RESPError {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
         match self {
             RESPError::Incomplete => "stream ended early".fmt(fmt),
+            RESPError::BulkTooLong => "ERR Protocol error: invalid bulk length".fmt(fmt),
             RESPError::Other(err) => err.fmt(fmt),
         }
     }
@@ -320,3 +412,78 @@ impl From<&StreamData> for RESP {
         resp
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::io::Cursor;
+
+    use super::{RESPError, RESP};
+
+    /// One encoding of every variant a peer can send
+    const FRAMES: &[&[u8]] = &[
+        b"+OK\r\n",
+        b"-ERR oops\r\n",
+        b":-42\r\n",
+        b"$5\r\nhello\r\n",
+        b"$0\r\n\r\n",
+        b"$-1\r\n",
+        b"*-1\r\n",
+        b"_\r\n",
+        b"*2\r\n$3\r\nget\r\n*1\r\n:1\r\n",
+    ];
+
+    #[test]
+    fn check_accepts_every_complete_frame() {
+        for frame in FRAMES {
+            // a second frame right behind it must be left alone
+            let buf = [*frame, b"+NEXT\r\n"].concat();
+
+            let mut cursor = Cursor::new(&buf[..]);
+            RESP::check(&mut cursor).unwrap();
+            assert_eq!(cursor.position() as usize, frame.len(), "{frame:?}");
+
+            let mut cursor = Cursor::new(&buf[..]);
+            let resp = RESP::parse_resp(&mut cursor).unwrap();
+            assert_eq!(cursor.position() as usize, frame.len(), "{frame:?}");
+            assert!(!matches!(resp, RESP::File(_)), "{frame:?}");
+        }
+
+        // a bulk that isn't followed by a CRLF is an RDB file
+        let mut cursor = Cursor::new(&b"$3\r\nrdb"[..]);
+        assert!(matches!(RESP::parse_resp(&mut cursor), Ok(RESP::File(data)) if data == "rdb"));
+    }
+
+    #[test]
+    fn check_reports_partial_frames_as_incomplete() {
+        for frame in FRAMES {
+            for len in 0..frame.len() {
+                let mut cursor = Cursor::new(&frame[..len]);
+                match RESP::check(&mut cursor) {
+                    Err(RESPError::Incomplete) => {}
+                    // the data of a bulk without its CRLF reads as an RDB file
+                    Ok(()) if frame.starts_with(b"$") || frame.starts_with(b"*2") => {}
+                    res => panic!("{:?} cut at {len}: {res:?}", frame),
+                }
+            }
+        }
+
+        let mut cursor = Cursor::new(&b"*abc\r\n"[..]);
+        assert!(matches!(RESP::check(&mut cursor), Err(RESPError::Other(_))));
+    }
+
+    #[test]
+    fn check_rejects_bulk_headers_past_the_limit() {
+        let mut cursor = Cursor::new(&b"*2\r\n$4\r\nabcd\r\n$4\r\nab"[..]);
+        assert!(matches!(
+            RESP::check_with_limit(&mut cursor, 4),
+            Err(RESPError::Incomplete)
+        ));
+
+        // the data doesn't have to arrive for the header to be refused
+        let mut cursor = Cursor::new(&b"*3\r\n$3\r\nSET\r\n$1\r\nk\r\n$10\r\n"[..]);
+        assert!(matches!(
+            RESP::check_with_limit(&mut cursor, 4),
+            Err(RESPError::BulkTooLong)
+        ));
+    }
+}
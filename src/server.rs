@@ -14,30 +14,51 @@
 
 use std::{
     future::Future,
+    net::SocketAddr,
     path::Path,
     sync::{
-        atomic::{AtomicU64, AtomicUsize, Ordering},
+        atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
         Arc,
     },
-    time::Duration,
+    time::{Duration, Instant},
 };
 
 use tokio::{
-    net::{TcpListener, TcpStream},
-    sync::{broadcast, mpsc, RwLock},
+    io::AsyncWriteExt,
+    net::{TcpListener, TcpStream, ToSocketAddrs},
+    sync::{broadcast, mpsc, oneshot, RwLock},
+    task::JoinHandle,
     time,
 };
 
 use crate::{
-    config::ServerConfig,
+    config::{ServerConfig, DEFAULT_PROTO_MAX_BULK_LEN, DEFAULT_SAVE_POINTS},
     connection::Connection,
+    del::Del,
+    durability::DurabilityBarrier,
+    exec::Exec,
     gen_rand_string,
+    latency::LatencyStats,
+    list::{LPop, RPop},
+    multi::Multi,
+    pause::ClientPause,
     ping::Ping,
     rdb::{self, DefaultFilter, RdbBuilder, RdbParser},
-    resp::RESP,
-    CliConfig, Command, Db, DbGuard, PSync, Replconf, ReplicaInfo, Role, Shutdown,
+    resp::{RESPError, RESP},
+    save::Save,
+    select::Select,
+    shutdown::ShutdownRequest,
+    spawn_ack_reader,
+    zset::{ZPopMax, ZPopMin},
+    CliConfig, Command, CommandError, Db, DbGuard, PSync, Replconf, ReplicaInfo, Role, Shutdown,
 };
 
+/// Wait before the first attempt to reconnect to a lost master,
+/// doubled after every failed attempt up to `MAX_RECONNECT_BACKOFF`
+const RECONNECT_BACKOFF: Duration = Duration::from_millis(100);
+
+const MAX_RECONNECT_BACKOFF: Duration = Duration::from_secs(5);
+
 #[derive(Debug)]
 pub struct Listener {
     // db => database guard
@@ -86,11 +107,88 @@ pub struct Handler {
     /// queued commands to be executed as part of a transaction
     pub transaction: Vec<RESP>,
 
+    /// Logical database selected with SELECT, commands are applied to it
+    pub db_index: usize,
+
     // shutdown listener
     shutdown: Shutdown,
 
     // handle to shutdown_complete_tx
     _shutdown_complete_tx: mpsc::Sender<()>,
+
+    // keeps the connection in the connected clients count, None
+    // for the link to the master
+    _client: Option<ClientGuard>,
+}
+
+/// Counts a client connection in `connected_clients` for as long as
+/// the guard lives
+///
+/// The count goes back down when the guard is dropped so every exit
+/// path of the handler is covered, errors and panics included
+#[derive(Debug)]
+pub struct ClientGuard {
+    clients: Arc<AtomicUsize>,
+}
+
+/// Handle to a server started with `start`
+///
+/// The server shuts down once the handle is dropped
+#[derive(Debug)]
+pub struct ServerHandle {
+    addr: SocketAddr,
+    shutdown: oneshot::Sender<()>,
+    task: JoinHandle<crate::Result<()>>,
+}
+
+impl ServerHandle {
+    /// Address the server is listening on, the actual port when
+    /// bound to port 0
+    pub fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Shut the server down and wait until every connection is closed
+    pub async fn shutdown(self) -> crate::Result<()> {
+        let _ = self.shutdown.send(());
+        self.task.await?
+    }
+}
+
+/// Bind to `addr` and run the server in the background
+pub async fn start(addr: impl ToSocketAddrs, config: CliConfig) -> crate::Result<ServerHandle> {
+    let listener = TcpListener::bind(addr).await?;
+    let addr = listener.local_addr()?;
+
+    let (shutdown, shutdown_rx) = oneshot::channel::<()>();
+    let task = tokio::spawn(run(listener, config, shutdown_rx));
+
+    Ok(ServerHandle {
+        addr,
+        shutdown,
+        task,
+    })
+}
+
+/// Listen for the signals asking the process to terminate, ctrl-c and
+/// on unix SIGTERM, the returned future resolves on the first of them
+///
+/// SIGTERM is subscribed to right away so it no longer kills the process
+/// once this returns, even before the future is polled
+pub fn shutdown_signal() -> std::io::Result<impl Future<Output = ()>> {
+    #[cfg(unix)]
+    let mut terminate = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())?;
+
+    Ok(async move {
+        #[cfg(unix)]
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => {}
+            _ = terminate.recv() => {}
+        }
+
+        #[cfg(not(unix))]
+        let _ = tokio::signal::ctrl_c().await;
+    })
 }
 
 /// Run the redis server
@@ -118,9 +216,27 @@ pub async fn run(
         role,
         master_repl_id,
         dir: config.dir.clone(),
-        dbfilename: config.dir.clone(),
+        dbfilename: config.dbfilename.clone(),
         network_config: Some(("".into(), config.port)),
         master_repl_offset: Arc::new(AtomicU64::new(0)),
+        ping_latency: Arc::new(LatencyStats::new()),
+        encoding: config.encoding,
+        max_clients: config.max_clients,
+        proto_max_bulk_len: config
+            .proto_max_bulk_len
+            .unwrap_or(DEFAULT_PROTO_MAX_BULK_LEN),
+        lazyfree: config.lazyfree,
+        timeout: (config.timeout > 0).then(|| Duration::from_secs(config.timeout)),
+        save: config
+            .save
+            .clone()
+            .unwrap_or_else(|| DEFAULT_SAVE_POINTS.to_vec()),
+        shutdown_request: Arc::new(ShutdownRequest::new()),
+        connected_clients: Arc::new(AtomicUsize::new(0)),
+        repl_db: Arc::new(AtomicUsize::new(0)),
+        client_pause: Arc::new(ClientPause::new()),
+        master_link_up: Arc::new(AtomicBool::new(false)),
+        durability: Arc::new(DurabilityBarrier::new()),
     };
 
     let rdb = if config.dir.is_some() && config.dbfilename.is_some() {
@@ -137,17 +253,11 @@ pub async fn run(
         None
     };
 
-    let derived_database = if let Some(rdb) = rdb {
-        let mut parser = RdbParser::new(DefaultFilter::new(), RdbBuilder::default(), rdb);
-        parser.parse()?
-    } else {
-        None
-    };
-
-    let db = match derived_database {
-        Some(database) => DbGuard::from_derived(database),
-        None => DbGuard::new(),
-    };
+    let db = DbGuard::new(notify_shutdown.subscribe());
+    if let Some(rdb) = rdb {
+        load_rdb(&db.db(), rdb)?;
+    }
+    db.db().set_lazyfree(config.lazyfree);
 
     let mut server = Listener {
         listener,
@@ -159,12 +269,29 @@ pub async fn run(
     };
 
     if let Some(master) = config.master {
-        let connection = server.handshake(master).await?;
-        let _ = server.listen_to_master(connection.unwrap()).await?;
+        server.db.db().disable_active_expiry();
+        // a master that isn't up yet is retried in the background,
+        // clients are served in the meantime
+        let connection = match handshake(&master, &server.config, &server.db.db()).await {
+            Ok(connection) => Some(connection),
+            Err(err) => {
+                println!("Handshake with master {} failed {:?}", master.key(), err);
+                None
+            }
+        };
+        server.listen_to_master(connection, master).await?;
     } else {
         server.init_repl_state();
+        tokio::spawn(propagate_expired(
+            server.db.db().expired_keys(),
+            server.replicas.clone(),
+            server.config.clone(),
+        ));
     }
 
+    // SAVE or NOSAVE given to SHUTDOWN, overriding the save points
+    let mut save = None;
+    let shutdown_request = server.config.shutdown_request.clone();
     tokio::select! {
         result = server.run() => {
             if let Err(err) = result {
@@ -175,9 +302,18 @@ pub async fn run(
         _ = shutdown => {
             println!("Shutdown redis server");
         }
+        requested = shutdown_request.requested() => {
+            println!("Shutdown requested by a client");
+            save = requested;
+        }
     }
 
+    // close the links to the replicas so they notice the master is gone
+    server.replicas.write().await.clear();
+
     let Listener {
+        db,
+        config: server_config,
         notify_shutdown,
         shutdown_complete_tx,
         ..
@@ -188,6 +324,24 @@ pub async fn run(
 
     let _ = shutdown_cmpl_rx.recv().await;
 
+    // persist the dataset once every connection is closed, like redis
+    // does on a clean shutdown, when the server was given a db file and
+    // save points. SHUTDOWN SAVE saves even without them
+    let save = save.unwrap_or(
+        server_config.dir.is_some()
+            && server_config.dbfilename.is_some()
+            && !server_config.save.is_empty(),
+    );
+    if save {
+        // SAVE is refused while a BGSAVE runs, let it finish first
+        while db.db().inner.save_in_progress.load(Ordering::SeqCst) {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        if let Some(RESP::Error(err)) = Save::new().apply(&db.db(), server_config).await? {
+            println!("Saving on shutdown failed {err}");
+        }
+    }
+
     Ok(())
 }
 
@@ -198,59 +352,70 @@ impl Listener {
         self.db.db().set_repl_id(repl_id);
     }
 
-    /// Initiate a handshake protocol between this replica node
-    /// and the master node
-    pub async fn handshake(&mut self, master: ReplicaInfo) -> crate::Result<Option<Connection>> {
-        // Connect master node's port
-        let addr = format!("{}:{}", master.host.clone(), master.port.clone());
-        let stream = TcpStream::connect(addr).await?;
-        let mut connection = Connection::new(stream, true);
-
-        // HANDSHAKE PROTOCOL
-        // send PING
-        connection.write_frame(&Ping::new(None).into()).await?;
-        let _ = connection.read_resp().await?;
-
-        let listening_conf = Replconf::new(vec![
-            "listening-port".into(),
-            self.config.network_config.as_ref().unwrap().1.to_string(),
-        ]);
-        connection.write_frame(&listening_conf.into()).await?;
-        connection.read_resp().await?;
-
-        let replconf_capa = Replconf::new(vec!["capa".into(), "eof".into(), "psync2".into()]);
-        connection.write_frame(&replconf_capa.into()).await?;
-        let _ = connection.read_resp().await?;
-
-        connection
-            .write_frame(&PSync::new("?".into(), "-1".into()).into())
-            .await?;
-        let _psync_resp = connection.read_resp().await?;
+    /// Apply the replication stream of the master on a background task
+    ///
+    /// When the link to the master drops the replica keeps trying to
+    /// connect again, backing off between attempts, and resyncs with
+    /// a new handshake
+    pub async fn listen_to_master(
+        &mut self,
+        connection: Option<Connection>,
+        master: ReplicaInfo,
+    ) -> crate::Result<()> {
+        let db = self.db.db();
+        let replicas = self.replicas.clone();
+        let config = self.config.clone();
+        let mut shutdown = Shutdown::new(self.notify_shutdown.subscribe());
+        let shutdown_complete_tx = self.shutdown_complete_tx.clone();
+
+        // pass the connection to a new handler
+        // in an async thread
+        tokio::spawn(async move {
+            // without a link to start from, connect like after losing one
+            let connection = match connection {
+                Some(connection) => connection,
+                None => match reconnect(&master, &config, &db, &mut shutdown).await {
+                    Some(connection) => connection,
+                    None => return,
+                },
+            };
 
-        let _empty_rdb_resp = connection.read_resp().await?;
+            let mut handler = Handler {
+                connection,
+                db,
+                is_replica: false,
+                replicas,
+                config,
+                is_multi: false,
+                transaction: vec![],
+                db_index: 0,
+                shutdown,
+                _shutdown_complete_tx: shutdown_complete_tx,
+                _client: None,
+            };
 
-        Ok(Some(connection))
-    }
+            loop {
+                println!("Listen to master");
+                handler.config.master_link_up.store(true, Ordering::SeqCst);
+                if let Err(err) = handler.run_master().await {
+                    println!("Master Handler error {:?}", err);
+                }
+                handler.config.master_link_up.store(false, Ordering::SeqCst);
 
-    pub async fn listen_to_master(&mut self, connection: Connection) -> crate::Result<()> {
-        let mut handler = Handler {
-            connection,
-            db: self.db.db(),
-            is_replica: false,
-            replicas: self.replicas.clone(),
-            config: self.config.clone(),
-            is_multi: false,
-            transaction: vec![],
-            shutdown: Shutdown::new(self.notify_shutdown.subscribe()),
-            _shutdown_complete_tx: self.shutdown_complete_tx.clone(),
-        };
+                if handler.shutdown.is_shutdown() {
+                    return;
+                }
 
-        tokio::spawn(async move {
-            // pass the connection to a new handler
-            // in an async thread
-            println!("Listen to master");
-            if let Err(err) = handler.run_master().await {
-                println!("Master Handler error {:?}", err);
+                println!("Lost connection to master {}, reconnecting", master.key());
+                match reconnect(&master, &handler.config, &handler.db, &mut handler.shutdown).await
+                {
+                    Some(connection) => {
+                        // a full resync starts the stream over on db 0
+                        handler.connection = connection;
+                        handler.db_index = 0;
+                    }
+                    None => return,
+                }
             }
         });
 
@@ -273,16 +438,28 @@ impl Listener {
 
             println!("Accept new connection {:?}", stream.peer_addr());
 
+            let clients = self.config.connected_clients.clone();
+            if clients.load(Ordering::SeqCst) >= self.config.max_clients {
+                reject(stream).await;
+                continue;
+            }
+
+            let mut connection = Connection::new(stream, false);
+            connection.idle_close = self.config.timeout;
+            connection.max_bulk_len = self.config.proto_max_bulk_len;
+
             let handler = Handler {
-                connection: Connection::new(stream, false),
+                connection,
                 db: self.db.db(),
                 is_replica: false,
                 config: self.config.clone(),
                 replicas: self.replicas.clone(),
                 is_multi: false,
                 transaction: vec![],
+                db_index: 0,
                 shutdown: Shutdown::new(self.notify_shutdown.subscribe()),
                 _shutdown_complete_tx: self.shutdown_complete_tx.clone(),
+                _client: Some(ClientGuard::new(clients)),
             };
 
             let sender = Arc::clone(&sender);
@@ -317,6 +494,28 @@ impl Listener {
     }
 }
 
+impl ClientGuard {
+    /// Count a new client connection
+    pub fn new(clients: Arc<AtomicUsize>) -> ClientGuard {
+        clients.fetch_add(1, Ordering::SeqCst);
+        ClientGuard { clients }
+    }
+}
+
+impl Drop for ClientGuard {
+    fn drop(&mut self) {
+        self.clients.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Reply to a connection over the max clients limit and close it
+async fn reject(mut stream: TcpStream) {
+    let _ = stream
+        .write_all(b"-ERR max number of clients reached\r\n")
+        .await;
+    let _ = stream.shutdown().await;
+}
+
 /// Handler struct implementation
 impl Handler {
     /// Process a single inbound connection
@@ -324,41 +523,105 @@ impl Handler {
     /// Request RESP are parsed from the socket buffer and processed using `Command`
     /// Response is written back to the socket
     pub async fn run(mut self, _sender: Arc<broadcast::Sender<RESP>>) -> crate::Result<()> {
+        self.connection.last_active_time = Some(Instant::now());
+
         while !self.shutdown.is_shutdown() && !self.connection.closed {
+            // only time spent waiting on the client counts, a command
+            // blocked on a key doesn't make the client idle
+            let idle_deadline = self.connection.idle_deadline();
             let resp = tokio::select! {
-                res = self.connection.read_resp() => res?,
+                res = self.connection.read_resp() => match res {
+                    // the rest of the stream can't be framed, reply
+                    // and close the connection like redis does
+                    Err(err) if matches!(err.downcast_ref(), Some(RESPError::BulkTooLong)) => {
+                        let _ = self.connection.write_frame(&RESP::Error(err.to_string())).await;
+                        return Ok(());
+                    }
+                    res => res?,
+                },
+                _ = idle(idle_deadline) => {
+                    println!("Closing idle connection");
+                    return Ok(());
+                }
                 _ = self.shutdown.recv() => return Ok(())
             };
+            self.connection.last_active_time = Some(Instant::now());
 
             let (resp, size) = match resp {
                 Some(resp_and_size) => resp_and_size,
                 None => continue,
             };
 
-            if self.is_multi {
-                // Map RESP to a Command
-                let command = Command::from_resp(resp.clone())?;
+            // an empty multibulk carries no command, ignore it like redis
+            if matches!(&resp, RESP::Array(parts) if parts.is_empty()) {
+                continue;
+            }
 
+            // Map RESP to a Command
+            let command = match Command::from_resp(resp.clone()) {
+                Ok(command) => command,
+                Err(err) => {
+                    self.reply_error(err).await?;
+                    continue;
+                }
+            };
+
+            // CLIENT is never held back so a pause can always be lifted
+            if !matches!(command, Command::Client(_)) {
+                let is_write = command.is_write() || matches!(command, Command::Exec(_));
+                tokio::select! {
+                    _ = self.config.client_pause.wait(is_write) => {}
+                    _ = self.shutdown.recv() => return Ok(())
+                }
+            }
+
+            if self.is_multi {
                 match command {
                     Command::Exec(_) => {
                         let mut responses = RESP::array();
+                        // the writes made, with the db they were made against
+                        let mut writes = vec![];
 
-                        for queued in self.transaction.iter() {
+                        for queued in std::mem::take(&mut self.transaction) {
                             let command = Command::from_resp(queued.clone())?;
+                            if let Command::Select(select) = command {
+                                responses.push(self.select(select.index()));
+                                continue;
+                            }
+
+                            if command.is_replicable_command() {
+                                writes.push((self.db_index, queued));
+                            }
+                            let rewrite = replicated_as(&command);
+
+                            // there's no rollback, a failing command takes
+                            // its slot in the reply and the rest still run
                             let response = command
                                 .apply(
                                     &mut self.connection,
-                                    &self.db,
+                                    &self.db.with_index(self.db_index),
                                     None,
                                     self.replicas.clone(),
                                     self.config.clone(),
                                 )
-                                .await?;
-                            if let Some(resp) = response {
-                                responses.push(resp);
+                                .await;
+                            match response {
+                                Ok(Some(resp)) => {
+                                    if let Some(write) = rewrite.and_then(|rewrite| rewrite(&resp))
+                                    {
+                                        writes.push((self.db_index, write));
+                                    }
+                                    responses.push(resp)
+                                }
+                                Ok(None) => {}
+                                Err(err) => responses.push(RESP::Error(err.to_string())),
                             }
                         }
 
+                        if let (Role::Master, false) = (&self.config.role, writes.is_empty()) {
+                            replicate_transaction(&self.config, &self.replicas, writes).await;
+                        }
+
                         self.connection.write_frame(&responses).await?;
 
                         self.is_multi = false;
@@ -380,35 +643,33 @@ impl Handler {
                     }
                 }
             } else {
-                // Map RESP to a Command
-                let command = Command::from_resp(resp.clone())?;
+                // Answer PING straight away so liveness probes never wait on
+                // replication bookkeeping or the keyspace lock
+                if let Command::Ping(ping) = command {
+                    self.ping(ping).await?;
+                    continue;
+                }
+
+                // SELECT only changes the connection state, it's replicated
+                // lazily along with the next write
+                if let Command::Select(select) = command {
+                    let resp = self.select(select.index());
+                    self.connection.write_frame(&resp).await?;
+                    continue;
+                }
 
                 match self.config.role {
                     Role::Master => match command {
-                        Command::Set(_) => {
-                            let replicas = &mut *self.replicas.write().await;
-                            let mut remove = vec![];
-
-                            for (idx, connection) in replicas.into_iter().enumerate() {
-                                let repl_result = connection.write_frame(&resp).await;
-                                println!(
-                                    "Replicate: {}, offset: {:?}, Result: {:?}",
-                                    idx + 1,
-                                    connection.repl_offset.load(Ordering::SeqCst),
-                                    repl_result
-                                );
-
-                                if repl_result.is_err() {
-                                    remove.push(idx);
-                                }
-                            }
-
-                            for idx in remove.iter() {
-                                replicas.swap_remove(*idx);
-                                println!("Remove Replica: {idx}");
-                            }
+                        _ if command.is_replicable_command() => {
+                            replicate(&self.config, &self.replicas, self.db_index, &resp).await;
                         }
                         Command::PSync(_) => {
+                            // holding the lock, no write is replicated
+                            // between taking the snapshot, reading the
+                            // offset and joining, the snapshot holds every
+                            // write up to this offset
+                            let mut replicas = self.replicas.write().await;
+
                             command
                                 .apply(
                                     &mut self.connection,
@@ -419,8 +680,20 @@ impl Handler {
                                 )
                                 .await?;
 
-                            self.connection.repl_offset.store(0, Ordering::SeqCst);
-                            self.replicas.write().await.push(self.connection);
+                            self.connection.is_replica = true;
+
+                            // the new replica starts on db 0, make the next
+                            // write select its db if the stream is elsewhere
+                            if self.config.repl_db.load(Ordering::SeqCst) != 0 {
+                                self.config.repl_db.store(usize::MAX, Ordering::SeqCst);
+                            }
+                            let offset = self.config.master_repl_offset.load(Ordering::SeqCst);
+                            self.connection.repl_offset.store(offset, Ordering::SeqCst);
+                            self.connection.mark_synced(offset);
+                            replicas.push(spawn_ack_reader(
+                                self.connection,
+                                self.config.durability.clone(),
+                            ));
                             return Ok(());
                         }
                         Command::Multi(_) => {
@@ -442,25 +715,35 @@ impl Handler {
                 }
 
                 if command.affects_offset() {
-                    self.config
-                        .master_repl_offset
-                        .fetch_add(size as u64, Ordering::SeqCst);
-                    for connection in &mut *self.replicas.write().await {
-                        connection
-                            .repl_offset
-                            .fetch_add(size as u64, Ordering::SeqCst);
-                    }
+                    advance_offset(&self.config, &self.replicas, size as u64).await;
                 }
 
-                let resp = command
-                    .apply(
+                let rewrite = replicated_as(&command);
+
+                // a command blocked on a key with no timeout would keep the
+                // server from shutting down, it's dropped on shutdown
+                let db = self.db.with_index(self.db_index);
+                let resp = tokio::select! {
+                    biased;
+                    resp = command.apply(
                         &mut self.connection,
-                        &self.db,
+                        &db,
                         None,
                         self.replicas.clone(),
                         self.config.clone(),
-                    )
-                    .await?;
+                    ) => resp?,
+                    _ = self.shutdown.recv() => return Ok(()),
+                };
+
+                if let (Some(rewrite), Some(reply), Role::Master) =
+                    (rewrite, &resp, &self.config.role)
+                {
+                    if let Some(write) = rewrite(reply) {
+                        replicate(&self.config, &self.replicas, self.db_index, &write).await;
+                        advance_offset(&self.config, &self.replicas, write.encoded_len() as u64)
+                            .await;
+                    }
+                }
 
                 if let Some(resp) = resp {
                     if !self.connection.is_master {
@@ -472,13 +755,48 @@ impl Handler {
         Ok(())
     }
 
+    /// Switch the connection to the logical database at `index`
+    fn select(&mut self, index: u64) -> RESP {
+        match usize::try_from(index) {
+            Ok(index) if index < self.db.databases() => {
+                self.db_index = index;
+                RESP::Simple("OK".to_string())
+            }
+            _ => RESP::Error("ERR DB index is out of range".to_string()),
+        }
+    }
+
+    /// Reply with a `CommandError`, any other error is returned
+    async fn reply_error(&mut self, err: crate::Error) -> crate::Result<()> {
+        let err = err.downcast::<CommandError>()?;
+
+        self.connection
+            .write_frame(&RESP::Error(err.to_string()))
+            .await?;
+
+        Ok(())
+    }
+
+    /// Reply to a PING and record its round-trip in the latency stats
+    async fn ping(&mut self, ping: Ping) -> crate::Result<()> {
+        let started = Instant::now();
+
+        if let Some(resp) = ping.apply(&mut self.connection).await? {
+            self.connection.write_frame(&resp).await?;
+        }
+
+        self.config.ping_latency.record(started.elapsed());
+
+        Ok(())
+    }
+
     /// Process a single inbound connection from master node
     ///
     /// Does the same as the run method above but
     pub async fn run_master(&mut self) -> crate::Result<()> {
         let offset = AtomicUsize::new(0);
 
-        while !self.shutdown.is_shutdown() {
+        while !self.shutdown.is_shutdown() && !self.connection.closed {
             let resp = tokio::select! {
                 res = self.connection.read_resp() => res?,
                 _ = self.shutdown.recv() => return Ok(())
@@ -489,21 +807,1107 @@ impl Handler {
                 None => continue,
             };
 
+            // an empty multibulk carries no command, ignore it like redis
+            if matches!(&resp, RESP::Array(parts) if parts.is_empty()) {
+                continue;
+            }
+
             // Map RESP to a Command
             let command = Command::from_resp(resp)?;
 
-            command
-                .apply(
-                    &mut self.connection,
-                    &self.db,
-                    Some(&offset),
-                    self.replicas.clone(),
-                    self.config.clone(),
-                )
-                .await?;
+            // the master only ever expects the ack of a GETACK back,
+            // writes are applied for their effect on the keyspace and
+            // their reply dropped. Anything else is skipped, commands
+            // like ECHO would write their reply to the master's link
+            match command {
+                Command::Select(select) => {
+                    self.select(select.index());
+                }
+                Command::Replconf(replconf) if replconf.is_getack() => {
+                    let ack = Replconf::ack(offset.load(Ordering::SeqCst));
+                    self.connection.write_frame(&ack).await?;
+                }
+                command if command.is_write() => {
+                    command
+                        .apply(
+                            &mut self.connection,
+                            &self.db.with_index(self.db_index),
+                            Some(&offset),
+                            self.replicas.clone(),
+                            self.config.clone(),
+                        )
+                        .await?;
+                }
+                _ => {}
+            }
 
             let _ = offset.fetch_add(size, Ordering::SeqCst);
         }
         Ok(())
     }
 }
+
+/// Turns the reply of a write into the write replicas are sent
+type Rewrite = Box<dyn FnOnce(&RESP) -> Option<RESP> + Send>;
+
+/// How a write that isn't sent to replicas as is gets replicated,
+/// given its reply, `None` for every other command
+///
+/// A blocking pop would block a replica forever, it's sent as the pop
+/// it ended up doing. XADD is sent with the id the entry got
+fn replicated_as(command: &Command) -> Option<Rewrite> {
+    // the non-blocking pop sent in place of a blocking one, given
+    // the key and the number of values popped
+    let pop: fn(String, u64) -> RESP = match command {
+        Command::XAdd(xadd) => {
+            let xadd = xadd.clone();
+            return Some(Box::new(move |reply| match reply {
+                RESP::Bulk(id) => Some(xadd.with_id(String::from_utf8_lossy(id).into()).into()),
+                _ => None,
+            }));
+        }
+        Command::BZPopMin(_) => |key, _| ZPopMin::new(key, None).into(),
+        Command::BZPopMax(_) => |key, _| ZPopMax::new(key, None).into(),
+        Command::BLPop(_) => |key, _| LPop::new(key, None).into(),
+        Command::BRPop(_) => |key, _| RPop::new(key, None).into(),
+        Command::BLMPop(cmd) if cmd.tail() => |key, count| RPop::new(key, Some(count)).into(),
+        Command::BLMPop(_) => |key, count| LPop::new(key, Some(count)).into(),
+        Command::BZMPop(cmd) if cmd.max() => |key, count| ZPopMax::new(key, Some(count)).into(),
+        Command::BZMPop(_) => |key, count| ZPopMin::new(key, Some(count)).into(),
+        _ => return None,
+    };
+
+    Some(Box::new(move |reply| match reply {
+        // the multi-key pops reply with an array of what they popped
+        RESP::Array(popped) => match (popped.first(), popped.get(1)) {
+            (Some(RESP::Bulk(key)), Some(RESP::Array(values))) => Some(pop(
+                String::from_utf8_lossy(key).to_string(),
+                values.len() as u64,
+            )),
+            (Some(RESP::Bulk(key)), _) => Some(pop(String::from_utf8_lossy(key).to_string(), 1)),
+            _ => None,
+        },
+        _ => None,
+    }))
+}
+
+/// Resolve once `deadline` passes, never without one
+async fn idle(deadline: Option<Instant>) {
+    match deadline {
+        Some(deadline) => time::sleep_until(deadline.into()).await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Initiate a handshake protocol between this replica node
+/// and the master node
+///
+/// The dataset the master sends after FULLRESYNC replaces the one in `db`
+pub async fn handshake(
+    master: &ReplicaInfo,
+    config: &ServerConfig,
+    db: &Db,
+) -> crate::Result<Connection> {
+    // Connect master node's port
+    let addr = format!("{}:{}", master.host.clone(), master.port.clone());
+    let stream = TcpStream::connect(addr).await?;
+    let mut connection = Connection::new(stream, true);
+
+    // HANDSHAKE PROTOCOL
+    // send PING
+    connection.write_frame(&Ping::new(None).into()).await?;
+    let _ = connection.read_resp().await?;
+
+    let listening_conf = Replconf::new(vec![
+        "listening-port".into(),
+        config.network_config.as_ref().unwrap().1.to_string(),
+    ]);
+    connection.write_frame(&listening_conf.into()).await?;
+    connection.read_resp().await?;
+
+    let replconf_capa = Replconf::new(vec![
+        "capa".into(),
+        "eof".into(),
+        "capa".into(),
+        "psync2".into(),
+    ]);
+    connection.write_frame(&replconf_capa.into()).await?;
+    let _ = connection.read_resp().await?;
+
+    connection
+        .write_frame(&PSync::new("?".into(), "-1".into()).into())
+        .await?;
+    let _psync_resp = connection.read_resp().await?;
+
+    let rdb = connection.read_rdb_file().await?;
+    load_rdb(db, rdb.to_vec())?;
+
+    Ok(connection)
+}
+
+/// Load every database of an RDB file into `db`, databases missing
+/// from the file are emptied
+fn load_rdb(db: &Db, rdb: Vec<u8>) -> crate::Result<()> {
+    let mut parser = RdbParser::new(DefaultFilter::new(), RdbBuilder::default(), rdb);
+    let mut databases = parser.parse_databases()?;
+
+    if let Some((index, _)) = databases
+        .iter()
+        .find(|(index, _)| *index as usize >= db.databases())
+    {
+        return Err(format!("RDB file selects DB {index}, past the configured databases").into());
+    }
+
+    for index in 0..db.databases() {
+        let database = match databases.iter().position(|(i, _)| *i as usize == index) {
+            Some(position) => databases.swap_remove(position).1,
+            None => Default::default(),
+        };
+        db.load(index, database);
+    }
+
+    Ok(())
+}
+
+/// Connect to the master again after losing the link to it, waiting
+/// longer after every failed attempt
+///
+/// Returns `None` if the server shuts down first
+async fn reconnect(
+    master: &ReplicaInfo,
+    config: &ServerConfig,
+    db: &Db,
+    shutdown: &mut Shutdown,
+) -> Option<Connection> {
+    let mut backoff = RECONNECT_BACKOFF;
+
+    loop {
+        tokio::select! {
+            _ = time::sleep(backoff) => {}
+            _ = shutdown.recv() => return None,
+        }
+
+        match handshake(master, config, db).await {
+            Ok(connection) => return Some(connection),
+            Err(err) => println!("Reconnecting to master {} failed {:?}", master.key(), err),
+        }
+
+        backoff = (backoff * 2).min(MAX_RECONNECT_BACKOFF);
+    }
+}
+
+/// Write `resp`, a write made against the database at `index`, to every
+/// connected replica, dropping the replicas that can no longer be written to
+///
+/// A SELECT is sent first when the replication stream is on another database
+async fn replicate(
+    config: &ServerConfig,
+    replicas: &RwLock<Vec<Connection>>,
+    index: usize,
+    resp: &RESP,
+) {
+    // the write lock keeps the SELECT and the write next to each other
+    let replicas = &mut *replicas.write().await;
+
+    if config.repl_db.swap(index, Ordering::SeqCst) != index {
+        let select: RESP = Select::new(index as u64).into();
+        write_to_replicas(replicas, &select).await;
+
+        let size = select.encoded_len() as u64;
+        config.master_repl_offset.fetch_add(size, Ordering::SeqCst);
+        for connection in replicas.iter() {
+            connection.repl_offset.fetch_add(size, Ordering::SeqCst);
+        }
+    }
+
+    write_to_replicas(replicas, resp).await;
+}
+
+/// Write the writes of a transaction, each made against the database
+/// next to it, to every connected replica wrapped in MULTI/EXEC so they're
+/// applied together, and advance the replication offset by all of it
+async fn replicate_transaction(
+    config: &ServerConfig,
+    replicas: &RwLock<Vec<Connection>>,
+    writes: Vec<(usize, RESP)>,
+) {
+    // the write lock keeps other writes out of the transaction
+    let replicas = &mut *replicas.write().await;
+
+    let mut frames = vec![Multi::new().into()];
+    for (index, write) in writes {
+        if config.repl_db.swap(index, Ordering::SeqCst) != index {
+            frames.push(Select::new(index as u64).into());
+        }
+        frames.push(write);
+    }
+    frames.push(Exec::new().into());
+
+    let mut size = 0;
+    for frame in &frames {
+        write_to_replicas(replicas, frame).await;
+        size += frame.encoded_len() as u64;
+    }
+
+    config.master_repl_offset.fetch_add(size, Ordering::SeqCst);
+    for connection in replicas.iter() {
+        connection.repl_offset.fetch_add(size, Ordering::SeqCst);
+    }
+}
+
+async fn write_to_replicas(replicas: &mut Vec<Connection>, resp: &RESP) {
+    let mut remove = vec![];
+
+    for (idx, connection) in replicas.iter_mut().enumerate() {
+        let repl_result = connection.write_frame(resp).await;
+        println!(
+            "Replicate: {}, offset: {:?}, Result: {:?}",
+            idx + 1,
+            connection.repl_offset.load(Ordering::SeqCst),
+            repl_result
+        );
+
+        if repl_result.is_err() {
+            remove.push(idx);
+        }
+    }
+
+    // remove from the back so swapped in replicas keep their index
+    for idx in remove.iter().rev() {
+        replicas.swap_remove(*idx);
+        println!("Remove Replica: {idx}");
+    }
+}
+
+/// Advance the master and replicas replication offset by `size` bytes
+async fn advance_offset(config: &ServerConfig, replicas: &RwLock<Vec<Connection>>, size: u64) {
+    config.master_repl_offset.fetch_add(size, Ordering::SeqCst);
+    for connection in &*replicas.read().await {
+        connection.repl_offset.fetch_add(size, Ordering::SeqCst);
+    }
+}
+
+/// Propagate a DEL to the replicas for every key the master
+/// evicts on expiry, replicas never expire keys on their own
+pub async fn propagate_expired(
+    mut expired: mpsc::UnboundedReceiver<(usize, String)>,
+    replicas: Arc<RwLock<Vec<Connection>>>,
+    config: ServerConfig,
+) {
+    while let Some((index, key)) = expired.recv().await {
+        let resp: RESP = Del::new(vec![key]).into();
+
+        replicate(&config, &replicas, index, &resp).await;
+        advance_offset(&config, &replicas, resp.encoded_len() as u64).await;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        sync::{atomic::Ordering, Arc},
+        time::Duration,
+    };
+
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::{TcpListener, TcpStream},
+        sync::{broadcast, mpsc, RwLock},
+    };
+
+    use super::{propagate_expired, Handler, Listener};
+    use crate::{
+        config::ServerConfig, connection::Connection, gen_rand_string, resp::RESP, Db, DbGuard,
+        SharedDb, Shutdown, Stream, ValueType,
+    };
+
+    fn replicas() -> Arc<RwLock<Vec<Connection>>> {
+        Arc::new(RwLock::new(vec![]))
+    }
+
+    /// Read a single CRLF terminated line off the socket
+    async fn read_line(stream: &mut TcpStream) -> Vec<u8> {
+        let mut line = vec![];
+        while !line.ends_with(b"\r\n") {
+            line.push(stream.read_u8().await.unwrap());
+        }
+        line
+    }
+
+    /// Play the replica side of the PSYNC handshake until the
+    /// connection has been promoted to a replica by its handler
+    async fn sync_fake_replica(
+        db: &Db,
+        config: &ServerConfig,
+        replicas: &Arc<RwLock<Vec<Connection>>>,
+    ) -> (TcpStream, broadcast::Sender<()>) {
+        let (mut replica, shutdown) =
+            spawn_handler(db.clone(), config.clone(), replicas.clone()).await;
+
+        replica
+            .write_all(b"*3\r\n$5\r\nPSYNC\r\n$1\r\n?\r\n$2\r\n-1\r\n")
+            .await
+            .unwrap();
+
+        // FULLRESYNC reply followed by the rdb file
+        read_line(&mut replica).await;
+        let rdb_len = String::from_utf8(read_line(&mut replica).await).unwrap();
+        let rdb_len: usize = rdb_len.trim()[1..].parse().unwrap();
+        replica.read_exact(&mut vec![0u8; rdb_len]).await.unwrap();
+
+        while replicas.read().await.is_empty() {
+            tokio::task::yield_now().await;
+        }
+
+        (replica, shutdown)
+    }
+
+    /// Spawn a handler for a single client connection and return
+    /// the client side of the socket
+    async fn spawn_handler(
+        db: Db,
+        config: ServerConfig,
+        replicas: Arc<RwLock<Vec<Connection>>>,
+    ) -> (TcpStream, broadcast::Sender<()>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let client = TcpStream::connect(listener.local_addr().unwrap())
+            .await
+            .unwrap();
+        let (stream, _) = listener.accept().await.unwrap();
+
+        let (notify_shutdown, _) = broadcast::channel::<()>(1);
+        let (shutdown_complete_tx, _) = mpsc::channel::<()>(1);
+
+        let handler = Handler {
+            connection: Connection::new(stream, false),
+            db,
+            is_replica: false,
+            config,
+            replicas,
+            is_multi: false,
+            transaction: vec![],
+            db_index: 0,
+            shutdown: Shutdown::new(notify_shutdown.subscribe()),
+            _shutdown_complete_tx: shutdown_complete_tx,
+            _client: None,
+        };
+
+        let (sender, _) = broadcast::channel::<RESP>(16);
+        tokio::spawn(async move {
+            if let Err(err) = handler.run(Arc::new(sender)).await {
+                println!("Handler error {:?}", err);
+            }
+        });
+
+        (client, notify_shutdown)
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    #[allow(clippy::await_holding_lock)]
+    async fn ping_flood_does_not_contend_on_db_lock() {
+        // skip the purge task, it would block a worker on the held lock
+        let db = Db::from(Arc::new(SharedDb::new()));
+        let config = ServerConfig::for_test();
+        let (mut client, _shutdown) = spawn_handler(db.clone(), config.clone(), replicas()).await;
+
+        // hold the keyspace lock for the whole flood, any PING
+        // touching it would never get a reply
+        let state = db.inner.state.lock().unwrap();
+
+        const PINGS: usize = 100;
+        for _ in 0..PINGS {
+            client.write_all(b"*1\r\n$4\r\nPING\r\n").await.unwrap();
+        }
+
+        let mut buf = vec![0u8; PINGS * b"+PONG\r\n".len()];
+        tokio::time::timeout(Duration::from_secs(2), client.read_exact(&mut buf))
+            .await
+            .expect("PING blocked on the keyspace lock")
+            .unwrap();
+
+        assert!(buf.chunks(7).all(|reply| reply == b"+PONG\r\n"));
+        drop(state);
+
+        // the sample is recorded right after the reply is flushed
+        tokio::time::timeout(Duration::from_secs(1), async {
+            while config.ping_latency.calls() < PINGS as u64 {
+                tokio::task::yield_now().await;
+            }
+        })
+        .await
+        .expect("PING latency was not recorded");
+        assert!(config.ping_latency.max() >= config.ping_latency.average());
+    }
+
+    #[tokio::test]
+    async fn replica_synced_after_writes_counts_from_the_resync() {
+        let db = Db::new();
+        db.set_repl_id(gen_rand_string(40));
+        let config = ServerConfig::for_test();
+        let replicas = replicas();
+
+        const SET: &[u8] = b"*3\r\n$3\r\nSET\r\n$3\r\nfoo\r\n$3\r\nbar\r\n";
+        const GETACK: &[u8] = b"*3\r\n$8\r\nREPLCONF\r\n$6\r\nGETACK\r\n$1\r\n*\r\n";
+
+        // written before any replica connected
+        let (mut client, _shutdown) =
+            spawn_handler(db.clone(), config.clone(), replicas.clone()).await;
+        client.write_all(SET).await.unwrap();
+        let mut ok = [0u8; 5];
+        client.read_exact(&mut ok).await.unwrap();
+        assert_eq!(
+            config.master_repl_offset.load(Ordering::SeqCst),
+            SET.len() as u64
+        );
+
+        // the snapshot holds that write, so the replica is already synced
+        let (mut replica, _replica_shutdown) = sync_fake_replica(&db, &config, &replicas).await;
+        client
+            .write_all(b"*3\r\n$4\r\nWAIT\r\n$1\r\n1\r\n$1\r\n0\r\n")
+            .await
+            .unwrap();
+        let mut reply = [0u8; 4];
+        tokio::time::timeout(Duration::from_secs(2), client.read_exact(&mut reply))
+            .await
+            .expect("WAIT hung on a freshly synced replica")
+            .unwrap();
+        assert_eq!(&reply, b":1\r\n");
+
+        // later acks count the bytes the replica got since the resync
+        client.write_all(SET).await.unwrap();
+        client
+            .write_all(b"*3\r\n$4\r\nWAIT\r\n$1\r\n1\r\n$1\r\n0\r\n")
+            .await
+            .unwrap();
+        client.read_exact(&mut ok).await.unwrap();
+
+        let mut propagated = vec![0u8; SET.len() + GETACK.len()];
+        replica.read_exact(&mut propagated).await.unwrap();
+        let ack = format!(
+            "*3\r\n$8\r\nREPLCONF\r\n$3\r\nACK\r\n${}\r\n{}\r\n",
+            SET.len().to_string().len(),
+            SET.len()
+        );
+        replica.write_all(ack.as_bytes()).await.unwrap();
+
+        tokio::time::timeout(Duration::from_secs(2), client.read_exact(&mut reply))
+            .await
+            .expect("WAIT did not count the replica's ack")
+            .unwrap();
+        assert_eq!(&reply, b":1\r\n");
+    }
+
+    #[tokio::test]
+    async fn replica_added_after_writes_starts_at_the_master_offset() {
+        let db = Db::new();
+        db.set_repl_id(gen_rand_string(40));
+        let config = ServerConfig::for_test();
+        let replicas = replicas();
+
+        const SET: &[u8] = b"*3\r\n$3\r\nSET\r\n$3\r\nfoo\r\n$3\r\nbar\r\n";
+
+        let (mut client, _shutdown) =
+            spawn_handler(db.clone(), config.clone(), replicas.clone()).await;
+        let mut ok = [0u8; 5];
+        client.write_all(SET).await.unwrap();
+        client.read_exact(&mut ok).await.unwrap();
+
+        let (mut replica, _replica_shutdown) = sync_fake_replica(&db, &config, &replicas).await;
+        let offset = |replicas: &Vec<Connection>| replicas[0].repl_offset.load(Ordering::SeqCst);
+        assert_eq!(offset(&*replicas.read().await), SET.len() as u64);
+
+        // and moves along with the master from there
+        client.write_all(SET).await.unwrap();
+        client.read_exact(&mut ok).await.unwrap();
+        replica.read_exact(&mut vec![0u8; SET.len()]).await.unwrap();
+        assert_eq!(
+            offset(&*replicas.read().await),
+            config.master_repl_offset.load(Ordering::SeqCst)
+        );
+        assert_eq!(offset(&*replicas.read().await), 2 * SET.len() as u64);
+    }
+
+    #[tokio::test]
+    async fn wait_targets_offset_of_preceding_write() {
+        let db = Db::new();
+        db.set_repl_id(gen_rand_string(40));
+        let config = ServerConfig::for_test();
+        let replicas = replicas();
+
+        let (mut replica, _replica_shutdown) = sync_fake_replica(&db, &config, &replicas).await;
+        let (mut client, _shutdown) = spawn_handler(db, config.clone(), replicas).await;
+
+        const SET: &[u8] = b"*3\r\n$3\r\nSET\r\n$3\r\nfoo\r\n$3\r\nbar\r\n";
+        const GETACK: &[u8] = b"*3\r\n$8\r\nREPLCONF\r\n$6\r\nGETACK\r\n$1\r\n*\r\n";
+
+        client.write_all(SET).await.unwrap();
+        client
+            .write_all(b"*3\r\n$4\r\nWAIT\r\n$1\r\n1\r\n$4\r\n1000\r\n")
+            .await
+            .unwrap();
+
+        // the replica receives the SET, then acks its offset on GETACK
+        let mut propagated = vec![0u8; SET.len()];
+        replica.read_exact(&mut propagated).await.unwrap();
+        assert_eq!(propagated, SET);
+
+        let mut getack = vec![0u8; GETACK.len()];
+        replica.read_exact(&mut getack).await.unwrap();
+        assert_eq!(getack, GETACK);
+
+        let ack = format!(
+            "*3\r\n$8\r\nREPLCONF\r\n$3\r\nACK\r\n${}\r\n{}\r\n",
+            SET.len().to_string().len(),
+            SET.len()
+        );
+        replica.write_all(ack.as_bytes()).await.unwrap();
+
+        let mut reply = [0u8; 9];
+        tokio::time::timeout(Duration::from_secs(2), client.read_exact(&mut reply))
+            .await
+            .expect("WAIT did not reply")
+            .unwrap();
+        assert_eq!(&reply, b"+OK\r\n:1\r\n");
+        assert_eq!(
+            config.master_repl_offset.load(Ordering::SeqCst),
+            SET.len() as u64
+        );
+    }
+
+    #[tokio::test]
+    async fn expired_key_is_propagated_as_del() {
+        let db = Db::new();
+        db.set_repl_id(gen_rand_string(40));
+        let config = ServerConfig::for_test();
+        let replicas = replicas();
+        tokio::spawn(propagate_expired(
+            db.expired_keys(),
+            replicas.clone(),
+            config.clone(),
+        ));
+
+        let (mut replica, _replica_shutdown) = sync_fake_replica(&db, &config, &replicas).await;
+
+        db.set(
+            "foo".into(),
+            ValueType::String("bar".into()),
+            Some(Duration::from_millis(10)),
+        );
+
+        const DEL: &[u8] = b"*2\r\n$3\r\ndel\r\n$3\r\nfoo\r\n";
+        let mut propagated = vec![0u8; DEL.len()];
+        tokio::time::timeout(Duration::from_secs(2), replica.read_exact(&mut propagated))
+            .await
+            .expect("expired key was not propagated")
+            .unwrap();
+        assert_eq!(propagated, DEL);
+        assert_eq!(
+            config.master_repl_offset.load(Ordering::SeqCst),
+            DEL.len() as u64
+        );
+    }
+
+    #[tokio::test]
+    async fn replica_drops_expired_key_on_master_del() {
+        let db = Db::new();
+        db.disable_active_expiry();
+        db.set(
+            "foo".into(),
+            ValueType::String("bar".into()),
+            Some(Duration::from_millis(1)),
+        );
+
+        // the replica side of the link to a fake master
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let stream = TcpStream::connect(listener.local_addr().unwrap())
+            .await
+            .unwrap();
+        let (mut master, _) = listener.accept().await.unwrap();
+
+        let (notify_shutdown, _) = broadcast::channel::<()>(1);
+        let (shutdown_complete_tx, _) = mpsc::channel::<()>(1);
+        let mut handler = Handler {
+            connection: Connection::new(stream, true),
+            db: db.clone(),
+            is_replica: false,
+            config: ServerConfig::for_test(),
+            replicas: replicas(),
+            is_multi: false,
+            transaction: vec![],
+            db_index: 0,
+            shutdown: Shutdown::new(notify_shutdown.subscribe()),
+            _shutdown_complete_tx: shutdown_complete_tx,
+            _client: None,
+        };
+        tokio::spawn(async move { handler.run_master().await });
+
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(db.get("foo").is_none());
+        assert!(db.inner.state.lock().unwrap().next_expiration().is_some());
+
+        master
+            .write_all(b"*2\r\n$3\r\ndel\r\n$3\r\nfoo\r\n")
+            .await
+            .unwrap();
+
+        tokio::time::timeout(Duration::from_secs(2), async {
+            while db.inner.state.lock().unwrap().next_expiration().is_some() {
+                tokio::task::yield_now().await;
+            }
+        })
+        .await
+        .expect("master DEL was not applied");
+    }
+
+    #[tokio::test]
+    async fn replica_counts_master_ping_without_replying() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let stream = TcpStream::connect(listener.local_addr().unwrap())
+            .await
+            .unwrap();
+        let (mut master, _) = listener.accept().await.unwrap();
+
+        let (notify_shutdown, _) = broadcast::channel::<()>(1);
+        let (shutdown_complete_tx, _) = mpsc::channel::<()>(1);
+        let mut handler = Handler {
+            connection: Connection::new(stream, true),
+            db: Db::new(),
+            is_replica: false,
+            config: ServerConfig::for_test(),
+            replicas: replicas(),
+            is_multi: false,
+            transaction: vec![],
+            db_index: 0,
+            shutdown: Shutdown::new(notify_shutdown.subscribe()),
+            _shutdown_complete_tx: shutdown_complete_tx,
+            _client: None,
+        };
+        tokio::spawn(async move { handler.run_master().await });
+
+        const PING: &[u8] = b"*1\r\n$4\r\nPING\r\n";
+        master.write_all(PING).await.unwrap();
+
+        // nothing is written back for the PING
+        let mut byte = [0u8; 1];
+        assert!(
+            tokio::time::timeout(Duration::from_millis(100), master.read(&mut byte))
+                .await
+                .is_err()
+        );
+
+        // but it's part of the offset the replica acknowledges
+        master
+            .write_all(b"*3\r\n$8\r\nREPLCONF\r\n$6\r\nGETACK\r\n$1\r\n*\r\n")
+            .await
+            .unwrap();
+        let ack = format!(
+            "*3\r\n$8\r\nREPLCONF\r\n$3\r\nACK\r\n${}\r\n{}\r\n",
+            PING.len().to_string().len(),
+            PING.len()
+        );
+        let mut reply = vec![0u8; ack.len()];
+        tokio::time::timeout(Duration::from_secs(2), master.read_exact(&mut reply))
+            .await
+            .expect("GETACK was not answered")
+            .unwrap();
+        assert_eq!(reply, ack.as_bytes());
+    }
+
+    #[tokio::test]
+    async fn wait_without_timeout_returns_once_target_is_reached() {
+        let db = Db::new();
+        db.set_repl_id(gen_rand_string(40));
+        let config = ServerConfig::for_test();
+        let replicas = replicas();
+
+        // nothing to wait on with zero required replicas
+        let (mut client, _shutdown) =
+            spawn_handler(db.clone(), config.clone(), replicas.clone()).await;
+        client
+            .write_all(b"*3\r\n$4\r\nWAIT\r\n$1\r\n0\r\n$1\r\n0\r\n")
+            .await
+            .unwrap();
+        let mut reply = [0u8; 4];
+        tokio::time::timeout(Duration::from_secs(2), client.read_exact(&mut reply))
+            .await
+            .expect("WAIT 0 0 did not reply")
+            .unwrap();
+        assert_eq!(&reply, b":0\r\n");
+
+        let (mut replica, _replica_shutdown) = sync_fake_replica(&db, &config, &replicas).await;
+
+        const SET: &[u8] = b"*3\r\n$3\r\nSET\r\n$3\r\nfoo\r\n$3\r\nbar\r\n";
+        const GETACK: &[u8] = b"*3\r\n$8\r\nREPLCONF\r\n$6\r\nGETACK\r\n$1\r\n*\r\n";
+
+        client.write_all(SET).await.unwrap();
+        client
+            .write_all(b"*3\r\n$4\r\nWAIT\r\n$1\r\n1\r\n$1\r\n0\r\n")
+            .await
+            .unwrap();
+
+        let mut ok = [0u8; 5];
+        client.read_exact(&mut ok).await.unwrap();
+        assert_eq!(&ok, b"+OK\r\n");
+
+        let mut propagated = vec![0u8; SET.len() + GETACK.len()];
+        replica.read_exact(&mut propagated).await.unwrap();
+
+        // blocks for as long as the replica holds back its ack
+        let mut reply = [0u8; 4];
+        assert!(
+            tokio::time::timeout(Duration::from_millis(100), client.read_exact(&mut reply))
+                .await
+                .is_err()
+        );
+
+        let ack = format!(
+            "*3\r\n$8\r\nREPLCONF\r\n$3\r\nACK\r\n${}\r\n{}\r\n",
+            SET.len().to_string().len(),
+            SET.len()
+        );
+        replica.write_all(ack.as_bytes()).await.unwrap();
+
+        tokio::time::timeout(Duration::from_secs(2), client.read_exact(&mut reply))
+            .await
+            .expect("WAIT did not reply after the ack")
+            .unwrap();
+        assert_eq!(&reply, b":1\r\n");
+    }
+
+    #[tokio::test]
+    async fn command_errors_are_replied_to() {
+        let db = Db::new();
+        db.set("stream".into(), ValueType::Stream(Stream::default()), None);
+        let (mut client, _shutdown) = spawn_handler(db, ServerConfig::for_test(), replicas()).await;
+
+        client
+            .write_all(b"*2\r\n$3\r\nGET\r\n$6\r\nstream\r\n")
+            .await
+            .unwrap();
+        assert_eq!(
+            read_line(&mut client).await,
+            b"-WRONGTYPE Operation against a key holding the wrong kind of value\r\n"
+        );
+
+        // the connection stays usable after a wrong arity
+        client.write_all(b"*1\r\n$3\r\nGET\r\n").await.unwrap();
+        assert_eq!(
+            read_line(&mut client).await,
+            b"-ERR wrong number of arguments for 'get' command\r\n"
+        );
+
+        client.write_all(b"*1\r\n$4\r\nPING\r\n").await.unwrap();
+        assert_eq!(read_line(&mut client).await, b"+PONG\r\n");
+    }
+
+    #[tokio::test]
+    async fn unknown_command_is_replied_to() {
+        let (mut client, _shutdown) =
+            spawn_handler(Db::new(), ServerConfig::for_test(), replicas()).await;
+
+        client
+            .write_all(b"*3\r\n$5\r\nBOGUS\r\n$1\r\na\r\n$1\r\nb\r\n")
+            .await
+            .unwrap();
+        assert_eq!(
+            read_line(&mut client).await,
+            b"-ERR unknown command 'bogus', with args beginning with: 'a' 'b' \r\n"
+        );
+
+        client.write_all(b"*1\r\n$4\r\nPING\r\n").await.unwrap();
+        assert_eq!(read_line(&mut client).await, b"+PONG\r\n");
+    }
+
+    #[tokio::test]
+    async fn empty_multibulk_is_ignored() {
+        let (mut client, _shutdown) =
+            spawn_handler(Db::new(), ServerConfig::for_test(), replicas()).await;
+
+        client
+            .write_all(b"*0\r\n*1\r\n$4\r\nPING\r\n")
+            .await
+            .unwrap();
+        assert_eq!(read_line(&mut client).await, b"+PONG\r\n");
+
+        client.write_all(b"*0\r\n").await.unwrap();
+        client.write_all(b"*1\r\n$4\r\nPING\r\n").await.unwrap();
+        assert_eq!(read_line(&mut client).await, b"+PONG\r\n");
+
+        // nothing else was replied
+        let mut rest = [0u8; 1];
+        assert!(
+            tokio::time::timeout(Duration::from_millis(50), client.read(&mut rest))
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn connections_over_maxclients_are_rejected() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (notify_shutdown, _) = broadcast::channel::<()>(1);
+        let (shutdown_complete_tx, _) = mpsc::channel::<()>(1);
+
+        let mut config = ServerConfig::for_test();
+        config.max_clients = 1;
+
+        let mut server = Listener {
+            db: DbGuard::new(notify_shutdown.subscribe()),
+            listener,
+            config,
+            replicas: replicas(),
+            notify_shutdown,
+            shutdown_complete_tx,
+        };
+        tokio::spawn(async move { server.run().await });
+
+        let mut first = TcpStream::connect(addr).await.unwrap();
+        first.write_all(b"*1\r\n$4\r\nPING\r\n").await.unwrap();
+        assert_eq!(read_line(&mut first).await, b"+PONG\r\n");
+
+        let mut second = TcpStream::connect(addr).await.unwrap();
+        assert_eq!(
+            read_line(&mut second).await,
+            b"-ERR max number of clients reached\r\n"
+        );
+        assert_eq!(second.read(&mut [0u8; 1]).await.unwrap(), 0);
+
+        // the first client is still served
+        first.write_all(b"*1\r\n$4\r\nPING\r\n").await.unwrap();
+        assert_eq!(read_line(&mut first).await, b"+PONG\r\n");
+    }
+
+    #[tokio::test]
+    async fn connected_clients_returns_to_zero() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (notify_shutdown, _) = broadcast::channel::<()>(1);
+        let (shutdown_complete_tx, _) = mpsc::channel::<()>(1);
+        let config = ServerConfig::for_test();
+        let clients = config.connected_clients.clone();
+
+        let db = DbGuard::new(notify_shutdown.subscribe());
+        db.db().set_repl_id(gen_rand_string(40));
+        let mut server = Listener {
+            db,
+            listener,
+            config,
+            replicas: replicas(),
+            notify_shutdown,
+            shutdown_complete_tx,
+        };
+        tokio::spawn(async move { server.run().await });
+
+        let mut connections = vec![];
+        for _ in 0..50 {
+            let mut client = TcpStream::connect(addr).await.unwrap();
+            client.write_all(b"*1\r\n$4\r\nPING\r\n").await.unwrap();
+            assert_eq!(read_line(&mut client).await, b"+PONG\r\n");
+            connections.push(client);
+        }
+        assert_eq!(clients.load(Ordering::SeqCst), 50);
+
+        // a client promoted to a replica stops counting as a client
+        let mut replica = TcpStream::connect(addr).await.unwrap();
+        replica
+            .write_all(b"*3\r\n$5\r\nPSYNC\r\n$1\r\n?\r\n$2\r\n-1\r\n")
+            .await
+            .unwrap();
+        read_line(&mut replica).await;
+
+        drop(connections);
+        tokio::time::timeout(Duration::from_secs(2), async {
+            while clients.load(Ordering::SeqCst) != 0 {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+        })
+        .await
+        .expect("connected clients did not return to zero");
+    }
+
+    #[tokio::test]
+    async fn select_scopes_commands_to_a_database() {
+        let db = Db::new();
+        let (mut client, _shutdown) =
+            spawn_handler(db.clone(), ServerConfig::for_test(), replicas()).await;
+
+        client
+            .write_all(b"*2\r\n$6\r\nSELECT\r\n$1\r\n1\r\n")
+            .await
+            .unwrap();
+        assert_eq!(read_line(&mut client).await, b"+OK\r\n");
+
+        client
+            .write_all(b"*3\r\n$3\r\nSET\r\n$3\r\nfoo\r\n$3\r\nbar\r\n")
+            .await
+            .unwrap();
+        assert_eq!(read_line(&mut client).await, b"+OK\r\n");
+        assert!(db.get("foo").is_none());
+        assert!(db.with_index(1).get("foo").is_some());
+
+        client
+            .write_all(b"*2\r\n$6\r\nSELECT\r\n$2\r\n16\r\n")
+            .await
+            .unwrap();
+        assert_eq!(
+            read_line(&mut client).await,
+            b"-ERR DB index is out of range\r\n"
+        );
+
+        // a failed SELECT keeps the current database
+        client
+            .write_all(b"*2\r\n$3\r\nGET\r\n$3\r\nfoo\r\n")
+            .await
+            .unwrap();
+        assert_eq!(read_line(&mut client).await, b"$3\r\n");
+        assert_eq!(read_line(&mut client).await, b"bar\r\n");
+    }
+
+    #[tokio::test]
+    async fn writes_to_another_database_replicate_a_select() {
+        let db = Db::new();
+        db.set_repl_id(gen_rand_string(40));
+        let config = ServerConfig::for_test();
+        let replicas = replicas();
+
+        let (mut replica, _replica_shutdown) = sync_fake_replica(&db, &config, &replicas).await;
+        let (mut client, _shutdown) = spawn_handler(db, config.clone(), replicas).await;
+
+        const SELECT: &[u8] = b"*2\r\n$6\r\nselect\r\n$1\r\n2\r\n";
+        const SET: &[u8] = b"*3\r\n$3\r\nSET\r\n$3\r\nfoo\r\n$3\r\nbar\r\n";
+
+        client
+            .write_all(b"*2\r\n$6\r\nSELECT\r\n$1\r\n2\r\n")
+            .await
+            .unwrap();
+        assert_eq!(read_line(&mut client).await, b"+OK\r\n");
+
+        // only the first write on the new database selects it
+        client.write_all(SET).await.unwrap();
+        client.write_all(SET).await.unwrap();
+        assert_eq!(read_line(&mut client).await, b"+OK\r\n");
+        assert_eq!(read_line(&mut client).await, b"+OK\r\n");
+
+        let expected = [SELECT, SET, SET].concat();
+        let mut propagated = vec![0u8; expected.len()];
+        replica.read_exact(&mut propagated).await.unwrap();
+        assert_eq!(propagated, expected);
+        assert_eq!(
+            config.master_repl_offset.load(Ordering::SeqCst),
+            expected.len() as u64
+        );
+    }
+
+    #[tokio::test]
+    async fn client_pause_delays_commands_until_unpaused() {
+        let db = Db::new();
+        let config = ServerConfig::for_test();
+        let (mut admin, _admin_shutdown) =
+            spawn_handler(db.clone(), config.clone(), replicas()).await;
+        let (mut client, _shutdown) = spawn_handler(db.clone(), config, replicas()).await;
+
+        admin
+            .write_all(b"*4\r\n$6\r\nCLIENT\r\n$5\r\nPAUSE\r\n$5\r\n10000\r\n$3\r\nALL\r\n")
+            .await
+            .unwrap();
+        assert_eq!(read_line(&mut admin).await, b"+OK\r\n");
+
+        client
+            .write_all(b"*3\r\n$3\r\nSET\r\n$3\r\nfoo\r\n$3\r\nbar\r\n")
+            .await
+            .unwrap();
+        let mut reply = [0u8; 5];
+        assert!(
+            tokio::time::timeout(Duration::from_millis(100), client.read_exact(&mut reply))
+                .await
+                .is_err()
+        );
+        assert!(db.get("foo").is_none());
+
+        admin
+            .write_all(b"*2\r\n$6\r\nCLIENT\r\n$7\r\nUNPAUSE\r\n")
+            .await
+            .unwrap();
+        assert_eq!(read_line(&mut admin).await, b"+OK\r\n");
+
+        tokio::time::timeout(Duration::from_secs(2), client.read_exact(&mut reply))
+            .await
+            .expect("SET was not resumed by UNPAUSE")
+            .unwrap();
+        assert_eq!(&reply, b"+OK\r\n");
+        assert!(db.get("foo").is_some());
+    }
+
+    #[tokio::test]
+    async fn client_pause_write_only_holds_writes_until_it_ends() {
+        let config = ServerConfig::for_test();
+        let (mut client, _shutdown) = spawn_handler(Db::new(), config, replicas()).await;
+
+        client
+            .write_all(b"*4\r\n$6\r\nCLIENT\r\n$5\r\nPAUSE\r\n$3\r\n200\r\n$5\r\nWRITE\r\n")
+            .await
+            .unwrap();
+        assert_eq!(read_line(&mut client).await, b"+OK\r\n");
+        let paused_at = std::time::Instant::now();
+
+        // reads go through while writes wait out the pause
+        client
+            .write_all(b"*2\r\n$3\r\nGET\r\n$3\r\nfoo\r\n")
+            .await
+            .unwrap();
+        assert_eq!(read_line(&mut client).await, b"$-1\r\n");
+        assert!(paused_at.elapsed() < Duration::from_millis(200));
+
+        client
+            .write_all(b"*3\r\n$3\r\nSET\r\n$3\r\nfoo\r\n$3\r\nbar\r\n")
+            .await
+            .unwrap();
+        assert_eq!(read_line(&mut client).await, b"+OK\r\n");
+        assert!(paused_at.elapsed() >= Duration::from_millis(200));
+    }
+
+    #[tokio::test]
+    async fn expired_key_is_gone_for_every_read() {
+        // no purge task, reads alone must hide the expired key
+        let db = Db::from(Arc::new(SharedDb::new()));
+        let (mut client, _shutdown) = spawn_handler(db, ServerConfig::for_test(), replicas()).await;
+
+        client
+            .write_all(b"*5\r\n$3\r\nSET\r\n$3\r\nfoo\r\n$3\r\nbar\r\n$2\r\nPX\r\n$1\r\n5\r\n")
+            .await
+            .unwrap();
+        assert_eq!(read_line(&mut client).await, b"+OK\r\n");
+        tokio::time::sleep(Duration::from_millis(20)).await;
+
+        client
+            .write_all(b"*2\r\n$4\r\nTYPE\r\n$3\r\nfoo\r\n")
+            .await
+            .unwrap();
+        assert_eq!(read_line(&mut client).await, b"+none\r\n");
+
+        client
+            .write_all(b"*2\r\n$6\r\nEXISTS\r\n$3\r\nfoo\r\n")
+            .await
+            .unwrap();
+        assert_eq!(read_line(&mut client).await, b":0\r\n");
+
+        client
+            .write_all(b"*2\r\n$3\r\nGET\r\n$3\r\nfoo\r\n")
+            .await
+            .unwrap();
+        assert_eq!(read_line(&mut client).await, b"$-1\r\n");
+
+        client
+            .write_all(b"*3\r\n$6\r\nOBJECT\r\n$8\r\nENCODING\r\n$3\r\nfoo\r\n")
+            .await
+            .unwrap();
+        assert_eq!(read_line(&mut client).await, b"$-1\r\n");
+
+        client
+            .write_all(b"*3\r\n$5\r\nDEBUG\r\n$6\r\nOBJECT\r\n$3\r\nfoo\r\n")
+            .await
+            .unwrap();
+        assert_eq!(read_line(&mut client).await, b"-ERR no such key\r\n");
+    }
+}
@@ -1,3 +1,7 @@
+use std::sync::Mutex;
+
+use tokio::sync::Notify;
+
 pub(crate) struct Shutdown {
     shutdown: bool,
     notify: tokio::sync::broadcast::Receiver<()>,
@@ -25,3 +29,33 @@ impl Shutdown {
         self.shutdown = true;
     }
 }
+
+/// A shutdown a client asked for with SHUTDOWN
+///
+/// The server waits on it next to the shutdown signal. It carries
+/// whether the dataset is saved before exiting, `None` leaves it to
+/// the configured save points
+#[derive(Debug, Default)]
+pub struct ShutdownRequest {
+    save: Mutex<Option<bool>>,
+    notify: Notify,
+}
+
+impl ShutdownRequest {
+    pub fn new() -> ShutdownRequest {
+        ShutdownRequest::default()
+    }
+
+    /// Ask the server to shut down, `save` overrides the save points
+    pub fn request(&self, save: Option<bool>) {
+        *self.save.lock().unwrap() = save;
+        // stores a permit when the server isn't waiting yet
+        self.notify.notify_one();
+    }
+
+    /// Resolve once a shutdown was requested with whether to save
+    pub async fn requested(&self) -> Option<bool> {
+        self.notify.notified().await;
+        *self.save.lock().unwrap()
+    }
+}
@@ -1,8 +1,15 @@
-use std::{collections::HashMap, time::Duration};
+use std::{
+    cmp::Ordering,
+    collections::{BTreeSet, HashMap, HashSet, VecDeque},
+    ops::Bound,
+    time::Duration,
+};
 
 use bytes::Bytes;
 use tokio::time::Instant;
 
+use crate::config::EncodingConfig;
+
 #[derive(Debug, Clone)]
 pub struct Value {
     pub expires_at: Option<Instant>,
@@ -13,7 +20,79 @@ pub struct Value {
 #[derive(Debug, Clone)]
 pub enum ValueType {
     String(Bytes),
-    Stream(Vec<StreamData>),
+    Stream(Stream),
+    Hash(Hash),
+    ZSet(ZSet),
+    List(VecDeque<Bytes>),
+    Set(Set),
+}
+
+/// Field-value storage for the hash type
+///
+/// Small hashes are stored as a flat vector of pairs, the equivalent of
+/// redis' `listpack` encoding, and converted to a `HashMap` once they
+/// outgrow the limits in `EncodingConfig`. A hash never converts back.
+///
+/// Fields given a TTL with HEXPIRE are tracked in `expirations`, an
+/// expired field is hidden from reads until it's purged
+#[derive(Debug, Clone)]
+pub struct Hash {
+    fields: HashFields,
+    expirations: HashMap<String, Instant>,
+}
+
+#[derive(Debug, Clone)]
+enum HashFields {
+    ListPack(Vec<(String, String)>),
+    HashTable(HashMap<String, String>),
+}
+
+/// Member storage for the set type
+///
+/// Sets of integers are kept as a sorted vector, the equivalent of
+/// redis' `intset` encoding, and other small sets as a flat vector
+/// like a `listpack`. Either converts once the set outgrows the
+/// limits in `EncodingConfig`, a set never converts back.
+#[derive(Debug, Clone)]
+pub enum Set {
+    IntSet(Vec<i64>),
+    ListPack(Vec<String>),
+    HashTable(HashSet<String>),
+}
+
+/// Members of a sorted set ordered by score, then by member
+///
+/// Scores are looked up by member in `scores` while `ordered` keeps
+/// the `(score, member)` pairs sorted for range queries, both always
+/// hold the same members
+#[derive(Debug, Clone, Default)]
+pub struct ZSet {
+    scores: HashMap<String, f64>,
+    ordered: BTreeSet<(Score, String)>,
+}
+
+/// Totally ordered score so it can be kept in a `BTreeSet`
+#[derive(Debug, Clone, Copy)]
+pub struct Score(pub f64);
+
+impl PartialEq for Score {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Score {}
+
+impl PartialOrd for Score {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Score {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -23,6 +102,507 @@ pub struct StreamData {
     pub _created_at: Instant,
 }
 
+/// Entries of a stream ordered by id
+///
+/// `last_id` is the largest id ever added, it's kept apart from the
+/// entries so ids removed by XDEL or trimming are never handed out again
+#[derive(Debug, Clone, Default)]
+pub struct Stream {
+    pub entries: Vec<StreamData>,
+    pub last_id: (u64, u64),
+}
+
+impl ValueType {
+    /// Name of the type as reported by `TYPE`
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            ValueType::String(_) => "string",
+            ValueType::Stream(_) => "stream",
+            ValueType::Hash(_) => "hash",
+            ValueType::ZSet(_) => "zset",
+            ValueType::List(_) => "list",
+            ValueType::Set(_) => "set",
+        }
+    }
+
+    /// Name of the internal encoding as reported by `OBJECT ENCODING`
+    pub fn encoding(&self) -> &'static str {
+        match self {
+            ValueType::String(bytes) => string_encoding(bytes),
+            ValueType::Stream(_) => "stream",
+            ValueType::Hash(hash) => hash.encoding(),
+            ValueType::ZSet(_) => "skiplist",
+            ValueType::List(_) => "quicklist",
+            ValueType::Set(set) => set.encoding(),
+        }
+    }
+}
+
+/// Strings that are an i64 written the way it'd be printed are reported
+/// as `int`, short strings as `embstr` and everything else as `raw`,
+/// same as redis
+fn string_encoding(bytes: &Bytes) -> &'static str {
+    const EMBSTR_SIZE_LIMIT: usize = 44;
+
+    let is_int = bytes.len() <= 20
+        && std::str::from_utf8(bytes)
+            .map(|s| {
+                s.parse::<i64>()
+                    .is_ok_and(|n| n.to_string().as_bytes() == bytes)
+            })
+            .unwrap_or(false);
+
+    if is_int {
+        "int"
+    } else if bytes.len() <= EMBSTR_SIZE_LIMIT {
+        "embstr"
+    } else {
+        "raw"
+    }
+}
+
+impl Hash {
+    pub fn new() -> Hash {
+        Hash {
+            fields: HashFields::ListPack(vec![]),
+            expirations: HashMap::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        let len = match &self.fields {
+            HashFields::ListPack(pairs) => pairs.len(),
+            HashFields::HashTable(map) => map.len(),
+        };
+        len - self.expired().count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn get(&self, field: &str) -> Option<&String> {
+        if self.is_expired(field) {
+            return None;
+        }
+
+        match &self.fields {
+            HashFields::ListPack(pairs) => pairs.iter().find(|(f, _)| f == field).map(|(_, v)| v),
+            HashFields::HashTable(map) => map.get(field),
+        }
+    }
+
+    /// Insert a field-value pair, converting to a hashtable when the
+    /// hash grows past the listpack limits
+    ///
+    /// Overwriting a field clears its TTL. Returns true if the field is new
+    pub fn insert(&mut self, field: String, value: String, config: &EncodingConfig) -> bool {
+        if self.is_expired(&field) {
+            self.remove(&field);
+        }
+        self.expirations.remove(&field);
+
+        if let HashFields::ListPack(pairs) = &mut self.fields {
+            if field.len() > config.hash_max_listpack_value
+                || value.len() > config.hash_max_listpack_value
+            {
+                self.convert();
+            } else if let Some(pair) = pairs.iter_mut().find(|(f, _)| *f == field) {
+                pair.1 = value;
+                return false;
+            } else if pairs.len() + 1 > config.hash_max_listpack_entries {
+                self.convert();
+            } else {
+                pairs.push((field, value));
+                return true;
+            }
+        }
+
+        match &mut self.fields {
+            HashFields::HashTable(map) => map.insert(field, value).is_none(),
+            HashFields::ListPack(_) => unreachable!("hash was converted to a hashtable"),
+        }
+    }
+
+    /// Remove a field, returns true if the field existed
+    pub fn remove(&mut self, field: &str) -> bool {
+        let expired = self.is_expired(field);
+        self.expirations.remove(field);
+
+        let removed = match &mut self.fields {
+            HashFields::ListPack(pairs) => match pairs.iter().position(|(f, _)| f == field) {
+                Some(idx) => {
+                    pairs.remove(idx);
+                    true
+                }
+                None => false,
+            },
+            HashFields::HashTable(map) => map.remove(field).is_some(),
+        };
+
+        removed && !expired
+    }
+
+    pub fn iter(&self) -> Box<dyn Iterator<Item = (&String, &String)> + '_> {
+        let pairs: Box<dyn Iterator<Item = (&String, &String)>> = match &self.fields {
+            HashFields::ListPack(pairs) => Box::new(pairs.iter().map(|(f, v)| (f, v))),
+            HashFields::HashTable(map) => Box::new(map.iter()),
+        };
+        Box::new(pairs.filter(|(field, _)| !self.is_expired(field)))
+    }
+
+    /// Live fields along with when they expire, `None` for the ones
+    /// without a TTL
+    pub fn iter_with_ttl(&self) -> impl Iterator<Item = (&String, &String, Option<Instant>)> {
+        self.iter()
+            .map(|(field, value)| (field, value, self.expirations.get(field).copied()))
+    }
+
+    /// Expire a live field at `at`, returns false if there's no such field
+    pub fn expire(&mut self, field: &str, at: Instant) -> bool {
+        if self.get(field).is_none() {
+            return false;
+        }
+
+        self.expirations.insert(field.to_string(), at);
+        true
+    }
+
+    /// When a field expires, `None` if there's no such field and
+    /// `Some(None)` if it has no TTL
+    pub fn expires_at(&self, field: &str) -> Option<Option<Instant>> {
+        self.get(field)?;
+
+        Some(self.expirations.get(field).copied())
+    }
+
+    /// Earliest expiration among the fields with a TTL
+    pub fn next_expiration(&self) -> Option<Instant> {
+        self.expirations.values().min().copied()
+    }
+
+    /// Drop every expired field, returns how many were dropped
+    pub fn purge_expired(&mut self) -> usize {
+        let expired = self.expired().cloned().collect::<Vec<String>>();
+        for field in expired.iter() {
+            self.remove(field);
+        }
+
+        expired.len()
+    }
+
+    pub fn encoding(&self) -> &'static str {
+        match self.fields {
+            HashFields::ListPack(_) => "listpack",
+            HashFields::HashTable(_) => "hashtable",
+        }
+    }
+
+    fn is_expired(&self, field: &str) -> bool {
+        self.expirations
+            .get(field)
+            .is_some_and(|at| *at <= Instant::now())
+    }
+
+    fn expired(&self) -> impl Iterator<Item = &String> {
+        let now = Instant::now();
+        self.expirations
+            .iter()
+            .filter(move |(_, at)| **at <= now)
+            .map(|(field, _)| field)
+    }
+
+    fn convert(&mut self) {
+        if let HashFields::ListPack(pairs) = &mut self.fields {
+            let map = std::mem::take(pairs).into_iter().collect();
+            self.fields = HashFields::HashTable(map);
+        }
+    }
+}
+
+impl Set {
+    pub fn new() -> Set {
+        Set::IntSet(vec![])
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            Set::IntSet(ints) => ints.len(),
+            Set::ListPack(members) => members.len(),
+            Set::HashTable(members) => members.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn contains(&self, member: &str) -> bool {
+        match self {
+            Set::IntSet(ints) => as_int(member).is_some_and(|int| ints.binary_search(&int).is_ok()),
+            Set::ListPack(members) => members.iter().any(|m| m == member),
+            Set::HashTable(members) => members.contains(member),
+        }
+    }
+
+    /// Insert a member, converting to a bigger encoding when the set
+    /// grows past the intset or listpack limits
+    ///
+    /// Returns true if the member is new
+    pub fn insert(&mut self, member: String, config: &EncodingConfig) -> bool {
+        if let Set::IntSet(ints) = self {
+            match as_int(&member) {
+                Some(int) => match ints.binary_search(&int) {
+                    Ok(_) => return false,
+                    Err(_) if ints.len() + 1 > config.set_max_intset_entries => self.convert(false),
+                    Err(idx) => {
+                        ints.insert(idx, int);
+                        return true;
+                    }
+                },
+                None => {
+                    let fits = ints.len() < config.set_max_listpack_entries;
+                    self.convert(fits);
+                }
+            }
+        }
+
+        if let Set::ListPack(members) = self {
+            if members.contains(&member) {
+                return false;
+            } else if members.len() + 1 > config.set_max_listpack_entries {
+                self.convert(false);
+            } else {
+                members.push(member);
+                return true;
+            }
+        }
+
+        match self {
+            Set::HashTable(members) => members.insert(member),
+            _ => unreachable!("set was converted to a hashtable"),
+        }
+    }
+
+    /// Remove a member, returns true if the member existed
+    pub fn remove(&mut self, member: &str) -> bool {
+        match self {
+            Set::IntSet(ints) => match as_int(member).map(|int| ints.binary_search(&int)) {
+                Some(Ok(idx)) => {
+                    ints.remove(idx);
+                    true
+                }
+                _ => false,
+            },
+            Set::ListPack(members) => match members.iter().position(|m| m == member) {
+                Some(idx) => {
+                    members.swap_remove(idx);
+                    true
+                }
+                None => false,
+            },
+            Set::HashTable(members) => members.remove(member),
+        }
+    }
+
+    pub fn iter(&self) -> Box<dyn Iterator<Item = String> + '_> {
+        match self {
+            Set::IntSet(ints) => Box::new(ints.iter().map(|int| int.to_string())),
+            Set::ListPack(members) => Box::new(members.iter().cloned()),
+            Set::HashTable(members) => Box::new(members.iter().cloned()),
+        }
+    }
+
+    pub fn encoding(&self) -> &'static str {
+        match self {
+            Set::IntSet(_) => "intset",
+            Set::ListPack(_) => "listpack",
+            Set::HashTable(_) => "hashtable",
+        }
+    }
+
+    /// Convert to a listpack if `to_listpack` is set and the set is an
+    /// intset, otherwise to a hashtable
+    fn convert(&mut self, to_listpack: bool) {
+        let members: Vec<String> = self.iter().collect();
+        *self = match self {
+            Set::IntSet(_) if to_listpack => Set::ListPack(members),
+            _ => Set::HashTable(members.into_iter().collect()),
+        };
+    }
+}
+
+/// Parse a member the way an intset would store it, only the
+/// canonical form of an integer round trips
+fn as_int(member: &str) -> Option<i64> {
+    member
+        .parse::<i64>()
+        .ok()
+        .filter(|int| int.to_string() == member)
+}
+
+impl ZSet {
+    pub fn new() -> ZSet {
+        ZSet::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.scores.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.scores.is_empty()
+    }
+
+    pub fn score(&self, member: &str) -> Option<f64> {
+        self.scores.get(member).copied()
+    }
+
+    /// Insert a member or update its score
+    ///
+    /// Returns true if the member is new
+    pub fn insert(&mut self, member: String, score: f64) -> bool {
+        let is_new = match self.scores.insert(member.clone(), score) {
+            Some(old) => {
+                self.ordered.remove(&(Score(old), member.clone()));
+                false
+            }
+            None => true,
+        };
+
+        self.ordered.insert((Score(score), member));
+        is_new
+    }
+
+    /// Position of a member counting from the lowest score
+    pub fn rank(&self, member: &str) -> Option<usize> {
+        let score = self.score(member)?;
+        let rank = self
+            .ordered
+            .range(..(Score(score), member.to_string()))
+            .count();
+        Some(rank)
+    }
+
+    /// Remove a member, returns true if the member existed
+    pub fn remove(&mut self, member: &str) -> bool {
+        match self.scores.remove(member) {
+            Some(score) => {
+                self.ordered.remove(&(Score(score), member.to_string()));
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Remove and return the member with the lowest score
+    pub fn pop_min(&mut self) -> Option<(String, f64)> {
+        let (score, member) = self.ordered.pop_first()?;
+        self.scores.remove(&member);
+        Some((member, score.0))
+    }
+
+    /// Remove and return the member with the highest score
+    pub fn pop_max(&mut self) -> Option<(String, f64)> {
+        let (score, member) = self.ordered.pop_last()?;
+        self.scores.remove(&member);
+        Some((member, score.0))
+    }
+
+    /// Members with their scores from the lowest score up
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = (&String, f64)> + '_ {
+        self.ordered.iter().map(|(score, member)| (member, score.0))
+    }
+
+    /// Members with their scores between `min` and `max`, from the
+    /// lowest score up
+    pub fn range_by_score(
+        &self,
+        min: Bound<f64>,
+        max: Bound<f64>,
+    ) -> impl Iterator<Item = (&String, f64)> + '_ {
+        self.iter()
+            .skip_while(move |(_, score)| match min {
+                Bound::Included(min) => *score < min,
+                Bound::Excluded(min) => *score <= min,
+                Bound::Unbounded => false,
+            })
+            .take_while(move |(_, score)| match max {
+                Bound::Included(max) => *score <= max,
+                Bound::Excluded(max) => *score < max,
+                Bound::Unbounded => true,
+            })
+    }
+
+    /// Members between `min` and `max` in lexicographic order, only
+    /// meaningful when every member has the same score
+    pub fn range_by_lex<'a>(
+        &'a self,
+        min: Bound<&'a str>,
+        max: Bound<&'a str>,
+    ) -> impl Iterator<Item = &'a String> + 'a {
+        self.ordered
+            .iter()
+            .map(|(_, member)| member)
+            .filter(move |member| match min {
+                Bound::Included(min) => member.as_str() >= min,
+                Bound::Excluded(min) => member.as_str() > min,
+                Bound::Unbounded => true,
+            })
+            .filter(move |member| match max {
+                Bound::Included(max) => member.as_str() <= max,
+                Bound::Excluded(max) => member.as_str() < max,
+                Bound::Unbounded => true,
+            })
+    }
+}
+
+/// Build a hash with the encoding it would have after inserting
+/// every pair under the default limits
+impl From<HashMap<String, String>> for Hash {
+    fn from(map: HashMap<String, String>) -> Self {
+        let config = EncodingConfig::default();
+        let mut hash = Hash::new();
+        for (field, value) in map {
+            hash.insert(field, value, &config);
+        }
+        hash
+    }
+}
+
+/// Build a set with the encoding it would have after inserting
+/// every member under the default limits
+impl From<HashSet<String>> for Set {
+    fn from(members: HashSet<String>) -> Self {
+        let config = EncodingConfig::default();
+        let mut set = Set::new();
+        for member in members {
+            set.insert(member, &config);
+        }
+        set
+    }
+}
+
+/// Build a stream of `entries`, its last id being the one of the last entry
+impl From<Vec<StreamData>> for Stream {
+    fn from(entries: Vec<StreamData>) -> Self {
+        let last_id = entries.last().map(|entry| entry.id).unwrap_or_default();
+        Stream { entries, last_id }
+    }
+}
+
+impl Default for Set {
+    fn default() -> Self {
+        Set::new()
+    }
+}
+
+impl Default for Hash {
+    fn default() -> Self {
+        Hash::new()
+    }
+}
+
 impl Value {
     pub fn new(data: ValueType, expiration: Option<Duration>) -> Value {
         // Convert expires at to timestamp using the .map method
@@ -47,3 +627,134 @@ impl Value {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::collections::VecDeque;
+
+    use bytes::Bytes;
+
+    use super::{Hash, Set, Stream, ValueType, ZSet};
+    use crate::config::EncodingConfig;
+
+    #[test]
+    fn small_hash_grows_from_listpack_to_hashtable() {
+        let config = EncodingConfig {
+            hash_max_listpack_entries: 2,
+            ..EncodingConfig::default()
+        };
+
+        let mut hash = Hash::new();
+        assert!(hash.insert("a".into(), "1".into(), &config));
+        assert!(hash.insert("b".into(), "2".into(), &config));
+        assert!(!hash.insert("b".into(), "3".into(), &config));
+        assert_eq!(hash.encoding(), "listpack");
+
+        assert!(hash.insert("c".into(), "4".into(), &config));
+        assert_eq!(hash.encoding(), "hashtable");
+        assert_eq!(hash.len(), 3);
+        assert_eq!(hash.get("b"), Some(&"3".to_string()));
+
+        // removing fields never converts back
+        assert!(hash.remove("c"));
+        assert_eq!(ValueType::Hash(hash).encoding(), "hashtable");
+    }
+
+    #[test]
+    fn long_hash_value_converts_to_hashtable() {
+        let config = EncodingConfig::default();
+
+        let mut hash = Hash::new();
+        hash.insert("field".into(), "short".into(), &config);
+        assert_eq!(hash.encoding(), "listpack");
+
+        let long = "x".repeat(config.hash_max_listpack_value + 1);
+        hash.insert("field".into(), long, &config);
+        assert_eq!(hash.encoding(), "hashtable");
+        assert_eq!(hash.len(), 1);
+    }
+
+    #[test]
+    fn small_int_set_grows_from_intset_to_hashtable() {
+        let config = EncodingConfig {
+            set_max_intset_entries: 3,
+            ..EncodingConfig::default()
+        };
+
+        let mut set = Set::new();
+        for member in ["3", "1", "2"] {
+            assert!(set.insert(member.into(), &config));
+        }
+        assert!(!set.insert("2".into(), &config));
+        assert_eq!(set.encoding(), "intset");
+        assert_eq!(set.iter().collect::<Vec<_>>(), ["1", "2", "3"]);
+
+        assert!(set.insert("4".into(), &config));
+        assert_eq!(set.encoding(), "hashtable");
+        assert_eq!(set.len(), 4);
+        assert!(set.contains("1") && set.contains("4"));
+
+        // removing members never converts back
+        assert!(set.remove("4"));
+        assert_eq!(ValueType::Set(set).encoding(), "hashtable");
+    }
+
+    #[test]
+    fn non_integer_member_converts_intset_to_listpack() {
+        let config = EncodingConfig {
+            set_max_listpack_entries: 3,
+            ..EncodingConfig::default()
+        };
+
+        let mut set = Set::new();
+        set.insert("1".into(), &config);
+        // not the canonical form of an integer
+        set.insert("02".into(), &config);
+        assert_eq!(set.encoding(), "listpack");
+        assert!(set.contains("1") && set.contains("02") && !set.contains("2"));
+
+        set.insert("a".into(), &config);
+        assert_eq!(set.encoding(), "listpack");
+        set.insert("b".into(), &config);
+        assert_eq!(set.encoding(), "hashtable");
+        assert_eq!(set.len(), 4);
+
+        // an intset too big for a listpack goes straight to a hashtable
+        let mut set = Set::new();
+        for member in ["1", "2", "3"] {
+            set.insert(member.into(), &config);
+        }
+        set.insert("a".into(), &config);
+        assert_eq!(set.encoding(), "hashtable");
+    }
+
+    #[test]
+    fn string_encodings() {
+        let encoding = |s: String| ValueType::String(Bytes::from(s)).encoding();
+
+        assert_eq!(encoding("12345".into()), "int");
+        assert_eq!(encoding("-12".into()), "int");
+        assert_eq!(encoding("hello".into()), "embstr");
+        // parse as integers but wouldn't be printed back the same
+        assert_eq!(encoding("007".into()), "embstr");
+        assert_eq!(encoding("+5".into()), "embstr");
+        assert_eq!(encoding("-0".into()), "embstr");
+        assert_eq!(encoding("x".repeat(45)), "raw");
+    }
+
+    #[test]
+    fn type_names() {
+        let cases = [
+            (ValueType::String(Bytes::from("1")), "string"),
+            (ValueType::Stream(Stream::default()), "stream"),
+            (ValueType::Hash(Hash::new()), "hash"),
+            (ValueType::ZSet(ZSet::new()), "zset"),
+            (ValueType::List(VecDeque::new()), "list"),
+            (ValueType::Set(Set::new()), "set"),
+        ];
+
+        for (value, name) in cases {
+            assert_eq!(value.type_name(), name);
+        }
+    }
+}
@@ -0,0 +1,89 @@
+//! Helpers shared by the integration tests, each test binary only
+//! uses some of them
+#![allow(dead_code)]
+
+use std::{io::Cursor, net::SocketAddr};
+
+use redis_starter_rust::{
+    config::DEFAULT_MAX_CLIENTS,
+    resp::RESP,
+    server::{self, ServerHandle},
+    CliConfig,
+};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+};
+
+/// Shuts the server down once dropped
+pub type ShutdownHandle = ServerHandle;
+
+/// Start a master server on an ephemeral port
+pub async fn spawn_test_server() -> (SocketAddr, ShutdownHandle) {
+    spawn_server(CliConfig {
+        max_clients: DEFAULT_MAX_CLIENTS,
+        ..Default::default()
+    })
+    .await
+}
+
+/// Start a server with `config` on an ephemeral port, the port in
+/// the config is only what the server reports about itself
+pub async fn spawn_server(config: CliConfig) -> (SocketAddr, ShutdownHandle) {
+    let handle = server::start("127.0.0.1:0", config).await.unwrap();
+
+    (handle.local_addr(), handle)
+}
+
+/// A client speaking raw RESP to the server
+pub struct TestClient {
+    stream: TcpStream,
+    buffer: Vec<u8>,
+}
+
+impl TestClient {
+    pub async fn connect(addr: SocketAddr) -> TestClient {
+        TestClient {
+            stream: TcpStream::connect(addr).await.unwrap(),
+            buffer: vec![],
+        }
+    }
+
+    /// Send a command and return the raw reply
+    pub async fn command(&mut self, args: &[&str]) -> String {
+        self.send(args).await;
+
+        self.read_reply().await
+    }
+
+    /// Send a command without waiting for a reply
+    pub async fn send(&mut self, args: &[&str]) {
+        let mut request = format!("*{}\r\n", args.len());
+        for arg in args {
+            request.push_str(&format!("${}\r\n{}\r\n", arg.len(), arg));
+        }
+        self.stream.write_all(request.as_bytes()).await.unwrap();
+    }
+
+    /// Read whatever the server sent, 0 once it closed the connection
+    pub async fn read_raw(&mut self, buf: &mut [u8]) -> usize {
+        self.stream.read(buf).await.unwrap()
+    }
+
+    /// Read a single reply frame
+    pub async fn read_reply(&mut self) -> String {
+        loop {
+            let mut cursor = Cursor::new(&self.buffer[..]);
+            if RESP::check(&mut cursor).is_ok() {
+                let len = cursor.position() as usize;
+                let reply = self.buffer.drain(..len).collect();
+                return String::from_utf8(reply).unwrap();
+            }
+
+            let mut buf = [0u8; 1024];
+            let read = self.stream.read(&mut buf).await.unwrap();
+            assert!(read > 0, "connection closed before a full reply");
+            self.buffer.extend_from_slice(&buf[..read]);
+        }
+    }
+}
@@ -0,0 +1,311 @@
+mod common;
+
+use std::{net::SocketAddr, time::Duration};
+
+use common::{spawn_server, spawn_test_server, ShutdownHandle, TestClient};
+use redis_starter_rust::{config::DEFAULT_MAX_CLIENTS, server, CliConfig, ReplicaInfo, Role};
+
+/// Start a replica of the master at `master`
+async fn spawn_replica(master: SocketAddr) -> (SocketAddr, ShutdownHandle) {
+    spawn_replica_on(master, 0).await
+}
+
+/// Start a replica of the master at `master` announcing `port` as
+/// the port it serves clients on
+async fn spawn_replica_on(master: SocketAddr, port: u64) -> (SocketAddr, ShutdownHandle) {
+    spawn_server(CliConfig {
+        port,
+        is_replication: true,
+        master: Some(ReplicaInfo {
+            host: master.ip().to_string(),
+            port: master.port().to_string(),
+            role: Role::Master,
+        }),
+        max_clients: DEFAULT_MAX_CLIENTS,
+        ..Default::default()
+    })
+    .await
+}
+
+/// Wait for the master to list the replica, it does so right after the
+/// RDB transfer, which can lag behind the replica finishing the handshake
+async fn wait_for_replica(master: &mut TestClient) {
+    let mut registered = false;
+    for _ in 0..20 {
+        if master.command(&["WAIT", "1", "50"]).await == ":1\r\n" {
+            registered = true;
+            break;
+        }
+    }
+    assert!(registered, "replica never registered with the master");
+}
+
+#[tokio::test]
+async fn writes_reach_the_replica() {
+    let (master_addr, _master) = spawn_test_server().await;
+    let (replica_addr, _replica) = spawn_replica(master_addr).await;
+
+    let mut master = TestClient::connect(master_addr).await;
+    let mut replica = TestClient::connect(replica_addr).await;
+
+    // the replica only serves clients once its handshake is done
+    assert_eq!(replica.command(&["PING"]).await, "+PONG\r\n");
+
+    wait_for_replica(&mut master).await;
+
+    assert_eq!(master.command(&["SET", "foo", "bar"]).await, "+OK\r\n");
+    assert_eq!(master.command(&["INCR", "counter"]).await, ":1\r\n");
+    assert_eq!(master.command(&["WAIT", "1", "1000"]).await, ":1\r\n");
+
+    // WAIT came back once the replica acknowledged both writes
+    assert_eq!(replica.command(&["GET", "foo"]).await, "$3\r\nbar\r\n");
+    assert_eq!(replica.command(&["GET", "counter"]).await, "$1\r\n1\r\n");
+
+    assert_eq!(master.command(&["DEL", "foo"]).await, ":1\r\n");
+    let mut deleted = false;
+    for _ in 0..20 {
+        if replica.command(&["GET", "foo"]).await == "$-1\r\n" {
+            deleted = true;
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(25)).await;
+    }
+    assert!(deleted, "DEL was not propagated");
+}
+
+#[tokio::test]
+async fn replica_reconnects_to_a_restarted_master() {
+    let (master_addr, master_handle) = spawn_test_server().await;
+    let (replica_addr, _replica) = spawn_replica(master_addr).await;
+
+    let mut master = TestClient::connect(master_addr).await;
+    let mut replica = TestClient::connect(replica_addr).await;
+    assert_eq!(replica.command(&["PING"]).await, "+PONG\r\n");
+    wait_for_replica(&mut master).await;
+
+    let info = replica.command(&["INFO", "replication"]).await;
+    assert!(info.contains("master_link_status:up"), "{info}");
+
+    drop(master);
+    master_handle.shutdown().await.unwrap();
+
+    let mut down = false;
+    for _ in 0..40 {
+        let info = replica.command(&["INFO", "replication"]).await;
+        if info.contains("master_link_status:down") {
+            down = true;
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(25)).await;
+    }
+    assert!(down, "replica didn't notice the master going away");
+
+    // same address, the replica keeps trying it
+    let master_handle = server::start(
+        master_addr,
+        CliConfig {
+            max_clients: DEFAULT_MAX_CLIENTS,
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+    let mut master = TestClient::connect(master_handle.local_addr()).await;
+    wait_for_replica(&mut master).await;
+
+    assert_eq!(master.command(&["SET", "foo", "bar"]).await, "+OK\r\n");
+    assert_eq!(master.command(&["WAIT", "1", "1000"]).await, ":1\r\n");
+    assert_eq!(replica.command(&["GET", "foo"]).await, "$3\r\nbar\r\n");
+
+    let info = replica.command(&["INFO", "replication"]).await;
+    assert!(info.contains("master_link_status:up"), "{info}");
+}
+
+#[tokio::test]
+async fn master_reports_the_port_a_replica_announced() {
+    let (master_addr, _master) = spawn_test_server().await;
+    let (replica_addr, _replica) = spawn_replica_on(master_addr, 7000).await;
+
+    let mut master = TestClient::connect(master_addr).await;
+    let mut replica = TestClient::connect(replica_addr).await;
+    assert_eq!(replica.command(&["PING"]).await, "+PONG\r\n");
+    wait_for_replica(&mut master).await;
+
+    let info = master.command(&["INFO", "replication"]).await;
+    assert!(info.contains("connected_slaves:1\r\n"), "{info}");
+    assert!(
+        info.contains("slave0:ip=127.0.0.1,port=7000,state=online"),
+        "{info}"
+    );
+}
+
+#[tokio::test]
+async fn replica_applies_every_kind_of_write() {
+    let (master_addr, _master) = spawn_test_server().await;
+    let (replica_addr, _replica) = spawn_replica(master_addr).await;
+
+    let mut master = TestClient::connect(master_addr).await;
+    let mut replica = TestClient::connect(replica_addr).await;
+    assert_eq!(replica.command(&["PING"]).await, "+PONG\r\n");
+    wait_for_replica(&mut master).await;
+
+    assert_eq!(master.command(&["SET", "foo", "bar"]).await, "+OK\r\n");
+    assert_eq!(master.command(&["RPUSH", "list", "a", "b"]).await, ":2\r\n");
+    assert_eq!(master.command(&["HSET", "hash", "f", "v"]).await, ":1\r\n");
+    assert_eq!(master.command(&["SADD", "set", "m"]).await, ":1\r\n");
+    assert_eq!(master.command(&["ZADD", "zset", "1", "m"]).await, ":1\r\n");
+
+    // poll rather than WAIT, the replica must pick the writes up from
+    // the stream on its own
+    let mut applied = false;
+    for _ in 0..40 {
+        if replica.command(&["GET", "foo"]).await == "$3\r\nbar\r\n"
+            && replica.command(&["ZSCORE", "zset", "m"]).await == "$1\r\n1\r\n"
+        {
+            applied = true;
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(25)).await;
+    }
+    assert!(applied, "writes were not propagated");
+
+    assert_eq!(replica.command(&["LLEN", "list"]).await, ":2\r\n");
+    assert_eq!(replica.command(&["HGET", "hash", "f"]).await, "$1\r\nv\r\n");
+    assert_eq!(replica.command(&["SISMEMBER", "set", "m"]).await, ":1\r\n");
+
+    // the replica's link stays usable for acks after all of the above
+    assert_eq!(master.command(&["WAIT", "1", "1000"]).await, ":1\r\n");
+}
+
+#[tokio::test]
+async fn transaction_writes_reach_the_replica() {
+    let (master_addr, _master) = spawn_test_server().await;
+    let (replica_addr, _replica) = spawn_replica(master_addr).await;
+
+    let mut master = TestClient::connect(master_addr).await;
+    let mut replica = TestClient::connect(replica_addr).await;
+    assert_eq!(replica.command(&["PING"]).await, "+PONG\r\n");
+    wait_for_replica(&mut master).await;
+
+    assert_eq!(master.command(&["MULTI"]).await, "+OK\r\n");
+    assert_eq!(master.command(&["SET", "foo", "bar"]).await, "+QUEUED\r\n");
+    assert_eq!(master.command(&["SELECT", "1"]).await, "+QUEUED\r\n");
+    assert_eq!(master.command(&["INCR", "counter"]).await, "+QUEUED\r\n");
+    assert_eq!(
+        master.command(&["EXEC"]).await,
+        "*3\r\n+OK\r\n+OK\r\n:1\r\n"
+    );
+
+    // WAIT only counts the replica once it acked the whole transaction
+    assert_eq!(master.command(&["WAIT", "1", "1000"]).await, ":1\r\n");
+
+    assert_eq!(replica.command(&["GET", "foo"]).await, "$3\r\nbar\r\n");
+    assert_eq!(replica.command(&["GET", "counter"]).await, "$-1\r\n");
+    assert_eq!(replica.command(&["SELECT", "1"]).await, "+OK\r\n");
+    assert_eq!(replica.command(&["GET", "counter"]).await, "$1\r\n1\r\n");
+}
+
+#[tokio::test]
+async fn xadd_reaches_the_replica_with_the_id_it_got() {
+    let (master_addr, _master) = spawn_test_server().await;
+    let (replica_addr, _replica) = spawn_replica(master_addr).await;
+
+    let mut master = TestClient::connect(master_addr).await;
+    let mut replica = TestClient::connect(replica_addr).await;
+    assert_eq!(replica.command(&["PING"]).await, "+PONG\r\n");
+    wait_for_replica(&mut master).await;
+
+    let reply = master.command(&["XADD", "stream", "*", "f", "v"]).await;
+    let id = reply.lines().nth(1).unwrap().to_string();
+    assert_eq!(master.command(&["WAIT", "1", "1000"]).await, ":1\r\n");
+
+    // the replica stores the id the master generated, not one of its own
+    let entries = replica.command(&["XRANGE", "stream", &id, &id]).await;
+    assert_eq!(
+        entries,
+        format!(
+            "*1\r\n*2\r\n${}\r\n{id}\r\n*2\r\n$1\r\nf\r\n$1\r\nv\r\n",
+            id.len()
+        )
+    );
+}
+
+#[tokio::test]
+async fn replica_started_before_its_master_connects_once_it_is_up() {
+    // reserve an address for the master without anything listening on it
+    let master_addr = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap();
+
+    let (replica_addr, _replica) = spawn_replica(master_addr).await;
+    let mut replica = TestClient::connect(replica_addr).await;
+
+    // clients are served while the master is unreachable
+    assert_eq!(replica.command(&["PING"]).await, "+PONG\r\n");
+    let info = replica.command(&["INFO", "replication"]).await;
+    assert!(info.contains("master_link_status:down"), "{info}");
+
+    let master_handle = server::start(
+        master_addr,
+        CliConfig {
+            max_clients: DEFAULT_MAX_CLIENTS,
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+    let mut master = TestClient::connect(master_handle.local_addr()).await;
+    wait_for_replica(&mut master).await;
+
+    assert_eq!(master.command(&["SET", "foo", "bar"]).await, "+OK\r\n");
+    assert_eq!(master.command(&["WAIT", "1", "1000"]).await, ":1\r\n");
+    assert_eq!(replica.command(&["GET", "foo"]).await, "$3\r\nbar\r\n");
+
+    let info = replica.command(&["INFO", "replication"]).await;
+    assert!(info.contains("master_link_status:up"), "{info}");
+}
+
+#[tokio::test]
+async fn replica_starts_with_the_masters_dataset() {
+    let (master_addr, _master) = spawn_test_server().await;
+    let mut master = TestClient::connect(master_addr).await;
+
+    // written before the replica exists, only the RDB carries them
+    assert_eq!(master.command(&["SET", "foo", "bar"]).await, "+OK\r\n");
+    assert_eq!(master.command(&["HSET", "hash", "f", "v"]).await, ":1\r\n");
+    assert_eq!(master.command(&["SELECT", "2"]).await, "+OK\r\n");
+    assert_eq!(master.command(&["RPUSH", "list", "a"]).await, ":1\r\n");
+    assert_eq!(master.command(&["SELECT", "0"]).await, "+OK\r\n");
+
+    let (replica_addr, _replica) = spawn_replica(master_addr).await;
+    let mut replica = TestClient::connect(replica_addr).await;
+    assert_eq!(replica.command(&["PING"]).await, "+PONG\r\n");
+    wait_for_replica(&mut master).await;
+
+    assert_eq!(replica.command(&["GET", "foo"]).await, "$3\r\nbar\r\n");
+    assert_eq!(replica.command(&["HGET", "hash", "f"]).await, "$1\r\nv\r\n");
+    assert_eq!(replica.command(&["GET", "list"]).await, "$-1\r\n");
+    assert_eq!(replica.command(&["SELECT", "2"]).await, "+OK\r\n");
+    assert_eq!(replica.command(&["LLEN", "list"]).await, ":1\r\n");
+
+    // the stream picks up from the snapshot
+    assert_eq!(master.command(&["SET", "after", "1"]).await, "+OK\r\n");
+    assert_eq!(master.command(&["WAIT", "1", "1000"]).await, ":1\r\n");
+    assert_eq!(replica.command(&["SELECT", "0"]).await, "+OK\r\n");
+    assert_eq!(replica.command(&["GET", "after"]).await, "$1\r\n1\r\n");
+}
+
+#[tokio::test]
+async fn wait_for_more_replicas_than_connected_reports_the_synced_ones() {
+    let (master_addr, _master) = spawn_test_server().await;
+    let (_replica_addr, _replica) = spawn_replica(master_addr).await;
+    let mut master = TestClient::connect(master_addr).await;
+    wait_for_replica(&mut master).await;
+
+    assert_eq!(master.command(&["SET", "foo", "bar"]).await, "+OK\r\n");
+    // the one replica acks right away, WAIT still holds out for three
+    let started = std::time::Instant::now();
+    assert_eq!(master.command(&["WAIT", "3", "200"]).await, ":1\r\n");
+    assert!(started.elapsed() >= Duration::from_millis(200));
+}
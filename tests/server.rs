@@ -0,0 +1,468 @@
+mod common;
+
+use std::time::Duration;
+
+use common::{spawn_test_server, TestClient};
+use redis_starter_rust::{config::DEFAULT_MAX_CLIENTS, rdb, server, CliConfig};
+use tokio::net::TcpStream;
+
+#[tokio::test]
+async fn set_get_del() {
+    let (addr, _shutdown) = spawn_test_server().await;
+    let mut client = TestClient::connect(addr).await;
+
+    assert_eq!(client.command(&["SET", "foo", "bar"]).await, "+OK\r\n");
+    assert_eq!(client.command(&["GET", "foo"]).await, "$3\r\nbar\r\n");
+    assert_eq!(client.command(&["TYPE", "foo"]).await, "+string\r\n");
+    assert_eq!(client.command(&["DEL", "foo", "nope"]).await, ":1\r\n");
+    assert_eq!(client.command(&["GET", "foo"]).await, "$-1\r\n");
+    assert_eq!(client.command(&["TYPE", "foo"]).await, "+none\r\n");
+}
+
+#[tokio::test]
+async fn expire_and_incr() {
+    let (addr, _shutdown) = spawn_test_server().await;
+    let mut client = TestClient::connect(addr).await;
+
+    assert_eq!(client.command(&["INCR", "counter"]).await, ":1\r\n");
+    assert_eq!(client.command(&["INCR", "counter"]).await, ":2\r\n");
+    assert_eq!(
+        client.command(&["PEXPIRE", "counter", "50"]).await,
+        ":1\r\n"
+    );
+    assert_eq!(client.command(&["EXPIRE", "missing", "10"]).await, ":0\r\n");
+
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert_eq!(client.command(&["GET", "counter"]).await, "$-1\r\n");
+    assert_eq!(client.command(&["INCR", "counter"]).await, ":1\r\n");
+}
+
+#[tokio::test]
+async fn multi_exec_replies_in_order() {
+    let (addr, _shutdown) = spawn_test_server().await;
+    let mut client = TestClient::connect(addr).await;
+    let mut other = TestClient::connect(addr).await;
+
+    assert_eq!(client.command(&["MULTI"]).await, "+OK\r\n");
+    assert_eq!(client.command(&["SET", "key", "1"]).await, "+QUEUED\r\n");
+    assert_eq!(client.command(&["INCR", "key"]).await, "+QUEUED\r\n");
+
+    // nothing runs before EXEC
+    assert_eq!(other.command(&["GET", "key"]).await, "$-1\r\n");
+
+    assert_eq!(client.command(&["EXEC"]).await, "*2\r\n+OK\r\n:2\r\n");
+    assert_eq!(other.command(&["GET", "key"]).await, "$1\r\n2\r\n");
+}
+
+#[tokio::test]
+async fn exec_runs_past_a_failing_command() {
+    let (addr, _shutdown) = spawn_test_server().await;
+    let mut client = TestClient::connect(addr).await;
+
+    assert_eq!(
+        client.command(&["XADD", "stream", "1-1", "f", "v"]).await,
+        "$3\r\n1-1\r\n"
+    );
+
+    assert_eq!(client.command(&["MULTI"]).await, "+OK\r\n");
+    assert_eq!(client.command(&["SET", "a", "1"]).await, "+QUEUED\r\n");
+    assert_eq!(client.command(&["GET", "stream"]).await, "+QUEUED\r\n");
+    assert_eq!(client.command(&["SET", "b", "2"]).await, "+QUEUED\r\n");
+
+    assert_eq!(
+        client.command(&["EXEC"]).await,
+        "*3\r\n+OK\r\n-WRONGTYPE Operation against a key holding the wrong kind of value\r\n+OK\r\n"
+    );
+    assert_eq!(client.command(&["GET", "a"]).await, "$1\r\n1\r\n");
+    assert_eq!(client.command(&["GET", "b"]).await, "$1\r\n2\r\n");
+}
+
+#[tokio::test]
+async fn errors_keep_the_connection_open() {
+    let (addr, shutdown) = spawn_test_server().await;
+    let mut client = TestClient::connect(addr).await;
+
+    assert_eq!(
+        client.command(&["NOSUCH"]).await,
+        "-ERR unknown command 'nosuch', with args beginning with: \r\n"
+    );
+    assert_eq!(
+        client.command(&["GET"]).await,
+        "-ERR wrong number of arguments for 'get' command\r\n"
+    );
+    assert_eq!(client.command(&["PING"]).await, "+PONG\r\n");
+
+    shutdown.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn malformed_integers_are_replied_to() {
+    let (addr, shutdown) = spawn_test_server().await;
+    let mut client = TestClient::connect(addr).await;
+
+    let not_integer = "-ERR value is not an integer or out of range\r\n";
+    for args in [
+        &["EXPIRE", "k", "abc"][..],
+        &["INCRBY", "k", "x"],
+        &["GETRANGE", "k", "a", "1"],
+        &["SETRANGE", "k", "-1", "x"],
+        &["SET", "k", "v", "PX", "abc"],
+        &["LPOP", "l", "-1"],
+        &["HINCRBY", "h", "f", "x"],
+        &["SELECT", "x"],
+        &["XREAD", "COUNT", "x", "STREAMS", "s", "0"],
+    ] {
+        assert_eq!(client.command(args).await, not_integer, "{args:?}");
+    }
+    assert_eq!(
+        client.command(&["BLPOP", "k", "-1"]).await,
+        "-ERR timeout is not a float or out of range\r\n"
+    );
+
+    assert_eq!(client.command(&["PING"]).await, "+PONG\r\n");
+
+    shutdown.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn bulk_strings_past_proto_max_bulk_len_are_refused() {
+    let (addr, shutdown) = common::spawn_server(CliConfig {
+        max_clients: DEFAULT_MAX_CLIENTS,
+        proto_max_bulk_len: Some(4),
+        ..Default::default()
+    })
+    .await;
+
+    let mut client = TestClient::connect(addr).await;
+    assert_eq!(client.command(&["SET", "k", "0123"]).await, "+OK\r\n");
+    assert_eq!(
+        client.command(&["SET", "k", "0123456789"]).await,
+        "-ERR Protocol error: invalid bulk length\r\n"
+    );
+
+    let mut buf = [0u8; 16];
+    assert_eq!(client.read_raw(&mut buf).await, 0);
+
+    let mut client = TestClient::connect(addr).await;
+    assert_eq!(client.command(&["GET", "k"]).await, "$4\r\n0123\r\n");
+
+    shutdown.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn shutdown_does_not_wait_on_blocked_clients() {
+    let (addr, shutdown) = spawn_test_server().await;
+
+    let mut clients = vec![];
+    for args in [
+        &["BLPOP", "l", "0"][..],
+        &["BZPOPMIN", "z", "0"],
+        &["BLMPOP", "0", "1", "l", "LEFT"],
+        &["XREAD", "BLOCK", "0", "STREAMS", "s", "$"],
+    ] {
+        let mut client = TestClient::connect(addr).await;
+        client.send(args).await;
+        clients.push(client);
+    }
+    // let the commands reach the server and block
+    let mut client = TestClient::connect(addr).await;
+    assert_eq!(client.command(&["PING"]).await, "+PONG\r\n");
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    tokio::time::timeout(Duration::from_secs(2), shutdown.shutdown())
+        .await
+        .expect("shutdown waited on a blocked client")
+        .unwrap();
+}
+
+#[tokio::test]
+async fn handle_reports_the_address_and_shuts_down() {
+    let config = CliConfig {
+        max_clients: DEFAULT_MAX_CLIENTS,
+        ..Default::default()
+    };
+    let handle = server::start("127.0.0.1:0", config).await.unwrap();
+    let addr = handle.local_addr();
+    assert_ne!(addr.port(), 0);
+
+    let mut client = TestClient::connect(addr).await;
+    assert_eq!(client.command(&["PING"]).await, "+PONG\r\n");
+    drop(client);
+
+    tokio::time::timeout(Duration::from_secs(2), handle.shutdown())
+        .await
+        .expect("server didn't shut down")
+        .unwrap();
+    assert!(TcpStream::connect(addr).await.is_err());
+}
+
+#[tokio::test]
+async fn option_keywords_ignore_case() {
+    let (addr, shutdown) = spawn_test_server().await;
+    let mut client = TestClient::connect(addr).await;
+
+    for (key, option) in [("a", "PX"), ("b", "Px"), ("c", "px")] {
+        assert_eq!(
+            client.command(&["SET", key, "v", option, "100000"]).await,
+            "+OK\r\n"
+        );
+        assert_ne!(client.command(&["PTTL", key]).await, ":-1\r\n");
+    }
+    assert_eq!(
+        client.command(&["SET", "d", "v", "eX", "100"]).await,
+        "+OK\r\n"
+    );
+    assert_eq!(client.command(&["TTL", "d"]).await, ":100\r\n");
+
+    // keys keep their case while the options around them don't matter
+    assert_eq!(
+        client.command(&["XADD", "MyStream", "1-1", "f", "v"]).await,
+        "$3\r\n1-1\r\n"
+    );
+    let reply = client
+        .command(&["XREAD", "bLoCk", "10", "Streams", "MyStream", "0-0"])
+        .await;
+    assert!(reply.contains("MyStream"), "unexpected reply {reply:?}");
+
+    shutdown.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn idle_clients_are_disconnected_after_the_timeout() {
+    let (addr, shutdown) = server_with_timeout(1).await;
+
+    let mut idle = TestClient::connect(addr).await;
+    assert_eq!(idle.command(&["PING"]).await, "+PONG\r\n");
+
+    // a client sending commands more often than the timeout stays
+    let mut active = TestClient::connect(addr).await;
+    for _ in 0..4 {
+        assert_eq!(active.command(&["PING"]).await, "+PONG\r\n");
+        tokio::time::sleep(Duration::from_millis(400)).await;
+    }
+
+    let mut buf = [0u8; 16];
+    let read = tokio::time::timeout(Duration::from_secs(2), idle.read_raw(&mut buf))
+        .await
+        .expect("the idle connection wasn't closed");
+    assert_eq!(read, 0);
+    assert_eq!(active.command(&["PING"]).await, "+PONG\r\n");
+
+    shutdown.shutdown().await.unwrap();
+}
+
+async fn server_with_timeout(timeout: u64) -> (std::net::SocketAddr, common::ShutdownHandle) {
+    common::spawn_server(CliConfig {
+        max_clients: DEFAULT_MAX_CLIENTS,
+        timeout,
+        ..Default::default()
+    })
+    .await
+}
+
+#[tokio::test]
+async fn unknown_subcommands_get_the_standard_error() {
+    let (addr, shutdown) = spawn_test_server().await;
+    let mut client = TestClient::connect(addr).await;
+
+    for command in ["OBJECT", "CLIENT", "CONFIG", "COMMAND", "DEBUG"] {
+        assert_eq!(
+            client.command(&[command, "bogus", "arg"]).await,
+            format!(
+                "-ERR Unknown subcommand or wrong number of arguments for 'bogus'. Try {command} HELP.\r\n"
+            ),
+        );
+    }
+    // a known subcommand with the wrong arguments gets the same error
+    assert_eq!(
+        client.command(&["OBJECT", "encoding"]).await,
+        "-ERR Unknown subcommand or wrong number of arguments for 'encoding'. Try OBJECT HELP.\r\n"
+    );
+    assert_eq!(
+        client.command(&["CONFIG", "GET"]).await,
+        "-ERR wrong number of arguments for 'config|get' command\r\n"
+    );
+
+    // the subcommand is quoted back, line breaks in it can't end the frame
+    assert_eq!(
+        client.command(&["CLIENT", "a\r\nb"]).await,
+        "-ERR Unknown subcommand or wrong number of arguments for 'a  b'. Try CLIENT HELP.\r\n"
+    );
+    assert_eq!(client.command(&["SET", "k", "v"]).await, "+OK\r\n");
+    assert_eq!(client.command(&["TYPE", "k"]).await, "+string\r\n");
+    assert_eq!(client.command(&["TYPE", "missing"]).await, "+none\r\n");
+
+    shutdown.shutdown().await.unwrap();
+}
+
+/// Start a server saving to `dir/dump.rdb`
+async fn server_saving_to(
+    dir: &std::path::Path,
+    save: Option<Vec<(u64, u64)>>,
+) -> (std::net::SocketAddr, common::ShutdownHandle) {
+    std::fs::create_dir_all(dir).unwrap();
+    common::spawn_server(CliConfig {
+        max_clients: DEFAULT_MAX_CLIENTS,
+        dir: Some(dir.to_string_lossy().into_owned()),
+        dbfilename: Some("dump.rdb".into()),
+        save,
+        ..Default::default()
+    })
+    .await
+}
+
+#[tokio::test]
+async fn dataset_is_saved_on_shutdown() {
+    let dir = std::env::temp_dir().join(format!("shutdown-save-{}", std::process::id()));
+    let (addr, shutdown) = server_saving_to(&dir, None).await;
+
+    let mut client = TestClient::connect(addr).await;
+    assert_eq!(client.command(&["SET", "foo", "bar"]).await, "+OK\r\n");
+    assert_eq!(client.command(&["RPUSH", "list", "a", "b"]).await, ":2\r\n");
+    drop(client);
+    shutdown.shutdown().await.unwrap();
+
+    let rdb = rdb::read_db_file(dir.join("dump.rdb")).unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+    let mut parser =
+        rdb::RdbParser::new(rdb::DefaultFilter::new(), rdb::RdbBuilder::default(), rdb);
+    let database = parser.parse().unwrap().unwrap();
+    assert!(database.entries.contains_key("foo"));
+    assert!(database.entries.contains_key("list"));
+}
+
+#[tokio::test]
+async fn every_database_survives_a_restart() {
+    let dir = std::env::temp_dir().join(format!("restart-dbs-{}", std::process::id()));
+    let (addr, shutdown) = server_saving_to(&dir, None).await;
+
+    let mut client = TestClient::connect(addr).await;
+    assert_eq!(client.command(&["SET", "a", "0"]).await, "+OK\r\n");
+    assert_eq!(client.command(&["SELECT", "1"]).await, "+OK\r\n");
+    assert_eq!(client.command(&["SET", "b", "1"]).await, "+OK\r\n");
+    assert_eq!(client.command(&["SAVE"]).await, "+OK\r\n");
+    drop(client);
+    shutdown.shutdown().await.unwrap();
+
+    let (addr, shutdown) = server_saving_to(&dir, Some(vec![])).await;
+    let mut client = TestClient::connect(addr).await;
+    assert_eq!(client.command(&["GET", "a"]).await, "$1\r\n0\r\n");
+    assert_eq!(client.command(&["GET", "b"]).await, "$-1\r\n");
+    assert_eq!(client.command(&["SELECT", "1"]).await, "+OK\r\n");
+    assert_eq!(client.command(&["GET", "b"]).await, "$1\r\n1\r\n");
+    assert_eq!(client.command(&["GET", "a"]).await, "$-1\r\n");
+    drop(client);
+    shutdown.shutdown().await.unwrap();
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn shutdown_saves_only_when_asked_to() {
+    let base = std::env::temp_dir().join(format!("shutdown-nosave-{}", std::process::id()));
+
+    // without save points nothing is saved
+    let dir = base.join("no-save-points");
+    let (addr, shutdown) = server_saving_to(&dir, Some(vec![])).await;
+    let mut client = TestClient::connect(addr).await;
+    assert_eq!(client.command(&["SET", "foo", "bar"]).await, "+OK\r\n");
+    drop(client);
+    shutdown.shutdown().await.unwrap();
+    assert!(!dir.join("dump.rdb").exists());
+
+    // SHUTDOWN NOSAVE skips the save points, the server stops
+    let dir = base.join("nosave");
+    let (addr, shutdown) = server_saving_to(&dir, None).await;
+    let mut client = TestClient::connect(addr).await;
+    assert_eq!(client.command(&["SET", "foo", "bar"]).await, "+OK\r\n");
+    client.send(&["SHUTDOWN", "NOSAVE"]).await;
+    let mut buf = [0u8; 16];
+    let read = tokio::time::timeout(Duration::from_secs(2), client.read_raw(&mut buf))
+        .await
+        .expect("SHUTDOWN didn't stop the server");
+    assert_eq!(read, 0);
+    shutdown.shutdown().await.unwrap();
+    assert!(!dir.join("dump.rdb").exists());
+
+    // SHUTDOWN SAVE saves without save points
+    let dir = base.join("save");
+    let (addr, shutdown) = server_saving_to(&dir, Some(vec![])).await;
+    let mut client = TestClient::connect(addr).await;
+    assert_eq!(client.command(&["SET", "foo", "bar"]).await, "+OK\r\n");
+    assert_eq!(
+        client.command(&["SHUTDOWN", "bogus"]).await,
+        "-ERR syntax error\r\n"
+    );
+    client.send(&["SHUTDOWN", "save"]).await;
+    assert_eq!(client.read_raw(&mut buf).await, 0);
+    shutdown.shutdown().await.unwrap();
+    assert!(dir.join("dump.rdb").exists());
+
+    std::fs::remove_dir_all(&base).unwrap();
+}
+
+#[tokio::test]
+async fn wait_rejects_malformed_counts() {
+    let (addr, shutdown) = spawn_test_server().await;
+    let mut client = TestClient::connect(addr).await;
+
+    for args in [
+        ["WAIT", "-1", "0"],
+        ["WAIT", "1", "-5"],
+        ["WAIT", "one", "0"],
+    ] {
+        assert_eq!(
+            client.command(&args).await,
+            "-ERR value is not an integer or out of range\r\n"
+        );
+    }
+
+    // more replicas than connected runs out the timeout once there's
+    // a write to wait on
+    assert_eq!(client.command(&["SET", "foo", "bar"]).await, "+OK\r\n");
+    let started = std::time::Instant::now();
+    assert_eq!(client.command(&["WAIT", "3", "100"]).await, ":0\r\n");
+    assert!(started.elapsed() >= Duration::from_millis(100));
+
+    shutdown.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn client_setinfo_shows_in_client_info() {
+    let (addr, shutdown) = spawn_test_server().await;
+    let mut client = TestClient::connect(addr).await;
+
+    assert_eq!(
+        client
+            .command(&["CLIENT", "SETINFO", "lib-name", "redis-py"])
+            .await,
+        "+OK\r\n"
+    );
+    assert_eq!(
+        client
+            .command(&["CLIENT", "SETINFO", "LIB-VER", "5.0.1"])
+            .await,
+        "+OK\r\n"
+    );
+    assert_eq!(
+        client.command(&["CLIENT", "SETINFO", "name", "x"]).await,
+        "-ERR Unrecognized option 'name'\r\n"
+    );
+    assert_eq!(
+        client
+            .command(&["CLIENT", "SETINFO", "lib-name", "redis py"])
+            .await,
+        "-ERR lib-name cannot contain spaces, newlines or special characters.\r\n"
+    );
+
+    assert_eq!(client.command(&["SELECT", "3"]).await, "+OK\r\n");
+    let info = client.command(&["CLIENT", "INFO"]).await;
+    assert!(
+        info.contains(&format!(
+            "laddr={addr} db=3 resp=2 lib-name=redis-py lib-ver=5.0.1\n"
+        )),
+        "unexpected reply {info:?}"
+    );
+
+    shutdown.shutdown().await.unwrap();
+}
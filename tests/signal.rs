@@ -0,0 +1,49 @@
+#![cfg(unix)]
+
+mod common;
+
+use std::time::Duration;
+
+use common::TestClient;
+use redis_starter_rust::{config::DEFAULT_MAX_CLIENTS, server, CliConfig};
+use tokio::net::TcpListener;
+
+#[tokio::test]
+async fn sigterm_shuts_down_and_saves() {
+    let dir = std::env::temp_dir().join(format!("redis-sigterm-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let config = || CliConfig {
+        dir: Some(dir.to_string_lossy().into_owned()),
+        dbfilename: Some("dump.rdb".into()),
+        max_clients: DEFAULT_MAX_CLIENTS,
+        ..Default::default()
+    };
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let signal = server::shutdown_signal().unwrap();
+    let server = tokio::spawn(server::run(listener, config(), signal));
+
+    let mut client = TestClient::connect(addr).await;
+    assert_eq!(client.command(&["SET", "foo", "bar"]).await, "+OK\r\n");
+    drop(client);
+
+    let status = std::process::Command::new("kill")
+        .args(["-TERM", &std::process::id().to_string()])
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    tokio::time::timeout(Duration::from_secs(5), server)
+        .await
+        .expect("server did not shut down on SIGTERM")
+        .unwrap()
+        .unwrap();
+
+    // the dataset was saved on the way out and is loaded again
+    let (addr, _shutdown) = common::spawn_server(config()).await;
+    let mut client = TestClient::connect(addr).await;
+    assert_eq!(client.command(&["GET", "foo"]).await, "$3\r\nbar\r\n");
+
+    let _ = std::fs::remove_dir_all(&dir);
+}